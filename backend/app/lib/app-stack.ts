@@ -1,6 +1,6 @@
 import { Runtime, Function, Code, CfnLayerVersion } from 'aws-cdk-lib/aws-lambda';
 import * as sns from 'aws-cdk-lib/aws-sns';
-import { App, Stack, RemovalPolicy } from 'aws-cdk-lib';
+import { App, Stack, RemovalPolicy, CfnOutput } from 'aws-cdk-lib';
 import { Rule, Schedule } from 'aws-cdk-lib/aws-events';
 import { LambdaFunction } from 'aws-cdk-lib/aws-events-targets';
 import { RetentionDays } from 'aws-cdk-lib/aws-logs';
@@ -25,6 +25,14 @@ export class Recipe3Stack extends Stack {
     const privateKey = process.env.PRIVATE_KEY || 'NO_PRIVATE_KEY';
     const nftStoreApiKey = process.env.NFT_STORAGE_API_KEY || 'No NFT Store API Key';
     const cloudConvertApiKey = process.env.CLOUD_CONVERT_API_KEY || 'No Cloud Conver API Key';
+    // Which model reads recipes, see "LLM providers" in the README
+    const llmEnvironment = {
+      LLM_PROVIDER: process.env.LLM_PROVIDER || 'openai',
+      LLM_BASE_URL: process.env.LLM_BASE_URL || '',
+      LLM_CHAT_MODEL: process.env.LLM_CHAT_MODEL || 'gpt-4',
+      LLM_VISION_MODEL: process.env.LLM_VISION_MODEL || 'gpt-4-vision-preview',
+      LLM_IMAGE_MODEL: process.env.LLM_IMAGE_MODEL || 'dall-e-2',
+    };
 
     // Setup our dynamo db table
     const dynamoTable = new Table(this, 'Recipes', {
@@ -59,6 +67,43 @@ export class Recipe3Stack extends Stack {
       tableName: 'RecipeBooks'
     });
 
+    // Progress of each recipe being added, polled through GET /jobs/{id}
+    const jobTable = new Table(this, 'Jobs', {
+      partitionKey: {
+        name: 'id',
+        type: AttributeType.STRING
+      },
+      readCapacity: 1,
+      writeCapacity: 1,
+      tableName: 'Jobs',
+      timeToLiveAttribute: 'expires_at',
+      removalPolicy: RemovalPolicy.DESTROY
+    });
+
+    // Non-canonical recipe URLs (AMP pages, tracking params) mapped to the recipe's uuid
+    const aliasTable = new Table(this, 'RecipeAliases', {
+      partitionKey: {
+        name: 'alias',
+        type: AttributeType.STRING
+      },
+      readCapacity: 1,
+      writeCapacity: 1,
+      tableName: 'RecipeAliases',
+      removalPolicy: RemovalPolicy.RETAIN
+    });
+
+    // Imports the worker gave up on, kept so tools/replayFailed can send them again
+    const deadLetterTable = new Table(this, 'DeadLetters', {
+      partitionKey: {
+        name: 'id',
+        type: AttributeType.STRING
+      },
+      readCapacity: 1,
+      writeCapacity: 1,
+      tableName: 'DeadLetters',
+      removalPolicy: RemovalPolicy.RETAIN
+    });
+
 
     // Lambda function to add a new recipe
     // Expects a string URL
@@ -68,6 +113,10 @@ export class Recipe3Stack extends Stack {
       blockPublicAccess: BlockPublicAccess.BLOCK_ACLS,
       accessControl: BucketAccessControl.BUCKET_OWNER_FULL_CONTROL,
       publicReadAccess: true,
+      // Prints and books are only saved long enough for their links to be used
+      lifecycleRules: [
+        { prefix: 'downloads/', expiration: Duration.days(1), noncurrentVersionExpiration: Duration.days(1) },
+      ],
     });
 
     // Create an IAM role for the Lambda function
@@ -78,15 +127,6 @@ export class Recipe3Stack extends Stack {
     // Attach the basic Lambda execution policy (You can adjust permissions as needed)
     lambdaRole.addManagedPolicy(iam.ManagedPolicy.fromAwsManagedPolicyName('service-role/AWSLambdaBasicExecutionRole'));
 
-    // Create a policy statement for creating SNS topics
-    const sqsPolicyStatement = new iam.PolicyStatement();
-    sqsPolicyStatement.addActions('sqs:CreateQueue', 'sqs:SendMessage'); // Add additional permissions as needed
-    sqsPolicyStatement.addAllResources(); // Grant access to all SNS resources, adjust as needed
-
-    // Attach the policy statement to the IAM role
-    lambdaRole.addToPolicy(sqsPolicyStatement);
-  
-
     const addRecipeWorker = new Function(this, 'addRecipeWorker', {
       description: "Add recipes worker",
       code: Code.fromAsset('lib/lambdas/addRecipeWorker/target/x86_64-unknown-linux-musl/release/lambda'),
@@ -96,7 +136,11 @@ export class Recipe3Stack extends Stack {
       environment: {
         RUST_BACKTRACE: '1',
         TABLE_NAME: 'Recipes',
+        JOB_TABLE_NAME: 'Jobs',
+        ALIAS_TABLE_NAME: 'RecipeAliases',
+        DEAD_LETTER_TABLE_NAME: 'DeadLetters',
         OPEN_AI_API_KEY: openAiApiKey,
+        ...llmEnvironment,
         BUCKET_NAME: s3Bucket.bucketName,
         CLOUD_CONVERT_API_KEY: cloudConvertApiKey
       },
@@ -125,6 +169,8 @@ export class Recipe3Stack extends Stack {
       environment: {
         RUST_BACKTRACE: '1',
         TABLE_NAME: 'Recipes',
+        JOB_TABLE_NAME: 'Jobs',
+        ALIAS_TABLE_NAME: 'RecipeAliases',
         SNS_ARN: recipeTopic.topicArn
       },
       logRetention: RetentionDays.ONE_WEEK,
@@ -156,7 +202,8 @@ export class Recipe3Stack extends Stack {
       handler: 'not.required',
       environment: {
         RUST_BACKTRACE: '1',
-        TABLE_NAME: 'RecipeBooks',
+        USER_TABLE_NAME: 'RecipeBooks',
+        RECIPE_TABLE_NAME: 'Recipes',
       },
       logRetention: RetentionDays.ONE_WEEK
     });
@@ -167,16 +214,62 @@ export class Recipe3Stack extends Stack {
       code: Code.fromAsset('lib/lambdas/getUserRecipes/target/x86_64-unknown-linux-musl/release/lambda'),
       runtime: Runtime.PROVIDED_AL2,
       handler: 'not.required',
+      // EPUB and site downloads copy in a picture per recipe
+      timeout: Duration.minutes(1),
+      memorySize: 512,
       environment: {
         RUST_BACKTRACE: '1',
         USER_TABLE_NAME: 'RecipeBooks',
         RECIPE_TABLE_NAME: 'Recipes',
+        BUCKET_NAME: s3Bucket.bucketName,
       },
       logRetention: RetentionDays.ONE_WEEK
     });
+    s3Bucket.grantRead(getUserRecipes);
+    s3Bucket.grantPut(getUserRecipes, 'downloads/*');
+
+    // Saves the recipes in a file exported from another recipe app
+    const importRecipes = new Function(this, 'importRecipes', {
+      description: "Import recipes",
+      code: Code.fromAsset('lib/lambdas/importRecipes/target/x86_64-unknown-linux-musl/release/lambda'),
+      runtime: Runtime.PROVIDED_AL2,
+      handler: 'not.required',
+      // A big export is a few thousand writes
+      timeout: Duration.minutes(5),
+      memorySize: 512,
+      environment: {
+        RUST_BACKTRACE: '1',
+        USER_TABLE_NAME: 'RecipeBooks',
+        RECIPE_TABLE_NAME: 'Recipes',
+      },
+      logRetention: RetentionDays.ONE_WEEK
+    });
+
+    // Lays out a recipe card, or a user's whole recipe book, as a PDF
+    const printRecipes = new Function(this, 'printRecipes', {
+      description: "Print recipes",
+      code: Code.fromAsset('lib/lambdas/printRecipes/target/x86_64-unknown-linux-musl/release/lambda'),
+      runtime: Runtime.PROVIDED_AL2,
+      handler: 'not.required',
+      // A big book decodes and shrinks an image per recipe
+      timeout: Duration.minutes(1),
+      memorySize: 512,
+      environment: {
+        RUST_BACKTRACE: '1',
+        RECIPE_TABLE_NAME: 'Recipes',
+        USER_TABLE_NAME: 'RecipeBooks',
+        ALIAS_TABLE_NAME: 'RecipeAliases',
+        BUCKET_NAME: s3Bucket.bucketName,
+      },
+      logRetention: RetentionDays.ONE_WEEK
+    });
+    s3Bucket.grantRead(printRecipes);
+    s3Bucket.grantPut(printRecipes, 'downloads/*');
 
     userTable.grantFullAccess(collectRecipe);
     userTable.grantFullAccess(getUserRecipes);
+    userTable.grantReadWriteData(importRecipes);
+    userTable.grantReadData(printRecipes);
 
     // Gets all recipes from dynamoDB
     const getRecipes = new Function(this, 'getRecipes', {
@@ -187,10 +280,80 @@ export class Recipe3Stack extends Stack {
       environment: {
         RUST_BACKTRACE: '1',
         TABLE_NAME: 'Recipes',
+        ALIAS_TABLE_NAME: 'RecipeAliases',
+      },
+      logRetention: RetentionDays.ONE_WEEK
+    });
+
+    // Rescales a recipe's ingredients to a serving count or multiplier
+    const scaleRecipe = new Function(this, 'scaleRecipe', {
+      description: "Scale recipe",
+      code: Code.fromAsset('lib/lambdas/scaleRecipe/target/x86_64-unknown-linux-musl/release/lambda'),
+      runtime: Runtime.PROVIDED_AL2,
+      handler: 'not.required',
+      environment: {
+        RUST_BACKTRACE: '1',
+        TABLE_NAME: 'Recipes',
+      },
+      logRetention: RetentionDays.ONE_WEEK
+    });
+
+    // Scores recipes against each other to find the same one saved twice
+    const findDuplicates = new Function(this, 'findDuplicates', {
+      description: "Find duplicate recipes",
+      code: Code.fromAsset('lib/lambdas/findDuplicates/target/x86_64-unknown-linux-musl/release/lambda'),
+      runtime: Runtime.PROVIDED_AL2,
+      handler: 'not.required',
+      environment: {
+        RUST_BACKTRACE: '1',
+        TABLE_NAME: 'Recipes',
+        ALIAS_TABLE_NAME: 'RecipeAliases',
+      },
+      // Reads the whole recipes table
+      timeout: Duration.seconds(30),
+      logRetention: RetentionDays.ONE_WEEK
+    });
+
+    // Gets the progress of a recipe being added
+    const getJob = new Function(this, 'getJob', {
+      description: "Get job",
+      code: Code.fromAsset('lib/lambdas/getJob/target/x86_64-unknown-linux-musl/release/lambda'),
+      runtime: Runtime.PROVIDED_AL2,
+      handler: 'not.required',
+      environment: {
+        RUST_BACKTRACE: '1',
+        JOB_TABLE_NAME: 'Jobs',
+        RECIPE_TABLE_NAME: 'Recipes',
       },
       logRetention: RetentionDays.ONE_WEEK
     });
 
+    // Streams a job's progress as server-sent events. API Gateway buffers
+    // responses, so this is served from a function URL in streaming mode
+    const jobEvents = new Function(this, 'jobEvents', {
+      description: "Stream job progress",
+      code: Code.fromAsset('lib/lambdas/jobEvents/target/x86_64-unknown-linux-musl/release/lambda'),
+      runtime: Runtime.PROVIDED_AL2,
+      handler: 'not.required',
+      timeout: Duration.minutes(10),
+      environment: {
+        RUST_BACKTRACE: '1',
+        JOB_TABLE_NAME: 'Jobs',
+        RECIPE_TABLE_NAME: 'Recipes',
+      },
+      logRetention: RetentionDays.ONE_WEEK
+    });
+    const jobEventsUrl = jobEvents.addFunctionUrl({
+      authType: lambda.FunctionUrlAuthType.NONE,
+      cors: {
+        allowedOrigins: ['*'],
+        allowedMethods: [lambda.HttpMethod.GET],
+      },
+    });
+    // This version of the CDK doesn't know about InvokeMode yet
+    (jobEventsUrl.node.defaultChild as lambda.CfnUrl).addPropertyOverride('InvokeMode', 'RESPONSE_STREAM');
+    new CfnOutput(this, 'JobEventsUrl', { value: jobEventsUrl.url });
+
     // Gets all recipes from dynamoDB
     const tesseract = new Function(this, 'tesseract', {
       description: "Add recipes",
@@ -201,6 +364,7 @@ export class Recipe3Stack extends Stack {
       environment: {
         RUST_BACKTRACE: '1',
         OPEN_AI_API_KEY: openAiApiKey,
+        ...llmEnvironment,
       },
       logRetention: RetentionDays.ONE_WEEK
     });
@@ -224,6 +388,21 @@ export class Recipe3Stack extends Stack {
     dynamoTable.grantFullAccess(addRecipeWorker);   
     dynamoTable.grantReadData(getUserRecipes); 
     dynamoTable.grantFullAccess(updateRecipe);
+    dynamoTable.grantReadData(scaleRecipe);
+    dynamoTable.grantReadData(getJob);
+    dynamoTable.grantReadData(findDuplicates);
+    dynamoTable.grantReadWriteData(importRecipes);
+    dynamoTable.grantReadData(printRecipes);
+    jobTable.grantReadWriteData(addRecipe);
+    jobTable.grantReadWriteData(addRecipeWorker);
+    jobTable.grantReadData(getJob);
+    jobTable.grantReadData(jobEvents);
+    aliasTable.grantReadData(getRecipes);
+    aliasTable.grantReadData(addRecipe);
+    aliasTable.grantReadWriteData(addRecipeWorker);
+    aliasTable.grantReadData(findDuplicates);
+    aliasTable.grantReadData(printRecipes);
+    deadLetterTable.grantReadWriteData(addRecipeWorker);
 
 
     // Create an API Gateway resource for each of the CRUD operations
@@ -233,7 +412,9 @@ export class Recipe3Stack extends Stack {
         allowOrigins: Cors.ALL_ORIGINS,
         allowMethods: Cors.ALL_METHODS,
         allowHeaders: Cors.DEFAULT_HEADERS,
-      }
+      },
+      // Imports are zips, which API Gateway would otherwise mangle as text
+      binaryMediaTypes: ['application/zip', 'application/octet-stream', 'application/gzip'],
     });
 
     // Integrate lambda functions with an API gateway
@@ -244,6 +425,11 @@ export class Recipe3Stack extends Stack {
     const getUserRecipesAPI = new LambdaIntegration(getUserRecipes);
     const tesseractAPI = new LambdaIntegration(tesseract);
     const updateRecipeAPI = new LambdaIntegration(updateRecipe);
+    const scaleRecipeAPI = new LambdaIntegration(scaleRecipe);
+    const getJobAPI = new LambdaIntegration(getJob);
+    const findDuplicatesAPI = new LambdaIntegration(findDuplicates);
+    const importRecipesAPI = new LambdaIntegration(importRecipes);
+    const printRecipesAPI = new LambdaIntegration(printRecipes);
 
     const mint = api.root.addResource('mint');
     mint.addMethod('POST', mintNFTAPI);
@@ -261,6 +447,22 @@ export class Recipe3Stack extends Stack {
 
     const update = api.root.addResource('update');
     update.addMethod('POST', updateRecipeAPI);
+
+    const scaleResource = api.root.addResource('scale');
+    scaleResource.addMethod('GET', scaleRecipeAPI);
+
+    const duplicates = api.root.addResource('duplicates');
+    duplicates.addMethod('GET', findDuplicatesAPI);
+
+    const importResource = api.root.addResource('import');
+    importResource.addMethod('POST', importRecipesAPI);
+
+    const print = api.root.addResource('print');
+    print.addMethod('GET', printRecipesAPI);
+
+    const jobs = api.root.addResource('jobs');
+    const job = jobs.addResource('{id}');
+    job.addMethod('GET', getJobAPI);
   }
 }
 
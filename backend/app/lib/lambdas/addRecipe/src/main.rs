@@ -3,12 +3,7 @@ use serde::Serialize;
 use serde_json::Value;
 use lambda_runtime::{LambdaEvent};
 use std::collections::HashMap;
-use aws_sdk_dynamodb::types::{AttributeValue};
-use aws_sdk_dynamodb::operation::get_item::GetItemInput;
 use std::env;
-use aws_config::{meta::region::RegionProviderChain, SdkConfig};
-use aws_sdk_dynamodb::{config::Region, meta::PKG_VERSION};
-use aws_sdk_dynamodb::Client as DbClient;
 use aws_sdk_sns::Client as SnsClient;
 use futures_util::StreamExt;
 use rayon::iter::ParallelIterator;
@@ -16,17 +11,9 @@ use std::iter::Iterator;
 use uuid::Uuid;
 use lambda_http::{service_fn, Response, Body, Error, Request};
 use serde_json::json;
-use aws_sdk_sqs::Client as SqsClient;
+use recipe_core::{error_response, find_by_url, make_config, ApiError, DynamoStore, Job, JobStore, Opt, RecipeResponse, RecipeStore};
 
 
-#[derive(Debug)]
-pub struct Opt {
-    /// The AWS Region.
-    pub region: Option<String>,
-    /// Whether to display additional information.
-    pub verbose: bool,
-}
-
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub enum ContentType {
     URL,
@@ -48,7 +35,7 @@ pub struct WorkerRequest {
     pub content_type: ContentType,
     pub credit: Option<String>,
     pub uuid: Option<String>,
-    pub sqs_url: String
+    pub job_id: String
 }
 
 #[derive(Debug, Serialize)]
@@ -58,48 +45,6 @@ pub struct SuccessResponse {
     pub body: String,
 }
 
-#[derive(Debug, Serialize)]
-pub struct FailureResponse {
-    pub body: String,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct Recipe {
-    pub uuid: String,
-    pub name: String,
-    pub ingredients: Vec<String>,
-    pub instructions: Vec<String>,
-    pub notes: String,
-    pub summary: String,
-    pub image: String,
-}
-
-impl From<&HashMap<String, AttributeValue>> for Recipe {
-    fn from(value: &HashMap<String, AttributeValue>) -> Self {
-        let mut recipe = Recipe {
-            uuid: as_string(value.get("uuid"), &String::from("UUID")),
-            name: as_string(value.get("name"), &String::from("NAME")),
-            ingredients: split_string(as_string(value.get("ingredients"), &String::from("INGREDIENTS"))),
-            instructions: split_string(as_string(value.get("instructions"), &String::from("INSTRUCTIONS"))),
-            notes: as_string(value.get("notes"), &String::from("NOTES")),
-            summary: as_string(value.get("summary"), &String::from("SUMMARY")),
-            image: as_string(value.get("image"), &String::from("IMAGE"))
-        };
-        recipe
-    }
-}
-
-// Implement Display for the Failure response so that we can then implement Error.
-impl std::fmt::Display for FailureResponse {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.body)
-    }
-}
-
-// Implement Error for the FailureResponse so that we can `?` (try) the Response
-// returned by `lambda_runtime::run(func).await` in `fn main`.
-impl std::error::Error for FailureResponse {}
-
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     let func = service_fn(handler);
@@ -108,73 +53,52 @@ async fn main() -> Result<(), Error> {
     Ok(())
 }
 
-pub fn make_region_provider(region: Option<String>) -> RegionProviderChain {
-    RegionProviderChain::first_try(region.map(Region::new))
-        .or_default_provider()
-        .or_else(Region::new("us-east-1"))
-}
-
-pub async fn make_config(opt: Opt) -> Result<SdkConfig, Error> {
-    let region_provider = make_region_provider(opt.region);
-
-    println!();
-    if opt.verbose {
-        println!("DynamoDB client version: {}", PKG_VERSION);
-        println!(
-            "Region:                  {}",
-            region_provider.region().await.unwrap().as_ref()
-        );
-        println!();
-    }
-
-    Ok(aws_config::from_env().region(region_provider).load().await)
-}
-
-fn as_string(val: Option<&AttributeValue>, default: &String) -> String {
-    if let Some(v) = val {
-        if let Ok(s) = v.as_s() {
-            return s.to_owned();
-        }
-    }
-    default.to_owned()
+async fn get_table_name() -> Option<String> {
+    env::var("TABLE_NAME").ok()
 }
 
-fn split_string(string: String) -> Vec<String> {
-    let escaped_strings: Vec<String> = string
-        .split(";")
-        .map(|substring| substring.replace("\\;", ";").replace("\\,", ",").replace("\\\\", "\\"))
-        .collect();
-
-    escaped_strings
+async fn get_alias_table_name() -> Option<String> {
+    env::var("ALIAS_TABLE_NAME").ok()
 }
 
-async fn get_table_name() -> Option<String> {
-    env::var("TABLE_NAME").ok()
+async fn get_job_table_name() -> Option<String> {
+    env::var("JOB_TABLE_NAME").ok()
 }
 
 async fn get_sns_arn() -> Option<String> {
     env::var("SNS_ARN").ok()
 }
 
-async fn get_recipe_from_db(client: &DbClient, table_name: &str, url: &str) -> Result<Option<Recipe>, Error> {
-    let pk = AttributeValue::S(url.to_string());
-    
-    let response = client.get_item()
-        .table_name(table_name)
-        .key("uuid".to_string(), pk)
-        .send().await?;
-
-    if let Some(recipe) = response.item {
-        return Ok(Some(Recipe::from(&recipe)));
-    } else {
-        return Ok(None);
+// Images and bulk text have no URL to key them by, so the client has to send a uuid
+fn check_request(url: &URLRequest) -> Result<(), ApiError> {
+    match (&url.content_type, &url.uuid) {
+        (ContentType::URL, _) | (_, Some(_)) => Ok(()),
+        (_, None) => Err(ApiError::Unprocessable(String::from("Image and bulk recipes need a uuid"))),
     }
 }
 
+// Recipe URLs are their own key once canonicalized, images and bulk text come with a uuid
+async fn get_existing_recipe(store: &dyn RecipeStore, url: &URLRequest) -> Result<Option<RecipeResponse>, Error> {
+    let recipe = match (&url.content_type, &url.uuid) {
+        (ContentType::URL, _) => find_by_url(store, &url.url).await?,
+        (_, Some(uuid)) => store.get(uuid).await?,
+        (_, None) => None,
+    };
+
+    Ok(recipe.map(RecipeResponse::from))
+}
+
 async fn generate_uuid() -> String {
     Uuid::new_v4().to_string()
 }
 
+// The worker moves the job along from here, clients poll GET /jobs/{id}
+async fn queue_job(jobs: &dyn JobStore) -> Result<Job, Error> {
+    let job = Job::new(&generate_uuid().await);
+    jobs.put_job(&job).await?;
+    Ok(job)
+}
+
 async fn handler(request: Request) -> Result<Response<String>, Error> {
     // 1. Create db client and get table name from env
     let opt = Opt {
@@ -183,40 +107,39 @@ async fn handler(request: Request) -> Result<Response<String>, Error> {
     };
     let config = match make_config(opt).await {
         Ok(c) => c,
-        Err(e) => {
-            return Ok(Response::builder()
-            .status(500)
-            .body(format!("Error making config: {}", e.to_string()))?);
-            
-        },
+        Err(e) => return error_response!(ApiError::Internal(format!("Error making config: {}", e))),
     };
-    let db_client = DbClient::new(&config);
-    println!("DB Client: {:?}", db_client);
-    let table_name = match get_table_name().await {
-        Some(t) => t,
-        None => {
-            return Ok(Response::builder()
-            .status(500)
-            .body(String::from("TABLE_NAME not set"))?);
-        }
+    let (table_name, job_table_name, alias_table_name) = match (get_table_name().await, get_job_table_name().await, get_alias_table_name().await) {
+        (Some(t), Some(j), Some(a)) => (t, j, a),
+        _ => return error_response!(ApiError::Internal(String::from("TABLE_NAME not set"))),
     };
     println!("Table Name: {}", table_name);
     // 2. Get URL from request
     let body = request.body();
-    let url: URLRequest = serde_json::from_slice(&body)?;
+    let url: URLRequest = match serde_json::from_slice(body) {
+        Ok(u) => u,
+        Err(e) => return error_response!(ApiError::from(e)),
+    };
+    if let Err(e) = check_request(&url) {
+        return error_response!(e);
+    }
     let url_value = &url.url;
     println!("URL: {}", url_value);
 
-    let pk = match url.content_type {
-        ContentType::URL => url_value.to_string(),
-        _ => url.uuid.as_ref().unwrap().clone(),
+    let store = DynamoStore::new(&config, &table_name)
+        .with_job_table(&job_table_name)
+        .with_alias_table(&alias_table_name);
+    let result = match get_existing_recipe(&store, &url).await {
+        Ok(r) => r,
+        Err(e) => return error_response!(e.into()),
     };
 
-    let result = get_recipe_from_db(&db_client, &table_name, &pk).await?;
-
     if let Some(recipe) = result {
         println!("Found recipe");
-        let json_string = serde_json::to_string(&recipe).unwrap();
+        let json_string = match serde_json::to_string(&recipe) {
+            Ok(j) => j,
+            Err(e) => return error_response!(ApiError::Internal(format!("Error writing recipe: {}", e))),
+        };
         Ok(Response::builder()
             .status(200)
             .header("Access-Control-Allow-Origin", "*")
@@ -225,33 +148,30 @@ async fn handler(request: Request) -> Result<Response<String>, Error> {
         // 4. Publish to SNS
         let sns_arn = match get_sns_arn().await {
             Some(t) => t,
-            None => {
-                return Ok(Response::builder()
-                    .status(500)
-                    .body(String::from("SNS_ARN not set"))?);
-            }
+            None => return error_response!(ApiError::Internal(String::from("SNS_ARN not set"))),
         };
         println!("SNS ARN: {:?}", sns_arn);
         let sns_client = SnsClient::new(&config);
-        let sqs_client = SqsClient::new(&config);
 
-        let sqs_url_response = sqs_client
-            .create_queue()
-            .queue_name(generate_uuid().await)
-            .send().await.unwrap();
-        let sqs_url = sqs_url_response.queue_url().unwrap();
-        let sqs_request = WorkerRequest {
+        let mut job = match queue_job(&store).await {
+            Ok(j) => j,
+            Err(e) => return error_response!(e.into()),
+        };
+        let sns_request = WorkerRequest {
             url: url.url,
             content_type: url.content_type,
             credit: url.credit,
             uuid: url.uuid,
-            sqs_url: sqs_url.to_string()
+            job_id: job.id.clone()
+        };
+        let message = match serde_json::to_string(&sns_request) {
+            Ok(m) => m,
+            Err(e) => return error_response!(ApiError::Internal(format!("Error writing message: {}", e))),
         };
-        let message = serde_json::to_string(&sqs_request).unwrap();
         let response = json!({
-            "sqs_url": sqs_url
+            "job_id": job.id
         });
-        let response = serde_json::to_string(&response).unwrap();
+        let response = response.to_string();
         println!("Message: {}", message);
         match sns_client
             .publish()
@@ -268,10 +188,11 @@ async fn handler(request: Request) -> Result<Response<String>, Error> {
                 },
                 Err(e) => {
                     println!("SNS Publish Failure: {:?}", e);
-                    return Ok(Response::builder()
-                        .status(500)
-                        .header("Access-Control-Allow-Origin", "*")
-                        .body(format!("SNS Publish Failure: {:?}", e))?);
+                    job.fail(&format!("SNS Publish Failure: {:?}", e));
+                    if let Err(put_error) = store.put_job(&job).await {
+                        println!("Error failing job {}: {}", job.id, put_error);
+                    }
+                    return error_response!(ApiError::Unavailable(format!("SNS Publish Failure: {}", e)));
                 }
             };
     }
@@ -297,8 +218,73 @@ mod tests {
         let url: URLRequest = serde_json::from_slice(body.as_bytes()).expect("Problem getting url");
         let expected = URLRequest {
             url: String::from("batman.com"),
-            content_type: ContentType::IMAGE
+            content_type: ContentType::IMAGE,
+            credit: None,
+            uuid: None
         };
         assert_eq!(url, expected);
     }
+
+    #[test]
+    fn test_check_request() {
+        let image = URLRequest {
+            url: String::from("https://example.com/card.png"),
+            content_type: ContentType::IMAGE,
+            credit: None,
+            uuid: None
+        };
+        let e = check_request(&image).unwrap_err();
+        assert_eq!((e.status(), e.code()), (422, "unprocessable"));
+        assert!(check_request(&URLRequest { uuid: Some(String::from("new")), ..image }).is_ok());
+
+        let broken = serde_json::from_str::<URLRequest>(r#"{"url": "batman.com"}"#).unwrap_err();
+        assert_eq!(ApiError::from(broken).status(), 422);
+    }
+
+    #[test]
+    fn test_queue_job() {
+        let store = recipe_core::MemoryStore::new();
+        let job = aw!(queue_job(&store)).unwrap();
+        assert_eq!(job.status, recipe_core::JobStatus::Queued);
+        assert_eq!(aw!(store.get_job(&job.id)).unwrap(), Some(job));
+    }
+
+    #[test]
+    fn test_get_existing_recipe() {
+        let store = recipe_core::MemoryStore::with_recipes(vec![
+            recipe_core::Recipe::new("https://tasty.co/recipe/taco-soup", "Taco Soup"),
+            recipe_core::Recipe::new("448b5102-ca04-4451-a18e-692acbeded01", "Grandma's Pie"),
+        ]);
+        let by_url = URLRequest {
+            url: String::from("https://tasty.co/recipe/taco-soup"),
+            content_type: ContentType::URL,
+            credit: None,
+            uuid: None
+        };
+        assert_eq!(aw!(get_existing_recipe(&store, &by_url)).unwrap().unwrap().name, "Taco Soup");
+
+        let amp_url = URLRequest {
+            url: String::from("https://Tasty.co/recipe/taco-soup/amp?utm_source=pinterest#steps"),
+            content_type: ContentType::URL,
+            credit: None,
+            uuid: None
+        };
+        assert_eq!(aw!(get_existing_recipe(&store, &amp_url)).unwrap().unwrap().name, "Taco Soup");
+
+        let by_uuid = URLRequest {
+            url: String::from("Grandma's pie, 2 cups flour..."),
+            content_type: ContentType::BULK,
+            credit: None,
+            uuid: Some(String::from("448b5102-ca04-4451-a18e-692acbeded01"))
+        };
+        assert_eq!(aw!(get_existing_recipe(&store, &by_uuid)).unwrap().unwrap().name, "Grandma's Pie");
+
+        let new_image = URLRequest {
+            url: String::from("https://example.com/card.png"),
+            content_type: ContentType::IMAGE,
+            credit: None,
+            uuid: Some(String::from("new"))
+        };
+        assert!(aw!(get_existing_recipe(&store, &new_image)).unwrap().is_none());
+    }
 }
@@ -0,0 +1,58 @@
+use scraper::{Html, Selector};
+use url::Url;
+
+use crate::jsonld;
+
+/**
+ * The URL the page says it lives at, from `<link rel="canonical">` or else
+ * the JSON-LD recipe's `url`. Relative URLs are resolved against the page.
+ */
+pub fn declared_url(document: &Html, page_url: &str) -> Option<String> {
+    let selector = Selector::parse("link[rel~=\"canonical\"]").unwrap();
+    let link = document
+        .select(&selector)
+        .find_map(|link| link.value().attr("href"))
+        .map(str::to_string);
+    let declared = link.or_else(|| {
+        jsonld::find_recipe_object(document)
+            .and_then(|recipe| recipe.get("url").and_then(|u| u.as_str()).map(str::to_string))
+    })?;
+
+    let resolved = Url::parse(page_url).and_then(|base| base.join(declared.trim())).ok()?;
+    match resolved.scheme() {
+        "http" | "https" => Some(resolved.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_canonical_link() {
+        let html = r#"<html><head>
+            <link rel="alternate" href="https://example.com/feed">
+            <link rel="canonical" href="/recipe/pomegranate-habanero-beef/">
+            </head><body></body></html>"#;
+        let document = Html::parse_document(html);
+        let page = "https://www.example.com/recipe/pomegranate-habanero-beef/AMP/";
+        assert_eq!(
+            declared_url(&document, page).as_deref(),
+            Some("https://www.example.com/recipe/pomegranate-habanero-beef/")
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_json_ld_url() {
+        let html = r#"<script type="application/ld+json">
+            {"@type": "Recipe", "name": "Taco Soup", "url": "https://example.com/taco-soup"}
+            </script>"#;
+        let document = Html::parse_document(html);
+        assert_eq!(
+            declared_url(&document, "https://example.com/amp/taco-soup").as_deref(),
+            Some("https://example.com/taco-soup")
+        );
+        assert_eq!(declared_url(&Html::parse_document("<p>Soup</p>"), "https://example.com/soup"), None);
+    }
+}
@@ -0,0 +1,107 @@
+use recipe_core::ErrorKind;
+use recipe_llm::LlmError;
+use reqwest::StatusCode;
+
+/**
+ * Why the worker couldn't import a recipe. The kind decides whether we try
+ * again and ends up in the dead letter, the message is what the job shows.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkerError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl WorkerError {
+    pub fn retryable(message: impl Into<String>) -> WorkerError {
+        WorkerError { kind: ErrorKind::Retryable, message: message.into() }
+    }
+
+    pub fn permanent(message: impl Into<String>) -> WorkerError {
+        WorkerError { kind: ErrorKind::Permanent, message: message.into() }
+    }
+
+    pub fn bad_input(message: impl Into<String>) -> WorkerError {
+        WorkerError { kind: ErrorKind::BadInput, message: message.into() }
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        self.kind == ErrorKind::Retryable
+    }
+
+    // Fetching what the user sent us, a 404 or 403 means their link is no good
+    pub fn from_source_status(status: StatusCode, what: &str) -> WorkerError {
+        let message = format!("{}: {}", what, status);
+        if is_transient(status) {
+            WorkerError::retryable(message)
+        } else {
+            WorkerError::bad_input(message)
+        }
+    }
+
+    // Calling a service of ours or OpenAI, where a 4xx means we got something wrong
+    pub fn from_service_status(status: StatusCode, what: &str) -> WorkerError {
+        let message = format!("{}: {}", what, status);
+        if is_transient(status) {
+            WorkerError::retryable(message)
+        } else {
+            WorkerError::permanent(message)
+        }
+    }
+
+    pub fn from_request(e: &reqwest::Error, what: &str) -> WorkerError {
+        let message = format!("{}: {}", what, e);
+        if e.is_builder() {
+            // e.g. not a URL at all
+            return WorkerError::bad_input(message);
+        }
+        match e.status() {
+            Some(status) if !is_transient(status) => WorkerError::permanent(message),
+            // Timeouts, dropped connections and the like
+            _ => WorkerError::retryable(message),
+        }
+    }
+
+    // Without a status the model never answered, which is worth another go
+    pub fn from_llm(e: &LlmError, what: &str) -> WorkerError {
+        let message = format!("{}: {}", what, e);
+        match e.status.and_then(|code| StatusCode::from_u16(code).ok()) {
+            Some(status) => WorkerError { message, ..WorkerError::from_service_status(status, what) },
+            None => WorkerError::retryable(message),
+        }
+    }
+}
+
+fn is_transient(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::REQUEST_TIMEOUT || status.is_server_error()
+}
+
+impl std::fmt::Display for WorkerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for WorkerError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_statuses() {
+        assert!(WorkerError::from_source_status(StatusCode::SERVICE_UNAVAILABLE, "Error reading URL").is_retryable());
+        assert_eq!(WorkerError::from_source_status(StatusCode::NOT_FOUND, "Error reading URL").kind, ErrorKind::BadInput);
+        assert_eq!(WorkerError::from_service_status(StatusCode::UNAUTHORIZED, "OpenAI").kind, ErrorKind::Permanent);
+        assert!(WorkerError::from_service_status(StatusCode::TOO_MANY_REQUESTS, "OpenAI").is_retryable());
+    }
+
+    #[test]
+    fn classifies_llm_errors() {
+        let limited = WorkerError::from_llm(&LlmError::new(Some(429), "Rate limit reached"), "Error getting response from LLM");
+        assert!(limited.is_retryable());
+        assert_eq!(limited.message, "Error getting response from LLM: 429 Rate limit reached");
+        assert_eq!(WorkerError::from_llm(&LlmError::new(Some(401), "bad key"), "LLM").kind, ErrorKind::Permanent);
+        assert!(WorkerError::from_llm(&LlmError::new(None, "error sending request"), "LLM").is_retryable());
+    }
+}
@@ -0,0 +1,310 @@
+use recipe_core::site_host;
+use scraper::{ElementRef, Html, Selector};
+
+use crate::text::element_text;
+use crate::Recipe;
+
+// How much each field contributes to an extractor's confidence
+const NAME_WEIGHT: f32 = 0.15;
+const INGREDIENTS_WEIGHT: f32 = 0.35;
+const INSTRUCTIONS_WEIGHT: f32 = 0.35;
+const SUMMARY_WEIGHT: f32 = 0.075;
+const NOTES_WEIGHT: f32 = 0.075;
+
+#[derive(Debug)]
+pub struct Extraction {
+    pub extractor: &'static str,
+    pub confidence: f32,
+    pub recipe: Recipe,
+}
+
+pub trait SiteExtractor: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // Returns None when the page isn't one this extractor understands
+    fn extract(&self, host: &str, document: &Html) -> Option<Extraction>;
+}
+
+/**
+ * Extractor for sites and plugins with stable class names. It claims a page
+ * either by domain or by finding `container` in the DOM, and reads every
+ * field from inside the container when there is one.
+ */
+pub struct SelectorExtractor {
+    pub name: &'static str,
+    pub domains: &'static [&'static str],
+    pub container: &'static str,
+    // Scales confidence for extractors keyed on weak, generic class names
+    pub trust: f32,
+    pub title: &'static str,
+    pub ingredients: &'static str,
+    pub instructions: &'static str,
+    pub summary: &'static str,
+    pub notes: &'static str,
+    pub recipe_yield: &'static str,
+}
+
+impl SiteExtractor for SelectorExtractor {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn extract(&self, host: &str, document: &Html) -> Option<Extraction> {
+        let domain_match = self
+            .domains
+            .iter()
+            .any(|d| host == *d || host.ends_with(&format!(".{}", d)));
+        let container = select_first(document.root_element(), self.container);
+        let signal = match (domain_match, container.is_some()) {
+            (true, _) => 1.0,
+            (false, true) => 0.9,
+            (false, false) => return None,
+        };
+        let scope = container.unwrap_or_else(|| document.root_element());
+
+        let recipe = Recipe {
+            name: select_first(scope, self.title).map(element_text).unwrap_or_default(),
+            ingredients: select_all(scope, self.ingredients),
+            instructions: select_all(scope, self.instructions),
+            notes: select_all(scope, self.notes).join("\n"),
+            summary: select_first(scope, self.summary).map(element_text).unwrap_or_default(),
+            recipe_yield: select_first(scope, self.recipe_yield).map(element_text),
+            total_time: None,
+            image: None,
+            author: None,
+        };
+
+        let found = |present: bool, weight: f32| if present { weight } else { 0.0 };
+        let score = found(!recipe.name.is_empty(), NAME_WEIGHT)
+            + found(!recipe.ingredients.is_empty(), INGREDIENTS_WEIGHT)
+            + found(!recipe.instructions.is_empty(), INSTRUCTIONS_WEIGHT)
+            + found(!recipe.summary.is_empty(), SUMMARY_WEIGHT)
+            + found(!recipe.notes.is_empty(), NOTES_WEIGHT);
+
+        Some(Extraction {
+            extractor: self.name,
+            confidence: score * signal * self.trust,
+            recipe,
+        })
+    }
+}
+
+fn select_first<'a>(scope: ElementRef<'a>, selector: &str) -> Option<ElementRef<'a>> {
+    let selector = Selector::parse(selector).ok()?;
+    scope.select(&selector).find(|e| !element_text(*e).is_empty())
+}
+
+// Selector lists come back in document order, so group headers listed
+// alongside items stay where they belong.
+fn select_all(scope: ElementRef, selector: &str) -> Vec<String> {
+    match Selector::parse(selector) {
+        Ok(s) => scope
+            .select(&s)
+            .map(element_text)
+            .filter(|t| !t.is_empty())
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+pub const WP_RECIPE_MAKER: SelectorExtractor = SelectorExtractor {
+    name: "wprm",
+    domains: &[],
+    container: ".wprm-recipe-container",
+    trust: 1.0,
+    title: ".wprm-recipe-name",
+    ingredients: ".wprm-recipe-ingredient-group-name, .wprm-recipe-ingredient",
+    instructions: ".wprm-recipe-instruction-group-name, .wprm-recipe-instruction-text",
+    summary: ".wprm-recipe-summary",
+    notes: ".wprm-recipe-notes",
+    recipe_yield: ".wprm-recipe-servings-with-unit, .wprm-recipe-servings",
+};
+
+pub const TASTY_RECIPES: SelectorExtractor = SelectorExtractor {
+    name: "tasty-recipes",
+    domains: &[],
+    container: ".tasty-recipes",
+    trust: 1.0,
+    title: ".tasty-recipes-title",
+    ingredients: ".tasty-recipes-ingredients h4, .tasty-recipes-ingredients li",
+    instructions: ".tasty-recipes-instructions h4, .tasty-recipes-instructions li",
+    summary: ".tasty-recipes-description",
+    notes: ".tasty-recipes-notes p, .tasty-recipes-notes li",
+    recipe_yield: ".tasty-recipes-yield",
+};
+
+pub const MEDIAVINE_CREATE: SelectorExtractor = SelectorExtractor {
+    name: "mediavine-create",
+    domains: &[],
+    container: ".mv-create-card",
+    trust: 1.0,
+    title: ".mv-create-title",
+    ingredients: ".mv-create-ingredients h4, .mv-create-ingredients li",
+    instructions: ".mv-create-instructions h4, .mv-create-instructions li",
+    summary: ".mv-create-description",
+    notes: ".mv-create-notes p, .mv-create-notes li",
+    recipe_yield: ".mv-create-yield",
+};
+
+pub const TASTY_CO: SelectorExtractor = SelectorExtractor {
+    name: "tasty.co",
+    domains: &["tasty.co"],
+    container: "",
+    trust: 1.0,
+    title: "h1.recipe-name, h1",
+    ingredients: ".ingredients__section .ingredient-section-name, .ingredients__section li",
+    instructions: ".prep-steps li",
+    summary: ".description",
+    notes: ".tips-list li",
+    recipe_yield: ".servings-display",
+};
+
+pub const ALLRECIPES: SelectorExtractor = SelectorExtractor {
+    name: "allrecipes",
+    domains: &["allrecipes.com"],
+    container: "",
+    trust: 1.0,
+    title: "h1.article-heading, h1",
+    ingredients: ".mm-recipes-structured-ingredients__list-item, .mntl-structured-ingredients__list-item",
+    instructions: ".mm-recipes-steps__content li > p, .recipe__steps-content li > p",
+    summary: ".article-subheading",
+    notes: ".mm-recipes-notes p",
+    recipe_yield: ".mm-recipes-details__item--servings .mm-recipes-details__value",
+};
+
+// The old `.recipe-content` / `.recipe-ingredient` selectors; common enough
+// that a match on them alone is weak evidence.
+pub const GENERIC: SelectorExtractor = SelectorExtractor {
+    name: "generic",
+    domains: &[],
+    container: ".recipe-content",
+    trust: 0.8,
+    title: "h1, h2",
+    ingredients: ".recipe-ingredient",
+    instructions: ".recipe-instruction",
+    summary: ".recipe-summary",
+    notes: ".recipe-notes",
+    recipe_yield: ".recipe-yield",
+};
+
+pub struct Registry {
+    extractors: Vec<Box<dyn SiteExtractor>>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        let mut registry = Registry::new();
+        registry.register(Box::new(WP_RECIPE_MAKER));
+        registry.register(Box::new(TASTY_RECIPES));
+        registry.register(Box::new(MEDIAVINE_CREATE));
+        registry.register(Box::new(TASTY_CO));
+        registry.register(Box::new(ALLRECIPES));
+        registry.register(Box::new(GENERIC));
+        registry
+    }
+}
+
+impl Registry {
+    pub fn new() -> Registry {
+        Registry { extractors: Vec::new() }
+    }
+
+    pub fn register(&mut self, extractor: Box<dyn SiteExtractor>) {
+        self.extractors.push(extractor);
+    }
+
+    // Runs every extractor that claims the page and keeps the highest score
+    pub fn best(&self, url: &str, document: &Html) -> Option<Extraction> {
+        // The same site the canonical URL checks use, so m.allrecipes.com is allrecipes.com
+        let host = site_host(url).unwrap_or_default();
+        let mut best: Option<Extraction> = None;
+        for extractor in &self.extractors {
+            if let Some(extraction) = extractor.extract(&host, document) {
+                println!("Extractor {} scored {:.2}", extractor.name(), extraction.confidence);
+                if best.as_ref().is_none_or(|b| extraction.confidence > b.confidence) {
+                    best = Some(extraction);
+                }
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn best(url: &str, html: &str) -> Extraction {
+        Registry::default()
+            .best(url, &Html::parse_document(html))
+            .expect("extraction")
+    }
+
+    #[test]
+    fn extracts_wprm() {
+        let extraction = best("https://mxriyum.com/lobster-mac-cheese/", include_str!("../fixtures/wprm.html"));
+        assert_eq!(extraction.extractor, "wprm");
+        assert_eq!(extraction.recipe.name, "Lobster Mac & Cheese");
+        assert_eq!(
+            extraction.recipe.ingredients,
+            vec!["Pasta", "1 lb cavatappi", "Sauce", "2 cups gruyere, shredded", "1 cup milk"]
+        );
+        assert_eq!(extraction.recipe.instructions, vec!["Boil the pasta.", "Melt the cheese into the milk."]);
+        assert_eq!(extraction.recipe.recipe_yield.as_deref(), Some("4 servings"));
+        assert!((extraction.confidence - 0.9).abs() < 0.001);
+    }
+
+    #[test]
+    fn extracts_tasty_recipes() {
+        let extraction = best("https://blog.example/soup", include_str!("../fixtures/tasty_recipes.html"));
+        assert_eq!(extraction.extractor, "tasty-recipes");
+        assert_eq!(extraction.recipe.name, "Tomato Soup");
+        assert_eq!(extraction.recipe.ingredients, vec!["2 lbs tomatoes", "1 onion"]);
+        assert_eq!(extraction.recipe.notes, "Freezes well.");
+    }
+
+    #[test]
+    fn extracts_mediavine_create() {
+        let extraction = best("https://blog.example/brownies", include_str!("../fixtures/mediavine.html"));
+        assert_eq!(extraction.extractor, "mediavine-create");
+        assert_eq!(extraction.recipe.name, "Fudgy Brownies");
+        assert_eq!(extraction.recipe.instructions, vec!["Melt the butter.", "Stir in the rest and bake."]);
+    }
+
+    #[test]
+    fn extracts_tasty_co_by_domain() {
+        let extraction = best("https://tasty.co/recipe/taco-soup", include_str!("../fixtures/tasty_co.html"));
+        assert_eq!(extraction.extractor, "tasty.co");
+        assert_eq!(extraction.recipe.name, "Taco Soup");
+        assert_eq!(extraction.recipe.ingredients, vec!["For the soup", "1 lb ground beef", "1 can black beans"]);
+        assert_eq!(extraction.recipe.instructions, vec!["Brown the beef.", "Add everything else and simmer."]);
+    }
+
+    #[test]
+    fn extracts_allrecipes_by_domain() {
+        let extraction = best(
+            "https://www.allrecipes.com/recipe/12578/cinnamon-pie/",
+            include_str!("../fixtures/allrecipes.html"),
+        );
+        assert_eq!(extraction.extractor, "allrecipes");
+        assert_eq!(extraction.recipe.name, "Cinnamon Pie");
+        assert_eq!(extraction.recipe.ingredients, vec!["1 cup white sugar", "2 eggs"]);
+        assert_eq!(extraction.recipe.instructions, vec!["Preheat the oven.", "Bake until set."]);
+    }
+
+    #[test]
+    fn domain_extractor_needs_matching_host() {
+        let html = Html::parse_document(include_str!("../fixtures/allrecipes.html"));
+        assert!(ALLRECIPES.extract("example.com", &html).is_none());
+    }
+
+    #[test]
+    fn generic_scores_below_plugins() {
+        let html = r#"<div class="recipe-content"><h2>Toast</h2>
+            <p class="recipe-ingredient">1 slice bread</p>
+            <p class="recipe-instruction">Toast it.</p></div>"#;
+        let extraction = best("https://example.com/toast", html);
+        assert_eq!(extraction.extractor, "generic");
+        assert!(extraction.confidence < 0.7);
+    }
+}
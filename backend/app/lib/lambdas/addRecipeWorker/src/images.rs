@@ -0,0 +1,72 @@
+use std::path::Path;
+
+use async_trait::async_trait;
+use aws_sdk_s3::operation::put_object::PutObjectError;
+use aws_sdk_s3::{error::SdkError, primitives::ByteStream, Client as s3Client};
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+use uuid::Uuid;
+
+use crate::error::WorkerError;
+
+/**
+ * Where recipe images end up. The worker only needs a public URL back,
+ * so tests can keep images in memory instead of S3.
+ */
+#[async_trait]
+pub trait ImageHost: Send + Sync {
+    // Stores the base64 encoded `image` and returns its URL
+    async fn upload(&self, image: &str) -> Result<String, WorkerError>;
+}
+
+pub struct S3Images {
+    client: s3Client,
+    bucket: String,
+    region: String,
+}
+
+impl S3Images {
+    pub fn new(config: &aws_types::sdk_config::SdkConfig, bucket: &str) -> S3Images {
+        S3Images {
+            client: s3Client::new(config),
+            bucket: bucket.to_string(),
+            region: config.region().map(|r| r.to_string()).unwrap_or_default(),
+        }
+    }
+}
+
+#[async_trait]
+impl ImageHost for S3Images {
+    async fn upload(&self, image: &str) -> Result<String, WorkerError> {
+        let decoded_image = base64::decode(image).map_err(|e| WorkerError::permanent(format!("Image isn't base64: {}", e)))?;
+        let file_name = format!("{}.jpg", Uuid::new_v4());
+        let path = format!("/tmp/{}", file_name);
+        // Lambda's /tmp is occasionally full or busy, a fresh attempt usually gets past it
+        let local = |e: std::io::Error| WorkerError::retryable(format!("Error writing image to {}: {}", path, e));
+        let mut file = File::create(&path).await.map_err(local)?;
+        file.write_all(&decoded_image).await.map_err(local)?;
+        let body = ByteStream::from_path(Path::new(&path))
+            .await
+            .map_err(|e| WorkerError::retryable(format!("Error reading image from {}: {}", path, e)))?;
+        if let Err(e) = self
+            .client
+            .put_object()
+            .bucket(self.bucket.clone())
+            .key(file_name.clone())
+            .body(body)
+            .send()
+            .await {
+            return Err(s3_error(e));
+        }
+        Ok(format!("https://{}.s3.{}.amazonaws.com/{}", self.bucket, self.region, file_name))
+    }
+}
+
+// Throttling and S3's own 5xx are worth retrying, access denied or a missing bucket aren't
+fn s3_error(e: SdkError<PutObjectError>) -> WorkerError {
+    let message = format!("Error uploading image to S3: {}", e);
+    match e.raw_response().map(|r| r.status().as_u16()) {
+        Some(status) if (400..500).contains(&status) && status != 429 && status != 408 => WorkerError::permanent(message),
+        _ => WorkerError::retryable(message),
+    }
+}
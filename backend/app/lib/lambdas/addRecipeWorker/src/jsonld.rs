@@ -0,0 +1,329 @@
+use scraper::{Html, Selector};
+use serde_json::Value;
+
+use crate::text::clean_text;
+use crate::Recipe;
+
+/**
+ * Extracts the schema.org Recipes from the page's JSON-LD blocks, usually
+ * one but roundups list several. Recipes missing the name, ingredients or
+ * instructions are left out; with none left the caller falls back to the LLM.
+ */
+pub fn extract(document: &Html) -> Vec<Recipe> {
+    let mut recipes: Vec<Recipe> = Vec::new();
+    for value in find_recipe_objects(document) {
+        let recipe = recipe_from_value(&value);
+        if !recipe.is_complete() {
+            println!("JSON-LD recipe is incomplete: {:?}", recipe);
+        } else if !recipes.iter().any(|r| r.name == recipe.name) {
+            // Some pages repeat the recipe, e.g. in @graph and an ItemList
+            recipes.push(recipe);
+        }
+    }
+    recipes
+}
+
+pub fn find_recipe_object(document: &Html) -> Option<Value> {
+    find_recipe_objects(document).into_iter().next()
+}
+
+fn find_recipe_objects(document: &Html) -> Vec<Value> {
+    let selector = Selector::parse("script[type=\"application/ld+json\"]").unwrap();
+    let mut found = Vec::new();
+    for script in document.select(&selector) {
+        let raw = script.text().collect::<String>();
+        let value: Value = match serde_json::from_str(raw.trim()) {
+            Ok(v) => v,
+            Err(e) => {
+                println!("Skipping malformed JSON-LD block: {:?}", e);
+                continue;
+            }
+        };
+        find_recipes_in(&value, &mut found);
+    }
+    found
+}
+
+fn find_recipes_in(value: &Value, found: &mut Vec<Value>) {
+    match value {
+        Value::Array(items) => items.iter().for_each(|item| find_recipes_in(item, found)),
+        Value::Object(map) => {
+            if is_type(value, "Recipe") {
+                found.push(value.clone());
+                return;
+            }
+            // Yoast and friends wrap everything in @graph, others hang the
+            // recipe off a WebPage or ItemList.
+            ["@graph", "mainEntity", "itemListElement", "item"]
+                .iter()
+                .filter_map(|key| map.get(*key))
+                .for_each(|v| find_recipes_in(v, found));
+        }
+        _ => {}
+    }
+}
+
+fn is_type(value: &Value, expected: &str) -> bool {
+    let matches = |t: &str| t.rsplit(['/', ':']).next() == Some(expected);
+    match value.get("@type") {
+        Some(Value::String(t)) => matches(t),
+        Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).any(matches),
+        _ => false,
+    }
+}
+
+pub fn recipe_from_value(value: &Value) -> Recipe {
+    let name = value.get("name").and_then(first_text).unwrap_or_default();
+    let summary = value.get("description").and_then(first_text).unwrap_or_default();
+
+    let mut ingredients = Vec::new();
+    // Very old markup used `ingredients` before recipeIngredient existed
+    if let Some(v) = value.get("recipeIngredient").or_else(|| value.get("ingredients")) {
+        collect_strings(v, &mut ingredients);
+    }
+
+    let mut instructions = Vec::new();
+    if let Some(v) = value.get("recipeInstructions") {
+        collect_instructions(v, &mut instructions);
+    }
+
+    Recipe {
+        name,
+        ingredients,
+        instructions,
+        notes: String::new(),
+        summary,
+        recipe_yield: value.get("recipeYield").and_then(recipe_yield),
+        total_time: value
+            .get("totalTime")
+            .and_then(Value::as_str)
+            .and_then(format_duration),
+        image: value.get("image").and_then(image_url),
+        author: value.get("author").and_then(author_name),
+    }
+}
+
+fn first_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(clean_text(s)).filter(|s| !s.is_empty()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Array(items) => items.iter().find_map(first_text),
+        _ => None,
+    }
+}
+
+fn collect_strings(value: &Value, out: &mut Vec<String>) {
+    match value {
+        Value::String(s) => {
+            let text = clean_text(s);
+            if !text.is_empty() {
+                out.push(text);
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|item| collect_strings(item, out)),
+        _ => {}
+    }
+}
+
+// Instructions come as a single string, a list of strings, a list of
+// HowToStep objects or HowToSections holding steps. Section names are kept as
+// their own item, the same way the LLM prompt asks for it.
+fn collect_instructions(value: &Value, out: &mut Vec<String>) {
+    match value {
+        Value::String(s) => {
+            for line in s.split('\n') {
+                let text = clean_text(line);
+                if !text.is_empty() {
+                    out.push(text);
+                }
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|item| collect_instructions(item, out)),
+        Value::Object(_) => {
+            let name = value.get("name").and_then(first_text);
+            let steps = value.get("itemListElement");
+            if is_type(value, "HowToSection") || (steps.is_some() && value.get("text").is_none()) {
+                if let Some(name) = name {
+                    out.push(name);
+                }
+                if let Some(steps) = steps {
+                    collect_instructions(steps, out);
+                }
+            } else if let Some(text) = value.get("text").and_then(first_text) {
+                out.push(text);
+            } else if let Some(name) = name {
+                out.push(name);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn recipe_yield(value: &Value) -> Option<String> {
+    match value {
+        // Sites often list ["4", "4 servings"]; the longer one reads better
+        Value::Array(items) => items
+            .iter()
+            .filter_map(first_text)
+            .max_by_key(|s| s.len()),
+        other => first_text(other),
+    }
+}
+
+fn image_url(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.trim().to_string()).filter(|s| !s.is_empty()),
+        Value::Array(items) => items.iter().find_map(image_url),
+        Value::Object(map) => map
+            .get("url")
+            .or_else(|| map.get("contentUrl"))
+            .and_then(image_url),
+        _ => None,
+    }
+}
+
+fn author_name(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(clean_text(s)).filter(|s| !s.is_empty()),
+        Value::Object(map) => map.get("name").and_then(first_text),
+        Value::Array(items) => {
+            let names: Vec<String> = items.iter().filter_map(author_name).collect();
+            Some(names.join(", ")).filter(|s| !s.is_empty())
+        }
+        _ => None,
+    }
+}
+
+// Turns an ISO 8601 duration such as PT1H30M into "1 hr 30 mins".
+pub fn format_duration(iso: &str) -> Option<String> {
+    let iso = iso.trim();
+    let rest = iso.strip_prefix('P').or_else(|| iso.strip_prefix('p'))?;
+    let mut minutes: u64 = 0;
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in rest.chars() {
+        match c.to_ascii_uppercase() {
+            'T' => in_time = true,
+            '0'..='9' | '.' => number.push(c),
+            unit => {
+                let n: f64 = number.parse().ok()?;
+                number.clear();
+                minutes += match (unit, in_time) {
+                    ('D', false) => n * 24.0 * 60.0,
+                    ('H', true) => n * 60.0,
+                    ('M', true) => n,
+                    ('S', true) => n / 60.0,
+                    _ => return None,
+                }
+                .round() as u64;
+            }
+        }
+    }
+    if minutes == 0 {
+        return None;
+    }
+
+    let (hours, mins) = (minutes / 60, minutes % 60);
+    let plural = |n: u64, word: &str| format!("{} {}{}", n, word, if n == 1 { "" } else { "s" });
+    Some(match (hours, mins) {
+        (0, m) => plural(m, "min"),
+        (h, 0) => plural(h, "hr"),
+        (h, m) => format!("{} {}", plural(h, "hr"), plural(m, "min")),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_graph_with_sections() {
+        let document = Html::parse_document(include_str!("../fixtures/jsonld_graph.html"));
+        let recipe = extract(&document).remove(0);
+        assert_eq!(recipe.name, "Garlic Bacon Shrimp Alfredo");
+        assert_eq!(recipe.ingredients[0], "1 1/2 cups heavy cream");
+        assert_eq!(recipe.ingredients[2], "Salt & pepper, to taste");
+        assert_eq!(
+            recipe.instructions,
+            vec![
+                "For the sauce",
+                "Cook the bacon until crisp.",
+                "Add the garlic and cream.",
+                "To finish",
+                "Toss with the pasta and shrimp.",
+            ]
+        );
+        assert_eq!(recipe.recipe_yield.as_deref(), Some("4 servings"));
+        assert_eq!(recipe.total_time.as_deref(), Some("1 hr 5 mins"));
+        assert_eq!(recipe.image.as_deref(), Some("https://example.com/alfredo-16x9.jpg"));
+        assert_eq!(recipe.author.as_deref(), Some("Jane Cook"));
+        assert_eq!(recipe.summary, "A creamy weeknight pasta.");
+    }
+
+    #[test]
+    fn parses_plain_string_instructions() {
+        let document = Html::parse_document(include_str!("../fixtures/jsonld_simple.html"));
+        let recipe = extract(&document).remove(0);
+        assert_eq!(recipe.name, "Cinnamon Pie");
+        assert_eq!(recipe.ingredients.len(), 3);
+        assert_eq!(recipe.instructions, vec!["Mix everything.", "Bake for 40 minutes."]);
+        assert_eq!(recipe.recipe_yield.as_deref(), Some("8"));
+        assert_eq!(recipe.author.as_deref(), Some("Allrecipes Member"));
+    }
+
+    #[test]
+    fn incomplete_recipe_falls_back() {
+        let document = Html::parse_document(
+            r#"<script type="application/ld+json">
+            {"@context": "https://schema.org", "@type": "Recipe", "name": "Soup", "recipeIngredient": ["water"]}
+            </script>"#,
+        );
+        assert!(find_recipe_object(&document).is_some());
+        assert!(extract(&document).is_empty());
+    }
+
+    #[test]
+    fn parses_roundups() {
+        let document = Html::parse_document(include_str!("../fixtures/jsonld_roundup.html"));
+        let recipes = extract(&document);
+        let names: Vec<&str> = recipes.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["Taco Soup", "Chicken Noodle Soup"]);
+        assert_eq!(recipes[1].author.as_deref(), Some("Sam Soup"));
+    }
+
+    #[test]
+    fn reads_our_own_export() {
+        let mut soup = recipe_core::Recipe::new("https://www.seriouseats.com/taco-soup", "Taco Soup");
+        soup.ingredients = recipe_ingredients::parse_all(&[String::from("1 lb ground beef"), String::from("2 cups beef broth")]);
+        soup.instructions = vec![String::from("Brown the beef."), String::from("Add the broth and simmer.")];
+        soup.summary = String::from("A quick weeknight soup.");
+        soup.recipe_yield = Some(String::from("4 servings"));
+        soup.total_time = Some(String::from("35 mins"));
+        soup.author = Some(String::from("Grandma"));
+        let exported = match recipe_export::export(recipe_export::ExportFormat::JsonLd, &[soup.clone(), soup.clone()]).unwrap() {
+            recipe_export::Document::Text(json) => json,
+            recipe_export::Document::Zip(_) => panic!("JSON-LD is one document"),
+        };
+        let document = Html::parse_document(&format!(r#"<script type="application/ld+json">{}</script>"#, exported));
+
+        // The same recipe twice in a @graph is read once
+        let recipes = extract(&document);
+        assert_eq!(recipes.len(), 1);
+        assert_eq!(recipes[0].name, soup.name);
+        assert_eq!(recipes[0].summary, soup.summary);
+        assert_eq!(recipes[0].ingredients, soup.raw_ingredients());
+        assert_eq!(recipes[0].instructions, soup.instructions);
+        assert_eq!(recipes[0].recipe_yield, soup.recipe_yield);
+        assert_eq!(recipes[0].total_time, soup.total_time);
+        assert_eq!(recipes[0].author, soup.author);
+    }
+
+    #[test]
+    fn formats_durations() {
+        assert_eq!(format_duration("PT45M").as_deref(), Some("45 mins"));
+        assert_eq!(format_duration("PT1H").as_deref(), Some("1 hr"));
+        assert_eq!(format_duration("P1DT2H").as_deref(), Some("26 hrs"));
+        assert_eq!(format_duration("PT0S"), None);
+        assert_eq!(format_duration("45 minutes"), None);
+    }
+}
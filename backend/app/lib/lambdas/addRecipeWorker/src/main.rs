@@ -6,16 +6,10 @@ use futures_util::future::join_all;
 use reqwest::get;
 use select::document::Document;
 use select::predicate::Name;
-use aws_sdk_dynamodb::types::AttributeValue;
-use aws_sdk_dynamodb::Client as DbClient;
 use aws_lambda_events::event::sns;
 use aws_config;
 use uuid::Uuid;
 use std::env;
-use openai_api_rs::v1::api;
-use openai_api_rs::v1::chat_completion::{self, ChatCompletionRequest};
-use openai_api_rs::v1::image::ImageGenerationRequest;
-use openai_api_rs::v1::error::APIError;
 use scraper::{Html, Selector};
 use lambda_http::{Response, Body, Error, Request};
 use lambda_runtime::{service_fn, LambdaEvent};
@@ -30,35 +24,37 @@ use base64;
 use tokio::io::AsyncWriteExt;
 use dotenv::dotenv;
 use std::any::Any;
-use aws_sdk_s3::Client as s3Client;
-use aws_sdk_s3::operation::put_object::{PutObjectError, PutObjectOutput};
-use aws_sdk_s3::{error::SdkError, primitives::ByteStream};
-use aws_sdk_s3::types::ObjectCannedAcl;
 use aws_types;
 use std::str::FromStr;
 use aws_sdk_sns::Client as SnsClient;
-use aws_sdk_sqs::Client as SqsClient;
-
-
-const PROMPT: &str = "Parse the recipe from the web page content and format it in JSON with the following structure: {name: <str>, ingredients: [], instructions: [], notes: <str>, summary: <str>}. If the words don't have spaces, add spaces so it's readable. Ensure the ingredients and instructions are a list of strings, if they have sections, just add the header as an item in the list.";
-
-#[derive(Debug, Serialize)]
-pub struct SuccessResponse {
-    pub body: String,
-}
-
-#[derive(Debug, Serialize)]
-pub struct FailureResponse {
-    pub body: String,
-}
-
-type WorkerResponse = Result<SuccessResponse, FailureResponse>;
-
-#[derive(Serialize)]
-pub struct SqsResponse {
-    pub status_code: u16,
-    pub body: String
-}
+use recipe_core::{aliases, derived_key, recipe_key, DeadLetter, DeadLetterStore, DynamoStore, EventBus, Job, JobStatus, JobStore, RecipeStore, StoreEventBus};
+use recipe_llm::{LlmConfig, LlmProvider};
+use error::WorkerError;
+use images::{ImageHost, S3Images};
+use retry::{retry, RetryPolicy};
+
+mod canonical;
+mod error;
+mod extractors;
+mod images;
+mod jsonld;
+mod microdata;
+mod readability;
+mod reply;
+mod retry;
+mod segment;
+mod text;
+
+// Site extractors below this score aren't trusted over the LLM
+const MIN_EXTRACTOR_CONFIDENCE: f32 = 0.7;
+
+// Roughly how much page text we send GPT-4 alongside the prompt
+const TOKEN_BUDGET: usize = 3000;
+
+const PROMPT: &str = "Parse every recipe from the content and format them in JSON with the following structure: {recipes: [{name: <str>, ingredients: [], instructions: [], notes: <str>, summary: <str>}]}. Most content has one recipe, but roundups and cookbook pages can have several. Ensure the ingredients and instructions are a list of strings, if they have sections, just add the header as an item in the list.";
+
+// The uuids of the saved recipes, or why we couldn't save them
+type WorkerResponse = Result<Vec<String>, WorkerError>;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Recipe {
@@ -67,6 +63,31 @@ pub struct Recipe {
     pub instructions: Vec<String>,
     pub notes: String,
     pub summary: String,
+    pub recipe_yield: Option<String>,
+    pub total_time: Option<String>,
+    pub image: Option<String>,
+    pub author: Option<String>,
+}
+
+impl Recipe {
+    // The minimum we need before we can skip the LLM
+    pub fn is_complete(&self) -> bool {
+        !self.name.trim().is_empty() && !self.ingredients.is_empty() && !self.instructions.is_empty()
+    }
+}
+
+#[derive(Debug)]
+pub enum WebContents {
+    // Usually one, roundups can have several
+    Structured(Vec<Recipe>),
+    Raw(String),
+}
+
+// What we read from a recipe page, and where the page says it really lives
+#[derive(Debug)]
+pub struct WebPage {
+    pub contents: WebContents,
+    pub canonical_url: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
@@ -82,30 +103,9 @@ pub struct URLRequest {
     pub content_type: ContentType,
     pub credit: Option<String>,
     pub uuid: Option<String>,
-    pub sqs_url: String
+    pub job_id: String
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct TesseractRequest {
-    pub url: String
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct TesseractResponse {
-    pub contents: String
-}
-
-// Implement Display for the Failure response so that we can then implement Error.
-impl std::fmt::Display for FailureResponse {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.body)
-    }
-}
-
-// Implement Error for the FailureResponse so that we can `?` (try) the Response
-// returned by `lambda_runtime::run(func).await` in `fn main`.
-impl std::error::Error for FailureResponse {}
-
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     let func = service_fn(handler);
@@ -160,46 +160,31 @@ async fn convert_heic_to_png(url: &str) -> String {
     String::from("new url")
 }
 
-async fn get_image_contents(image_url: &str) -> Result<SuccessResponse, FailureResponse> {
-    let uri = "https://ucowpmolm0.execute-api.us-east-1.amazonaws.com/prod/tesseract";
+// The text of a photo of a recipe, read by the vision model
+async fn get_image_contents(llm: &dyn LlmProvider, image_url: &str) -> Result<String, WorkerError> {
     let mut url = image_url.to_string();
     if is_heic_url(&url) {
         url = convert_heic_to_png(&url).await.to_string();
     }
-    let request = TesseractRequest {
-        url: url,
-    };
-    let client = reqwest::Client::new();
-    let response = client
-        .post(uri)
-        .json(&request)
-        .send()
-        .await.unwrap();
-    
-    // Parse the response
-    let output: TesseractResponse = match response.json().await {
-        Ok(t) => t,
-        Err(err) => {
-            return Err(FailureResponse {
-                body: err.to_string(),
-            });
-        }
-    };
-    println!("{:?}", output);
-    Ok(SuccessResponse {
-        body: output.contents,
-    })
+    let contents = llm
+        .transcribe(&url)
+        .await
+        .map_err(|e| WorkerError::from_llm(&e, "Error reading image"))?;
+    println!("{:?}", contents);
+    Ok(contents)
 }
 
-async fn get_web_contents(url: &str) -> Result<SuccessResponse, FailureResponse> {
+async fn get_web_contents(url: &str) -> Result<WebPage, WorkerError> {
     // Send a GET request to the URL
     let response = match get(url).await {
-        Ok(r) => r,
+        Ok(r) if r.status().is_success() => r,
+        Ok(r) => {
+            println!("Error reading URL: {:?} {:?}", url, r.status());
+            return Err(WorkerError::from_source_status(r.status(), "Error reading URL"));
+        }
         Err(e) => {
             println!("Error reading URL: {:?} {:?}", url, e);
-            return Err(FailureResponse {
-                body: format!("Error reading URL: {}", e)
-            });
+            return Err(WorkerError::from_request(&e, "Error reading URL"));
         }
     };
 
@@ -208,127 +193,118 @@ async fn get_web_contents(url: &str) -> Result<SuccessResponse, FailureResponse>
         Ok(b) => b,
         Err(e) => {
             println!("Error reading URL contents: {:?}", e);
-            return Err(FailureResponse {
-                body: format!("Error reading URL contents: {}", e)
-            });
+            return Err(WorkerError::retryable(format!("Error reading URL contents: {}", e)));
         }
     };
     let document = Html::parse_document(&body);
 
-    // Use CSS selectors to identify the recipe elements
-    let recipe_title_selector = Selector::parse("h1").unwrap();
-    let ingredient_selector = Selector::parse(".recipe-ingredient").unwrap();
-    let instruction_selector = Selector::parse(".recipe-instruction").unwrap();
-    let recipe_page = Selector::parse(".recipe-content").unwrap();
-    let mariyum_recipe_content = Selector::parse(".wprm-recipe-container").unwrap();
-    let meta_data_selector = Selector::parse("script[type=\"application/ld+json\"]").expect("Failed to parse selector");
+    Ok(WebPage {
+        contents: read_document(url, &document),
+        canonical_url: canonical::declared_url(&document, url),
+    })
+}
+
+fn read_document(url: &str, document: &Html) -> WebContents {
+    // Prefer the page's own schema.org data, it's exact and free
+    let recipes = jsonld::extract(document);
+    if !recipes.is_empty() {
+        println!("Found {} JSON-LD recipes: {:?}", recipes.len(), recipes.iter().map(|r| &r.name).collect::<Vec<_>>());
+        return WebContents::Structured(recipes);
+    }
+
+    // Older blogs mark the recipe up inline instead
+    if let Some(recipe) = microdata::extract(document, url) {
+        return WebContents::Structured(vec![recipe]);
+    }
 
+    // Then recipe plugins and sites we know the markup of
+    if let Some(extraction) = extractors::Registry::default().best(url, document) {
+        if extraction.confidence >= MIN_EXTRACTOR_CONFIDENCE && extraction.recipe.is_complete() {
+            println!("Using {} extractor: {}", extraction.extractor, extraction.recipe.name);
+            return WebContents::Structured(vec![extraction.recipe]);
+        }
+    }
 
-    // Extract the recipe title
+    // Otherwise the LLM gets the page's main content as clean text
+    let recipe_title_selector = Selector::parse("h1").unwrap();
     let recipe_title = document
         .select(&recipe_title_selector)
         .next()
-        .map(|element| element.text().collect::<String>())
-        .unwrap_or_else(|| "Recipe title not found".to_owned());
+        .map(text::element_text)
+        .unwrap_or_default();
 
     println!("Recipe Title: {}", recipe_title);
 
-    println!("Recipe Contents:");
-    let mut recipe_content_list = Vec::new();
-    // for recipe_content in document.select(&recipe_page) {
-    //     println!("{}", recipe_content.text().collect::<String>());
-    //     recipe_content_list.push(recipe_content.text().collect::<String>());
-    // }
-
-    // for recipe_content in document.select(&mariyum_recipe_content) {
-    //     println!("{}", recipe_content.text().collect::<String>());
-    //     recipe_content_list.push(recipe_content.text().collect::<String>());
-    // }
-    println!("Recipe Metadata: ");
-    for recipe_content in document.select(&meta_data_selector) {
-        recipe_content_list.push(recipe_content.text().collect::<String>());
+    let main_content = readability::main_content(document);
+    let mut sections = Vec::new();
+    if !recipe_title.is_empty() && !main_content.contains(&recipe_title) {
+        sections.push(recipe_title);
+    }
+    // Incomplete structured data is still better than nothing
+    if let Some(value) = jsonld::find_recipe_object(document) {
+        let partial = jsonld::recipe_from_value(&value);
+        if !partial.ingredients.is_empty() {
+            sections.push(format!("Ingredients:\n{}", partial.ingredients.join("\n")));
+        }
+        if !partial.instructions.is_empty() {
+            sections.push(format!("Instructions:\n{}", partial.instructions.join("\n")));
+        }
     }
+    sections.push(main_content);
 
-    let mut recipe = format!("{} {}", recipe_title, recipe_content_list.join(""));
-    recipe = recipe.replace('\n', " ");
-    let words: Vec<&str> = recipe.split_whitespace().take(800).collect();
+    let recipe = readability::truncate_to_tokens(&sections.join("\n\n"), TOKEN_BUDGET);
+    println!("Recipe Contents: {}", recipe);
 
-    return Ok(SuccessResponse {
-        body: words.join("")
-    });
+    WebContents::Raw(recipe)
 }
 
 async fn get_cloud_convert_api_key() -> Option<String> {
     env::var("CLOUD_CONVERT_API_KEY").ok()
 }
 
-async fn get_api_key() -> Option<String> {
-    env::var("OPEN_AI_API_KEY").ok()
-}
-
 async fn get_table_name() -> Option<String> {
     env::var("TABLE_NAME").ok()
 }
 
-async fn get_bucket_name() -> Option<String> {
-    env::var("BUCKET_NAME").ok()
+async fn get_alias_table_name() -> Option<String> {
+    env::var("ALIAS_TABLE_NAME").ok()
 }
 
-async fn generate_recipe_image(summary: &String, title: &String) -> Result<String, FailureResponse> {
-    let open_ai_api_key = get_api_key().await;
-    if let Some(api_key) = open_ai_api_key {
-        let client = api::Client::new(api_key);
-        let req = ImageGenerationRequest {
-            prompt: summary.clone(),
-            n: None,
-            size: None,
-            response_format: None,
-            user: None,
-        };
-        println!("Image gen request: {:?}", req);
-        let mut result = match client.image_generation(req).await {
-            Ok(r) => r,
-            Err(err) => {
-                println!("Error with OpenAI, trying with Title: {:?}", err);
-                let req = ImageGenerationRequest {
-                    prompt: format!("A realistic photo of {}", title.clone()),
-                    n: None,
-                    size: None,
-                    response_format: None,
-                    user: None,
-                };
-                match client.image_generation(req).await {
-                    Ok(r) => r,
-                    Err(e) => {
-                        println!("Error with OpenAI: {:?}", e);
-                        return Err(FailureResponse {
-                            body: format!("Error getting response from OpenAI: {:?}", e),
-                        });
-                    }
-                }
-            }
-        };        
-        println!("Result: {:?}", result);
+async fn get_dead_letter_table_name() -> Option<String> {
+    env::var("DEAD_LETTER_TABLE_NAME").ok()
+}
 
-        let url = result.data[0].url.clone();
+async fn get_job_table_name() -> Option<String> {
+    env::var("JOB_TABLE_NAME").ok()
+}
 
-        let response = reqwest::get(url)
-            .await
-            .expect("Failed to send request");
+async fn get_bucket_name() -> Option<String> {
+    env::var("BUCKET_NAME").ok()
+}
 
-        // Read the response body as bytes
-        let image_bytes = response
-            .bytes()
-            .await
-            .expect("Failed to read response body");
+async fn download_image(image_url: &str) -> Result<String, WorkerError> {
+    let response = match reqwest::get(image_url).await {
+        Ok(r) if r.status().is_success() => r,
+        Ok(r) => return Err(WorkerError::from_source_status(r.status(), "Error downloading image")),
+        Err(e) => return Err(WorkerError::from_request(&e, "Error downloading image")),
+    };
+    match response.bytes().await {
+        Ok(bytes) => Ok(base64::encode(&bytes)),
+        Err(e) => Err(WorkerError::retryable(format!("Error reading image: {}", e)))
+    }
+}
 
-        // Encode the image bytes as base64
-        let base64_encoded = base64::encode(&image_bytes);
-        return Ok(base64_encoded);
+async fn generate_recipe_image(llm: &dyn LlmProvider, summary: &str, title: &str) -> Result<String, WorkerError> {
+    println!("Image gen request: {:?}", summary);
+    match llm.generate_image(summary).await {
+        Ok(image) => Ok(image),
+        Err(err) => {
+            println!("Error generating image, trying with Title: {:?}", err);
+            llm.generate_image(&format!("A realistic photo of {}", title))
+                .await
+                .map_err(|e| WorkerError::from_llm(&e, "Error generating image"))
+        }
     }
-    return Err(FailureResponse {
-        body: String::from("API Key Not Set")
-    });
 }
 
 async fn upload_to_arweave(image: String) -> Result<String, Error> {
@@ -368,115 +344,49 @@ async fn upload_to_arweave(image: String) -> Result<String, Error> {
     Ok(image_url)
 }
 
-async fn upload_to_s3(image: String, client: &s3Client, region: String) -> Result<String, Error> {
-    let decoded_image = base64::decode(image).unwrap();
-    let file_name = format!("{}.jpg", generate_uuid().await);
-    let mut file = File::create(&format!("/tmp/{}", file_name).to_string()).await.unwrap(); // Specify the desired file path and extension here
-    file.write_all(&decoded_image).await.unwrap();
-    let bucket_name = get_bucket_name().await.unwrap();
-    let body = ByteStream::from_path(Path::new(&format!("/tmp/{}", file_name).to_string())).await;
-    client
-        .put_object()
-        .bucket(bucket_name.clone())
-        .key(file_name.clone())
-        .body(body.unwrap())
-        .send()
-        .await?;
-    Ok(format!("https://{}.s3.{}.amazonaws.com/{}", bucket_name, region, file_name))
-
-}
-
-async fn parse_recipe(contents: String) -> Result<Recipe, FailureResponse> {
-    let open_ai_api_key = get_api_key().await;
-    if let Some(api_key) = open_ai_api_key {
-        let client = api::Client::new(api_key);
-        let req = ChatCompletionRequest {
-            model: chat_completion::GPT4.to_string(),
-            messages: vec![chat_completion::ChatCompletionMessage {
-                role: chat_completion::MessageRole::user,
-                content: format!("{} {}", PROMPT.to_string(), contents),
-                name: None,
-                function_call: None,
-            }],
-            functions: None,
-            function_call: None,
-            temperature: None,
-            top_p: None,
-            n: None,
-            stream: None,
-            stop: None,
-            max_tokens: None,
-            presence_penalty: None,
-            frequency_penalty: None,
-            logit_bias: None,
-            user: None,
-        };
-        println!("Chat Request: {:?}", req);
-        let result = match client.chat_completion(req).await {
-            Ok(r) => r,
-            Err(e) => {
-                println!("Error with OpenAI: {:?}", e);
-                return Err(FailureResponse {
-                    body: format!("Error getting response from OpenAI: {:?}", e)
-                });
-            }
-        };
-        println!("{:?}", result.choices[0].message.content);
-        let generated_content = match &result.choices[0].message.content {
-            Some(c) => c,
-            None => {
-                println!("Could not get message content");
-                return Err(FailureResponse {
-                    body: format!("Could not get message content")
-                })
-            },
-        };
-        let content = match extract_json(&generated_content) {
-            Some(s) => s,
-            None => {
-                println!("Error parsing recipe conents!");
-                return Err(FailureResponse {
-                    body: format!("Error parsing recipe contents!")
-                });            
-            },
-        };
-        let recipe: Recipe = match serde_json::from_str(&content) {
-            Ok(r) => r,
-            Err(e) => {
-                println!("Error parsing JSON {:?}", e);
-                return Err(FailureResponse {
-                    body: format!("Error parsing JSON {:?}", e)
-                });
-            }
-        };
-        return Ok(recipe);
+/**
+ * Asks the model for the recipes as JSON in our schema. If any of them
+ * aren't usable we ask once more, telling it what was wrong, and keep
+ * what's usable from that; a source that still doesn't give us a recipe
+ * probably doesn't have one.
+ */
+async fn parse_recipes(llm: &dyn LlmProvider, contents: &str) -> Result<Vec<Recipe>, WorkerError> {
+    let prompt = format!("{} {}", PROMPT, contents);
+    let reply = ask_for_recipes(llm, &prompt).await?;
+    let first = reply::read_recipes(&reply);
+    if first.problems.is_empty() {
+        return Ok(first.recipes);
     }
-    return Err(FailureResponse {
-        body: String::from("API Key Not Set")
-    });
-}
-
-async fn join_strings(strings: Vec<String>) -> String {
-    strings
-        .iter()
-        .map(|string| {
-            let escaped_string = string
-                .replace("\\", "\\\\") // Escape backslashes
-                .replace(",", "\\,") // Escape commas
-                .replace(";", "\\;"); // Escape semicolons
-            escaped_string
-        })
-        .collect::<Vec<String>>()
-        .join(";")
+    println!("Unusable recipes, asking again: {:?}", first.problems);
+    let repaired = reply::read_recipes(&ask_for_recipes(llm, &reply::repair_prompt(&prompt, &reply, &first)).await?);
+    // Keep what was fine the first time, models sometimes send those back too
+    let mut recipes = first.recipes;
+    for recipe in repaired.recipes {
+        if !recipes.iter().any(|r| r.name.eq_ignore_ascii_case(&recipe.name)) {
+            recipes.push(recipe);
+        }
+    }
+    if recipes.is_empty() {
+        println!("Error parsing recipe contents: {:?}", repaired.problems);
+        return Err(WorkerError::permanent(format!("Error parsing recipe contents: {}", repaired.problems.join("; "))));
+    }
+    if !repaired.problems.is_empty() {
+        println!("Skipping recipes we couldn't use: {:?}", repaired.problems);
+    }
+    Ok(recipes)
 }
 
-async fn split_string(string: String) -> Vec<String> {
-    let escaped_strings: Vec<String> = string
-        .split(";")
-        .map(|substring| substring.replace("\\;", ";").replace("\\,", ",").replace("\\\\", "\\"))
-        .collect();
-
-    escaped_strings
+async fn ask_for_recipes(llm: &dyn LlmProvider, prompt: &str) -> Result<String, WorkerError> {
+    match llm.chat_json(prompt, "recipes", &reply::recipes_schema()).await {
+        Ok(reply) => {
+            println!("{:?}", reply);
+            Ok(reply)
+        }
+        Err(e) => {
+            println!("Error with LLM: {:?}", e);
+            Err(WorkerError::from_llm(&e, "Error getting response from LLM"))
+        }
+    }
 }
 
 async fn generate_uuid() -> String {
@@ -485,87 +395,87 @@ async fn generate_uuid() -> String {
 
 
 /**
- * Data format:
- * primary_key: uuid
- * name: string
- * ingredients: []
- * instructions: []
- * notes: string
+ * Builds the stored recipe from what we scraped or parsed. Ingredients are
+ * parsed, nutrition estimated and the recipe fingerprinted for duplicate
+ * checks here so readers don't have to.
  */
-pub async fn add_to_db(client: &DbClient, recipe: Recipe, url: &str, image_url: &str, table: &String, credit: Option<String>) -> Result<String, Error> {
-    let uuid = AttributeValue::S(url.to_string());
-    let credit = if let Some(c) = credit {
-        AttributeValue::S(c)
-    } else {
-        AttributeValue::S(url.to_string())
-    };
-    let name = AttributeValue::S(recipe.name);
-    let ingredients = AttributeValue::S(join_strings(recipe.ingredients).await);
-    let instructions = AttributeValue::S(join_strings(recipe.instructions).await);
-    let notes = AttributeValue::S(recipe.notes);
-    let summary = AttributeValue::S(recipe.summary);
-    let image = AttributeValue::S(image_url.to_string());
+fn to_stored_recipe(recipe: Recipe, url: &str, image_url: &str, credit: Option<String>) -> recipe_core::Recipe {
+    let mut stored = recipe_core::Recipe::new(url, &recipe.name);
+    stored.ingredients = recipe_ingredients::parse_all(&recipe.ingredients);
+    stored.instructions = recipe.instructions;
+    stored.notes = recipe.notes;
+    stored.summary = recipe.summary;
+    stored.image = image_url.to_string();
+    stored.credit = Some(credit.unwrap_or_else(|| url.to_string()));
+    stored.recipe_yield = recipe.recipe_yield;
+    stored.total_time = recipe.total_time;
+    stored.author = recipe.author;
+    stored.estimate_nutrition();
+    stored.compute_fingerprint();
+    stored
+}
 
-    let request = client
-        .put_item()
-        .table_name(table)
-        .item("uuid", uuid)
-        .item("credit", credit)
-        .item("name", name)
-        .item("ingredients", ingredients)
-        .item("instructions", instructions)
-        .item("notes", notes)
-        .item("summary", summary)
-        .item("image", image);
+// Roundups say who each recipe is by, which beats crediting the page that collected them
+fn recipe_credit(recipe: &Recipe, credit: Option<String>, several: bool) -> Option<String> {
+    match credit {
+        Some(c) => Some(c),
+        None if several => recipe.author.clone(),
+        None => None,
+    }
+}
 
-    println!("Executing request [{request:?}] to add item...");
+/**
+ * Saves a recipe built by `to_stored_recipe` and returns its uuid. `url`,
+ * as we were sent it, is pointed at the recipe; only the first of several
+ * recipes from a source gets it.
+ */
+pub async fn add_to_db(store: &dyn RecipeStore, recipe: &recipe_core::Recipe, url: Option<&str>) -> Result<String, Error> {
+    println!("Adding recipe {} to the store...", recipe.uuid);
 
-    request.send().await?;
+    store.put(recipe).await?;
+    if let Some(url) = url {
+        save_aliases(store, url, &recipe.uuid).await;
+    }
 
-    Ok(String::from("Recipe Added!"))
+    Ok(recipe.uuid.clone())
 }
-fn extract_json(json_string: &str) -> Option<String> {
-    // Find the positions of the first opening and closing curly braces
-    let start_pos = json_string.find('{');
-    let end_pos = json_string.find('}');
 
-    if let (Some(start), Some(end)) = (start_pos, end_pos) {
-        // Extract the content between the curly braces, including the braces themselves
-        let json_body = &json_string[start..=end];
-        return Some(json_body.trim().to_string());
+// Every recipe saved from the source keyed `uuid`
+async fn saved_keys(store: &dyn RecipeStore, uuid: &str) -> Vec<String> {
+    let mut keys = vec![uuid.to_string()];
+    while let Ok(Some(_)) = store.get(&derived_key(uuid, keys.len())).await {
+        keys.push(derived_key(uuid, keys.len()));
     }
-
-    // If no match was found, return None
-    None
+    keys
 }
 
-async fn send_message(sqs_response: SqsResponse, body: &str) {
-    let request: URLRequest = match serde_json::from_str(&body) {
-        Ok(r) => r,
-        Err(e) => {
-            println!("Error getting request");
-            return
+/**
+ * Points the other ways of writing `url` at the recipe. The recipe is saved
+ * either way, so a failure only costs us a duplicate later and is logged.
+ */
+async fn save_aliases(store: &dyn RecipeStore, url: &str, uuid: &str) {
+    for alias in aliases(url, uuid) {
+        if let Err(e) = store.add_alias(&alias, uuid).await {
+            println!("Error saving alias {} for {}: {:?}", alias, uuid, e);
         }
-    };
-
-    let sqs_url = request.sqs_url;
-
-    let config: aws_types::sdk_config::SdkConfig = aws_config::load_from_env().await;
+    }
+}
 
-    let sqs_client = SqsClient::new(&config);
+/**
+ * Moves the job on and tells anyone watching. Clients only use this to show
+ * progress, so if it can't be published we log it and carry on with the recipe.
+ */
+async fn track(events: &dyn EventBus, job: &mut Job, status: JobStatus) {
+    job.advance(status);
+    publish_job(events, job).await;
+}
 
-    let message = serde_json::to_string(&sqs_response).unwrap();
-    println!("Message: {:?}", message);
-    match sqs_client
-        .send_message()
-        .queue_url(sqs_url)
-        .message_body(message)
-        .send()
-        .await {
-            Ok(s) => println!("SNS Publish Success! {:?}", s),
-            Err(e) => println!("SNS Pulish Failed! {:?}", e)
-        };
+async fn publish_job(events: &dyn EventBus, job: &Job) {
+    if let Err(e) = events.publish(job).await {
+        println!("Error publishing job {}: {:?}", job.id, e);
+    }
 }
+
 /**
  * We need a new function that can take in different types of raw contents
  * URL of recipe
@@ -574,113 +484,219 @@ async fn send_message(sqs_response: SqsResponse, body: &str) {
  * Then we move to the worker where it takes in the contents and goes from there
  */
 
-async fn worker(body: &str) -> WorkerResponse {
-    let config: aws_types::sdk_config::SdkConfig = aws_config::load_from_env().await;
-    let url: URLRequest = match serde_json::from_str(&body) {
-        Ok(u) => u,
-        Err(e) => {
-            println!("Error matching URL: {:?}", e);
-            return Err(
-                FailureResponse {
-                    body: format!("Error matching URL: {:?}", e)
-                }
-            );
-        }
-    };
+async fn worker(store: &dyn RecipeStore, events: &dyn EventBus, llm: &dyn LlmProvider, images: &dyn ImageHost, job: &mut Job, url: URLRequest) -> WorkerResponse {
     let url_value = url.url;
     println!("URL: {}", url_value);
+    // The model's API rate limits us and S3 has bad moments, so the slow steps get a few tries
+    let policy = RetryPolicy::default();
 
     // 1. Determine content type:
-    let contents = match url.content_type {
-        ContentType::URL => get_web_contents(&url_value).await?.body,
-        ContentType::IMAGE => get_image_contents(&url_value).await?.body,
-        ContentType::BULK => url_value.clone(),
+    track(events, job, JobStatus::Fetching).await;
+    let (contents, canonical_url) = match url.content_type {
+        ContentType::URL => {
+            let page = get_web_contents(&url_value).await?;
+            (page.contents, page.canonical_url)
+        },
+        ContentType::IMAGE => (WebContents::Raw(get_image_contents(llm, &url_value).await?), None),
+        ContentType::BULK => (WebContents::Raw(url_value.clone()), None),
     };
 
-    // 2. Get the uuid, if recipe URL, use the URL
-    let uuid = match url.content_type {
-        ContentType::URL => url_value,
-        ContentType::IMAGE => url.uuid.unwrap(),
-        ContentType::BULK => url.uuid.unwrap(),
+    // 2. Get the uuid, if recipe URL, use the page's canonical URL
+    let is_url = url.content_type == ContentType::URL;
+    let is_bulk = url.content_type == ContentType::BULK;
+    let uuid = match (url.content_type, url.uuid) {
+        (ContentType::URL, _) => recipe_key(&url_value, canonical_url.as_deref()),
+        (_, Some(uuid)) => uuid,
+        (_, None) => {
+            return Err(WorkerError::bad_input("No uuid for image or bulk recipe"));
+        }
     };
 
-    // 3. Parse recipe from web contents
-    let recipe = match parse_recipe(contents).await {
-        Ok(r) => r,
-        Err(e) => {
-            return Err(
-                FailureResponse {
-                    body: format!("Error parsing recipe: {:?}", e)
-                }
-            );
-        },
+    // We may already have it under another URL, e.g. the AMP version of the page
+    if is_url {
+        if let Ok(Some(_)) = store.get(&uuid).await {
+            println!("Recipe already saved as {}", uuid);
+            save_aliases(store, &url_value, &uuid).await;
+            return Ok(saved_keys(store, &uuid).await);
+        }
+    }
+
+    // 3. Parse the recipes from the contents, unless the page gave us complete ones
+    track(events, job, JobStatus::Parsing).await;
+    let recipes = match contents {
+        WebContents::Structured(r) => r,
+        WebContents::Raw(raw) if is_bulk => parse_bulk(llm, events, &policy, job, &raw).await?,
+        WebContents::Raw(raw) => retry(&policy, "Parsing recipe", || parse_recipes(llm, &raw)).await?,
     };
 
-    // 4. Generate recipe image
-    let s3_client = s3Client::new(&config);
-    let region = config.region().unwrap().as_ref();
-    let image_url = match generate_recipe_image(&recipe.summary, &recipe.name).await {
-        Ok(url) => match upload_to_s3(url, &s3_client, region.to_string()).await {
-            Ok(u) => u,
-            Err(e) => {
-                println!("Error uploading to arweave: {:?}", e);
-                String::from("https://arweave.net/imiGGOP3GIoPcVUJAoZIaBI7DqQRZ7nPSiqunzMIMxQ")    
+    // 4. Save each under its own key, the first under the source's so links to it still work
+    let several = recipes.len() > 1;
+    let mut uuids = Vec::new();
+    for (index, recipe) in recipes.into_iter().enumerate() {
+        // Getting the image is the slow part, so let clients show the name meanwhile
+        job.name = Some(recipe.name.clone());
+        track(events, job, JobStatus::Imaging).await;
+        let image_url = recipe_image(llm, images, &policy, &recipe).await?;
+
+        job.image = Some(image_url.clone());
+        track(events, job, JobStatus::Saving).await;
+        let credit = recipe_credit(&recipe, url.credit.clone(), several).unwrap_or_else(|| uuid.clone());
+        let mut stored = to_stored_recipe(recipe, &derived_key(&uuid, index), &image_url, Some(credit));
+        if several {
+            stored.source = Some(uuid.clone());
+        }
+        match add_to_db(store, &stored, (index == 0).then_some(url_value.as_str())).await {
+            Ok(uuid) => {
+                job.record_saved(&stored.name, &uuid);
+                uuids.push(uuid);
             }
-        },
-        Err(e) => {
-            println!("Error generating image: {:?}", e);
-            String::from("https://arweave.net/imiGGOP3GIoPcVUJAoZIaBI7DqQRZ7nPSiqunzMIMxQ")
+            // Usually DynamoDB throttling us
+            Err(e) => return Err(WorkerError::retryable(format!("Error saving recipe: {}", e))),
         }
-    };
+    }
+    Ok(uuids)
+}
 
-    // 5. Add recipe to db
-    let db_client = DbClient::new(&config);
-    let table_name = match get_table_name().await {
-        Some(t) => t,
-        None => {
-            return Err(
-                FailureResponse {
-                    body: format!("Table Name Not Set")
+/**
+ * Pasted text can be a whole family cookbook, so it's split into recipes
+ * and each piece parsed on its own. A piece that fails is noted on the job
+ * and the rest carry on; only if none of them work does the import fail.
+ */
+async fn parse_bulk(llm: &dyn LlmProvider, events: &dyn EventBus, policy: &RetryPolicy, job: &mut Job, text: &str) -> Result<Vec<Recipe>, WorkerError> {
+    let pieces = segment::split(llm, text).await;
+    if pieces.len() == 1 {
+        return retry(policy, "Parsing recipe", || parse_recipes(llm, &pieces[0])).await;
+    }
+    println!("Split bulk text into {} pieces", pieces.len());
+    let mut recipes = Vec::new();
+    let mut first_error = None;
+    for (i, piece) in pieces.iter().enumerate() {
+        match retry(policy, "Parsing recipe", || parse_recipes(llm, piece)).await {
+            Ok(found) => {
+                for recipe in &found {
+                    job.record_parsed(i + 1, &recipe.name);
                 }
-            );
+                recipes.extend(found);
+            }
+            // The rest would only run out of time too
+            Err(e) if policy.expired() => return Err(e),
+            Err(e) => {
+                println!("Error parsing piece {}: {}", i + 1, e);
+                job.record_failed(i + 1, &e.message);
+                first_error.get_or_insert(e);
+            }
         }
-    };
-    match add_to_db(&db_client, recipe, &uuid, &image_url, &table_name, url.credit).await {
-        Ok(_) => {
-            return Ok(
-                SuccessResponse {
-                    body: format!("Success")
-                }
-            );
+        publish_job(events, job).await;
+    }
+    match first_error {
+        Some(e) if recipes.is_empty() => Err(e),
+        _ => Ok(recipes),
+    }
+}
+
+/**
+ * The recipe's own image if we can get it, otherwise one we generate, or
+ * the placeholder if generating fails. Failing to store it fails the job
+ * though, rather than saving the recipe pointing at the wrong image.
+ */
+async fn recipe_image(llm: &dyn LlmProvider, images: &dyn ImageHost, policy: &RetryPolicy, recipe: &Recipe) -> Result<String, WorkerError> {
+    let image = match &recipe.image {
+        Some(source_image) => match download_image(source_image).await {
+            Ok(i) => Ok(i),
+            Err(e) => {
+                println!("Error downloading source image, generating one: {:?}", e);
+                retry(policy, "Generating image", || generate_recipe_image(llm, &recipe.summary, &recipe.name)).await
+            }
         },
+        None => retry(policy, "Generating image", || generate_recipe_image(llm, &recipe.summary, &recipe.name)).await,
+    };
+    match image {
+        Ok(image) => retry(policy, "Uploading image", || images.upload(&image)).await,
+        // Out of time for the rest of the job too
+        Err(e) if policy.expired() => Err(e),
         Err(e) => {
-            return Err(
-                FailureResponse {
-                    body: format!("Failed! {:?}", e)
-                }
-            );
+            println!("Error generating image: {:?}", e);
+            Ok(String::from("https://arweave.net/imiGGOP3GIoPcVUJAoZIaBI7DqQRZ7nPSiqunzMIMxQ"))
         }
-    };
+    }
 }
 
+/**
+ * Keeps the failed request so it can be replayed with `tools/replayFailed`.
+ * If even that fails we hand the error back to Lambda, which retries the
+ * whole message rather than let it vanish.
+ */
+async fn dead_letter(store: &dyn DeadLetterStore, id: &str, request: &str, e: &WorkerError) -> Result<(), Error> {
+    println!("Import {} failed ({}): {}", id, e.kind.as_str(), e);
+    store.put_dead_letter(&DeadLetter::new(id, request, e.kind, &e.message)).await?;
+    Ok(())
+}
+
+// A job that's already saved was sent before, replayed or redelivered by SNS, so it starts over
+fn start_job(saved: Option<Job>, id: &str) -> Job {
+    match saved {
+        Some(mut job) => {
+            job.reset();
+            job
+        }
+        None => Job::new(id),
+    }
+}
 
 async fn handler(event: LambdaEvent<sns::SnsEvent>) -> Result<String, Error> {
     // 1. Get SNS event records
     let records = event.payload.records;
 
-    // 2. iterate through records and call worker function
+    // 2. Create the store, jobs are kept next to the recipes
+    let config: aws_types::sdk_config::SdkConfig = aws_config::load_from_env().await;
+    let (table_name, job_table_name, alias_table_name, dead_letter_table_name) = match (
+        get_table_name().await,
+        get_job_table_name().await,
+        get_alias_table_name().await,
+        get_dead_letter_table_name().await,
+    ) {
+        (Some(t), Some(j), Some(a), Some(d)) => (t, j, a, d),
+        _ => {
+            return Err("Table Name Not Set".into());
+        }
+    };
+    let bucket_name = get_bucket_name().await.ok_or("BUCKET_NAME not set")?;
+    let images = S3Images::new(&config, &bucket_name);
+    let llm = LlmConfig::from_env()?.provider()?;
+    let store = DynamoStore::new(&config, &table_name)
+        .with_job_table(&job_table_name)
+        .with_alias_table(&alias_table_name)
+        .with_dead_letter_table(&dead_letter_table_name);
+    // Progress goes through the Jobs table, where jobEvents and GET /jobs/{id} read it
+    let events = StoreEventBus::new(store.clone());
+
+    // 3. iterate through records and call worker function, recording how each job ends
     for record in records {
-        let message = match worker(&record.sns.message).await {
-            Ok(s) => SqsResponse {
-                status_code: 200,
-                body: s.body
-            },
-            Err(e) => SqsResponse {
-                status_code: 500,
-                body: e.body
+        let url: URLRequest = match serde_json::from_str(&record.sns.message) {
+            Ok(u) => u,
+            Err(e) => {
+                println!("Error matching URL: {:?}", e);
+                // No job to fail, but keep it so someone can see what was sent
+                let e = WorkerError::bad_input(format!("Error reading request: {}", e));
+                dead_letter(&store, &generate_uuid().await, &record.sns.message, &e).await?;
+                continue;
             }
         };
-        send_message(message, &record.sns.message).await;
+        let mut job = start_job(store.get_job(&url.job_id).await.ok().flatten(), &url.job_id);
+        let failure = match worker(&store, &events, llm.as_ref(), &images, &mut job, url).await {
+            Ok(uuids) => {
+                job.finish_all(&uuids);
+                None
+            }
+            Err(e) => {
+                job.fail(&e.message);
+                Some(e)
+            }
+        };
+        publish_job(&events, &job).await;
+        if let Some(e) = failure {
+            dead_letter(&store, &job.id, &record.sns.message, &e).await?;
+        }
     }
 
     Ok("Success!".to_string())
@@ -689,6 +705,9 @@ async fn handler(event: LambdaEvent<sns::SnsEvent>) -> Result<String, Error> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use futures_util::StreamExt;
+    use recipe_llm::MockProvider;
+    use std::sync::Mutex;
 
     macro_rules! aw {
         ($e:expr) => {
@@ -696,12 +715,309 @@ mod tests {
         };
     }
 
+    // Whatever LLM_PROVIDER in ../../.env points at
+    fn llm() -> Box<dyn LlmProvider> {
+        dotenv::from_filename("../../.env").ok();
+        LlmConfig::from_env().unwrap().provider().unwrap()
+    }
+
+    fn s3_images() -> S3Images {
+        let config = aw!(aws_config::load_from_env());
+        S3Images::new(&config, &env::var("BUCKET_NAME").unwrap())
+    }
+
+    #[derive(Default)]
+    struct MemoryImages {
+        uploaded: Mutex<Vec<String>>,
+    }
+
+    #[async_trait::async_trait]
+    impl ImageHost for MemoryImages {
+        async fn upload(&self, image: &str) -> Result<String, WorkerError> {
+            let mut uploaded = self.uploaded.lock().unwrap();
+            uploaded.push(image.to_string());
+            Ok(format!("https://images.example.com/{}.jpg", uploaded.len()))
+        }
+    }
+
+    // S3 having a bad day
+    struct DownImages;
+
+    #[async_trait::async_trait]
+    impl ImageHost for DownImages {
+        async fn upload(&self, _image: &str) -> Result<String, WorkerError> {
+            Err(WorkerError::retryable("Error uploading image to S3: 503 Service Unavailable"))
+        }
+    }
+
+    fn taco_soup_llm() -> MockProvider {
+        MockProvider::from_file(concat!(env!("CARGO_MANIFEST_DIR"), "/../recipe-llm/fixtures/taco_soup.json")).unwrap()
+    }
+
+    fn import(llm: &MockProvider, content_type: ContentType, url: &str) -> (recipe_core::MemoryStore, Vec<Job>, WorkerResponse) {
+        let store = recipe_core::MemoryStore::new();
+        let events = recipe_core::MemoryEventBus::new();
+        let images = MemoryImages::default();
+        let watching = aw!(events.subscribe("job-1")).unwrap();
+        let request = URLRequest {
+            url: url.to_string(),
+            content_type,
+            credit: Some(String::from("Grandma")),
+            uuid: Some(String::from("recipe-1")),
+            job_id: String::from("job-1"),
+        };
+        let mut job = Job::new("job-1");
+        let result = aw!(worker(&store, &events, llm, &images, &mut job, request));
+        // Like the handler, which publishes how the job ended
+        aw!(publish_job(&events, &job));
+        drop(events);
+        (store, aw!(watching.collect()), result)
+    }
+
+    #[test]
+    fn test_import_pipeline() {
+        let llm = taco_soup_llm();
+        let (store, seen, result) = import(&llm, ContentType::BULK, "Taco Soup\n1 lb ground beef\n2 cups beef broth");
+        assert_eq!(result.unwrap(), vec!["recipe-1"]);
+
+        let stored = aw!(store.get("recipe-1")).unwrap().unwrap();
+        assert_eq!(stored.name, "Taco Soup");
+        assert_eq!(stored.ingredients[0].item, "ground beef");
+        assert_eq!(stored.instructions.len(), 3);
+        assert_eq!(stored.image, "https://images.example.com/1.jpg");
+        assert_eq!(stored.credit.as_deref(), Some("Grandma"));
+        assert_eq!(stored.source, None);
+
+        let statuses: Vec<JobStatus> = seen.iter().map(|j| j.status).collect();
+        assert_eq!(statuses, vec![JobStatus::Fetching, JobStatus::Parsing, JobStatus::Imaging, JobStatus::Saving]);
+        // The parse prompt, then an image of the summary
+        let calls = llm.calls();
+        assert!(calls[0].starts_with(PROMPT));
+        assert_eq!(calls[1], "A quick weeknight soup with everything you love about tacos.");
+    }
+
+    #[test]
+    fn test_import_image_pipeline() {
+        let llm = taco_soup_llm();
+        let (store, _, result) = import(&llm, ContentType::IMAGE, "https://example.com/taco-soup-card.png");
+        assert_eq!(result.unwrap(), vec!["recipe-1"]);
+        assert_eq!(aw!(store.get("recipe-1")).unwrap().unwrap().name, "Taco Soup");
+        assert!(llm.calls()[0].ends_with("https://example.com/taco-soup-card.png"));
+    }
+
+    #[test]
+    fn test_parse_recipe_repair() {
+        let chili = r#"{"name": "Chili", "ingredients": ["2 lb ground beef"], "instructions": ["Simmer"], "notes": "", "summary": ""}"#;
+        let placeholder = r#"Sure! {"name": "<str>", "ingredients": [], "instructions": ["Simmer"], "notes": "", "summary": ""}"#;
+        let llm = MockProvider::new(recipe_llm::Fixtures {
+            chat: vec![
+                recipe_llm::Fixture::reply("these problems", chili),
+                recipe_llm::Fixture::reply("Chili", placeholder),
+            ],
+            ..Default::default()
+        });
+        let recipes = aw!(parse_recipes(&llm, "Chili\n2 lb ground beef")).unwrap();
+        assert_eq!(recipes[0].name, "Chili");
+        // The second try is told what was wrong with the first
+        let calls = llm.calls();
+        assert_eq!(calls.len(), 2);
+        assert!(calls[1].contains(placeholder));
+        assert!(calls[1].contains("- recipe 1: ingredients must list at least one ingredient"));
+
+        // Only once though
+        let stubborn = MockProvider::new(recipe_llm::Fixtures {
+            chat: vec![recipe_llm::Fixture::reply("Chili", placeholder)],
+            ..Default::default()
+        });
+        let e = aw!(parse_recipes(&stubborn, "Chili\n2 lb ground beef")).unwrap_err();
+        assert_eq!(e.kind, recipe_core::ErrorKind::Permanent);
+        assert_eq!(stubborn.calls().len(), 2);
+    }
+
+    #[test]
+    fn test_parse_recipe_repairs_only_the_rejects() {
+        let soups = r#"{"recipes": [
+            {"name": "Taco Soup", "ingredients": ["1 lb ground beef"], "instructions": ["Simmer"], "notes": "", "summary": ""},
+            {"name": "<str>", "ingredients": ["2 chicken breasts"], "instructions": ["Simmer"], "notes": "", "summary": ""}
+        ]}"#;
+        let fixed = r#"{"recipes": [{"name": "Chicken Noodle Soup", "ingredients": ["2 chicken breasts"], "instructions": ["Simmer"], "notes": "", "summary": ""}]}"#;
+        let llm = MockProvider::new(recipe_llm::Fixtures {
+            chat: vec![
+                recipe_llm::Fixture::reply("these problems", fixed),
+                recipe_llm::Fixture::reply("Our best soups", soups),
+            ],
+            ..Default::default()
+        });
+        let recipes = aw!(parse_recipes(&llm, "Our best soups\nTaco Soup...\nChicken Noodle Soup...")).unwrap();
+        let names: Vec<&str> = recipes.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["Taco Soup", "Chicken Noodle Soup"]);
+        // Taco Soup was fine, so the model isn't asked about it again
+        let repair = &llm.calls()[1];
+        assert!(repair.contains(r#""name":"<str>""#));
+        assert!(!repair.contains(r#""name":"Taco Soup""#));
+    }
+
+    #[test]
+    fn test_import_roundup() {
+        let soups = r#"{"recipes": [
+            {"name": "Taco Soup", "ingredients": ["1 lb ground beef"], "instructions": ["Simmer"], "notes": "", "summary": "Tacos, as soup"},
+            {"name": "Chicken Noodle Soup", "ingredients": ["2 chicken breasts"], "instructions": ["Simmer"], "notes": "", "summary": "A classic"}
+        ]}"#;
+        let llm = MockProvider::new(recipe_llm::Fixtures {
+            chat: vec![recipe_llm::Fixture::reply("Our best soups", soups)],
+            ..Default::default()
+        });
+        let (store, seen, result) = import(&llm, ContentType::BULK, "Our best soups\nTaco Soup...\nChicken Noodle Soup...");
+        assert_eq!(result.unwrap(), vec!["recipe-1", "recipe-1#recipe-2"]);
+
+        let first = aw!(store.get("recipe-1")).unwrap().unwrap();
+        let second = aw!(store.get("recipe-1#recipe-2")).unwrap().unwrap();
+        assert_eq!(first.name, "Taco Soup");
+        assert_eq!(second.name, "Chicken Noodle Soup");
+        assert_eq!(second.source.as_deref(), Some("recipe-1"));
+        assert_eq!(second.credit.as_deref(), Some("Grandma"));
+        assert_eq!(second.image, "https://images.example.com/2.jpg");
+        assert_eq!(aw!(saved_keys(&store, "recipe-1")), vec!["recipe-1", "recipe-1#recipe-2"]);
+        // Each recipe gets its own trip through imaging and saving
+        assert_eq!(seen.iter().filter(|j| j.status == JobStatus::Saving).count(), 2);
+    }
+
+    #[test]
+    fn test_import_cookbook() {
+        let cookbook = "Taco Soup\nIngredients\n1 lb ground beef\nDirections\nSimmer.\n\nMystery Dish\nIngredients\n1 cup love\nDirections\nStir.\n\nChili\nIngredients\n2 lb ground beef\nDirections\nSimmer all day.";
+        let chili = r#"{"name": "Chili", "ingredients": ["2 lb ground beef"], "instructions": ["Simmer all day."], "notes": "", "summary": ""}"#;
+        let mut fixtures: recipe_llm::Fixtures = serde_json::from_str(include_str!("../../recipe-llm/fixtures/taco_soup.json")).unwrap();
+        fixtures.chat.push(recipe_llm::Fixture::reply("Chili\nIngredients", chili));
+        fixtures.chat.push(recipe_llm::Fixture::reply("Mystery Dish", "I can't tell"));
+        let llm = MockProvider::new(fixtures);
+        let (store, seen, result) = import(&llm, ContentType::BULK, cookbook);
+        assert_eq!(result.unwrap(), vec!["recipe-1", "recipe-1#recipe-2"]);
+        assert_eq!(aw!(store.get("recipe-1#recipe-2")).unwrap().unwrap().name, "Chili");
+
+        let results = &seen.last().unwrap().results;
+        assert_eq!(results.len(), 3);
+        assert_eq!((results[0].part, results[0].recipe_uuid.as_deref()), (1, Some("recipe-1")));
+        assert_eq!(results[1].part, 2);
+        assert!(results[1].error.as_deref().unwrap().starts_with("Error parsing recipe contents"));
+        assert_eq!((results[2].part, results[2].recipe_uuid.as_deref()), (3, Some("recipe-1#recipe-2")));
+    }
+
+    #[test]
+    fn test_recipe_credit() {
+        let mut recipe: Recipe = serde_json::from_str(r#"{"name": "Chili", "ingredients": [], "instructions": [], "notes": "", "summary": ""}"#).unwrap();
+        recipe.author = Some(String::from("Sam Soup"));
+        assert_eq!(recipe_credit(&recipe, None, true).as_deref(), Some("Sam Soup"));
+        assert_eq!(recipe_credit(&recipe, None, false), None);
+        assert_eq!(recipe_credit(&recipe, Some(String::from("Grandma")), true).as_deref(), Some("Grandma"));
+    }
+
+    #[test]
+    fn test_import_unparseable() {
+        // No fixture for chili, like a model that won't answer, which isn't worth retrying
+        let llm = taco_soup_llm();
+        let (store, _, result) = import(&llm, ContentType::BULK, "Chili\n2 lb ground beef");
+        assert_eq!(result.unwrap_err().kind, recipe_core::ErrorKind::Permanent);
+        assert!(aw!(store.get("recipe-1")).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_image_upload_fails() {
+        let llm = taco_soup_llm();
+        let mut recipe: Recipe = serde_json::from_str(r#"{"name": "Taco Soup", "ingredients": [], "instructions": [], "notes": "", "summary": ""}"#).unwrap();
+        recipe.summary = String::from("A quick weeknight soup with everything you love about tacos.");
+        let policy = RetryPolicy { base_delay: Duration::ZERO, max_delay: Duration::ZERO, ..RetryPolicy::default() };
+        // Not saved with the placeholder, the job fails so it can be tried again
+        let e = aw!(recipe_image(&llm, &DownImages, &policy, &recipe)).unwrap_err();
+        assert_eq!(e.kind, recipe_core::ErrorKind::Retryable);
+        assert!(e.message.contains("503"));
+    }
+
     #[test]
     fn it_works() {
         let result = 2 + 2;
         assert_eq!(result, 4);
     }
 
+    #[test]
+    fn test_add_to_store() {
+        let store = recipe_core::MemoryStore::new();
+        let recipe = Recipe {
+            name: String::from("Taco Soup"),
+            ingredients: vec![String::from("1 lb ground beef"), String::from("2 cups beef broth")],
+            instructions: vec![String::from("Brown the beef"), String::from("Simmer")],
+            notes: String::new(),
+            summary: String::from("A quick soup"),
+            recipe_yield: Some(String::from("4 servings")),
+            total_time: None,
+            image: None,
+            author: None,
+        };
+        let url = "https://tasty.co/recipe/taco-soup/amp?utm_source=pinterest";
+        let canonical = "https://tasty.co/recipe/taco-soup";
+        let stored = to_stored_recipe(recipe, &recipe_core::canonicalize_url(url), "https://example.com/soup.jpg", None);
+        let uuid = aw!(add_to_db(&store, &stored, Some(url))).unwrap();
+        assert_eq!(uuid, canonical);
+
+        let stored = aw!(store.get(canonical)).unwrap().unwrap();
+        assert_eq!(stored.credit.as_deref(), Some(canonical));
+        assert_eq!(aw!(store.alias(url)).unwrap().as_deref(), Some(canonical));
+        assert_eq!(stored.ingredients[0].item, "ground beef");
+        assert_eq!(stored.nutrition.unwrap().servings, Some(4.0));
+        assert_eq!(stored.fingerprint.unwrap().ingredients.len(), 64);
+    }
+
+    #[test]
+    fn test_track_job() {
+        let events = recipe_core::MemoryEventBus::new();
+        let watching = aw!(events.subscribe("job-1")).unwrap();
+
+        let mut job = Job::new("job-1");
+        aw!(track(&events, &mut job, JobStatus::Parsing));
+        job.name = Some(String::from("Taco Soup"));
+        aw!(track(&events, &mut job, JobStatus::Imaging));
+        job.fail("Error uploading image");
+        aw!(publish_job(&events, &job));
+
+        let seen: Vec<Job> = aw!(watching.collect());
+        let statuses: Vec<JobStatus> = seen.iter().map(|j| j.status).collect();
+        assert_eq!(statuses, vec![JobStatus::Parsing, JobStatus::Imaging, JobStatus::Failed]);
+        assert_eq!(seen[1].name.as_deref(), Some("Taco Soup"));
+        assert_eq!(seen[2].error.as_deref(), Some("Error uploading image"));
+    }
+
+    #[test]
+    fn test_start_job() {
+        let mut redelivered = Job::new("job-1");
+        redelivered.name = Some(String::from("Taco Soup"));
+        redelivered.image = Some(String::from("https://example.com/soup.jpg"));
+        redelivered.record_parsed(1, "Taco Soup");
+        redelivered.record_failed(2, "No ingredients");
+        redelivered.finish_all(&[String::from("https://tasty.co/recipe/taco-soup")]);
+
+        // Results from the first delivery don't get a second set appended to them
+        let job = start_job(Some(redelivered), "job-1");
+        assert!(job.results.is_empty());
+        assert!(job.recipe_uuids.is_empty());
+        assert_eq!((job.recipe_uuid.as_ref(), job.name.as_ref(), job.image.as_ref()), (None, None, None));
+        assert_eq!(start_job(None, "job-2").id, "job-2");
+    }
+
+    #[test]
+    fn test_dead_letter() {
+        let store = recipe_core::MemoryStore::new();
+        let request = r#"{"url":"https://tasty.co/recipe/taco-soup","content_type":"URL","credit":null,"uuid":null,"job_id":"job-1"}"#;
+        let e = WorkerError::retryable("Error getting response from OpenAI: 429 Too Many Requests");
+        aw!(dead_letter(&store, "job-1", request, &e)).unwrap();
+
+        let letters = aw!(store.list_dead_letters()).unwrap();
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].id, "job-1");
+        assert_eq!(letters[0].kind, recipe_core::ErrorKind::Retryable);
+        // Replaying sends exactly what we were sent
+        let replayed: URLRequest = serde_json::from_str(&letters[0].request).unwrap();
+        assert_eq!(replayed.url, "https://tasty.co/recipe/taco-soup");
+    }
+
     #[test]
     fn get_document_tasty() {
         let url = "https://tasty.co/recipe/garlic-bacon-shrimp-alfredo";
@@ -731,11 +1047,8 @@ mod tests {
         println!("Testing Image Generation!");
         let content = String::from("These Cinnamon Rolls with Cream Cheese Frosting are a delicious treat made with a soft and fluffy dough rolled with a sweet and aromatic filling. The dough is prepared using a yeast mixture and a combination of sugar, eggs, flour, salt, and melted butter. The filling is made with butter, brown sugar, cinnamon, cloves, and nutmeg. The cream cheese frosting adds a creamy and tangy element to the rolls. Enjoy these homemade cinnamon rolls fresh from the oven with a delectable cream cheese frosting!");
         let title = String::from("Cinnamon Rolls");
-        let response = aw!(generate_recipe_image(&content, &title));
-        let config = aw!(aws_config::load_from_env());
-        let s3_client = s3Client::new(&config);
-        let region = config.region().unwrap().as_ref();
-        let arweave_url = aw!(upload_to_s3(response.unwrap(), &s3_client, region.to_string()));
+        let response = aw!(generate_recipe_image(llm().as_ref(), &content, &title));
+        let arweave_url = aw!(s3_images().upload(&response.unwrap()));
         println!("S3 URL: {:?}", arweave_url);
     }
 
@@ -743,24 +1056,24 @@ mod tests {
     fn test_image_reader_png() {
         dotenv::from_filename("../../.env").ok();
         let url = "https://recipe3stack-recipeimagesdc582a3a-1q2uf0c8a37h6.s3.amazonaws.com/IMG_1476.png";
-        let contents = aw!(get_image_contents(&url));
-        println!("{:?}", contents.unwrap().body);
+        let contents = aw!(get_image_contents(llm().as_ref(), &url));
+        println!("{:?}", contents.unwrap());
     }
 
     #[test]
     fn test_image_reader_heic() {
         dotenv::from_filename("../../.env").ok();
         let url = "https://recipe3stack-recipeimagesdc582a3a-1q2uf0c8a37h6.s3.amazonaws.com/IMG_1476.heic";
-        let contents = aw!(get_image_contents(&url));
-        println!("{:?}", contents.unwrap().body);
+        let contents = aw!(get_image_contents(llm().as_ref(), &url));
+        println!("{:?}", contents.unwrap());
     }
 
     #[test]
     fn test_image_reader_jpg() {
         dotenv::from_filename("../../.env").ok();
         let url = "https://recipe3stack-recipeimagesdc582a3a-1q2uf0c8a37h6.s3.amazonaws.com/IMG_2314.jpg";
-        let contents = aw!(get_image_contents(&url));
-        println!("{:?}", contents.unwrap().body);
+        let contents = aw!(get_image_contents(llm().as_ref(), &url));
+        println!("{:?}", contents.unwrap());
     }
 
     #[test]
@@ -775,10 +1088,7 @@ mod tests {
 
         // Encode the image bytes as base64
         let base64_encoded = base64::encode(&image_bytes);
-        let config = aw!(aws_config::load_from_env());
-        let s3_client = s3Client::new(&config);
-        let region = config.region().unwrap().as_ref();
-        let arweave_url = aw!(upload_to_s3(base64_encoded, &s3_client, region.to_string()));
+        let arweave_url = aw!(s3_images().upload(&base64_encoded));
         println!("S3 URL: {:?}", arweave_url);
     }
 
@@ -0,0 +1,264 @@
+use scraper::{ElementRef, Html, Selector};
+use url::Url;
+
+use crate::jsonld::format_duration;
+use crate::text::{clean_text, element_text};
+use crate::Recipe;
+
+type Extractor = fn(&Html) -> Option<Recipe>;
+
+/**
+ * Extracts a recipe from inline markup: schema.org microdata, schema.org
+ * RDFa or the hRecipe microformat, in that order. Like the JSON-LD parser it
+ * only returns complete recipes. A relative image is resolved against
+ * `page_url` so it can be downloaded.
+ */
+pub fn extract(document: &Html, page_url: &str) -> Option<Recipe> {
+    let extractors: [(&str, Extractor); 3] = [
+        ("microdata", extract_microdata),
+        ("RDFa", extract_rdfa),
+        ("hRecipe", extract_hrecipe),
+    ];
+    for (style, extractor) in extractors {
+        if let Some(mut recipe) = extractor(document) {
+            if recipe.is_complete() {
+                recipe.image = recipe.image.map(|image| absolute(page_url, &image));
+                println!("Found {} recipe: {}", style, recipe.name);
+                return Some(recipe);
+            }
+            println!("{} recipe is incomplete: {:?}", style, recipe);
+        }
+    }
+    None
+}
+
+// "pancakes.jpg" on https://example.com/recipes/pancakes is https://example.com/recipes/pancakes.jpg
+fn absolute(page_url: &str, image: &str) -> String {
+    match Url::parse(page_url).and_then(|base| base.join(image.trim())) {
+        Ok(url) => url.to_string(),
+        Err(_) => image.to_string(),
+    }
+}
+
+fn extract_microdata(document: &Html) -> Option<Recipe> {
+    let selector = Selector::parse("[itemscope][itemtype]").unwrap();
+    let root = document
+        .select(&selector)
+        .find(|e| is_recipe_type(e.value().attr("itemtype")))?;
+    Some(build_recipe(&scoped_properties(root, "itemscope", "itemprop")))
+}
+
+fn extract_rdfa(document: &Html) -> Option<Recipe> {
+    let selector = Selector::parse("[typeof]").unwrap();
+    let root = document
+        .select(&selector)
+        .find(|e| is_recipe_type(e.value().attr("typeof")))?;
+    Some(build_recipe(&scoped_properties(root, "typeof", "property")))
+}
+
+fn extract_hrecipe(document: &Html) -> Option<Recipe> {
+    let selector = Selector::parse(".hrecipe, .h-recipe").unwrap();
+    let root = document.select(&selector).next()?;
+    Some(build_recipe(&microformat_properties(root)))
+}
+
+fn is_recipe_type(types: Option<&str>) -> bool {
+    types
+        .unwrap_or_default()
+        .split_whitespace()
+        .any(|t| t.rsplit(['/', ':']).next() == Some("Recipe"))
+}
+
+// Strips vocabulary prefixes such as `schema:` or a full IRI
+fn local_name(name: &str) -> &str {
+    name.rsplit(['/', ':', '#']).next().unwrap_or(name)
+}
+
+// Collects `prop_attr` properties that belong to `root` itself rather than
+// to an item nested inside it (an author Person, a HowToStep, ...).
+fn scoped_properties<'a>(root: ElementRef<'a>, scope_attr: &str, prop_attr: &str) -> Vec<(String, ElementRef<'a>)> {
+    let mut properties = Vec::new();
+    for element in root.descendants().skip(1).filter_map(ElementRef::wrap) {
+        let names = match element.value().attr(prop_attr) {
+            Some(n) => n,
+            None => continue,
+        };
+        let owner = element
+            .ancestors()
+            .filter_map(ElementRef::wrap)
+            .find(|a| a.value().attr(scope_attr).is_some());
+        if owner.map(|o| o.id()) != Some(root.id()) {
+            continue;
+        }
+        for name in names.split_whitespace() {
+            properties.push((local_name(name).to_string(), element));
+        }
+    }
+    properties
+}
+
+// Maps hRecipe (and microformats2 h-recipe) class names onto the schema.org
+// property names so both can share `build_recipe`.
+fn microformat_properties(root: ElementRef) -> Vec<(String, ElementRef)> {
+    let mut properties = Vec::new();
+    for element in root.descendants().skip(1).filter_map(ElementRef::wrap) {
+        // The author's hCard has its own `fn`, which isn't the recipe name
+        let in_card = element
+            .ancestors()
+            .filter_map(ElementRef::wrap)
+            .take_while(|a| a.id() != root.id())
+            .any(|a| a.value().classes().any(|c| matches!(c, "vcard" | "h-card")));
+        for class in element.value().classes() {
+            let name = match class {
+                "fn" | "p-name" if !in_card => "name",
+                "ingredient" | "p-ingredient" => "recipeIngredient",
+                "instructions" | "e-instructions" => "recipeInstructions",
+                "yield" | "p-yield" => "recipeYield",
+                "summary" | "p-summary" => "description",
+                "author" | "p-author" => "author",
+                "photo" | "u-photo" => "image",
+                "duration" | "dt-duration" => "totalTime",
+                _ => continue,
+            };
+            properties.push((name.to_string(), element));
+        }
+    }
+    properties
+}
+
+fn build_recipe(properties: &[(String, ElementRef)]) -> Recipe {
+    let values = |names: &[&str]| -> Vec<ElementRef> {
+        properties
+            .iter()
+            .filter(|(name, _)| names.contains(&name.as_str()))
+            .map(|(_, element)| *element)
+            .collect()
+    };
+    let first = |names: &[&str]| -> Option<String> {
+        values(names)
+            .into_iter()
+            .map(property_value)
+            .find(|v| !v.is_empty())
+    };
+
+    let ingredients = values(&["recipeIngredient", "ingredients"])
+        .into_iter()
+        .map(property_value)
+        .filter(|v| !v.is_empty())
+        .collect();
+    let instructions = values(&["recipeInstructions"])
+        .into_iter()
+        .flat_map(block_lines)
+        .collect();
+
+    Recipe {
+        name: first(&["name"]).unwrap_or_default(),
+        ingredients,
+        instructions,
+        notes: String::new(),
+        summary: first(&["description"]).unwrap_or_default(),
+        recipe_yield: first(&["recipeYield"]),
+        total_time: first(&["totalTime"]).map(|t| format_duration(&t).unwrap_or(t)),
+        image: first(&["image"]),
+        author: values(&["author"]).into_iter().map(author_value).find(|v| !v.is_empty()),
+    }
+}
+
+// Authors are usually a nested Person or hCard; take its name, not "By ..."
+fn author_value(element: ElementRef) -> String {
+    let name = Selector::parse("[itemprop~=\"name\"], [property~=\"name\"], .fn, .p-name").unwrap();
+    match element.select(&name).next() {
+        Some(n) => property_value(n),
+        None => property_value(element),
+    }
+}
+
+fn property_value(element: ElementRef) -> String {
+    let value_title = Selector::parse(".value-title[title]").unwrap();
+    if let Some(inner) = element.select(&value_title).next() {
+        return clean_text(inner.value().attr("title").unwrap_or_default());
+    }
+
+    let e = element.value();
+    let attr = e.attr("content").or_else(|| match e.name() {
+        "img" | "source" => e.attr("src"),
+        "a" | "link" => e.attr("href"),
+        "time" => e.attr("datetime"),
+        "data" | "meter" => e.attr("value"),
+        _ => None,
+    });
+    match attr {
+        Some(a) => clean_text(a),
+        None => element_text(element),
+    }
+}
+
+// An instructions property is often one element wrapping a whole list
+fn block_lines(element: ElementRef) -> Vec<String> {
+    for selector in ["li", "p"] {
+        let selector = Selector::parse(selector).unwrap();
+        let lines: Vec<String> = element
+            .select(&selector)
+            .map(element_text)
+            .filter(|l| !l.is_empty())
+            .collect();
+        if !lines.is_empty() {
+            return lines;
+        }
+    }
+    let text = property_value(element);
+    if text.is_empty() {
+        Vec::new()
+    } else {
+        vec![text]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_microdata() {
+        let document = Html::parse_document(include_str!("../fixtures/microdata.html"));
+        let recipe = extract(&document, "https://example.com/recipes/pancakes").expect("recipe");
+        assert_eq!(recipe.name, "Grandma's Apple Crisp");
+        assert_eq!(recipe.ingredients, vec!["6 apples, sliced", "1 cup oats", "1/2 cup butter"]);
+        assert_eq!(recipe.instructions, vec!["Heat oven to 350°F.", "Layer apples and topping.", "Bake 45 minutes."]);
+        assert_eq!(recipe.recipe_yield.as_deref(), Some("6 servings"));
+        assert_eq!(recipe.total_time.as_deref(), Some("1 hr"));
+        assert_eq!(recipe.image.as_deref(), Some("https://example.com/crisp.jpg"));
+        assert_eq!(recipe.author.as_deref(), Some("Grandma Jo"));
+        assert_eq!(recipe.summary, "Warm & crunchy.");
+    }
+
+    #[test]
+    fn parses_rdfa() {
+        let document = Html::parse_document(include_str!("../fixtures/rdfa.html"));
+        let recipe = extract(&document, "https://example.com/recipes/pancakes").expect("recipe");
+        assert_eq!(recipe.name, "Lemon Bars");
+        assert_eq!(recipe.ingredients, vec!["2 cups flour", "4 eggs", "3 lemons, juiced"]);
+        assert_eq!(recipe.instructions, vec!["Bake the crust.", "Pour over the filling and bake again."]);
+        assert_eq!(recipe.total_time.as_deref(), Some("50 mins"));
+        assert_eq!(recipe.author.as_deref(), Some("Sam Baker"));
+    }
+
+    #[test]
+    fn parses_hrecipe() {
+        let document = Html::parse_document(include_str!("../fixtures/hrecipe.html"));
+        let recipe = extract(&document, "https://example.com/recipes/pancakes").expect("recipe");
+        assert_eq!(recipe.name, "Buttermilk Pancakes");
+        assert_eq!(recipe.ingredients, vec!["2 cups flour", "2 cups buttermilk", "2 eggs"]);
+        assert_eq!(recipe.instructions, vec!["Whisk it all together.", "Cook on a hot griddle."]);
+        assert_eq!(recipe.recipe_yield.as_deref(), Some("12 pancakes"));
+        assert_eq!(recipe.total_time.as_deref(), Some("20 mins"));
+        assert_eq!(recipe.author.as_deref(), Some("Pat"));
+        assert_eq!(recipe.image.as_deref(), Some("https://example.com/recipes/pancakes.jpg"));
+    }
+
+    #[test]
+    fn ignores_pages_without_markup() {
+        let document = Html::parse_document("<html><body><h1>Just a blog post</h1><p>Nothing here.</p></body></html>");
+        assert!(extract(&document, "https://example.com/blog").is_none());
+    }
+}
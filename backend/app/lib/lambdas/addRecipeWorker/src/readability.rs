@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+
+use scraper::{ElementRef, Html, Node, Selector};
+
+use crate::text::{collapse_whitespace, element_text};
+
+// Never part of the content, whatever their class says
+const SKIP_TAGS: &[&str] = &[
+    "script", "style", "noscript", "template", "nav", "header", "footer", "aside", "form",
+    "iframe", "svg", "canvas", "video", "audio", "figure", "button", "select", "input", "textarea",
+];
+
+const CANDIDATE_TAGS: &[&str] = &["article", "main", "section", "div", "td", "body"];
+
+const NEGATIVE_TOKENS: &[&str] = &[
+    "ad", "ads", "advert", "advertisement", "sponsor", "sponsored", "promo", "banner", "sidebar",
+    "share", "sharing", "social", "related", "newsletter", "subscribe", "popup", "modal",
+    "cookie", "cookies", "breadcrumb", "breadcrumbs", "comment", "comments", "disqus",
+    "footer", "header", "nav", "navigation", "menu", "widget", "jump", "print", "rating", "reviews",
+];
+
+// Recipe cards use words like "header" and "print" in their own class names,
+// so these protect an element from being dropped.
+const RECIPE_TOKENS: &[&str] = &[
+    "recipe", "ingredient", "ingredients", "instruction", "instructions", "directions", "method",
+];
+
+const POSITIVE_TOKENS: &[&str] = &[
+    "recipe", "ingredients", "instructions", "directions", "article", "content", "entry", "post",
+    "main", "body", "text",
+];
+
+/**
+ * Finds the block of the page most likely to hold the recipe, the way
+ * Readability does, and renders it as plain text. Headings become `#` lines
+ * and list items keep their bullets or step numbers so the LLM can still see
+ * the structure.
+ */
+pub fn main_content(document: &Html) -> String {
+    let root = best_candidate(document).unwrap_or_else(|| {
+        let body = Selector::parse("body").unwrap();
+        document.select(&body).next().unwrap_or_else(|| document.root_element())
+    });
+    let mut renderer = Renderer::default();
+    renderer.walk(root);
+    renderer.finish()
+}
+
+fn class_tokens(element: ElementRef) -> Vec<String> {
+    let e = element.value();
+    let names = format!("{} {}", e.attr("class").unwrap_or_default(), e.attr("id").unwrap_or_default());
+    names
+        .split([' ', '-', '_'])
+        .filter(|t| !t.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn has_token(tokens: &[String], list: &[&str]) -> bool {
+    tokens.iter().any(|t| list.contains(&t.as_str()))
+}
+
+fn is_skipped(element: ElementRef) -> bool {
+    if SKIP_TAGS.contains(&element.value().name()) {
+        return true;
+    }
+    let tokens = class_tokens(element);
+    has_token(&tokens, NEGATIVE_TOKENS) && !has_token(&tokens, RECIPE_TOKENS)
+}
+
+fn class_weight(element: ElementRef) -> f32 {
+    let tokens = class_tokens(element);
+    let mut weight = 0.0;
+    if has_token(&tokens, POSITIVE_TOKENS) {
+        weight += 25.0;
+    }
+    if has_token(&tokens, NEGATIVE_TOKENS) {
+        weight -= 25.0;
+    }
+    weight
+}
+
+fn best_candidate(document: &Html) -> Option<ElementRef<'_>> {
+    let blocks = Selector::parse("p, li, pre, td").unwrap();
+    let mut scores: HashMap<_, (ElementRef, f32)> = HashMap::new();
+
+    for block in document.select(&blocks) {
+        if block.ancestors().filter_map(ElementRef::wrap).chain([block]).any(is_skipped) {
+            continue;
+        }
+        let text = element_text(block);
+        let length = text.chars().count();
+        // Ingredient lines are short, prose paragraphs shouldn't be
+        let minimum = if block.value().name() == "li" { 1 } else { 25 };
+        if length < minimum {
+            continue;
+        }
+        let score = 1.0 + text.matches(',').count() as f32 + (length as f32 / 100.0).min(3.0);
+
+        let containers = block
+            .ancestors()
+            .filter_map(ElementRef::wrap)
+            .filter(|a| CANDIDATE_TAGS.contains(&a.value().name()))
+            .take(5);
+        for (level, container) in containers.enumerate() {
+            let divider = match level {
+                0 => 1.0,
+                1 => 2.0,
+                l => l as f32 * 3.0,
+            };
+            scores
+                .entry(container.id())
+                .or_insert_with(|| (container, class_weight(container)))
+                .1 += score / divider;
+        }
+    }
+
+    scores
+        .into_values()
+        .map(|(element, score)| (element, score * (1.0 - link_density(element))))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(element, _)| element)
+}
+
+fn link_density(element: ElementRef) -> f32 {
+    let links = Selector::parse("a").unwrap();
+    let total = element_text(element).len();
+    if total == 0 {
+        return 0.0;
+    }
+    let linked: usize = element.select(&links).map(|a| element_text(a).len()).sum();
+    (linked as f32 / total as f32).min(1.0)
+}
+
+#[derive(Default)]
+struct Renderer {
+    lines: Vec<String>,
+    current: String,
+    prefix: Option<String>,
+    // None for a bulleted list, Some(step) for a numbered one
+    lists: Vec<Option<usize>>,
+}
+
+impl Renderer {
+    fn walk(&mut self, element: ElementRef) {
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) => self.current.push_str(text),
+                Node::Element(_) => {
+                    let el = match ElementRef::wrap(child) {
+                        Some(e) => e,
+                        None => continue,
+                    };
+                    if is_skipped(el) {
+                        continue;
+                    }
+                    self.element(el);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn element(&mut self, el: ElementRef) {
+        match el.value().name() {
+            name @ ("h1" | "h2" | "h3" | "h4" | "h5" | "h6") => {
+                let level = name[1..].parse::<usize>().unwrap_or(1);
+                self.flush();
+                self.lines.push(String::new());
+                self.prefix = Some(format!("{} ", "#".repeat(level)));
+                self.walk(el);
+                self.flush();
+            }
+            list @ ("ul" | "ol") => {
+                self.flush();
+                self.lists.push(if list == "ol" { Some(0) } else { None });
+                self.walk(el);
+                self.lists.pop();
+                self.flush();
+            }
+            "li" => {
+                self.flush();
+                self.prefix = Some(match self.lists.last_mut() {
+                    Some(Some(step)) => {
+                        *step += 1;
+                        format!("{}. ", step)
+                    }
+                    _ => String::from("- "),
+                });
+                self.walk(el);
+                self.flush();
+            }
+            "br" => self.flush(),
+            "p" | "div" | "section" | "article" | "main" | "table" | "tr" | "blockquote" | "pre"
+            | "dl" | "dt" | "dd" => {
+                self.flush();
+                self.walk(el);
+                self.flush();
+            }
+            _ => self.walk(el),
+        }
+    }
+
+    // The prefix waits for the first line that actually has text, so
+    // `<li><p>...</p></li>` still gets its bullet.
+    fn flush(&mut self) {
+        let text = collapse_whitespace(&self.current);
+        self.current.clear();
+        if !text.is_empty() {
+            let prefix = self.prefix.take().unwrap_or_default();
+            self.lines.push(format!("{}{}", prefix, text));
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.flush();
+        let mut out: Vec<String> = Vec::new();
+        for line in self.lines {
+            if line.is_empty() && out.last().is_none_or(|l| l.is_empty()) {
+                continue;
+            }
+            out.push(line);
+        }
+        out.join("\n").trim().to_string()
+    }
+}
+
+// Rough count for English text, about four characters per token
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+// Keeps whole lines while they fit, then as many words of the next line as fit
+pub fn truncate_to_tokens(text: &str, budget: usize) -> String {
+    let mut out: Vec<String> = Vec::new();
+    let mut used = 0;
+    for line in text.lines() {
+        let cost = estimate_tokens(line) + 1;
+        if used + cost > budget {
+            let room = budget.saturating_sub(used + 1) * 4;
+            let mut partial = String::new();
+            for word in line.split_whitespace() {
+                if partial.len() + word.len() + 1 > room {
+                    break;
+                }
+                if !partial.is_empty() {
+                    partial.push(' ');
+                }
+                partial.push_str(word);
+            }
+            if !partial.is_empty() {
+                out.push(partial);
+            }
+            break;
+        }
+        used += cost;
+        out.push(line.to_string());
+    }
+    out.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_recipe_and_drops_chrome() {
+        let document = Html::parse_document(include_str!("../fixtures/readability.html"));
+        let content = main_content(&document);
+        assert!(content.contains("## Ingredients\n- 2 cups all-purpose flour\n- 1 cup sugar, divided"));
+        assert!(content.contains("## Directions\n1. Whisk the dry ingredients together.\n2. Bake for 25 minutes."));
+        assert!(content.contains("These muffins are the best thing I've baked all year, honestly."));
+        for junk in ["Home", "Subscribe", "Great recipe!", "Buy now", "trackPageview", "Related posts"] {
+            assert!(!content.contains(junk), "found {:?}", junk);
+        }
+    }
+
+    #[test]
+    fn truncates_on_word_boundaries() {
+        let text = "one two three four\nfive six seven eight nine ten eleven twelve";
+        assert_eq!(truncate_to_tokens(text, 100), text);
+        assert_eq!(truncate_to_tokens(text, 10), "one two three four\nfive six");
+        assert_eq!(truncate_to_tokens(text, 0), "");
+    }
+}
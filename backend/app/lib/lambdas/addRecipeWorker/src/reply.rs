@@ -0,0 +1,265 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::Recipe;
+
+// Names models fill in when they couldn't find the real one
+const PLACEHOLDER_NAMES: [&str; 9] = ["<str>", "name", "recipe", "recipe name", "untitled", "unknown", "n/a", "none", "null"];
+
+/**
+ * What we ask the model for: every recipe in the content, each the same
+ * shape as `Recipe` without the fields only structured data gives us.
+ */
+pub fn recipes_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "recipes": {
+                "type": "array",
+                "items": recipe_schema(),
+                "minItems": 1,
+                "description": "Every recipe in the content, in the order they appear"
+            }
+        },
+        "required": ["recipes"],
+        "additionalProperties": false
+    })
+}
+
+fn recipe_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "name": { "type": "string", "description": "The recipe's title" },
+            "ingredients": {
+                "type": "array",
+                "items": { "type": "string" },
+                "minItems": 1,
+                "description": "One ingredient per item, section headers as their own item"
+            },
+            "instructions": {
+                "type": "array",
+                "items": { "type": "string" },
+                "minItems": 1,
+                "description": "One step per item, section headers as their own item"
+            },
+            "notes": { "type": "string" },
+            "summary": { "type": "string", "description": "A sentence or two describing the dish" }
+        },
+        "required": ["name", "ingredients", "instructions", "notes", "summary"],
+        "additionalProperties": false
+    })
+}
+
+/**
+ * The first complete JSON object in `text`, however much prose is around it.
+ * Braces inside strings don't count, and an object that turns out not to be
+ * JSON is skipped for the next one.
+ */
+pub fn extract_json(text: &str) -> Option<&str> {
+    let mut start = 0;
+    while let Some(offset) = text[start..].find('{') {
+        let from = start + offset;
+        if let Some(end) = balanced_end(&text[from..]) {
+            let candidate = &text[from..from + end];
+            if serde_json::from_str::<Value>(candidate).is_ok() {
+                return Some(candidate);
+            }
+        }
+        start = from + 1;
+    }
+    None
+}
+
+// Length of the object `text` starts with, if it closes
+fn balanced_end(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// Everything wrong with a recipe the model gave us, empty if it'll do
+pub fn validate(recipe: &Recipe) -> Vec<String> {
+    let mut problems = Vec::new();
+    let name = recipe.name.trim();
+    if name.is_empty() || PLACEHOLDER_NAMES.contains(&name.to_lowercase().as_str()) {
+        problems.push(format!("name must be the recipe's real title, not {:?}", recipe.name));
+    }
+    if recipe.ingredients.iter().all(|i| i.trim().is_empty()) {
+        problems.push(String::from("ingredients must list at least one ingredient"));
+    }
+    if recipe.instructions.iter().all(|i| i.trim().is_empty()) {
+        problems.push(String::from("instructions must list at least one step"));
+    }
+    problems
+}
+
+// Servers that ignore the schema sometimes answer with the bare recipe
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Answer {
+    Recipes { recipes: Vec<Recipe> },
+    Recipe(Recipe),
+}
+
+// What we made of one reply from the model
+#[derive(Debug, Default)]
+pub struct Reading {
+    pub recipes: Vec<Recipe>,
+    // Recipes it gave that we can't use, to send back for repair
+    pub rejected: Vec<Recipe>,
+    // What's wrong with them, or with the whole answer, in words we can hand back to the model
+    pub problems: Vec<String>,
+}
+
+impl Reading {
+    fn unusable(problem: impl Into<String>) -> Reading {
+        Reading { problems: vec![problem.into()], ..Reading::default() }
+    }
+}
+
+/**
+ * Reads the recipes out of the model's reply, keeping the usable ones apart
+ * from the rest so only those need asking about again.
+ */
+pub fn read_recipes(reply: &str) -> Reading {
+    let json = match extract_json(reply) {
+        Some(j) => j,
+        None => return Reading::unusable("the answer must contain a JSON object"),
+    };
+    let recipes = match serde_json::from_str(json) {
+        Ok(Answer::Recipes { recipes }) => recipes,
+        Ok(Answer::Recipe(recipe)) => vec![recipe],
+        Err(e) => return Reading::unusable(format!("the JSON doesn't match the schema: {}", e)),
+    };
+    if recipes.is_empty() {
+        return Reading::unusable("recipes must list at least one recipe");
+    }
+    let mut reading = Reading::default();
+    for recipe in recipes {
+        let wrong = validate(&recipe);
+        if wrong.is_empty() {
+            reading.recipes.push(recipe);
+            continue;
+        }
+        // Numbered as they'll be in the repair prompt, which only has the rejects
+        let number = reading.rejected.len() + 1;
+        reading.problems.extend(wrong.into_iter().map(|p| format!("recipe {}: {}", number, p)));
+        reading.rejected.push(recipe);
+    }
+    reading
+}
+
+/**
+ * Asks again about what the first answer got wrong: just the rejected
+ * recipes if it had usable ones, otherwise the whole answer.
+ */
+pub fn repair_prompt(prompt: &str, reply: &str, reading: &Reading) -> String {
+    let problems = reading.problems.join("\n- ");
+    if reading.recipes.is_empty() {
+        return format!(
+            "{}\n\nYour last answer was:\n{}\n\nIt had these problems:\n- {}\nAnswer again with the corrected recipes.",
+            prompt, reply, problems
+        );
+    }
+    let rejected = json!({ "recipes": reading.rejected.iter().map(|r| json!({
+        "name": r.name,
+        "ingredients": r.ingredients,
+        "instructions": r.instructions,
+        "notes": r.notes,
+        "summary": r.summary,
+    })).collect::<Vec<Value>>() });
+    format!(
+        "{}\n\nYour last answer had {} usable recipes, but these ones weren't:\n{}\n\nThey had these problems:\n- {}\nAnswer again with only these recipes, corrected.",
+        prompt,
+        reading.recipes.len(),
+        rejected,
+        problems
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_nested_json() {
+        let reply = r#"Here you go: {"name": "Taco {Soup}", "extra": {"servings": 4}, "notes": "Use \"good\" broth }"} Enjoy!"#;
+        assert_eq!(
+            extract_json(reply),
+            Some(r#"{"name": "Taco {Soup}", "extra": {"servings": 4}, "notes": "Use \"good\" broth }"}"#)
+        );
+        // The first brace isn't JSON, the second is
+        assert_eq!(extract_json(r#"Fill in {name}: {"name": "Chili"}"#), Some(r#"{"name": "Chili"}"#));
+        assert_eq!(extract_json(r#"{"name": "Chili""#), None);
+        assert_eq!(extract_json("No recipe here"), None);
+    }
+
+    const TACO_SOUP: &str = r#"{"name": "Taco Soup", "ingredients": ["1 lb ground beef"], "instructions": ["Simmer"], "notes": "", "summary": ""}"#;
+    const PLACEHOLDER: &str = r#"{"name": "<str>", "ingredients": [], "instructions": ["Simmer"], "notes": "", "summary": ""}"#;
+
+    #[test]
+    fn validates_recipes() {
+        let reading = read_recipes(&format!(r#"{{"recipes": [{}]}}"#, TACO_SOUP));
+        assert_eq!(reading.recipes[0].name, "Taco Soup");
+        assert!(reading.problems.is_empty());
+        // A bare recipe is fine too
+        assert_eq!(read_recipes(TACO_SOUP).recipes.len(), 1);
+
+        let reading = read_recipes(PLACEHOLDER);
+        assert!(reading.recipes.is_empty());
+        assert_eq!(reading.rejected.len(), 1);
+        assert_eq!(reading.problems.len(), 2);
+        assert!(reading.problems[0].starts_with("recipe 1: name must be"));
+        assert!(reading.problems[1].starts_with("recipe 1: ingredients must"));
+
+        assert!(read_recipes(r#"{"name": "Taco Soup"}"#).problems[0].starts_with("the JSON doesn't match"));
+        assert_eq!(read_recipes("Sorry, I can't find a recipe").problems, vec!["the answer must contain a JSON object"]);
+        assert_eq!(read_recipes(r#"{"recipes": []}"#).problems, vec!["recipes must list at least one recipe"]);
+    }
+
+    #[test]
+    fn reads_several_recipes() {
+        let reading = read_recipes(&format!(r#"Here they are: {{"recipes": [{}, {}]}}"#, TACO_SOUP, PLACEHOLDER));
+        assert_eq!(reading.recipes.len(), 1);
+        assert_eq!(reading.rejected[0].name, "<str>");
+        assert_eq!(reading.problems[0], "recipe 1: name must be the recipe's real title, not \"<str>\"");
+    }
+
+    #[test]
+    fn repairs_only_the_rejects() {
+        let reply = format!(r#"{{"recipes": [{}, {}]}}"#, TACO_SOUP, PLACEHOLDER);
+        let prompt = repair_prompt("Parse every recipe", &reply, &read_recipes(&reply));
+        assert!(prompt.starts_with("Parse every recipe"));
+        assert!(prompt.contains(r#"{"recipes":[{"ingredients":[],"instructions":["Simmer"],"name":"<str>","notes":"","summary":""}]}"#));
+        assert!(!prompt.contains("Taco Soup"));
+        assert!(prompt.contains("- recipe 1: ingredients must list at least one ingredient"));
+
+        // Nothing usable, so the whole answer goes back
+        let prompt = repair_prompt("Parse every recipe", "I can't tell", &read_recipes("I can't tell"));
+        assert!(prompt.contains("Your last answer was:\nI can't tell"));
+    }
+}
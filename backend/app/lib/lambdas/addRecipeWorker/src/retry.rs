@@ -0,0 +1,178 @@
+use std::future::Future;
+use tokio::time::{sleep, Duration, Instant};
+
+use crate::error::WorkerError;
+
+// The worker times out after five minutes, this leaves time to record how the job went
+const JOB_BUDGET: Duration = Duration::from_secs(4 * 60);
+
+/**
+ * How hard to try a step before giving up on it. Each attempt at parsing
+ * can be two GPT-4 calls, the answer and its repair, so one step is up to
+ * six calls and their waits, and pasted text goes through that once per
+ * piece. Attempts alone can't keep that inside the worker's timeout, so
+ * every step also shares the job's deadline.
+ */
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    // Including the first
+    pub attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    // No attempts are started after this
+    pub deadline: Instant,
+}
+
+// For a job starting now
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            attempts: 3,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(10),
+            deadline: Instant::now() + JOB_BUDGET,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn expired(&self) -> bool {
+        Instant::now() >= self.deadline
+    }
+
+    /**
+     * Exponential backoff with full jitter: anywhere up to base * 2^retry,
+     * capped, so imports that hit a rate limit together don't retry together.
+     */
+    pub fn delay(&self, retry: u32) -> Duration {
+        let ceiling = self
+            .base_delay
+            .checked_mul(1 << retry.min(16))
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+        ceiling.mul_f64(rand::random::<f64>())
+    }
+}
+
+/**
+ * Runs `op` until it works, fails with something that isn't retryable, or
+ * runs out of attempts, and returns how it last went. Running out of time
+ * is a permanent failure, trying the job again would only do the same.
+ */
+pub async fn retry<T, F, Fut>(policy: &RetryPolicy, what: &str, mut op: F) -> Result<T, WorkerError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, WorkerError>>,
+{
+    let mut attempt = 1;
+    loop {
+        if policy.expired() {
+            return Err(WorkerError::permanent(format!("{} ran out of time", what)));
+        }
+        match op().await {
+            Err(e) if e.is_retryable() && attempt < policy.attempts => {
+                let delay = policy.delay(attempt - 1);
+                if Instant::now() + delay >= policy.deadline {
+                    return Err(WorkerError::permanent(format!("{} ran out of time: {}", what, e.message)));
+                }
+                println!("{} failed on attempt {}, retrying in {:?}: {}", what, attempt, delay, e);
+                sleep(delay).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn instant() -> RetryPolicy {
+        RetryPolicy {
+            attempts: 3,
+            base_delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+            deadline: Instant::now() + Duration::from_secs(60),
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_until_it_works() {
+        let calls = Cell::new(0);
+        let result = retry(&instant(), "Parsing", || {
+            calls.set(calls.get() + 1);
+            let call = calls.get();
+            async move {
+                if call < 3 {
+                    Err(WorkerError::retryable("429 Too Many Requests"))
+                } else {
+                    Ok(call)
+                }
+            }
+        })
+        .await;
+        assert_eq!(result, Ok(3));
+    }
+
+    #[tokio::test]
+    async fn gives_up() {
+        let calls = Cell::new(0);
+        let result: Result<(), WorkerError> = retry(&instant(), "Parsing", || {
+            calls.set(calls.get() + 1);
+            async { Err(WorkerError::retryable("503 Service Unavailable")) }
+        })
+        .await;
+        assert!(result.unwrap_err().is_retryable());
+        assert_eq!(calls.get(), 3);
+
+        calls.set(0);
+        let result: Result<(), WorkerError> = retry(&instant(), "Parsing", || {
+            calls.set(calls.get() + 1);
+            async { Err(WorkerError::permanent("API Key Not Set")) }
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[tokio::test]
+    async fn runs_out_of_time() {
+        let calls = Cell::new(0);
+        let policy = RetryPolicy { deadline: Instant::now(), ..instant() };
+        let result: Result<(), WorkerError> = retry(&policy, "Parsing", || {
+            calls.set(calls.get() + 1);
+            async { Ok(()) }
+        })
+        .await;
+        assert_eq!(result, Err(WorkerError::permanent("Parsing ran out of time")));
+        assert_eq!(calls.get(), 0);
+
+        // Waiting for the next attempt would go past it, so that's the last
+        let policy = RetryPolicy {
+            base_delay: Duration::from_secs(60),
+            max_delay: Duration::from_secs(60),
+            deadline: Instant::now() + Duration::from_millis(1),
+            ..instant()
+        };
+        let result: Result<(), WorkerError> = retry(&policy, "Parsing", || {
+            calls.set(calls.get() + 1);
+            async { Err(WorkerError::retryable("429 Too Many Requests")) }
+        })
+        .await;
+        let e = result.unwrap_err();
+        assert_eq!(e.kind, recipe_core::ErrorKind::Permanent);
+        assert!(e.message.starts_with("Parsing ran out of time"));
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn backs_off_with_jitter() {
+        let policy = RetryPolicy::default();
+        for retry in 0..20 {
+            let ceiling = Duration::from_secs(1 << retry.min(4)).min(policy.max_delay);
+            assert!(policy.delay(retry) <= ceiling);
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use recipe_llm::LlmProvider;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::readability::estimate_tokens;
+use crate::reply::extract_json;
+use crate::TOKEN_BUDGET;
+
+// Enough of each line for the model to spot titles without sending it the whole book
+const LINE_PREVIEW_CHARS: usize = 60;
+
+const SEGMENT_PROMPT: &str = "The numbered lines below are pasted from a document that may hold several recipes. Give the line number where each recipe starts, including the first, in order. A recipe starts at its title.";
+
+const INGREDIENTS_MARKERS: [&str; 6] = ["ingredients", "ingredient", "you will need", "you'll need", "what you need", "what you'll need"];
+const DIRECTIONS_MARKERS: [&str; 7] = ["directions", "instructions", "method", "preparation", "steps", "how to make it", "to make"];
+// Lines under a title that aren't the next recipe's title
+const META_PREFIXES: [&str; 8] = ["serves", "servings", "makes", "yield", "prep", "cook", "total", "from "];
+
+fn marker_text(line: &str) -> String {
+    line.trim().trim_start_matches('#').trim().trim_end_matches(':').trim().to_lowercase()
+}
+
+fn is_ingredients_marker(line: &str) -> bool {
+    INGREDIENTS_MARKERS.contains(&marker_text(line).as_str())
+}
+
+fn is_directions_marker(line: &str) -> bool {
+    DIRECTIONS_MARKERS.contains(&marker_text(line).as_str())
+}
+
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+// Short and unpunctuated, and not an ingredient, a step or a serving count
+fn is_heading(line: &str) -> bool {
+    let text = line.trim();
+    if text.starts_with('#') {
+        return !is_ingredients_marker(text) && !is_directions_marker(text);
+    }
+    let lower = text.to_lowercase();
+    !text.is_empty()
+        && text.chars().count() <= 60
+        && text.split_whitespace().count() <= 8
+        && !text.ends_with(['.', ':', ',', ';', '!', '?'])
+        && !text.starts_with(|c: char| c.is_ascii_digit() || "-*•½¼¾⅓⅔⅛".contains(c))
+        && !META_PREFIXES.iter().any(|p| lower.starts_with(p))
+        && !is_ingredients_marker(text)
+        && !is_directions_marker(text)
+}
+
+// A title starts a block, after a blank line, unless it's a markdown heading
+fn starts_block(lines: &[&str], i: usize) -> bool {
+    is_heading(lines[i]) && (i == 0 || is_blank(lines[i - 1]) || lines[i].trim_start().starts_with('#'))
+}
+
+/**
+ * Where each recipe starts, from its "Ingredients" line: the nearest title
+ * above it that starts a block, but below the previous recipe's ingredients.
+ */
+fn starts_from_markers(lines: &[&str]) -> Option<Vec<usize>> {
+    let markers: Vec<usize> = (0..lines.len()).filter(|&i| is_ingredients_marker(lines[i])).collect();
+    if markers.len() < 2 {
+        return None;
+    }
+    let mut starts = vec![0];
+    for pair in markers.windows(2) {
+        let (previous, marker) = (pair[0], pair[1]);
+        let start = (previous + 1..marker).rev().find(|&i| starts_block(lines, i)).unwrap_or(marker);
+        starts.push(start);
+    }
+    Some(starts)
+}
+
+/**
+ * Recipes separated by two or more blank lines, each starting with a
+ * title and long enough to be a recipe. A single recipe spaced out that
+ * way has its sections start with "Ingredients" or "Directions" instead.
+ */
+fn starts_from_blank_lines(lines: &[&str]) -> Option<Vec<usize>> {
+    let mut starts = Vec::new();
+    let mut blanks = 2;
+    for (i, line) in lines.iter().enumerate() {
+        if is_blank(line) {
+            blanks += 1;
+            continue;
+        }
+        if blanks >= 2 {
+            starts.push(i);
+        }
+        blanks = 0;
+    }
+    let recipe_like = |(i, &start): (usize, &usize)| {
+        let end = starts.get(i + 1).copied().unwrap_or(lines.len());
+        is_heading(lines[start]) && lines[start..end].iter().filter(|l| !is_blank(l)).count() >= 3
+    };
+    if starts.len() < 2 || !starts.iter().enumerate().all(recipe_like) {
+        return None;
+    }
+    starts[0] = 0;
+    Some(starts)
+}
+
+fn chunks(lines: &[&str], starts: &[usize]) -> Vec<String> {
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, &start)| {
+            let end = starts.get(i + 1).copied().unwrap_or(lines.len());
+            lines[start..end].join("\n").trim().to_string()
+        })
+        .filter(|chunk| !chunk.is_empty())
+        .collect()
+}
+
+/**
+ * Splits pasted text into recipe sized pieces by its layout: "Ingredients"
+ * headers first, then recipes set apart by blank lines. None when neither
+ * finds more than one recipe.
+ */
+pub fn split_by_layout(text: &str) -> Option<Vec<String>> {
+    let lines: Vec<&str> = text.lines().collect();
+    let starts = starts_from_markers(&lines).or_else(|| starts_from_blank_lines(&lines))?;
+    let pieces = chunks(&lines, &starts);
+    if pieces.len() > 1 {
+        Some(pieces)
+    } else {
+        None
+    }
+}
+
+#[derive(Deserialize)]
+struct Starts {
+    starts: Vec<usize>,
+}
+
+// Asks the model where recipes start, for text whose layout gives us nothing to go on
+async fn starts_from_llm(llm: &dyn LlmProvider, lines: &[&str]) -> Option<Vec<usize>> {
+    let numbered: Vec<String> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !is_blank(line))
+        .map(|(i, line)| format!("{}: {}", i + 1, line.trim().chars().take(LINE_PREVIEW_CHARS).collect::<String>()))
+        .collect();
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "starts": { "type": "array", "items": { "type": "integer", "minimum": 1 } }
+        },
+        "required": ["starts"],
+        "additionalProperties": false
+    });
+    let prompt = format!("{}\n\n{}", SEGMENT_PROMPT, numbered.join("\n"));
+    let reply = match llm.chat_json(&prompt, "recipe_starts", &schema).await {
+        Ok(r) => r,
+        Err(e) => {
+            println!("Error finding recipe boundaries: {:?}", e);
+            return None;
+        }
+    };
+    let Starts { starts } = serde_json::from_str(extract_json(&reply)?).ok()?;
+    // Line numbers count from 1, and models don't always keep to the text
+    let mut starts: Vec<usize> = starts.into_iter().filter(|&n| n >= 1 && n <= lines.len()).map(|n| n - 1).collect();
+    starts.sort_unstable();
+    starts.dedup();
+    if starts.first() != Some(&0) {
+        starts.insert(0, 0);
+    }
+    Some(starts)
+}
+
+/**
+ * The recipes in a pasted document, one piece each. Short text with no
+ * layout to go on is taken as one recipe, as it always was; long text
+ * without any gets its boundaries from the model, and stays whole if that
+ * fails.
+ */
+pub async fn split(llm: &dyn LlmProvider, text: &str) -> Vec<String> {
+    if let Some(pieces) = split_by_layout(text) {
+        return pieces;
+    }
+    if estimate_tokens(text) <= TOKEN_BUDGET {
+        return vec![text.to_string()];
+    }
+    let lines: Vec<&str> = text.lines().collect();
+    match starts_from_llm(llm, &lines).await {
+        Some(starts) => {
+            let pieces = chunks(&lines, &starts);
+            if pieces.is_empty() {
+                vec![text.to_string()]
+            } else {
+                pieces
+            }
+        }
+        None => vec![text.to_string()],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use recipe_llm::{Fixture, Fixtures, MockProvider};
+
+    const COOKBOOK: &str = "Grandma's Recipes
+
+Taco Soup
+Serves 4
+
+A family favorite.
+
+Ingredients:
+1 lb ground beef
+2 cups beef broth
+
+Directions:
+Brown the beef.
+Simmer for 20 minutes.
+
+# Cornbread
+Ingredients
+1 cup cornmeal
+1 egg
+Directions
+Bake at 400F for 20 minutes.
+
+Chili
+
+Ingredients
+2 lb ground beef
+1 can kidney beans
+Method
+Simmer all day.";
+
+    #[test]
+    fn splits_on_ingredients_markers() {
+        let pieces = split_by_layout(COOKBOOK).unwrap();
+        assert_eq!(pieces.len(), 3);
+        assert!(pieces[0].starts_with("Grandma's Recipes\n\nTaco Soup\nServes 4"));
+        assert!(pieces[0].ends_with("Simmer for 20 minutes."));
+        assert!(pieces[1].starts_with("# Cornbread"));
+        assert!(pieces[2].starts_with("Chili\n\nIngredients"));
+    }
+
+    #[test]
+    fn splits_on_blank_lines() {
+        let text = "Pancakes\n1 cup flour\n1 egg\nMix and fry.\n\n\nWaffles\n2 cups flour\n2 eggs\nMix and bake in the iron.";
+        let pieces = split_by_layout(text).unwrap();
+        assert_eq!(pieces, vec!["Pancakes\n1 cup flour\n1 egg\nMix and fry.", "Waffles\n2 cups flour\n2 eggs\nMix and bake in the iron."]);
+
+        // One recipe spaced out, not two
+        let spaced = "Pancakes\n1 cup flour\n1 egg\n\n\nDirections\nMix and fry.\nServe warm.";
+        assert_eq!(split_by_layout(spaced), None);
+        assert_eq!(split_by_layout("Taco Soup\nIngredients\n1 lb ground beef\nDirections\nSimmer."), None);
+    }
+
+    #[tokio::test]
+    async fn asks_the_model_for_long_text() {
+        let soup = "Taco Soup then a long story about the soup ".repeat(200);
+        let stew = "Beef Stew and another long story about the stew ".repeat(200);
+        let text = format!("{}\n{}\n{}\n{}", "Taco Soup", soup, "Beef Stew", stew);
+        let llm = MockProvider::new(Fixtures {
+            chat: vec![Fixture::reply("Give the line number", r#"{"starts": [1, 3, 99]}"#)],
+            ..Fixtures::default()
+        });
+        let pieces = split(&llm, &text).await;
+        assert_eq!(pieces.len(), 2);
+        assert!(pieces[1].starts_with("Beef Stew\n"));
+
+        // Short text stays whole without asking
+        assert_eq!(split(&llm, "Taco Soup\n1 lb ground beef").await, vec!["Taco Soup\n1 lb ground beef"]);
+        assert_eq!(llm.calls().len(), 1);
+    }
+}
@@ -0,0 +1,25 @@
+use scraper::{ElementRef, Html};
+
+// Turns a snippet that may contain markup or entities (JSON-LD descriptions
+// often do) into plain, single-spaced text.
+pub fn clean_text(raw: &str) -> String {
+    let fragment = Html::parse_fragment(raw);
+    let text = fragment.root_element().text().collect::<String>();
+    collapse_whitespace(&text)
+}
+
+pub fn element_text(element: ElementRef) -> String {
+    collapse_whitespace(&element.text().collect::<String>())
+}
+
+// Also drops the stray space markup leaves before punctuation, e.g. WPRM's
+// `<span>gruyere</span> <span>, shredded</span>`.
+pub fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .replace(" ,", ",")
+        .replace(" ;", ";")
+        .replace(" )", ")")
+        .replace("( ", "(")
+}
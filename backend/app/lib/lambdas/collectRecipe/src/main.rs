@@ -1,22 +1,11 @@
 use serde::Deserialize;
 use serde::Serialize;
 use serde_json::{json, Value};
-use aws_sdk_dynamodb::types::AttributeValue;
-use aws_sdk_dynamodb::Client as DbClient;
 use std::env;
 use lambda_http::{Response, Body, Error, Request};
 use lambda_runtime::{service_fn, LambdaEvent};
 use dotenv::dotenv;
-use aws_config::{meta::region::RegionProviderChain, SdkConfig};
-use aws_sdk_dynamodb::{config::Region, meta::PKG_VERSION};
-
-#[derive(Debug)]
-pub struct Opt {
-    /// The AWS Region.
-    pub region: Option<String>,
-    /// Whether to display additional information.
-    pub verbose: bool,
-}
+use recipe_core::{error_response, make_config, ApiError, DynamoStore, Opt, RecipeStore};
 
 #[derive(Deserialize)]
 pub struct RequestBody {
@@ -33,30 +22,12 @@ async fn main() -> Result<(), Error> {
     Ok(())
 }
 
-pub fn make_region_provider(region: Option<String>) -> RegionProviderChain {
-    RegionProviderChain::first_try(region.map(Region::new))
-        .or_default_provider()
-        .or_else(Region::new("us-east-1"))
-}
-
-pub async fn make_config(opt: Opt) -> Result<SdkConfig, Error> {
-    let region_provider = make_region_provider(opt.region);
-
-    println!();
-    if opt.verbose {
-        println!("DynamoDB client version: {}", PKG_VERSION);
-        println!(
-            "Region:                  {}",
-            region_provider.region().await.unwrap().as_ref()
-        );
-        println!();
-    }
-
-    Ok(aws_config::from_env().region(region_provider).load().await)
+async fn get_user_table_name() -> Option<String> {
+    env::var("USER_TABLE_NAME").ok()
 }
 
-async fn get_table_name() -> Option<String> {
-    env::var("TABLE_NAME").ok()
+async fn get_recipe_table_name() -> Option<String> {
+    env::var("RECIPE_TABLE_NAME").ok()
 }
 
 async fn handler(request: Request) -> Result<Response<String>, Error> {
@@ -67,42 +38,27 @@ async fn handler(request: Request) -> Result<Response<String>, Error> {
         };
         let config = match make_config(opt).await {
             Ok(c) => c,
-            Err(e) => {
-                return Ok(Response::builder()
-                .status(500)
-                .header("Access-Control-Allow-Origin", "*")
-                .body(format!("Error making config: {}", e.to_string()))?);
-                
-            },
+            Err(e) => return error_response!(ApiError::Internal(format!("Error making config: {}", e))),
         };
-        let db_client = DbClient::new(&config);
-        println!("DB Client: {:?}", db_client);
-        let table_name = match get_table_name().await {
-            Some(t) => t,
-            None => {
-                return Ok(Response::builder()
-                .status(500)
-                .header("Access-Control-Allow-Origin", "*")
-                .body(String::from("TABLE_NAME not set"))?);
-            }
+        let (user_table_name, recipe_table_name) = match (get_user_table_name().await, get_recipe_table_name().await) {
+            (Some(u), Some(r)) => (u, r),
+            _ => return error_response!(ApiError::Internal(String::from("TABLE_NAME not set"))),
         };
-        println!("Table Name: {}", table_name);
+        let store = DynamoStore::new(&config, &recipe_table_name).with_user_table(&user_table_name);
 
         // 2. Get request body
         let body = request.body();
-        let recipe_collected: RequestBody = serde_json::from_slice(&body)?;
-
-        // 3. Add recipe to DB
-        let username = AttributeValue::S(recipe_collected.username);
-        let uuid = AttributeValue::S(recipe_collected.uuid);
+        let recipe_collected: RequestBody = match serde_json::from_slice(body) {
+            Ok(r) => r,
+            Err(e) => return error_response!(ApiError::from(e)),
+        };
 
-        let db_request = db_client
-            .put_item()
-            .table_name(table_name)
-            .item("username", username)
-            .item("uuid", uuid);
+        // 3. Add recipe to the user's recipe book
+        collect_recipe(&store, recipe_collected).await.or_else(|e| error_response!(e.into()))
+}
 
-        db_request.send().await?;
+async fn collect_recipe(store: &dyn RecipeStore, recipe_collected: RequestBody) -> Result<Response<String>, Error> {
+        store.collect(&recipe_collected.username, &recipe_collected.uuid).await?;
 
         Ok(Response::builder()
             .status(200)
@@ -133,4 +89,16 @@ mod tests {
         let res = aw!(handler(req));
         println!("Response: {:?}", res);
     }
+
+    #[test]
+    fn test_collect_recipe_in_memory() {
+        let store = recipe_core::MemoryStore::new();
+        let body = RequestBody {
+            username: String::from("dmbluesmith"),
+            uuid: String::from("https://tasty.co/recipe/taco-soup")
+        };
+        let res = aw!(collect_recipe(&store, body)).unwrap();
+        assert_eq!(res.status().as_u16(), 200);
+        assert_eq!(aw!(store.collection("dmbluesmith")).unwrap(), vec!["https://tasty.co/recipe/taco-soup"]);
+    }
 }
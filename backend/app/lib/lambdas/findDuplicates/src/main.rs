@@ -0,0 +1,133 @@
+use std::env;
+use lambda_http::{service_fn, Response, Error, Request, RequestExt};
+use recipe_core::{duplicate_groups, error_response, find_by_url, find_duplicates, make_config, ApiError, DynamoStore, Opt, RecipeStore, DUPLICATE_THRESHOLD};
+
+async fn get_table_name() -> Option<String> {
+    env::var("TABLE_NAME").ok()
+}
+
+async fn get_alias_table_name() -> Option<String> {
+    env::var("ALIAS_TABLE_NAME").ok()
+}
+
+// ?threshold=0.8 to only see closer matches, between 0 and 1
+fn get_threshold(threshold: Option<&str>) -> Result<f64, String> {
+    match threshold {
+        None => Ok(DUPLICATE_THRESHOLD),
+        Some(t) => match t.parse::<f64>() {
+            Ok(v) if (0.0..=1.0).contains(&v) => Ok(v),
+            _ => Err(format!("Invalid threshold: {}", t)),
+        },
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let func = service_fn(handler);
+    lambda_http::run(func).await?;
+
+    Ok(())
+}
+
+async fn handler(request: Request) -> Result<Response<String>, Error> {
+    // 1. Read the recipe, if any, and the threshold from the query string
+    let query_params = request.query_string_parameters();
+    let url = query_params.first("url").map(String::from);
+    let threshold = match get_threshold(query_params.first("threshold")) {
+        Ok(t) => t,
+        Err(e) => return error_response!(ApiError::BadRequest(e)),
+    };
+
+    // 2. Create the store from the table names in env
+    let opt = Opt {
+        region: Some("us-east-1".to_string()),
+        verbose: true,
+    };
+    let config = match make_config(opt).await {
+        Ok(c) => c,
+        Err(e) => return error_response!(ApiError::Internal(format!("Error making config: {}", e))),
+    };
+    let (table_name, alias_table_name) = match (get_table_name().await, get_alias_table_name().await) {
+        (Some(t), Some(a)) => (t, a),
+        _ => return error_response!(ApiError::Internal(String::from("TABLE_NAME not set"))),
+    };
+    let store = DynamoStore::new(&config, &table_name).with_alias_table(&alias_table_name);
+
+    get_duplicates(&store, url.as_deref(), threshold).await.or_else(|e| error_response!(e.into()))
+}
+
+/**
+ * With a url, the likely duplicates of that recipe, best first. Without one,
+ * every recipe that has any, so they can be reviewed and merged.
+ */
+async fn get_duplicates(store: &dyn RecipeStore, url: Option<&str>, threshold: f64) -> Result<Response<String>, Error> {
+    // 3. Score the recipe against the others, or every recipe against each other
+    let json_string = match url {
+        Some(url) => {
+            let recipe = match find_by_url(store, url).await? {
+                Some(r) => r,
+                None => return error_response!(ApiError::NotFound(format!("No recipe found for {}", url))),
+            };
+            serde_json::to_string(&find_duplicates(store, &recipe, threshold).await?)
+        }
+        None => serde_json::to_string(&duplicate_groups(&store.list().await?, threshold)),
+    };
+    let json_string = match json_string {
+        Ok(j) => j,
+        Err(e) => return error_response!(ApiError::Internal(format!("Error writing duplicates: {}", e))),
+    };
+
+    Ok(Response::builder()
+        .status(200)
+        .header("Access-Control-Allow-Origin", "*")
+        .body(json_string)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use recipe_core::{MemoryStore, Recipe};
+    use serde_json::Value;
+
+    fn recipe(uuid: &str, name: &str, ingredients: &[&str]) -> Recipe {
+        let mut recipe = Recipe::new(uuid, name);
+        recipe.ingredients = recipe_ingredients::parse_all(&ingredients.iter().map(|i| i.to_string()).collect::<Vec<_>>());
+        recipe.instructions = vec![String::from("Brown the beef, then simmer everything for 20 minutes")];
+        recipe
+    }
+
+    fn store() -> MemoryStore {
+        let soup = ["1 lb ground beef", "2 cups beef broth", "1 can black beans", "1 packet taco seasoning"];
+        MemoryStore::with_recipes(vec![
+            recipe("https://tasty.co/recipe/taco-soup", "Taco Soup", &soup),
+            recipe("448b5102-ca04-4451-a18e-692acbeded01", "Grandma's Taco Soup", &soup),
+            recipe("https://example.com/chili", "Chili", &["2 lb pork shoulder", "3 dried ancho chiles", "1 onion"]),
+        ])
+    }
+
+    #[test]
+    fn test_get_threshold() {
+        assert_eq!(get_threshold(None), Ok(DUPLICATE_THRESHOLD));
+        assert_eq!(get_threshold(Some("0.9")), Ok(0.9));
+        assert!(get_threshold(Some("2")).is_err());
+        assert!(get_threshold(Some("most")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_duplicates() {
+        let res = get_duplicates(&store(), Some("https://tasty.co/recipe/taco-soup?utm_source=x"), DUPLICATE_THRESHOLD).await.unwrap();
+        assert_eq!(res.status().as_u16(), 200);
+        let body: Value = serde_json::from_str(res.body()).unwrap();
+        assert_eq!(body.as_array().unwrap().len(), 1);
+        assert_eq!(body[0]["name"], "Grandma's Taco Soup");
+        assert_eq!(body[0]["score"], 1.0);
+
+        let res = get_duplicates(&store(), None, DUPLICATE_THRESHOLD).await.unwrap();
+        let body: Value = serde_json::from_str(res.body()).unwrap();
+        assert_eq!(body.as_array().unwrap().len(), 2);
+        assert!(body.as_array().unwrap().iter().all(|group| group["uuid"] != "https://example.com/chili"));
+
+        let res = get_duplicates(&store(), Some("https://example.com/missing"), DUPLICATE_THRESHOLD).await.unwrap();
+        assert_eq!(res.status().as_u16(), 404);
+    }
+}
@@ -0,0 +1,142 @@
+use serde::Serialize;
+use std::env;
+use lambda_http::{service_fn, Response, Error, Request, RequestExt};
+use recipe_core::{error_response, make_config, ApiError, DynamoStore, Job, JobStatus, JobStore, Opt, RecipeResponse, RecipeStore};
+
+#[derive(Serialize, Debug)]
+pub struct JobResponse {
+    #[serde(flatten)]
+    pub job: Job,
+    // Sent along once the job is done so clients don't have to fetch it again
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recipe: Option<RecipeResponse>,
+    // Every recipe it saved, in order, for sources that had several
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub recipes: Vec<RecipeResponse>,
+}
+
+async fn get_job_table_name() -> Option<String> {
+    env::var("JOB_TABLE_NAME").ok()
+}
+
+async fn get_recipe_table_name() -> Option<String> {
+    env::var("RECIPE_TABLE_NAME").ok()
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let func = service_fn(handler);
+    lambda_http::run(func).await?;
+
+    Ok(())
+}
+
+async fn handler(request: Request) -> Result<Response<String>, Error> {
+    // 1. Read the job id from the path, GET /jobs/{id}
+    let id = match request.path_parameters().first("id") {
+        Some(id) => id.to_string(),
+        None => return error_response!(ApiError::BadRequest(String::from("No job id supplied"))),
+    };
+
+    // 2. Create the store from the table names in env
+    let opt = Opt {
+        region: Some("us-east-1".to_string()),
+        verbose: true,
+    };
+    let config = match make_config(opt).await {
+        Ok(c) => c,
+        Err(e) => return error_response!(ApiError::Internal(format!("Error making config: {}", e))),
+    };
+    let (job_table_name, recipe_table_name) = match (get_job_table_name().await, get_recipe_table_name().await) {
+        (Some(j), Some(r)) => (j, r),
+        _ => return error_response!(ApiError::Internal(String::from("TABLE_NAME not set"))),
+    };
+    let store = DynamoStore::new(&config, &recipe_table_name).with_job_table(&job_table_name);
+
+    get_job(&store, &store, &id).await.or_else(|e| error_response!(e.into()))
+}
+
+async fn get_job(jobs: &dyn JobStore, recipes: &dyn RecipeStore, id: &str) -> Result<Response<String>, Error> {
+    // 3. Get the job, and its recipes if it's done
+    let job = match jobs.get_job(id).await? {
+        Some(j) => j,
+        None => return error_response!(ApiError::NotFound(format!("No job found for {}", id))),
+    };
+    let mut saved = match job.status {
+        JobStatus::Done => recipes.get_many(&job.recipe_uuids).await?,
+        _ => Vec::new(),
+    };
+    // Batch gets come back in any order
+    saved.sort_by_key(|r| job.recipe_uuids.iter().position(|u| *u == r.uuid));
+    let saved: Vec<RecipeResponse> = saved.into_iter().map(RecipeResponse::from).collect();
+    let recipe = saved.first().cloned();
+
+    let json_string = match serde_json::to_string(&JobResponse { job, recipe, recipes: saved }) {
+        Ok(j) => j,
+        Err(e) => return error_response!(ApiError::Internal(format!("Error writing job: {}", e))),
+    };
+    Ok(Response::builder()
+        .status(200)
+        .header("Access-Control-Allow-Origin", "*")
+        .body(json_string)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use recipe_core::{MemoryStore, Recipe};
+    use serde_json::Value;
+
+    macro_rules! aw {
+        ($e:expr) => {
+            tokio_test::block_on($e)
+        };
+    }
+
+    #[test]
+    fn test_get_job() {
+        let store = MemoryStore::with_recipes(vec![Recipe::new("https://tasty.co/recipe/taco-soup", "Taco Soup")]);
+        let mut parsing = Job::new("parsing");
+        parsing.advance(JobStatus::Parsing);
+        let mut done = Job::new("done");
+        done.finish("https://tasty.co/recipe/taco-soup");
+        aw!(store.put_job(&parsing)).unwrap();
+        aw!(store.put_job(&done)).unwrap();
+
+        let res = aw!(get_job(&store, &store, "parsing")).unwrap();
+        assert_eq!(res.status().as_u16(), 200);
+        let body: Value = serde_json::from_str(res.body()).unwrap();
+        assert_eq!(body["status"], "parsing");
+        assert!(body.get("recipe").is_none());
+
+        let res = aw!(get_job(&store, &store, "done")).unwrap();
+        let body: Value = serde_json::from_str(res.body()).unwrap();
+        assert_eq!(body["status"], "done");
+        assert_eq!(body["recipe_uuid"], "https://tasty.co/recipe/taco-soup");
+        assert_eq!(body["recipe"]["name"], "Taco Soup");
+
+        let mut roundup = Job::new("roundup");
+        roundup.finish_all(&[String::from("https://tasty.co/best-soups"), String::from("https://tasty.co/best-soups#recipe-2")]);
+        aw!(store.put_job(&roundup)).unwrap();
+        aw!(store.put(&Recipe::new("https://tasty.co/best-soups", "Chili"))).unwrap();
+        aw!(store.put(&Recipe::new("https://tasty.co/best-soups#recipe-2", "Chicken Noodle Soup"))).unwrap();
+        let res = aw!(get_job(&store, &store, "roundup")).unwrap();
+        let body: Value = serde_json::from_str(res.body()).unwrap();
+        assert_eq!(body["recipe_uuids"][1], "https://tasty.co/best-soups#recipe-2");
+        assert_eq!(body["recipe"]["name"], "Chili");
+        assert_eq!(body["recipes"][1]["name"], "Chicken Noodle Soup");
+
+        let res = aw!(get_job(&store, &store, "missing")).unwrap();
+        assert_eq!(res.status().as_u16(), 404);
+        let body: Value = serde_json::from_str(res.body()).unwrap();
+        assert_eq!(body["code"], "not_found");
+        assert_eq!(body["message"], "No job found for missing");
+    }
+
+    #[test]
+    fn test_no_job_id() {
+        let req = Request::default();
+        let res = aw!(handler(req)).unwrap();
+        assert_eq!(res.status().as_u16(), 400);
+    }
+}
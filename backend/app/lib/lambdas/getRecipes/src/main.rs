@@ -1,12 +1,10 @@
 use serde::Deserialize;
 use serde::Serialize;
-use serde_json::Value;
+use recipe_core::{error_response, find_by_url, get_units, make_config, ApiError, DynamoStore, Opt, RecipeResponse, RecipeStore};
+use recipe_export::{export, get_format, Document};
 use lambda_runtime::{LambdaEvent};
 use std::collections::HashMap;
-use aws_sdk_dynamodb::types::AttributeValue;
 use std::env;
-use aws_config::{meta::region::RegionProviderChain, SdkConfig};
-use aws_sdk_dynamodb::{config::Region, meta::PKG_VERSION, Client};
 use clap::Parser;
 use futures_util::StreamExt;
 use rayon::iter::ParallelIterator;
@@ -14,16 +12,6 @@ use std::iter::Iterator;
 use lambda_http::{service_fn, Response, Body, Error, Request, RequestExt};
 
 
-#[derive(Debug)]
-pub struct Opt {
-    /// The AWS Region.
-    pub region: Option<String>,
-
-    /// Whether to display additional information.
-    pub verbose: bool,
-}
-
-
 #[derive(Debug, Serialize)]
 pub struct SuccessResponse {
     pub status_code: u8,
@@ -36,50 +24,6 @@ pub struct URLRequest {
     pub url: String,
 }
 
-#[derive(Debug, Serialize)]
-pub struct FailureResponse {
-    pub body: String,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct Recipe {
-    pub uuid: String,
-    pub name: String,
-    pub ingredients: Vec<String>,
-    pub instructions: Vec<String>,
-    pub notes: String,
-    pub summary: String,
-    pub image: String,
-    pub credit: String
-}
-
-impl From<&HashMap<String, AttributeValue>> for Recipe {
-    fn from(value: &HashMap<String, AttributeValue>) -> Self {
-        let mut recipe = Recipe {
-            uuid: as_string(value.get("uuid"), &String::from("UUID")),
-            name: as_string(value.get("name"), &String::from("NAME")),
-            ingredients: split_string(as_string(value.get("ingredients"), &String::from("INGREDIENTS"))),
-            instructions: split_string(as_string(value.get("instructions"), &String::from("INSTRUCTIONS"))),
-            notes: as_string(value.get("notes"), &String::from("NOTES")),
-            summary: as_string(value.get("summary"), &String::from("SUMMARY")),
-            image: as_string(value.get("image"), &String::from("IMAGE")),
-            credit: as_string(value.get("credit"), &String::from("CREDIT"))
-        };
-        recipe
-    }
-}
-
-// Implement Display for the Failure response so that we can then implement Error.
-impl std::fmt::Display for FailureResponse {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.body)
-    }
-}
-
-// Implement Error for the FailureResponse so that we can `?` (try) the Response
-// returned by `lambda_runtime::run(func).await` in `fn main`.
-impl std::error::Error for FailureResponse {}
-
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     let func = service_fn(handler);
@@ -88,130 +32,30 @@ async fn main() -> Result<(), Error> {
     Ok(())
 }
 
-pub fn make_region_provider(region: Option<String>) -> RegionProviderChain {
-    RegionProviderChain::first_try(region.map(Region::new))
-        .or_default_provider()
-        .or_else(Region::new("us-east-1"))
-}
-
-pub async fn make_config(opt: Opt) -> Result<SdkConfig, Error> {
-    let region_provider = make_region_provider(opt.region);
-
-    println!();
-    if opt.verbose {
-        println!("DynamoDB client version: {}", PKG_VERSION);
-        println!(
-            "Region:                  {}",
-            region_provider.region().await.unwrap().as_ref()
-        );
-        println!();
-    }
-
-    Ok(aws_config::from_env().region(region_provider).load().await)
-}
-
-fn as_string(val: Option<&AttributeValue>, default: &String) -> String {
-    if let Some(v) = val {
-        if let Ok(s) = v.as_s() {
-            return s.to_owned();
-        }
-    }
-    default.to_owned()
-}
-
-fn split_string(string: String) -> Vec<String> {
-    let escaped_strings: Vec<String> = string
-        .split(";")
-        .map(|substring| substring.replace("\\;", ";").replace("\\,", ",").replace("\\\\", "\\"))
-        .collect();
-
-    escaped_strings
-}
-
 async fn get_table_name() -> Option<String> {
     env::var("TABLE_NAME").ok()
 }
 
-async fn get_recipe_from_db(client: &Client, table_name: &str, url: &str) -> Result<Vec<Recipe>, Error> {
-    let pk = AttributeValue::S(url.to_string());
-    
-    let response = client.get_item()
-        .table_name(table_name)
-        .key("uuid".to_string(), pk)
-        .send().await?;
-    let mut recipes = Vec::new();
-    if let Some(recipe) = response.item {
-        recipes.push(Recipe::from(&recipe));
-        return Ok(recipes);
-    } else {
-        return Ok(recipes);
-    }
+async fn get_alias_table_name() -> Option<String> {
+    env::var("ALIAS_TABLE_NAME").ok()
 }
 
-async fn get_recipes_from_db(client: &Client, table_name: &str) -> Result<Vec<Recipe>, FailureResponse> {
-    let tables = match client.list_tables().send().await {
-        Ok(t) => t,
-        Err(e) => {
-            println!("Client list tables error: {}", e.to_string());
-            return Err(FailureResponse {
-                body: format!("Error reading from db: {:?}", e)
-            });
-        }
-    };
-    println!("Tables: {:?}", tables);
-    let items = match client
-        .scan()
-        .table_name(table_name)
-        .send()
-        .await {
-            Ok(i) => i,
-            Err(e) => {
-                return Err(FailureResponse {
-                    body: format!("Err db: {:?}", e)
-                });
-            }
-        };
-
-    
-    let recipes: Vec<Recipe> = items
-        .items
-        .unwrap()
-        .iter()
-        .map(|hashmap| Recipe::from(hashmap))
-        .collect();
-    return Ok(recipes);
-}
-
-async fn handler(request: Request) -> Result<Response<String>, Error> {
-    // 1. Create db client and get table name from env
+async fn handler(request: Request) -> Result<Response<Body>, Error> {
+    // 1. Create the store from the table name in env
     let opt = Opt {
         region: Some("us-east-1".to_string()),
         verbose: true,
     };
     let config = match make_config(opt).await {
         Ok(c) => c,
-        Err(e) => {
-            return Ok(Response::builder()
-            .status(500)
-            .body(format!("Error making config: {}", e.to_string()))?);
-            
-        },
+        Err(e) => return error_response!(ApiError::Internal(format!("Error making config: {}", e))),
     };
-    println!("Config: {:?}", config);
-    let db_client = Client::new(&config);
-    println!("DB Client: {:?}", db_client);
-    let table_name = match get_table_name().await {
-        Some(t) => t,
-        None => {
-            return Ok(Response::builder()
-            .status(500)
-            .body(String::from("TABLE_NAME not set"))?);
-        }
+    let (table_name, alias_table_name) = match (get_table_name().await, get_alias_table_name().await) {
+        (Some(t), Some(a)) => (t, a),
+        _ => return error_response!(ApiError::Internal(String::from("TABLE_NAME not set"))),
     };
     println!("Table Name: {}", table_name);
-    // 2. Get recipes from db
-
-    // 2a. Check if url provided then get just that recipe, otherwise return all recipes
+    let store = DynamoStore::new(&config, &table_name).with_alias_table(&alias_table_name);
 
     let query_params = request.query_string_parameters();
     let query_params_map: HashMap<String, String> = query_params
@@ -219,19 +63,127 @@ async fn handler(request: Request) -> Result<Response<String>, Error> {
         .map(|(key, value)| (key.to_string(), value.to_string()))
         .collect();
 
+    get_recipes(&store, &query_params_map).await.or_else(|e| error_response!(e.into()))
+}
+
+async fn get_recipes(store: &dyn RecipeStore, query_params_map: &HashMap<String, String>) -> Result<Response<Body>, Error> {
+    let units = match get_units(
+        query_params_map.get("units").map(String::as_str),
+        query_params_map.get("measure").map(String::as_str),
+    ) {
+        Ok(u) => u,
+        Err(e) => return error_response!(ApiError::BadRequest(e)),
+    };
+    let format = match get_format(query_params_map.get("format").map(String::as_str)) {
+        Ok(f) => f,
+        Err(e) => return error_response!(ApiError::BadRequest(e)),
+    };
+
+    // 2. Get recipes from the store
+    // 2a. Check if url provided then get just that recipe, however the URL is written, otherwise return all recipes
     let recipes = if let Some(url_value) = query_params_map.get("url") {
-        get_recipe_from_db(&db_client, &table_name, &url_value).await?
+        find_by_url(store, url_value).await?.into_iter().collect()
     } else {
-        get_recipes_from_db(&db_client, &table_name).await?
+        store.list().await?
     };
 
+    // 2b. Export them as another app's format, if that's what was asked for
+    if let Some(format) = format {
+        let document = match export(format, &recipes) {
+            Ok(d) => d,
+            Err(e) => return error_response!(ApiError::BadRequest(e)),
+        };
+        let content_type = document.content_type(format);
+        let body = match document {
+            Document::Text(text) => Body::from(text),
+            // Several Cooklang recipes, a file each
+            Document::Zip(zip) => Body::Binary(zip),
+        };
+        return Ok(Response::builder()
+            .status(200)
+            .header("Access-Control-Allow-Origin", "*")
+            .header("Content-Type", content_type)
+            .body(body)?);
+    }
+    let mut recipes: Vec<RecipeResponse> = recipes.into_iter().map(RecipeResponse::from).collect();
+
+    // 2c. Convert to the requested units
+    if let Some((system, measure)) = units {
+        recipes.iter_mut().for_each(|recipe| recipe.convert_units(system, measure));
+    }
+
     // 3. Return said recipes in JSON format
-    let json_string = serde_json::to_string(&recipes).unwrap();
-    let mut cors = HashMap::new();
-    cors.insert(String::from("Access-Control-Allow-Origin"), String::from("*"));
+    let json_string = match serde_json::to_string(&recipes) {
+        Ok(j) => j,
+        Err(e) => return error_response!(ApiError::Internal(format!("Error writing recipes: {}", e))),
+    };
 
     Ok(Response::builder()
         .status(200)
         .header("Access-Control-Allow-Origin", "*")
-        .body(json_string)?)
+        .body(Body::from(json_string))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use recipe_core::{MemoryStore, Recipe};
+
+    fn store() -> MemoryStore {
+        let mut soup = Recipe::new("https://tasty.co/recipe/taco-soup", "Taco Soup");
+        soup.ingredients = recipe_ingredients::parse_all(&[String::from("2 cups beef broth")]);
+        soup.instructions = vec![String::from("Simmer at 350°F")];
+        MemoryStore::with_recipes(vec![soup, Recipe::new("abc", "Gyoza")])
+    }
+
+    fn params(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[tokio::test]
+    async fn test_get_recipes() {
+        let res = get_recipes(&store(), &params(&[])).await.unwrap();
+        let recipes: Vec<RecipeResponse> = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(recipes.len(), 2);
+
+        let res = get_recipes(&store(), &params(&[("url", "https://tasty.co/recipe/taco-soup"), ("units", "metric")])).await.unwrap();
+        let recipes: Vec<RecipeResponse> = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(recipes[0].ingredients, vec!["2 cups beef broth"]);
+        assert_eq!(recipes[0].converted_ingredients.as_ref().unwrap()[0].text, "475 ml beef broth");
+        assert_eq!(recipes[0].instructions, vec!["Simmer at 175°C"]);
+
+        let res = get_recipes(&store(), &params(&[("url", "https://tasty.co/amp/recipe/taco-soup?fbclid=abc")])).await.unwrap();
+        let recipes: Vec<RecipeResponse> = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(recipes[0].name, "Taco Soup");
+    }
+
+    #[tokio::test]
+    async fn test_get_recipes_export() {
+        let res = get_recipes(&store(), &params(&[("url", "https://tasty.co/recipe/taco-soup"), ("format", "markdown")])).await.unwrap();
+        assert_eq!(res.status().as_u16(), 200);
+        assert_eq!(res.headers()["Content-Type"], "text/markdown; charset=utf-8");
+        let markdown = std::str::from_utf8(res.body()).unwrap();
+        assert!(markdown.starts_with("# Taco Soup\n"));
+        assert!(markdown.contains("- 2 cups beef broth\n"));
+
+        let res = get_recipes(&store(), &params(&[("format", "jsonld")])).await.unwrap();
+        let ld: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(ld["@graph"].as_array().unwrap().len(), 2);
+
+        // Cooklang is a file per recipe, so both come zipped
+        let res = get_recipes(&store(), &params(&[("format", "cooklang")])).await.unwrap();
+        assert_eq!(res.headers()["Content-Type"], "application/zip");
+        assert!(matches!(res.body(), Body::Binary(zip) if zip.starts_with(b"PK")));
+
+        let res = get_recipes(&store(), &params(&[("format", "docx")])).await.unwrap();
+        assert_eq!(res.status().as_u16(), 400);
+    }
+
+    #[tokio::test]
+    async fn test_get_recipes_bad_units() {
+        let res = get_recipes(&store(), &params(&[("units", "cubits")])).await.unwrap();
+        assert_eq!(res.status().as_u16(), 400);
+        let body: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(body["code"], "bad_request");
+    }
 }
@@ -1,16 +1,15 @@
 use serde::Deserialize;
 use serde::Serialize;
+use recipe_book::Package;
+use recipe_bucket::{Downloads, Images, S3Downloads, S3Images};
+use recipe_core::{error_response, get_units, make_config, ApiError, DynamoStore, Opt, Recipe, RecipeResponse, RecipeStore, StoreError};
+use recipe_export::{export, get_format, Document};
 use serde_json::{json, Value};
-use aws_sdk_dynamodb::types::AttributeValue;
-use aws_sdk_dynamodb::Client as DbClient;
 use std::env;
 use lambda_http::{Response, Body, Error, Request};
 use lambda_runtime::{service_fn, LambdaEvent};
 use dotenv::dotenv;
-use aws_config::{meta::region::RegionProviderChain, SdkConfig};
-use aws_sdk_dynamodb::{config::Region, meta::PKG_VERSION};
 use std::collections::HashMap;
-use futures::future::try_join_all;
 use lambda_http::http::HeaderValue;
 use http::header::HeaderMap;
 use lambda_http::ext::extensions::RequestExt;
@@ -21,79 +20,11 @@ use lambda_http::request::from_str;
 
 
 
-#[derive(Debug)]
-pub struct Opt {
-    /// The AWS Region.
-    pub region: Option<String>,
-    /// Whether to display additional information.
-    pub verbose: bool,
-}
-
 #[derive(Deserialize)]
 pub struct RequestBody {
     pub username: String
 }
 
-#[derive(Serialize, Debug)]
-pub struct RecipeMetaData {
-    pub username: String,
-    pub uuid: String
-}
-
-impl From<&HashMap<String, AttributeValue>> for RecipeMetaData {
-    fn from(value: &HashMap<String, AttributeValue>) -> Self {
-        let mut recipe = RecipeMetaData {
-            username: as_string(value.get("username"), &String::from("USERNAME")),
-            uuid: as_string(value.get("uuid"), &String::from("UUID"))
-        };
-        recipe
-    }
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct Recipe {
-    pub uuid: String,
-    pub name: String,
-    pub ingredients: Vec<String>,
-    pub instructions: Vec<String>,
-    pub notes: String,
-    pub summary: String,
-    pub image: String,
-}
-
-impl From<&HashMap<String, AttributeValue>> for Recipe {
-    fn from(value: &HashMap<String, AttributeValue>) -> Self {
-        let mut recipe = Recipe {
-            uuid: as_string(value.get("uuid"), &String::from("UUID")),
-            name: as_string(value.get("name"), &String::from("NAME")),
-            ingredients: split_string(as_string(value.get("ingredients"), &String::from("INGREDIENTS"))),
-            instructions: split_string(as_string(value.get("instructions"), &String::from("INSTRUCTIONS"))),
-            notes: as_string(value.get("notes"), &String::from("NOTES")),
-            summary: as_string(value.get("summary"), &String::from("SUMMARY")),
-            image: as_string(value.get("image"), &String::from("IMAGE"))
-        };
-        recipe
-    }
-}
-
-fn as_string(val: Option<&AttributeValue>, default: &String) -> String {
-    if let Some(v) = val {
-        if let Ok(s) = v.as_s() {
-            return s.to_owned();
-        }
-    }
-    default.to_owned()
-}
-
-fn split_string(string: String) -> Vec<String> {
-    let escaped_strings: Vec<String> = string
-        .split(";")
-        .map(|substring| substring.replace("\\;", ";").replace("\\,", ",").replace("\\\\", "\\"))
-        .collect();
-
-    escaped_strings
-}
-
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     let func = service_fn(handler);
@@ -102,18 +33,6 @@ async fn main() -> Result<(), Error> {
     Ok(())
 }
 
-pub fn make_region_provider(region: Option<String>) -> RegionProviderChain {
-    RegionProviderChain::first_try(region.map(Region::new))
-        .or_default_provider()
-        .or_else(Region::new("us-east-1"))
-}
-
-pub async fn make_config(opt: Opt) -> Result<SdkConfig, Error> {
-    let region_provider = make_region_provider(opt.region);
-
-    Ok(aws_config::from_env().region(region_provider).load().await)
-}
-
 async fn get_user_table_name() -> Option<String> {
     env::var("USER_TABLE_NAME").ok()
 }
@@ -122,31 +41,8 @@ async fn get_recipe_table_name() -> Option<String> {
     env::var("RECIPE_TABLE_NAME").ok()
 }
 
-async fn get_recipe_from_db(client: &DbClient, table_name: &String, r: RecipeMetaData) -> Result<Recipe, Error> {
-    let pk = AttributeValue::S(r.uuid);
-
-    let response = client.get_item()
-        .table_name(table_name)
-        .key("uuid".to_string(), pk)
-        .send()
-        .await?;
-
-    if let Some(recipe) = response.item {
-        return Ok(Recipe::from(&recipe));
-    } else {
-        return Err(Error::from("Error getting item"));
-    }
-}
-
-async fn fetch_recipes(client: &DbClient, table_name: &String, recipe_meta_data_vec: Vec<RecipeMetaData>) -> Result<Vec<Recipe>, Error> {
-    let futures = recipe_meta_data_vec
-        .into_iter()
-        .map(|meta_data| get_recipe_from_db(client, table_name, meta_data));
-
-    // Use try_join_all to asynchronously collect the results
-    let results: Result<Vec<Recipe>, Error> = try_join_all(futures).await;
-
-    results
+async fn get_bucket_name() -> Option<String> {
+    env::var("BUCKET_NAME").ok()
 }
 
 fn get_request_username(request: Request) -> Option<RequestBody> {
@@ -161,93 +57,179 @@ fn get_request_username(request: Request) -> Option<RequestBody> {
     }
 }
 
-async fn handler(request: Request) -> Result<Response<String>, Error> {
-        // 1. Create db client and get table name from env
+async fn handler(request: Request) -> Result<Response<Body>, Error> {
+        // 1. Create the store from the table names in env
         let opt = Opt {
             region: Some("us-east-1".to_string()),
             verbose: true,
         };
         let config = match make_config(opt).await {
             Ok(c) => c,
-            Err(e) => {
-                return Ok(Response::builder()
-                .status(400)
-                .body(format!("Error making config: {}", e.to_string()))?);
-                
-            },
+            Err(e) => return error_response!(ApiError::Internal(format!("Error making config: {}", e))),
         };
-        let db_client = DbClient::new(&config);
 
         let user_table_name = match get_user_table_name().await {
             Some(t) => t,
-            None => {
-                return Ok(Response::builder()
-                .status(400)
-                .body(String::from("TABLE_NAME not set"))?);
-            }
+            None => return error_response!(ApiError::Internal(String::from("USER_TABLE_NAME not set"))),
         };
 
         let recipe_table_name = match get_recipe_table_name().await {
             Some(t) => t,
-            None => {
-                return Ok(Response::builder()
-                .status(400)
-                .body(String::from("TABLE_NAME not set"))?);
-            }
+            None => return error_response!(ApiError::Internal(String::from("RECIPE_TABLE_NAME not set"))),
         };
+        let store = DynamoStore::new(&config, &recipe_table_name).with_user_table(&user_table_name);
 
-        // 2. Get request user
+        // Books for e-readers and the web are files with the pictures in, so they also need the bucket
+        if let Some(package) = request.query_string_parameters().first("format").and_then(Package::parse) {
+            let bucket_name = match get_bucket_name().await {
+                Some(b) => b,
+                None => return error_response!(ApiError::Internal(String::from("BUCKET_NAME not set"))),
+            };
+            let images = S3Images::new(&config, &bucket_name);
+            let downloads = S3Downloads::new(&config, &bucket_name);
+            return package_user_recipes(&store, &images, &downloads, request, package).await.or_else(|e| error_response!(e.into()));
+        }
+
+        get_user_recipes(&store, request).await.or_else(|e| error_response!(e.into()))
+}
+
+/**
+ * Every recipe in the user's book, or none if they haven't collected any.
+ */
+async fn fetch_recipes(store: &dyn RecipeStore, username: &str) -> Result<Vec<Recipe>, StoreError> {
+        let uuids = store.collection(username).await?;
+        if uuids.is_empty() {
+            return Ok(Vec::new());
+        }
+        println!("Recipes for User: {:?}", uuids);
+        store.get_many(&uuids).await
+}
+
+async fn get_user_recipes(store: &dyn RecipeStore, request: Request) -> Result<Response<Body>, Error> {
+        // 2. Get request user and the units or format they want
+        let query_params = request.query_string_parameters();
+        let units = match get_units(query_params.first("units"), query_params.first("measure")) {
+            Ok(u) => u,
+            Err(e) => return error_response!(ApiError::BadRequest(e)),
+        };
+        let format = match get_format(query_params.first("format")) {
+            Ok(f) => f,
+            Err(e) => return error_response!(ApiError::BadRequest(e)),
+        };
         let user = match get_request_username(request) {
             Some(u) => u,
-            None => {
-                return Ok(Response::builder()
-                    .status(400)
-                    .body(String::from("No username supplied"))?);
-            }
+            None => return error_response!(ApiError::BadRequest(String::from("No username supplied"))),
         };
 
-        // 3. Get user's recipes
-        let user_recipes = db_client
-            .query()
-            .table_name(user_table_name)
-            .key_condition_expression("#un = :username")
-            .expression_attribute_names("#un", "username")
-            .expression_attribute_values(":username", AttributeValue::S(user.username))
-            .send()
-            .await?;
-
-        // 4. For each recipe uuid, get the recipe
-        if let Some(items) = user_recipes.items {
-            let recipe_meta_data: Vec<RecipeMetaData> = items.iter().map(|item| {
-                RecipeMetaData::from(item)
-            }).collect();
-            println!("Recipes for User: {:?}", recipe_meta_data);
-
-            let recipes = match fetch_recipes(&db_client, &recipe_table_name, recipe_meta_data).await {
-                Ok(r) => r,
-                Err(e) => {
-                    return Ok(Response::builder()
-                        .status(400)
-                        .body(format!("Error fetching recipes: {}", e))?);
-                }
-            };
-
-            let json_string = serde_json::to_string(&recipes).unwrap();
-            Ok(Response::builder()
+        // 3. Get every recipe they collected
+        let recipes = match fetch_recipes(store, &user.username).await {
+            Ok(r) => r,
+            Err(e) => return error_response!(ApiError::from(e)),
+        };
+        if recipes.is_empty() {
+            return Ok(Response::builder()
                 .status(200)
-                .header("Access-Control-Allow-Origin", "*")
-                .body(json_string)?)        
-        } else {
+                .body(Body::from("No recipes found for user"))?);
+        }
+
+        // 4. Export them as another app's format, if that's what was asked for
+        if let Some(format) = format {
+            let document = match export(format, &recipes) {
+                Ok(d) => d,
+                Err(e) => return error_response!(ApiError::BadRequest(e)),
+            };
+            let content_type = document.content_type(format);
+            let body = match document {
+                Document::Text(text) => Body::from(text),
+                // Cooklang is a recipe to a file, so a book of them is a zip
+                Document::Zip(zip) => Body::Binary(zip),
+            };
             return Ok(Response::builder()
                 .status(200)
-                .body(String::from("No recipes found for user"))?);
+                .header("Access-Control-Allow-Origin", "*")
+                .header("Content-Type", content_type)
+                .body(body)?);
+        }
+        let mut recipes: Vec<RecipeResponse> = recipes.into_iter().map(RecipeResponse::from).collect();
+
+        if let Some((system, measure)) = units {
+            recipes.iter_mut().for_each(|recipe| recipe.convert_units(system, measure));
+        }
+
+        let json_string = match serde_json::to_string(&recipes) {
+            Ok(j) => j,
+            Err(e) => return error_response!(ApiError::Internal(format!("Error writing recipes: {}", e))),
+        };
+        Ok(Response::builder()
+            .status(200)
+            .header("Access-Control-Allow-Origin", "*")
+            .body(Body::from(json_string))?)
+}
+
+// "dmbluesmith" downloads as dmbluesmith-recipes.epub
+fn file_name(username: &str, package: Package) -> String {
+    let name: String = username.chars().filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_').collect();
+    if name.is_empty() {
+        format!("recipes.{}", package.extension())
+    } else {
+        format!("{}-recipes.{}", name, package.extension())
+    }
+}
+
+/**
+ * The user's recipe book, A to Z, as an EPUB or a static site, with the
+ * pictures we host copied in so it reads offline. With the pictures in
+ * it's soon too big to send back, so it's saved and the client sent a
+ * link to it.
+ */
+async fn package_user_recipes(store: &dyn RecipeStore, images: &dyn Images, downloads: &dyn Downloads, request: Request, package: Package) -> Result<Response<Body>, Error> {
+        // 2. Get request user
+        let user = match get_request_username(request) {
+            Some(u) => u,
+            None => return error_response!(ApiError::BadRequest(String::from("No username supplied"))),
+        };
+
+        // 3. Get every recipe they collected
+        let mut recipes = match fetch_recipes(store, &user.username).await {
+            Ok(r) => r,
+            Err(e) => return error_response!(ApiError::from(e)),
+        };
+        if recipes.is_empty() {
+            return error_response!(ApiError::NotFound(format!("No recipes found for {}", user.username)));
         }
+        recipes.sort_by_key(|r| r.name.to_lowercase());
+
+        // 4. Copy in their pictures and bundle them up
+        let mut pictures = Vec::new();
+        for recipe in &recipes {
+            pictures.push(if recipe.image.is_empty() { None } else { images.get(&recipe.image).await });
+        }
+        let book: Vec<(&Recipe, Option<&[u8]>)> = recipes.iter().zip(&pictures).map(|(r, p)| (r, p.as_deref())).collect();
+        let title = format!("{}'s Recipes", user.username);
+        let bytes = match recipe_book::package(package, &title, &book) {
+            Ok(b) => b,
+            Err(e) => return error_response!(ApiError::Internal(e)),
+        };
+
+        // 5. Save it and send them to it, with the link in the body too for clients that don't follow redirects
+        let link = match downloads.save(&file_name(&user.username, package), package.content_type(), bytes).await {
+            Ok(l) => l,
+            Err(e) => return error_response!(ApiError::Unavailable(e)),
+        };
+        Ok(Response::builder()
+            .status(303)
+            .header("Access-Control-Allow-Origin", "*")
+            .header("Location", &link)
+            .header("Content-Type", "application/json")
+            .body(Body::from(json!({ "url": link }).to_string()))?)
 }
 
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use recipe_bucket::{MemoryDownloads, MemoryImages, PNG};
+    use recipe_core::MemoryStore;
 
     macro_rules! aw {
         ($e:expr) => {
@@ -271,4 +253,113 @@ mod tests {
         println!("Response: {:?}", res);
         assert_eq!(res.unwrap().status().as_u16(), 200);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_get_user_recipes_from_memory() {
+        let mut soup = Recipe::new("https://tasty.co/recipe/taco-soup", "Taco Soup");
+        soup.ingredients = recipe_ingredients::parse_all(&[String::from("1 lb ground beef")]);
+        let store = MemoryStore::with_recipes(vec![soup, Recipe::new("abc", "Gyoza")]);
+        aw!(store.collect("dmbluesmith", "https://tasty.co/recipe/taco-soup")).unwrap();
+
+        let params = HashMap::from([
+            (String::from("username"), String::from("dmbluesmith")),
+            (String::from("measure"), String::from("weight")),
+        ]);
+        let req = Request::default().with_query_string_parameters(params);
+        let res = aw!(get_user_recipes(&store, req)).unwrap();
+        let recipes: Vec<RecipeResponse> = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(recipes.len(), 1);
+        assert_eq!(recipes[0].name, "Taco Soup");
+        assert_eq!(recipes[0].converted_ingredients.as_ref().unwrap()[0].text, "455 g ground beef");
+
+        let res = aw!(get_user_recipes(&store, Request::default())).unwrap();
+        assert_eq!(res.status().as_u16(), 400);
+        let body: Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(body["message"], "No username supplied");
+    }
+
+    #[test]
+    fn test_export_user_recipes() {
+        let mut soup = Recipe::new("https://tasty.co/recipe/taco-soup", "Taco Soup");
+        soup.ingredients = recipe_ingredients::parse_all(&[String::from("1 lb ground beef")]);
+        soup.instructions = vec![String::from("Brown the ground beef.")];
+        let mut gyoza = Recipe::new("abc", "Gyoza");
+        gyoza.instructions = vec![String::from("Pan fry until crisp.")];
+        let store = MemoryStore::with_recipes(vec![soup, gyoza]);
+        aw!(store.collect("dmbluesmith", "https://tasty.co/recipe/taco-soup")).unwrap();
+        aw!(store.collect("dmbluesmith", "abc")).unwrap();
+
+        let params = HashMap::from([
+            (String::from("username"), String::from("dmbluesmith")),
+            (String::from("format"), String::from("cooklang")),
+        ]);
+        let req = Request::default().with_query_string_parameters(params);
+        let res = aw!(get_user_recipes(&store, req)).unwrap();
+        assert_eq!(res.status().as_u16(), 200);
+        assert_eq!(res.headers()["Content-Type"], "application/zip");
+        // A .cook file each, which import reads back as the whole book
+        let zip = match res.body() {
+            Body::Binary(zip) => zip,
+            other => panic!("expected a zip, got {:?}", other),
+        };
+        let mut names: Vec<String> = recipe_import::read(recipe_import::Format::Cooklang, zip, None).unwrap().into_iter().map(|p| p.unwrap().name).collect();
+        names.sort();
+        assert_eq!(names, ["Gyoza", "Taco Soup"]);
+
+        // One recipe is one Cooklang file
+        aw!(store.collect("tacofan", "https://tasty.co/recipe/taco-soup")).unwrap();
+        let req = Request::default().with_query_string_parameters(HashMap::from([
+            (String::from("username"), String::from("tacofan")),
+            (String::from("format"), String::from("cooklang")),
+        ]));
+        let res = aw!(get_user_recipes(&store, req)).unwrap();
+        assert_eq!(res.headers()["Content-Type"], "text/plain; charset=utf-8");
+        let cook = std::str::from_utf8(res.body()).unwrap();
+        assert!(cook.contains("Brown the @ground beef{1%lb}."));
+        assert_eq!(cook.matches(">> title:").count(), 1);
+
+        let params = HashMap::from([
+            (String::from("username"), String::from("dmbluesmith")),
+            (String::from("format"), String::from("recipeml")),
+        ]);
+        let req = Request::default().with_query_string_parameters(params);
+        let res = aw!(get_user_recipes(&store, req)).unwrap();
+        assert_eq!(res.headers()["Content-Type"], "application/xml");
+        assert_eq!(std::str::from_utf8(res.body()).unwrap().matches("<recipe>").count(), 2);
+    }
+
+    const IMAGE_URL: &str = "https://recipe-images.s3.us-east-1.amazonaws.com/soup.jpg";
+
+    #[test]
+    fn test_package_user_recipes() {
+        let mut soup = Recipe::new("https://tasty.co/recipe/taco-soup", "Taco Soup");
+        soup.image = IMAGE_URL.to_string();
+        soup.instructions = vec![String::from("Brown the ground beef.")];
+        let store = MemoryStore::with_recipes(vec![soup, Recipe::new("abc", "Gyoza"), Recipe::new("def", "Apple Pie")]);
+        aw!(store.collect("dmbluesmith", "https://tasty.co/recipe/taco-soup")).unwrap();
+        aw!(store.collect("dmbluesmith", "abc")).unwrap();
+        let images = MemoryImages::with_images(vec![(IMAGE_URL, PNG)]);
+        let downloads = MemoryDownloads::new();
+        let request = |username: &str| Request::default().with_query_string_parameters(HashMap::from([(String::from("username"), username.to_string())]));
+
+        let res = aw!(package_user_recipes(&store, &images, &downloads, request("dmbluesmith"), Package::Epub)).unwrap();
+        assert_eq!(res.status().as_u16(), 303);
+        let link = MemoryDownloads::link("dmbluesmith-recipes.epub");
+        assert_eq!(res.headers()["Location"], link.as_str());
+        let body: Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(body["url"], link);
+        let epub = downloads.saved().pop().unwrap();
+        assert_eq!(epub.content_type, "application/epub+zip");
+        assert!(epub.bytes.starts_with(b"PK"));
+        // Pictures go in as they are, and only recipes with one ask for it
+        assert!(epub.bytes.windows(PNG.len()).any(|w| w == PNG));
+        assert_eq!(images.asked(), vec![IMAGE_URL]);
+
+        let res = aw!(package_user_recipes(&store, &images, &downloads, request("dmbluesmith"), Package::Site)).unwrap();
+        assert_eq!(res.headers()["Location"], MemoryDownloads::link("dmbluesmith-recipes.zip").as_str());
+        assert_eq!(downloads.saved().pop().unwrap().content_type, "application/zip");
+
+        let res = aw!(package_user_recipes(&store, &images, &downloads, request("nobody"), Package::Epub)).unwrap();
+        assert_eq!(res.status().as_u16(), 404);
+    }
+}
@@ -0,0 +1,208 @@
+use serde::Serialize;
+use std::env;
+use lambda_http::{service_fn, Response, Error, Request, RequestExt};
+use recipe_core::{error_response, make_config, ApiError, DynamoStore, Opt, RecipeResult, RecipeStore};
+use recipe_import::{import_key, read, Format};
+
+#[derive(Serialize, Debug)]
+pub struct ImportResponse {
+    pub format: &'static str,
+    // How many of the recipes were saved, the rest have an error in `recipes`
+    pub imported: usize,
+    // Of those, how many were there from an earlier import and left as they were
+    pub already_imported: usize,
+    pub recipes: Vec<RecipeResult>,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let func = service_fn(handler);
+    lambda_http::run(func).await?;
+
+    Ok(())
+}
+
+async fn get_user_table_name() -> Option<String> {
+    env::var("USER_TABLE_NAME").ok()
+}
+
+async fn get_recipe_table_name() -> Option<String> {
+    env::var("RECIPE_TABLE_NAME").ok()
+}
+
+// ?format=paprika, or else guessed from ?filename=Dinners.paprikarecipes
+fn get_format(format: Option<&str>, filename: Option<&str>) -> Result<Format, String> {
+    match (format, filename) {
+        (Some(f), _) => Format::parse(f).ok_or_else(|| format!("Unknown format: {}", f)),
+        (None, Some(name)) => Format::from_filename(name).ok_or_else(|| format!("Can't tell the format of {}, supply format", name)),
+        (None, None) => Err(String::from("Supply either format or filename")),
+    }
+}
+
+async fn handler(request: Request) -> Result<Response<String>, Error> {
+    // 1. Read who's importing and what from the query string, the file is the body
+    let query_params = request.query_string_parameters();
+    let username = match query_params.first("username") {
+        Some(u) => u.to_string(),
+        None => return error_response!(ApiError::BadRequest(String::from("No username supplied"))),
+    };
+    let filename = query_params.first("filename").map(str::to_string);
+    let format = match get_format(query_params.first("format"), filename.as_deref()) {
+        Ok(f) => f,
+        Err(e) => return error_response!(ApiError::BadRequest(e)),
+    };
+    let bytes: &[u8] = request.body();
+    if bytes.is_empty() {
+        return error_response!(ApiError::BadRequest(String::from("No file supplied")));
+    }
+
+    // 2. Create the store from the table names in env
+    let opt = Opt {
+        region: Some("us-east-1".to_string()),
+        verbose: true,
+    };
+    let config = match make_config(opt).await {
+        Ok(c) => c,
+        Err(e) => return error_response!(ApiError::Internal(format!("Error making config: {}", e))),
+    };
+    let (user_table_name, recipe_table_name) = match (get_user_table_name().await, get_recipe_table_name().await) {
+        (Some(u), Some(r)) => (u, r),
+        _ => return error_response!(ApiError::Internal(String::from("TABLE_NAME not set"))),
+    };
+    let store = DynamoStore::new(&config, &recipe_table_name).with_user_table(&user_table_name);
+
+    import_recipes(&store, &username, format, bytes, filename.as_deref()).await.or_else(|e| error_response!(e.into()))
+}
+
+/**
+ * Saves every recipe in the file and adds it to the user's recipe book.
+ * Nothing goes through the model: the apps already split everything into
+ * fields. Recipes are keyed by what's in them, so one imported before is
+ * left alone rather than undoing the user's edits, and if the store fails
+ * partway the import can simply be sent again.
+ */
+async fn import_recipes(store: &dyn RecipeStore, username: &str, format: Format, bytes: &[u8], filename: Option<&str>) -> Result<Response<String>, Error> {
+    // 3. Parse the file, one bad recipe is reported in its place
+    let parsed = match read(format, bytes, filename) {
+        Ok(p) => p,
+        Err(e) => return error_response!(ApiError::Unprocessable(e.to_string())),
+    };
+
+    // 4. Save each recipe and collect it
+    let mut recipes = Vec::new();
+    let mut already_imported = 0;
+    for (i, parsed) in parsed.into_iter().enumerate() {
+        let part = i + 1;
+        let imported = match parsed {
+            Ok(r) => r,
+            Err(e) => {
+                println!("Skipping recipe {} of the import: {}", part, e);
+                recipes.push(RecipeResult { part, name: None, recipe_uuid: None, error: Some(e.to_string()) });
+                continue;
+            }
+        };
+        let recipe = imported.to_recipe(&import_key(username, &imported), username);
+        match store.get(&recipe.uuid).await {
+            Ok(Some(_)) => already_imported += 1,
+            Ok(None) => {
+                if let Err(e) = store.put(&recipe).await {
+                    return error_response!(ApiError::Internal(format!("Error saving {}: {}", recipe.uuid, e)));
+                }
+            }
+            Err(e) => return error_response!(ApiError::Internal(format!("Error reading {}: {}", recipe.uuid, e))),
+        }
+        if let Err(e) = store.collect(username, &recipe.uuid).await {
+            return error_response!(ApiError::Internal(format!("Error collecting {}: {}", recipe.uuid, e)));
+        }
+        recipes.push(RecipeResult { part, name: Some(recipe.name), recipe_uuid: Some(recipe.uuid), error: None });
+    }
+
+    let response = ImportResponse {
+        format: format.as_str(),
+        imported: recipes.iter().filter(|r| r.recipe_uuid.is_some()).count(),
+        already_imported,
+        recipes,
+    };
+    let json_string = match serde_json::to_string(&response) {
+        Ok(j) => j,
+        Err(e) => return error_response!(ApiError::Internal(format!("Error writing import: {}", e))),
+    };
+    Ok(Response::builder()
+        .status(200)
+        .header("Access-Control-Allow-Origin", "*")
+        .body(json_string)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use recipe_core::RecipeUpdate;
+    use serde_json::Value;
+
+    macro_rules! aw {
+        ($e:expr) => {
+            tokio_test::block_on($e)
+        };
+    }
+
+    const TACO_SOUP: &str = ">> title: Taco Soup\n>> source: https://www.seriouseats.com/taco-soup\n\nBrown the @ground beef{1%lb}, add @beef broth{2%cups} and simmer.";
+
+    #[test]
+    fn test_get_format() {
+        assert_eq!(get_format(Some("mela"), Some("Dinners.paprikarecipes")), Ok(Format::Mela));
+        assert_eq!(get_format(None, Some("Dinners.paprikarecipes")), Ok(Format::Paprika));
+        assert!(get_format(Some("word"), None).is_err());
+        assert!(get_format(None, Some("Dinners")).is_err());
+        assert!(get_format(None, None).is_err());
+    }
+
+    #[test]
+    fn test_import_recipes() {
+        let store = recipe_core::MemoryStore::new();
+        let res = aw!(import_recipes(&store, "dmbluesmith", Format::Cooklang, TACO_SOUP.as_bytes(), Some("taco_soup.cook"))).unwrap();
+        assert_eq!(res.status().as_u16(), 200);
+        let body: Value = serde_json::from_str(res.body()).unwrap();
+        assert_eq!(body["format"], "cooklang");
+        assert_eq!(body["imported"], 1);
+        let uuid = body["recipes"][0]["recipe_uuid"].as_str().unwrap().to_string();
+        assert!(uuid.starts_with("import:dmbluesmith:"));
+
+        let stored = aw!(store.get(&uuid)).unwrap().unwrap();
+        assert_eq!(stored.name, "Taco Soup");
+        assert_eq!(stored.ingredients[0].item, "ground beef");
+        assert_eq!(stored.owner.as_deref(), Some("dmbluesmith"));
+        assert_eq!(stored.source.as_deref(), Some("https://www.seriouseats.com/taco-soup"));
+        assert_eq!(aw!(store.collection("dmbluesmith")).unwrap(), vec![uuid.clone()]);
+
+        assert_eq!(body["already_imported"], 0);
+
+        // The same file again lands on the same recipe, without undoing the user's edits
+        let edit = RecipeUpdate {
+            uuid: uuid.clone(),
+            notes: Some(String::from("Double the beef")),
+            ..RecipeUpdate::default()
+        };
+        aw!(store.update("dmbluesmith", &edit)).unwrap();
+        let res = aw!(import_recipes(&store, "dmbluesmith", Format::Cooklang, TACO_SOUP.as_bytes(), None)).unwrap();
+        let body: Value = serde_json::from_str(res.body()).unwrap();
+        assert_eq!((body["imported"].as_u64(), body["already_imported"].as_u64()), (Some(1), Some(1)));
+        assert_eq!(body["recipes"][0]["recipe_uuid"], uuid.as_str());
+        assert_eq!(aw!(store.list()).unwrap().len(), 1);
+        assert_eq!(aw!(store.get(&uuid)).unwrap().unwrap().notes, "Double the beef");
+        assert_eq!(aw!(store.collection("dmbluesmith")).unwrap(), vec![uuid]);
+    }
+
+    #[test]
+    fn test_import_reports_bad_recipes() {
+        let store = recipe_core::MemoryStore::new();
+        let mmf = "MMMMM----- Recipe via Meal-Master (tm) v8.05\n      Title: \n      1 lb Ground beef\nMMMMM\n";
+        let res = aw!(import_recipes(&store, "dmbluesmith", Format::MealMaster, mmf.as_bytes(), None)).unwrap();
+        let body: Value = serde_json::from_str(res.body()).unwrap();
+        assert_eq!(body["imported"], 0);
+        assert_eq!(body["recipes"][0]["error"], "Can't import recipe: recipe has no title");
+        assert!(aw!(store.list()).unwrap().is_empty());
+
+        let res = aw!(import_recipes(&store, "dmbluesmith", Format::Paprika, b"not a zip", None)).unwrap();
+        assert_eq!(res.status().as_u16(), 422);
+    }
+}
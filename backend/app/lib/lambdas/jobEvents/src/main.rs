@@ -0,0 +1,115 @@
+use std::convert::Infallible;
+use std::env;
+use std::sync::Arc;
+use axum::extract::{Path, State};
+use axum::http::{header, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use futures_util::stream::{Stream, StreamExt};
+use lambda_http::Error;
+use recipe_core::{make_config, ApiError, DynamoStore, EventBus, Job, Opt, StoreEventBus};
+
+async fn get_job_table_name() -> Option<String> {
+    env::var("JOB_TABLE_NAME").ok()
+}
+
+async fn get_recipe_table_name() -> Option<String> {
+    env::var("RECIPE_TABLE_NAME").ok()
+}
+
+/**
+ * Routes:
+ * GET /jobs/{id}/events: server-sent events of the job's progress. Each
+ * event is named after the job's status and carries the whole job as JSON,
+ * including the recipe's name and image once the worker has them. The
+ * stream closes after `done` or `failed`.
+ */
+pub fn app(events: Arc<dyn EventBus>) -> Router {
+    Router::new()
+        .route("/jobs/:id/events", get(job_events))
+        .with_state(events)
+}
+
+// The same {"code", "message"} JSON the other lambdas send, see recipe_core::ApiError
+fn error_response(e: ApiError) -> Response {
+    let status = StatusCode::from_u16(e.status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    (status, [(header::CONTENT_TYPE, "application/json")], e.body()).into_response()
+}
+
+fn to_event(job: Job) -> Result<Event, Infallible> {
+    let event = Event::default().event(job.status.as_str()).id(job.updated_at.to_string());
+    Ok(event.json_data(&job).unwrap_or_else(|_| Event::default().event("error")))
+}
+
+async fn job_events(
+    State(events): State<Arc<dyn EventBus>>,
+    Path(id): Path<String>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, Response> {
+    let jobs = match events.subscribe(&id).await {
+        Ok(j) => j,
+        Err(e) => return Err(error_response(e.into())),
+    };
+    Ok(Sse::new(jobs.map(to_event)).keep_alive(KeepAlive::default()))
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let opt = Opt {
+        region: Some("us-east-1".to_string()),
+        verbose: true,
+    };
+    let config = make_config(opt).await?;
+    let (job_table_name, recipe_table_name) = match (get_job_table_name().await, get_recipe_table_name().await) {
+        (Some(j), Some(r)) => (j, r),
+        _ => return Err("TABLE_NAME not set".into()),
+    };
+    let store = DynamoStore::new(&config, &recipe_table_name).with_job_table(&job_table_name);
+
+    // The worker runs in another lambda, so we watch the Jobs table for what it writes
+    let events = StoreEventBus::new(store);
+    lambda_http::run_with_streaming_response(app(Arc::new(events))).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use http_body_util::BodyExt;
+    use recipe_core::{JobStatus, MemoryEventBus};
+    use tower::ServiceExt;
+
+    fn job(status: JobStatus) -> Job {
+        let mut job = Job::new("job-1");
+        job.advance(status);
+        job
+    }
+
+    #[tokio::test]
+    async fn test_streams_job_events() {
+        let bus = Arc::new(MemoryEventBus::new());
+        bus.publish(&job(JobStatus::Parsing)).await.unwrap();
+
+        let request = Request::builder().uri("/jobs/job-1/events").body(Body::empty()).unwrap();
+        let response = app(bus.clone()).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+
+        // The worker carries on once the client is listening
+        let mut imaging = job(JobStatus::Imaging);
+        imaging.name = Some(String::from("Taco Soup"));
+        let mut done = imaging.clone();
+        done.finish("https://tasty.co/recipe/taco-soup");
+        bus.publish(&imaging).await.unwrap();
+        bus.publish(&done).await.unwrap();
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        let events: Vec<&str> = body.lines().filter_map(|l| l.strip_prefix("event: ")).collect();
+        assert_eq!(events, vec!["parsing", "imaging", "done"]);
+        assert!(body.contains(r#""name":"Taco Soup""#));
+        assert!(body.contains(r#""recipe_uuid":"https://tasty.co/recipe/taco-soup""#));
+    }
+}
@@ -0,0 +1,226 @@
+use serde_json::json;
+use std::env;
+use lambda_http::{service_fn, Response, Error, Request, RequestExt};
+use recipe_bucket::{Downloads, Images, S3Downloads, S3Images};
+use recipe_core::{error_response, find_by_url, make_config, ApiError, DynamoStore, Opt, Recipe, RecipeStore};
+use recipe_print::{render_book, render_card};
+
+// "Taco Soup" downloads as Taco-Soup.pdf
+fn file_name(name: &str) -> String {
+    let words: Vec<String> = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_string)
+        .collect();
+    if words.is_empty() {
+        String::from("recipes.pdf")
+    } else {
+        format!("{}.pdf", words.join("-"))
+    }
+}
+
+// Sends the client off to the saved PDF, with the link in the body too for clients that don't follow redirects
+async fn pdf_response(downloads: &dyn Downloads, pdf: Vec<u8>, name: &str) -> Result<Response<String>, Error> {
+    let link = match downloads.save(&file_name(name), "application/pdf", pdf).await {
+        Ok(l) => l,
+        Err(e) => return error_response!(ApiError::Unavailable(e)),
+    };
+    Ok(Response::builder()
+        .status(303)
+        .header("Access-Control-Allow-Origin", "*")
+        .header("Location", &link)
+        .header("Content-Type", "application/json")
+        .body(json!({ "url": link }).to_string())?)
+}
+
+// What was asked for: one recipe's card, or a user's whole book
+enum Print {
+    Card(String),
+    Book(String),
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let func = service_fn(handler);
+    lambda_http::run(func).await?;
+
+    Ok(())
+}
+
+async fn get_recipe_table_name() -> Option<String> {
+    env::var("RECIPE_TABLE_NAME").ok()
+}
+
+async fn get_user_table_name() -> Option<String> {
+    env::var("USER_TABLE_NAME").ok()
+}
+
+async fn get_alias_table_name() -> Option<String> {
+    env::var("ALIAS_TABLE_NAME").ok()
+}
+
+async fn get_bucket_name() -> Option<String> {
+    env::var("BUCKET_NAME").ok()
+}
+
+async fn handler(request: Request) -> Result<Response<String>, Error> {
+    // 1. Read what to print from the query string
+    let query_params = request.query_string_parameters();
+    let print = match (query_params.first("url"), query_params.first("username")) {
+        (Some(url), _) => Print::Card(url.to_string()),
+        (None, Some(username)) => Print::Book(username.to_string()),
+        (None, None) => return error_response!(ApiError::BadRequest(String::from("Supply either url or username"))),
+    };
+
+    // 2. Create the store and the image bucket from env
+    let opt = Opt {
+        region: Some("us-east-1".to_string()),
+        verbose: true,
+    };
+    let config = match make_config(opt).await {
+        Ok(c) => c,
+        Err(e) => return error_response!(ApiError::Internal(format!("Error making config: {}", e))),
+    };
+    let (recipe_table_name, user_table_name, alias_table_name, bucket_name) =
+        match (get_recipe_table_name().await, get_user_table_name().await, get_alias_table_name().await, get_bucket_name().await) {
+            (Some(r), Some(u), Some(a), Some(b)) => (r, u, a, b),
+            _ => return error_response!(ApiError::Internal(String::from("TABLE_NAME or BUCKET_NAME not set"))),
+        };
+    let store = DynamoStore::new(&config, &recipe_table_name).with_user_table(&user_table_name).with_alias_table(&alias_table_name);
+    let images = S3Images::new(&config, &bucket_name);
+    let downloads = S3Downloads::new(&config, &bucket_name);
+
+    let printed = match print {
+        Print::Card(url) => print_recipe(&store, &images, &downloads, &url).await,
+        Print::Book(username) => print_book(&store, &images, &downloads, &username).await,
+    };
+    printed.or_else(|e| error_response!(e.into()))
+}
+
+async fn image(images: &dyn Images, recipe: &Recipe) -> Option<Vec<u8>> {
+    if recipe.image.is_empty() {
+        None
+    } else {
+        images.get(&recipe.image).await
+    }
+}
+
+/**
+ * One recipe as a printable card.
+ */
+async fn print_recipe(store: &dyn RecipeStore, images: &dyn Images, downloads: &dyn Downloads, url: &str) -> Result<Response<String>, Error> {
+    // 3. Get the recipe, however its URL is written
+    let recipe = match find_by_url(store, url).await? {
+        Some(r) => r,
+        None => return error_response!(ApiError::NotFound(format!("No recipe found for {}", url))),
+    };
+
+    // 4. Lay it out
+    let image = image(images, &recipe).await;
+    match render_card(&recipe, image.as_deref()) {
+        Ok(pdf) => pdf_response(downloads, pdf, &recipe.name).await,
+        Err(e) => error_response!(ApiError::Internal(e)),
+    }
+}
+
+/**
+ * Everything in a user's recipe book, A to Z, as one PDF with a contents
+ * page.
+ */
+async fn print_book(store: &dyn RecipeStore, images: &dyn Images, downloads: &dyn Downloads, username: &str) -> Result<Response<String>, Error> {
+    // 3. Get every recipe they collected
+    let uuids = store.collection(username).await?;
+    if uuids.is_empty() {
+        return error_response!(ApiError::NotFound(format!("No recipes found for {}", username)));
+    }
+    let mut recipes = store.get_many(&uuids).await?;
+    recipes.sort_by_key(|r| r.name.to_lowercase());
+
+    // 4. Lay them out, with whatever pictures we have
+    let mut pictures = Vec::new();
+    for recipe in &recipes {
+        pictures.push(image(images, recipe).await);
+    }
+    let book: Vec<(&Recipe, Option<&[u8]>)> = recipes.iter().zip(&pictures).map(|(r, p)| (r, p.as_deref())).collect();
+    let title = format!("{}'s Recipes", username);
+    match render_book(&title, &book) {
+        Ok(pdf) => pdf_response(downloads, pdf, &title).await,
+        Err(e) => error_response!(ApiError::Internal(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use recipe_bucket::{MemoryDownloads, MemoryImages, PNG};
+    use recipe_core::MemoryStore;
+
+    macro_rules! aw {
+        ($e:expr) => {
+            tokio_test::block_on($e)
+        };
+    }
+
+    const IMAGE_URL: &str = "https://recipe-images.s3.us-east-1.amazonaws.com/soup.jpg";
+
+    fn store() -> MemoryStore {
+        let mut soup = Recipe::new("https://tasty.co/recipe/taco-soup", "Taco Soup");
+        soup.image = IMAGE_URL.to_string();
+        soup.instructions = vec![String::from("Simmer.")];
+        let mut gyoza = Recipe::new("abc", "Gyoza");
+        gyoza.image = String::from("https://example.com/gyoza.jpg");
+        MemoryStore::with_recipes(vec![soup, gyoza, Recipe::new("def", "Apple Pie")])
+    }
+
+    // The file the client was sent to, and what it's called
+    fn pdf(res: &Response<String>, downloads: &MemoryDownloads) -> (String, Vec<u8>) {
+        assert_eq!(res.status().as_u16(), 303);
+        let saved = downloads.saved().pop().unwrap();
+        assert_eq!(saved.content_type, "application/pdf");
+        let link = MemoryDownloads::link(&saved.file_name);
+        assert_eq!(res.headers()["Location"], link.as_str());
+        let body: serde_json::Value = serde_json::from_str(res.body()).unwrap();
+        assert_eq!(body["url"], link);
+        (saved.file_name, saved.bytes)
+    }
+
+    #[test]
+    fn test_print_recipe() {
+        let images = MemoryImages::with_images(vec![(IMAGE_URL, PNG)]);
+        let downloads = MemoryDownloads::new();
+        let res = aw!(print_recipe(&store(), &images, &downloads, "https://tasty.co/amp/recipe/taco-soup?fbclid=abc")).unwrap();
+        let (name, pdf) = pdf(&res, &downloads);
+        assert_eq!(name, "Taco-Soup.pdf");
+        assert!(pdf.starts_with(b"%PDF"));
+        assert!(String::from_utf8_lossy(&pdf).contains("/Image"));
+        assert_eq!(images.asked(), vec![IMAGE_URL]);
+
+        let res = aw!(print_recipe(&store(), &images, &downloads, "https://tasty.co/recipe/chili")).unwrap();
+        assert_eq!(res.status().as_u16(), 404);
+    }
+
+    #[test]
+    fn test_print_book() {
+        let store = store();
+        let images = MemoryImages::new();
+        let downloads = MemoryDownloads::new();
+        aw!(store.collect("dmbluesmith", "https://tasty.co/recipe/taco-soup")).unwrap();
+        aw!(store.collect("dmbluesmith", "abc")).unwrap();
+        let res = aw!(print_book(&store, &images, &downloads, "dmbluesmith")).unwrap();
+        let (name, pdf) = pdf(&res, &downloads);
+        assert_eq!(name, "dmbluesmith-s-Recipes.pdf");
+        assert!(pdf.starts_with(b"%PDF"));
+        // A to Z, and recipes without a picture don't ask for one
+        assert_eq!(images.asked(), vec!["https://example.com/gyoza.jpg", IMAGE_URL]);
+
+        let res = aw!(print_book(&store, &images, &downloads, "nobody")).unwrap();
+        assert_eq!(res.status().as_u16(), 404);
+    }
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(file_name("Taco Soup"), "Taco-Soup.pdf");
+        assert_eq!(file_name("Mac & Cheese!"), "Mac-Cheese.pdf");
+        assert_eq!(file_name("餃子"), "recipes.pdf");
+    }
+}
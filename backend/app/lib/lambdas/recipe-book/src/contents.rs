@@ -0,0 +1,162 @@
+use recipe_core::Recipe;
+use std::collections::HashSet;
+use std::io::{Cursor, Write};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+// What kind of picture it is, by its first bytes, for the kinds every e-reader and browser shows
+fn media_type(bytes: &[u8]) -> Option<(&'static str, &'static str)> {
+    if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+        Some(("image/jpeg", "jpg"))
+    } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some(("image/png", "png"))
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some(("image/gif", "gif"))
+    } else if bytes.len() >= 12 && bytes.starts_with(b"RIFF") && &bytes[8..12] == b"WEBP" {
+        Some(("image/webp", "webp"))
+    } else {
+        None
+    }
+}
+
+// The plain letter under an accented one, so "Crème Brûlée" is named creme-brulee
+fn fold(c: char) -> char {
+    match c {
+        'à'..='å' => 'a',
+        'ç' => 'c',
+        'è'..='ë' => 'e',
+        'ì'..='ï' => 'i',
+        'ñ' => 'n',
+        'ò'..='ö' | 'ø' => 'o',
+        'ù'..='ü' => 'u',
+        'ý' | 'ÿ' => 'y',
+        _ => c,
+    }
+}
+
+// "Grandma's Taco Soup!" is grandma-s-taco-soup
+pub(crate) fn slug(name: &str) -> String {
+    let name: String = name.to_lowercase().chars().map(fold).collect();
+    let words: Vec<&str> = name.split(|c: char| !c.is_ascii_alphanumeric()).filter(|w| !w.is_empty()).collect();
+    let mut slug = String::new();
+    for word in words {
+        if !slug.is_empty() && slug.len() + word.len() >= 60 {
+            break;
+        }
+        if !slug.is_empty() {
+            slug.push('-');
+        }
+        slug += word;
+    }
+    if slug.is_empty() {
+        String::from("recipe")
+    } else {
+        slug
+    }
+}
+
+// A recipe's picture, copied in beside its page
+pub(crate) struct Picture<'a> {
+    pub(crate) file: String,
+    pub(crate) media_type: &'static str,
+    pub(crate) bytes: &'a [u8],
+}
+
+pub(crate) struct Entry<'a> {
+    pub(crate) recipe: &'a Recipe,
+    // The recipe's page and picture are named this, without an extension
+    pub(crate) name: String,
+    pub(crate) picture: Option<Picture<'a>>,
+}
+
+/**
+ * Names every recipe's files after the recipe, numbering the second of two
+ * with the same name, and works out what kind its picture is. A file that
+ * isn't a kind of picture we know is left out rather than failing the
+ * whole book.
+ */
+pub(crate) fn entries<'a>(recipes: &[(&'a Recipe, Option<&'a [u8]>)]) -> Vec<Entry<'a>> {
+    let mut taken = HashSet::new();
+    let mut entries = Vec::new();
+    for (recipe, image) in recipes {
+        let base = slug(&recipe.name);
+        let mut name = base.clone();
+        let mut n = 1;
+        while !taken.insert(name.clone()) {
+            n += 1;
+            name = format!("{}-{}", base, n);
+        }
+        let picture = image.and_then(|bytes| match media_type(bytes) {
+            Some((media_type, extension)) => Some(Picture { file: format!("{}.{}", name, extension), media_type, bytes }),
+            None => {
+                println!("Leaving out a picture for {} we can't read", recipe.name);
+                None
+            }
+        });
+        entries.push(Entry { recipe, name, picture });
+    }
+    entries
+}
+
+/**
+ * A zip built up one file at a time. Pictures are already compressed, so
+ * they go in as they are, like the mimetype an EPUB needs uncompressed.
+ */
+pub(crate) struct Archive {
+    zip: ZipWriter<Cursor<Vec<u8>>>,
+}
+
+impl Archive {
+    pub(crate) fn new() -> Archive {
+        Archive { zip: ZipWriter::new(Cursor::new(Vec::new())) }
+    }
+
+    pub(crate) fn add(&mut self, path: &str, bytes: &[u8], compress: bool) -> Result<(), String> {
+        let method = if compress { CompressionMethod::Deflated } else { CompressionMethod::Stored };
+        self.zip.start_file(path, FileOptions::default().compression_method(method)).map_err(|e| format!("Error writing {}: {}", path, e))?;
+        self.zip.write_all(bytes).map_err(|e| format!("Error writing {}: {}", path, e))
+    }
+
+    pub(crate) fn finish(mut self) -> Result<Vec<u8>, String> {
+        Ok(self.zip.finish().map_err(|e| format!("Error finishing zip: {}", e))?.into_inner())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use recipe_core::test_support::taco_soup;
+    use std::io::Read;
+    use zip::ZipArchive;
+
+    pub(crate) const PNG: &[u8] = b"\x89PNG\r\n\x1a\n and the rest of a picture";
+
+    // Every file in a zip, in the order they went in
+    pub(crate) fn files(zip: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let mut archive = ZipArchive::new(Cursor::new(zip)).unwrap();
+        (0..archive.len())
+            .map(|i| {
+                let mut file = archive.by_index(i).unwrap();
+                let mut bytes = Vec::new();
+                file.read_to_end(&mut bytes).unwrap();
+                (file.name().to_string(), bytes)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn names_files() {
+        assert_eq!(slug("Grandma's Taco Soup!"), "grandma-s-taco-soup");
+        assert_eq!(slug("Crème Brûlée"), "creme-brulee");
+        assert_eq!(slug("焼き餃子"), "recipe");
+        assert!(slug(&"Very Long Soup ".repeat(10)).len() < 60);
+
+        let soup = taco_soup();
+        let gyoza = Recipe::new("abc", "Gyoza");
+        let entries = entries(&[(&soup, Some(PNG)), (&soup, Some(b"<html>".as_slice())), (&gyoza, None)]);
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["taco-soup", "taco-soup-2", "gyoza"]);
+        assert_eq!(entries[0].picture.as_ref().map(|p| (p.file.as_str(), p.media_type)), Some(("taco-soup.png", "image/png")));
+        assert!(entries[1].picture.is_none());
+    }
+}
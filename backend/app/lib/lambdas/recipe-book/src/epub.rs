@@ -0,0 +1,159 @@
+use recipe_core::Recipe;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::contents::{entries, slug, Archive, Entry};
+use crate::html::{article, escape, index, STYLE};
+
+const CONTAINER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+<rootfiles>
+<rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+</rootfiles>
+</container>
+"#;
+
+// An XHTML content document, `stylesheet` relative to it
+fn page(title: &str, stylesheet: &str, body: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n<html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\" lang=\"en\" xml:lang=\"en\">\n<head>\n<meta charset=\"utf-8\"/>\n<title>{}</title>\n<link rel=\"stylesheet\" type=\"text/css\" href=\"{}\"/>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(title),
+        stylesheet,
+        body
+    )
+}
+
+// "2023-11-14T22:13:20Z", the only form dcterms:modified takes
+fn timestamp(secs: u64) -> String {
+    // Days since 1970 to a date, counting from 1 March 0000 so leap days fall at the end of a year
+    let days = secs / 86_400 + 719_468;
+    let (era, day_of_era) = (days / 146_097, days % 146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 { month_from_march + 3 } else { month_from_march - 9 };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    let time = secs % 86_400;
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, time / 3600, time % 3600 / 60, time % 60)
+}
+
+/**
+ * The package document: what the book is, every file in it, and the
+ * order to read them in. The first picture is the cover readers show in
+ * their library.
+ */
+fn package_document(title: &str, entries: &[Entry], modified: &str) -> String {
+    let mut manifest = String::from(
+        "<item id=\"index\" href=\"index.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n<item id=\"style\" href=\"style.css\" media-type=\"text/css\"/>\n",
+    );
+    let mut spine = String::from("<itemref idref=\"index\"/>\n");
+    let mut cover = true;
+    for (i, entry) in entries.iter().enumerate() {
+        manifest += &format!("<item id=\"recipe-{}\" href=\"recipes/{}.xhtml\" media-type=\"application/xhtml+xml\"/>\n", i + 1, entry.name);
+        spine += &format!("<itemref idref=\"recipe-{}\"/>\n", i + 1);
+        if let Some(picture) = &entry.picture {
+            let properties = if cover { " properties=\"cover-image\"" } else { "" };
+            manifest += &format!("<item id=\"image-{}\" href=\"images/{}\" media-type=\"{}\"{}/>\n", i + 1, picture.file, picture.media_type, properties);
+            cover = false;
+        }
+    }
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"book-id\">\n<metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n<dc:identifier id=\"book-id\">urn:recipe3:{}</dc:identifier>\n<dc:title>{}</dc:title>\n<dc:language>en</dc:language>\n<meta property=\"dcterms:modified\">{}</meta>\n</metadata>\n<manifest>\n{}</manifest>\n<spine>\n{}</spine>\n</package>\n",
+        slug(title),
+        escape(title),
+        modified,
+        manifest,
+        spine
+    )
+}
+
+/**
+ * A whole recipe book as an EPUB 3 for e-readers: a front page that's
+ * also the table of contents, then a page per recipe, with the pictures
+ * copied in so it reads offline.
+ */
+pub fn render_epub(title: &str, recipes: &[(&Recipe, Option<&[u8]>)]) -> Result<Vec<u8>, String> {
+    let entries = entries(recipes);
+    let modified = timestamp(SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0));
+
+    // 1. The mimetype has to come first, uncompressed, so readers know what they've opened
+    let mut archive = Archive::new();
+    archive.add("mimetype", b"application/epub+zip", false)?;
+    archive.add("META-INF/container.xml", CONTAINER.as_bytes(), true)?;
+    archive.add("OEBPS/content.opf", package_document(title, &entries, &modified).as_bytes(), true)?;
+    archive.add("OEBPS/style.css", STYLE.as_bytes(), true)?;
+
+    // 2. The contents, then each recipe and its picture
+    let contents = index(title, &entries, |name| format!("recipes/{}.xhtml", name), " epub:type=\"toc\"");
+    archive.add("OEBPS/index.xhtml", page(title, "style.css", &contents).as_bytes(), true)?;
+    for entry in &entries {
+        let image = entry.picture.as_ref().map(|p| format!("../images/{}", p.file));
+        let body = article(entry.recipe, image.as_deref());
+        archive.add(&format!("OEBPS/recipes/{}.xhtml", entry.name), page(&entry.recipe.name, "../style.css", &body).as_bytes(), true)?;
+        if let Some(picture) = &entry.picture {
+            archive.add(&format!("OEBPS/images/{}", picture.file), picture.bytes, false)?;
+        }
+    }
+    archive.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contents::tests::{files, PNG};
+    use recipe_core::test_support::taco_soup;
+    use roxmltree::{Document, ParsingOptions};
+
+    fn parse(xml: &str) -> Document<'_> {
+        Document::parse_with_options(xml, ParsingOptions { allow_dtd: true, ..ParsingOptions::default() }).unwrap()
+    }
+
+    #[test]
+    fn writes_epub() {
+        let soup = taco_soup();
+        let gyoza = Recipe::new("abc", "Gyoza");
+        let epub = render_epub("Family Recipes", &[(&soup, Some(PNG)), (&gyoza, None)]).unwrap();
+        let files = files(&epub);
+        let names: Vec<&str> = files.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "mimetype",
+                "META-INF/container.xml",
+                "OEBPS/content.opf",
+                "OEBPS/style.css",
+                "OEBPS/index.xhtml",
+                "OEBPS/recipes/taco-soup.xhtml",
+                "OEBPS/images/taco-soup.png",
+                "OEBPS/recipes/gyoza.xhtml",
+            ]
+        );
+        // Readers find the mimetype at a fixed place, which only works if it's stored as is
+        assert_eq!(&epub[30..38], b"mimetype");
+        assert_eq!(&epub[38..58], b"application/epub+zip");
+        assert_eq!(files[6].1, PNG);
+
+        // Every document is well formed, and the manifest lists every file but itself
+        for (name, bytes) in &files[1..] {
+            if !name.ends_with(".css") && !name.ends_with(".png") {
+                parse(std::str::from_utf8(bytes).unwrap());
+            }
+        }
+        let opf = String::from_utf8(files[2].1.clone()).unwrap();
+        let opf = parse(&opf);
+        let hrefs: Vec<&str> = opf.descendants().filter_map(|n| n.attribute("href")).collect();
+        assert_eq!(hrefs, ["index.xhtml", "style.css", "recipes/taco-soup.xhtml", "images/taco-soup.png", "recipes/gyoza.xhtml"]);
+        let index = String::from_utf8(files[4].1.clone()).unwrap();
+        assert!(index.contains("<nav epub:type=\"toc\">"));
+        assert!(index.contains("<a href=\"recipes/gyoza.xhtml\">Gyoza</a>"));
+        let page = String::from_utf8(files[5].1.clone()).unwrap();
+        assert!(page.contains("<img src=\"../images/taco-soup.png\""));
+    }
+
+    #[test]
+    fn dates_modified() {
+        assert_eq!(timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(timestamp(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(timestamp(1_700_000_000), "2023-11-14T22:13:20Z");
+    }
+}
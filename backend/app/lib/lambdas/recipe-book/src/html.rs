@@ -0,0 +1,130 @@
+use recipe_core::Recipe;
+use recipe_export::{page_url, to_words};
+
+use crate::contents::Entry;
+
+// Shared by the EPUB and the site, so a book reads the same on both
+pub(crate) const STYLE: &str = "body { font-family: Georgia, serif; line-height: 1.5; max-width: 40em; margin: 0 auto; padding: 1em; }
+h1, h2 { font-family: Helvetica, Arial, sans-serif; line-height: 1.2; }
+h2 { border-bottom: 1px solid #999; padding-bottom: 0.2em; }
+img { display: block; max-width: 100%; max-height: 60vh; margin: 1em auto; }
+.byline, .details, .count { font-style: italic; color: #555; }
+ul.ingredients { padding-left: 1.2em; }
+ol.instructions li { margin-bottom: 0.5em; }
+nav ol { list-style: none; padding: 0; }
+nav li { margin: 0.3em 0; }
+";
+
+// The markup in every page is XHTML, which HTML reads too, so only the XML escapes are used
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// "By Grandma · From Serious Eats", linked to the page it came from when there is one
+fn byline(recipe: &Recipe) -> Option<String> {
+    let from = match (recipe.credit.as_deref(), page_url(recipe)) {
+        (Some(credit), Some(url)) => Some(format!("<a href=\"{}\">{}</a>", escape(url), escape(credit))),
+        (None, Some(url)) => Some(format!("<a href=\"{}\">{}</a>", escape(url), escape(url))),
+        (Some(credit), None) => Some(escape(credit)),
+        (None, None) => None,
+    };
+    let parts: Vec<String> = [recipe.author.as_deref().map(|a| format!("By {}", escape(a))), from.map(|f| format!("From {}", f))]
+        .into_iter()
+        .flatten()
+        .collect();
+    Some(parts.join(" · ")).filter(|b| !b.is_empty())
+}
+
+// "Makes 4 servings · 35 mins"
+fn details(recipe: &Recipe) -> Option<String> {
+    let parts: Vec<String> = [recipe.recipe_yield.as_deref().map(|y| format!("Makes {}", y)), recipe.total_time.as_deref().map(to_words)]
+        .into_iter()
+        .flatten()
+        .map(|p| escape(&p))
+        .collect();
+    Some(parts.join(" · ")).filter(|d| !d.is_empty())
+}
+
+/**
+ * What goes on a recipe's page: its name, who it's by and how much it
+ * makes, the picture, ingredients, numbered steps and notes. `image` is
+ * where the page finds its copy of the picture.
+ */
+pub(crate) fn article(recipe: &Recipe, image: Option<&str>) -> String {
+    let mut out = format!("<article class=\"recipe\">\n<h1>{}</h1>\n", escape(&recipe.name));
+    if let Some(byline) = byline(recipe) {
+        out += &format!("<p class=\"byline\">{}</p>\n", byline);
+    }
+    if let Some(details) = details(recipe) {
+        out += &format!("<p class=\"details\">{}</p>\n", details);
+    }
+    if !recipe.summary.is_empty() {
+        out += &format!("<p class=\"summary\">{}</p>\n", escape(&recipe.summary));
+    }
+    if let Some(image) = image {
+        out += &format!("<img src=\"{}\" alt=\"{}\"/>\n", escape(image), escape(&recipe.name));
+    }
+
+    if !recipe.ingredients.is_empty() {
+        out += "<h2>Ingredients</h2>\n<ul class=\"ingredients\">\n";
+        for ingredient in &recipe.ingredients {
+            out += &format!("<li>{}</li>\n", escape(&ingredient.raw));
+        }
+        out += "</ul>\n";
+    }
+    if !recipe.instructions.is_empty() {
+        out += "<h2>Instructions</h2>\n<ol class=\"instructions\">\n";
+        for step in &recipe.instructions {
+            out += &format!("<li>{}</li>\n", escape(step));
+        }
+        out += "</ol>\n";
+    }
+    if !recipe.notes.trim().is_empty() {
+        out += "<h2>Notes</h2>\n";
+        for paragraph in recipe.notes.lines().filter(|l| !l.trim().is_empty()) {
+            out += &format!("<p class=\"notes\">{}</p>\n", escape(paragraph));
+        }
+    }
+    out + "</article>\n"
+}
+
+/**
+ * The book's front page: its title, how many recipes it has and a link to
+ * each. `page` gives the link for a recipe from its file name, and
+ * `nav_type` marks the list as the EPUB's table of contents.
+ */
+pub(crate) fn index(title: &str, entries: &[Entry], page: impl Fn(&str) -> String, nav_type: &str) -> String {
+    let count = match entries.len() {
+        1 => String::from("1 recipe"),
+        n => format!("{} recipes", n),
+    };
+    let mut out = format!("<h1>{}</h1>\n<p class=\"count\">{}</p>\n<nav{}>\n<h2>Contents</h2>\n<ol>\n", escape(title), count, nav_type);
+    for entry in entries {
+        out += &format!("<li><a href=\"{}\">{}</a></li>\n", escape(&page(&entry.name)), escape(&entry.recipe.name));
+    }
+    out + "</ol>\n</nav>\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use recipe_core::test_support::taco_soup;
+
+    #[test]
+    fn writes_article() {
+        let mut soup = taco_soup();
+        soup.summary = String::from("A <quick> & easy weeknight soup.");
+        let article = article(&soup, Some("../images/taco-soup.png"));
+        assert!(article.contains("<h1>Taco Soup</h1>"));
+        assert!(article.contains("From <a href=\"https://www.seriouseats.com/taco-soup\">Serious Eats</a>"));
+        assert!(article.contains("Makes 4 servings · 35 mins"));
+        assert!(article.contains("A &lt;quick&gt; &amp; easy weeknight soup."));
+        assert!(article.contains("<img src=\"../images/taco-soup.png\" alt=\"Taco Soup\"/>"));
+        assert!(article.contains("<li>Add the beef broth and black beans and simmer for 20 minutes.</li>"));
+        assert!(roxmltree::Document::parse(&article).is_ok());
+
+        // Nothing to show, no empty headings
+        let bare = super::article(&Recipe::new("abc", "Gyoza"), None);
+        assert_eq!(bare, "<article class=\"recipe\">\n<h1>Gyoza</h1>\n</article>\n");
+    }
+}
@@ -0,0 +1,9 @@
+mod contents;
+mod epub;
+mod html;
+mod package;
+mod site;
+
+pub use epub::render_epub;
+pub use package::{package, Package};
+pub use site::render_site;
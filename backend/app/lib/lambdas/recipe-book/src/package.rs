@@ -0,0 +1,63 @@
+use recipe_core::Recipe;
+
+use crate::{epub, site};
+
+/**
+ * The ways a whole recipe book can be downloaded as a bundle of files,
+ * pictures and all. The exports in recipe-export are single text files.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Package {
+    // EPUB 3, for e-readers
+    Epub,
+    // A zip of plain HTML pages to host anywhere, or open straight from disk
+    Site,
+}
+
+impl Package {
+    pub fn parse(name: &str) -> Option<Package> {
+        let package = match name.trim().to_lowercase().as_str() {
+            "epub" => Package::Epub,
+            "site" | "html" => Package::Site,
+            _ => return None,
+        };
+        Some(package)
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Package::Epub => "application/epub+zip",
+            Package::Site => "application/zip",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Package::Epub => "epub",
+            Package::Site => "zip",
+        }
+    }
+}
+
+/**
+ * Bundles the recipes, in the order given, with whichever pictures the
+ * caller has for them. Nothing here goes out to fetch one.
+ */
+pub fn package(package: Package, title: &str, recipes: &[(&Recipe, Option<&[u8]>)]) -> Result<Vec<u8>, String> {
+    match package {
+        Package::Epub => epub::render_epub(title, recipes),
+        Package::Site => site::render_site(title, recipes),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_packages() {
+        assert_eq!(Package::parse(" EPUB "), Some(Package::Epub));
+        assert_eq!(Package::parse("html"), Some(Package::Site));
+        assert_eq!(Package::parse("markdown"), None);
+    }
+}
@@ -0,0 +1,92 @@
+use recipe_core::Recipe;
+use recipe_export::{export, Document, ExportFormat};
+
+use crate::contents::{entries, Archive};
+use crate::html::{article, escape, index, STYLE};
+
+// An HTML page, `root` leading back to the top of the site from it
+fn page(title: &str, root: &str, head: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\"/>\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\"/>\n<title>{}</title>\n<link rel=\"stylesheet\" href=\"{}style.css\"/>\n{}</head>\n<body>\n{}</body>\n</html>\n",
+        escape(title),
+        root,
+        head,
+        body
+    )
+}
+
+// The recipe as schema.org JSON-LD, so search engines and recipe apps can read a hosted page
+fn structured_data(recipe: &Recipe) -> String {
+    let json = match export(ExportFormat::JsonLd, std::slice::from_ref(recipe)) {
+        Ok(Document::Text(json)) => json,
+        _ => String::new(),
+    };
+    // A "</script>" in the recipe's text would otherwise end the script early
+    format!("<script type=\"application/ld+json\">\n{}\n</script>\n", json.replace("</", "<\\/"))
+}
+
+/**
+ * A whole recipe book as a zip of static HTML: an index.html linking to a
+ * page per recipe, with the pictures copied in. Every link is relative, so
+ * it works uploaded anywhere or opened straight from disk.
+ */
+pub fn render_site(title: &str, recipes: &[(&Recipe, Option<&[u8]>)]) -> Result<Vec<u8>, String> {
+    let entries = entries(recipes);
+
+    // 1. The index and the styles every page shares
+    let mut archive = Archive::new();
+    let contents = index(title, &entries, |name| format!("recipes/{}.html", name), "");
+    archive.add("index.html", page(title, "", "", &contents).as_bytes(), true)?;
+    archive.add("style.css", STYLE.as_bytes(), true)?;
+
+    // 2. Each recipe and its picture
+    for entry in &entries {
+        let image = entry.picture.as_ref().map(|p| format!("../images/{}", p.file));
+        let body = format!("<p><a href=\"../index.html\">{}</a></p>\n{}", escape(title), article(entry.recipe, image.as_deref()));
+        let html = page(&entry.recipe.name, "../", &structured_data(entry.recipe), &body);
+        archive.add(&format!("recipes/{}.html", entry.name), html.as_bytes(), true)?;
+        if let Some(picture) = &entry.picture {
+            archive.add(&format!("images/{}", picture.file), picture.bytes, false)?;
+        }
+    }
+    archive.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contents::tests::{files, PNG};
+    use recipe_core::test_support::taco_soup;
+    use serde_json::Value;
+
+    #[test]
+    fn writes_site() {
+        let mut soup = taco_soup();
+        soup.notes = String::from("Don't close the </script> tag early.");
+        let gyoza = Recipe::new("abc", "Gyoza");
+        let site = render_site("Family Recipes", &[(&soup, Some(PNG)), (&gyoza, Some(b"not a picture".as_slice()))]).unwrap();
+        let files = files(&site);
+        let names: Vec<&str> = files.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, ["index.html", "style.css", "recipes/taco-soup.html", "images/taco-soup.png", "recipes/gyoza.html"]);
+        assert_eq!(files[3].1, PNG);
+
+        let index = String::from_utf8(files[0].1.clone()).unwrap();
+        assert!(index.contains("<title>Family Recipes</title>"));
+        assert!(index.contains("2 recipes"));
+        assert!(index.contains("<a href=\"recipes/taco-soup.html\">Taco Soup</a>"));
+
+        let page = String::from_utf8(files[2].1.clone()).unwrap();
+        assert!(page.contains("<link rel=\"stylesheet\" href=\"../style.css\"/>"));
+        assert!(page.contains("<a href=\"../index.html\">Family Recipes</a>"));
+        assert!(page.contains("<img src=\"../images/taco-soup.png\" alt=\"Taco Soup\"/>"));
+        assert!(page.contains("<li>1 lb ground beef</li>"));
+        // One script, holding the recipe
+        assert_eq!(page.matches("</script>").count(), 1);
+        let json = page.split("<script type=\"application/ld+json\">").nth(1).unwrap().split("</script>").next().unwrap();
+        let json: Value = serde_json::from_str(json).unwrap();
+        assert_eq!(json["name"], "Taco Soup");
+
+        let page = String::from_utf8(files[4].1.clone()).unwrap();
+        assert!(!page.contains("<img"));
+    }
+}
@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use aws_sdk_s3::{presigning::PresigningConfig, primitives::ByteStream, Client as s3Client};
+use uuid::Uuid;
+
+/**
+ * Where files made for the user to download go. A print or a book with
+ * pictures is soon bigger than the 6 MB a Lambda can send back, so it's
+ * saved and the client gets a link to it instead; tests keep them in
+ * memory.
+ */
+#[async_trait]
+pub trait Downloads: Send + Sync {
+    // Saves the file and returns a link that downloads it as `file_name`
+    async fn save(&self, file_name: &str, content_type: &str, bytes: Vec<u8>) -> Result<String, String>;
+}
+
+// How long a link to a saved file works
+const LINK_EXPIRES_IN: Duration = Duration::from_secs(60 * 60);
+
+// A PDF opens in the browser, anything else downloads
+fn disposition(content_type: &str) -> &'static str {
+    if content_type == "application/pdf" {
+        "inline"
+    } else {
+        "attachment"
+    }
+}
+
+pub struct S3Downloads {
+    client: s3Client,
+    bucket: String,
+}
+
+impl S3Downloads {
+    pub fn new(config: &aws_types::sdk_config::SdkConfig, bucket: &str) -> S3Downloads {
+        S3Downloads {
+            client: s3Client::new(config),
+            bucket: bucket.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl Downloads for S3Downloads {
+    async fn save(&self, file_name: &str, content_type: &str, bytes: Vec<u8>) -> Result<String, String> {
+        // Under a uuid so nobody can guess someone else's book; the bucket deletes downloads/ after a day
+        let key = format!("downloads/{}/{}", Uuid::new_v4(), file_name);
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(&key)
+            .content_type(content_type)
+            .content_disposition(format!("{}; filename=\"{}\"", disposition(content_type), file_name))
+            .body(ByteStream::from(bytes))
+            .send()
+            .await
+            .map_err(|e| format!("Error saving {}: {}", key, e))?;
+        let presigning = PresigningConfig::expires_in(LINK_EXPIRES_IN).map_err(|e| e.to_string())?;
+        let link = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(&key)
+            .presigned(presigning)
+            .await
+            .map_err(|e| format!("Error signing a link to {}: {}", key, e))?;
+        Ok(link.uri().to_string())
+    }
+}
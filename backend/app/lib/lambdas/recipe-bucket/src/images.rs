@@ -0,0 +1,73 @@
+use async_trait::async_trait;
+use aws_sdk_s3::Client as s3Client;
+
+/**
+ * Where the pictures for a print or a book come from. Only pictures we
+ * host are used, so nothing waits on somebody else's site; tests keep
+ * them in memory.
+ */
+#[async_trait]
+pub trait Images: Send + Sync {
+    // The picture at `url`, or None if it isn't ours or can't be read
+    async fn get(&self, url: &str) -> Option<Vec<u8>>;
+}
+
+pub struct S3Images {
+    client: s3Client,
+    bucket: String,
+}
+
+impl S3Images {
+    pub fn new(config: &aws_types::sdk_config::SdkConfig, bucket: &str) -> S3Images {
+        S3Images {
+            client: s3Client::new(config),
+            bucket: bucket.to_string(),
+        }
+    }
+}
+
+// The key in our bucket for "https://{bucket}.s3.{region}.amazonaws.com/{key}", the URLs the worker saves
+fn s3_key<'a>(url: &'a str, bucket: &str) -> Option<&'a str> {
+    let rest = url.strip_prefix("https://")?.strip_prefix(bucket)?.strip_prefix(".s3.")?;
+    let (host, key) = rest.split_once('/')?;
+    if host.ends_with(".amazonaws.com") && !key.is_empty() {
+        Some(key)
+    } else {
+        None
+    }
+}
+
+#[async_trait]
+impl Images for S3Images {
+    async fn get(&self, url: &str) -> Option<Vec<u8>> {
+        let key = s3_key(url, &self.bucket)?;
+        let object = match self.client.get_object().bucket(&self.bucket).key(key).send().await {
+            Ok(o) => o,
+            Err(e) => {
+                println!("Error reading image {}: {}", key, e);
+                return None;
+            }
+        };
+        match object.body.collect().await {
+            Ok(bytes) => Some(bytes.into_bytes().to_vec()),
+            Err(e) => {
+                println!("Error reading image {}: {}", key, e);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_keys() {
+        let url = "https://recipe-images.s3.us-east-1.amazonaws.com/soup.jpg";
+        assert_eq!(s3_key(url, "recipe-images"), Some("soup.jpg"));
+        assert_eq!(s3_key(url, "recipe"), None);
+        assert_eq!(s3_key("https://example.com/soup.jpg", "recipe-images"), None);
+        assert_eq!(s3_key("https://recipe-images.s3.evil.com/soup.jpg", "recipe-images"), None);
+    }
+}
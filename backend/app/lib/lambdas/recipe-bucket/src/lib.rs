@@ -0,0 +1,7 @@
+mod downloads;
+mod images;
+mod memory;
+
+pub use downloads::{Downloads, S3Downloads};
+pub use images::{Images, S3Images};
+pub use memory::{MemoryDownloads, MemoryImages, Saved, PNG};
@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+
+use crate::downloads::Downloads;
+use crate::images::Images;
+
+// A 1x1 white PNG, small enough for a test to print or package
+pub const PNG: &[u8] = &[
+    0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01,
+    0x08, 0x02, 0x00, 0x00, 0x00, 0x90, 0x77, 0x53, 0xde, 0x00, 0x00, 0x00, 0x0c, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0xf8, 0xff, 0xff, 0x3f,
+    0x00, 0x05, 0xfe, 0x02, 0xfe, 0x0d, 0xef, 0x46, 0xb8, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+];
+
+/**
+ * Pictures kept in memory, for tests and running handlers locally. It
+ * remembers every url it was asked for, so a test can check which
+ * recipes wanted a picture.
+ */
+#[derive(Debug, Default)]
+pub struct MemoryImages {
+    images: HashMap<String, Vec<u8>>,
+    asked: Mutex<Vec<String>>,
+}
+
+impl MemoryImages {
+    pub fn new() -> MemoryImages {
+        MemoryImages::default()
+    }
+
+    pub fn with_images(images: Vec<(&str, &[u8])>) -> MemoryImages {
+        MemoryImages {
+            images: images.into_iter().map(|(url, bytes)| (url.to_string(), bytes.to_vec())).collect(),
+            ..MemoryImages::default()
+        }
+    }
+
+    // Every url asked for, in order
+    pub fn asked(&self) -> Vec<String> {
+        self.asked.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl Images for MemoryImages {
+    async fn get(&self, url: &str) -> Option<Vec<u8>> {
+        self.asked.lock().unwrap().push(url.to_string());
+        self.images.get(url).cloned()
+    }
+}
+
+// A file saved for download
+#[derive(Debug, Clone, PartialEq)]
+pub struct Saved {
+    pub file_name: String,
+    pub content_type: String,
+    pub bytes: Vec<u8>,
+}
+
+/**
+ * Keeps saved files in memory, for tests and running handlers locally.
+ * The link to a file is `MemoryDownloads::link` of its name.
+ */
+#[derive(Debug, Default)]
+pub struct MemoryDownloads {
+    saved: Mutex<Vec<Saved>>,
+}
+
+impl MemoryDownloads {
+    pub fn new() -> MemoryDownloads {
+        MemoryDownloads::default()
+    }
+
+    pub fn link(file_name: &str) -> String {
+        format!("https://downloads.example.com/{}", file_name)
+    }
+
+    // Every file saved, in order
+    pub fn saved(&self) -> Vec<Saved> {
+        self.saved.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl Downloads for MemoryDownloads {
+    async fn save(&self, file_name: &str, content_type: &str, bytes: Vec<u8>) -> Result<String, String> {
+        let saved = Saved {
+            file_name: file_name.to_string(),
+            content_type: content_type.to_string(),
+            bytes,
+        };
+        self.saved.lock().unwrap().push(saved);
+        Ok(MemoryDownloads::link(file_name))
+    }
+}
@@ -0,0 +1,237 @@
+use url::Url;
+
+use crate::recipe::Recipe;
+use crate::store::{RecipeStore, StoreError};
+
+// Query parameters that only say where a click came from
+const TRACKING_PARAMS: [&str; 14] = [
+    "fbclid", "gclid", "dclid", "gbraid", "wbraid", "msclkid", "yclid", "mc_cid", "mc_eid", "igshid", "_ga", "_gl", "ref",
+    "ref_src",
+];
+
+fn is_tracking(key: &str, value: &str) -> bool {
+    let key = key.to_lowercase();
+    key.starts_with("utm_")
+        || TRACKING_PARAMS.contains(&key.as_str())
+        || key == "amp"
+        || (key == "outputtype" && value.eq_ignore_ascii_case("amp"))
+}
+
+fn is_amp(segment: &str) -> bool {
+    segment.eq_ignore_ascii_case("amp")
+}
+
+fn web_url(url: &str) -> Option<Url> {
+    Url::parse(url.trim()).ok().filter(|u| u.scheme() == "http" || u.scheme() == "https")
+}
+
+/**
+ * The form of a recipe URL we key recipes by. The host is lowercased, the
+ * fragment and tracking parameters dropped, what's left of the query
+ * sorted, and AMP versions of the page (`/amp/`, `?amp=1`, `amp.` hosts)
+ * folded into the regular one. Anything that isn't a web URL, like the
+ * uuids of image and bulk recipes, comes back as it is.
+ */
+pub fn canonicalize_url(url: &str) -> String {
+    let mut parsed = match web_url(url) {
+        Some(u) => u,
+        None => return url.trim().to_string(),
+    };
+    parsed.set_fragment(None);
+
+    if let Some(host) = parsed.host_str().and_then(|h| h.strip_prefix("amp.")).map(str::to_string) {
+        if host.contains('.') {
+            // Only fails for URLs that can't have a host, which we've ruled out
+            let _ = parsed.set_host(Some(&host));
+        }
+    }
+
+    let segments: Option<Vec<String>> = parsed
+        .path_segments()
+        .map(|s| s.filter(|segment| !is_amp(segment)).map(str::to_string).collect());
+    if let Some(segments) = segments {
+        parsed.set_path(&format!("/{}", segments.join("/")));
+    }
+
+    let mut params: Vec<(String, String)> = parsed
+        .query_pairs()
+        .filter(|(k, v)| !is_tracking(k, v))
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    if params.is_empty() {
+        parsed.set_query(None);
+    } else {
+        params.sort();
+        parsed.query_pairs_mut().clear().extend_pairs(params);
+    }
+    parsed.to_string()
+}
+
+/**
+ * The site a web URL is on: its host, lowercased, without the www., amp.
+ * or m. a site serves the same pages under. Extractors match sites by it
+ * and canonical URLs are only trusted on the same one.
+ */
+pub fn site_host(url: &str) -> Option<String> {
+    let host = web_url(url)?.host_str()?.to_lowercase();
+    let host = ["www.", "amp.", "m."]
+        .iter()
+        .find_map(|prefix| host.strip_prefix(prefix).filter(|h| h.contains('.')))
+        .map(str::to_string)
+        .unwrap_or(host);
+    Some(host)
+}
+
+// Whether a page at `url` can speak for `declared`, i.e. they're on the same site
+fn same_site(url: &str, declared: &str) -> bool {
+    match (site_host(url), site_host(declared)) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
+/**
+ * The key to save a recipe from `url` under. Pages usually say what their
+ * canonical URL is, with `<link rel="canonical">` or the JSON-LD `url`, and
+ * that wins over whatever URL we were given, but only on the same site: a
+ * page can't claim to be somebody else's recipe and take over its key.
+ */
+pub fn recipe_key(url: &str, declared: Option<&str>) -> String {
+    match declared.filter(|d| same_site(url, d)) {
+        Some(declared) => canonicalize_url(declared),
+        None => canonicalize_url(url),
+    }
+}
+
+/**
+ * The key for recipe `index` (from 0) of several from one source, e.g. a
+ * roundup post or a cookbook page. The first keeps the source's key so
+ * links to the source still find it, the rest get `#recipe-2` and so on.
+ * Canonicalizing drops fragments, so these mustn't be canonicalized again.
+ */
+pub fn derived_key(source_key: &str, index: usize) -> String {
+    if index == 0 {
+        source_key.to_string()
+    } else {
+        format!("{}#recipe-{}", source_key, index + 1)
+    }
+}
+
+// The URLs that should lead to the recipe saved under `key`
+pub fn aliases(url: &str, key: &str) -> Vec<String> {
+    let mut aliases: Vec<String> = Vec::new();
+    for alias in [url.trim().to_string(), canonicalize_url(url)] {
+        if alias != key && !aliases.contains(&alias) {
+            aliases.push(alias);
+        }
+    }
+    aliases
+}
+
+/**
+ * Finds the recipe for a URL however it was written: by its canonical form,
+ * through the alias index, or under the exact URL for recipes saved before
+ * we canonicalized them.
+ */
+pub async fn find_by_url(store: &dyn RecipeStore, url: &str) -> Result<Option<Recipe>, StoreError> {
+    let url = url.trim();
+    let canonical = canonicalize_url(url);
+    if let Some(recipe) = store.get(&canonical).await? {
+        return Ok(Some(recipe));
+    }
+    let mut candidates = vec![canonical.clone()];
+    if url != canonical {
+        candidates.push(url.to_string());
+    }
+    for candidate in &candidates {
+        if let Some(uuid) = store.alias(candidate).await? {
+            if let Some(recipe) = store.get(&uuid).await? {
+                return Ok(Some(recipe));
+            }
+        }
+    }
+    if url != canonical {
+        return store.get(url).await;
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::MemoryStore;
+
+    #[test]
+    fn canonicalizes_urls() {
+        let cases = [
+            (
+                "https://www.Example.COM/recipe/pomegranate-habanero-beef/AMP/",
+                "https://www.example.com/recipe/pomegranate-habanero-beef/",
+            ),
+            (
+                "https://example.com/amp/taco-soup?utm_source=pinterest&utm_medium=social#recipe",
+                "https://example.com/taco-soup",
+            ),
+            ("https://amp.example.com/taco-soup?amp=1&fbclid=abc", "https://example.com/taco-soup"),
+            ("https://example.com/search?q=soup&page=2&gclid=x", "https://example.com/search?page=2&q=soup"),
+            ("http://example.com:80/soup", "http://example.com/soup"),
+            ("448b5102-ca04-4451-a18e-692acbeded01", "448b5102-ca04-4451-a18e-692acbeded01"),
+        ];
+        for (url, expected) in cases {
+            assert_eq!(canonicalize_url(url), expected, "{}", url);
+        }
+    }
+
+    #[test]
+    fn prefers_the_declared_url() {
+        let url = "https://example.com/amp/taco-soup?utm_source=x";
+        assert_eq!(recipe_key(url, Some("https://example.com/recipes/taco-soup/")), "https://example.com/recipes/taco-soup/");
+        assert_eq!(recipe_key(url, Some("/relative")), "https://example.com/taco-soup");
+        assert_eq!(
+            aliases(url, "https://example.com/recipes/taco-soup/"),
+            vec![url.to_string(), String::from("https://example.com/taco-soup")]
+        );
+        assert!(aliases("https://example.com/taco-soup", "https://example.com/taco-soup").is_empty());
+        // The mobile and www. versions of a site are the same site
+        assert_eq!(recipe_key("https://m.example.com/taco-soup", Some("https://www.example.com/taco-soup")), "https://www.example.com/taco-soup");
+        assert_eq!(derived_key("https://example.com/best-soups", 0), "https://example.com/best-soups");
+        assert_eq!(derived_key("https://example.com/best-soups", 2), "https://example.com/best-soups#recipe-3");
+    }
+
+    #[test]
+    fn finds_sites() {
+        assert_eq!(site_host("https://www.AllRecipes.com/recipe/1").as_deref(), Some("allrecipes.com"));
+        assert_eq!(site_host("http://user@tasty.co:8080?x=1").as_deref(), Some("tasty.co"));
+        assert_eq!(site_host("https://m.bbcgoodfood.com/recipes/1").as_deref(), Some("bbcgoodfood.com"));
+        assert_eq!(site_host("https://amp.example.com/soup").as_deref(), Some("example.com"));
+        // Only a prefix in front of a whole domain
+        assert_eq!(site_host("https://www.co/soup").as_deref(), Some("www.co"));
+        assert_eq!(site_host("not a url"), None);
+    }
+
+    #[test]
+    fn ignores_canonicals_on_other_sites() {
+        let url = "https://attacker.example/taco-soup?utm_source=x";
+        assert_eq!(recipe_key(url, Some("https://tasty.co/recipe/taco-soup")), "https://attacker.example/taco-soup");
+        assert_eq!(recipe_key(url, Some("https://tasty.co.attacker.example/recipe/taco-soup")), "https://attacker.example/taco-soup");
+        assert_eq!(recipe_key("https://evil.tasty.co/soup", Some("https://tasty.co/recipe/taco-soup")), "https://evil.tasty.co/soup");
+    }
+
+    #[tokio::test]
+    async fn finds_recipes_by_any_url() {
+        let store = MemoryStore::with_recipes(vec![
+            Recipe::new("https://example.com/recipes/taco-soup/", "Taco Soup"),
+            Recipe::new("https://example.com/Old-Key/AMP/", "Old Soup"),
+        ]);
+        store.add_alias("https://example.com/taco-soup", "https://example.com/recipes/taco-soup/").await.unwrap();
+
+        let find = |url: &'static str| {
+            let store = &store;
+            async move { find_by_url(store, url).await.unwrap().map(|r| r.name) }
+        };
+        assert_eq!(find("https://EXAMPLE.com/recipes/taco-soup/#comments").await.as_deref(), Some("Taco Soup"));
+        assert_eq!(find("https://example.com/taco-soup?utm_campaign=x").await.as_deref(), Some("Taco Soup"));
+        assert_eq!(find("https://example.com/Old-Key/AMP/").await.as_deref(), Some("Old Soup"));
+        assert_eq!(find("https://example.com/chili").await, None);
+    }
+}
@@ -0,0 +1,32 @@
+use aws_config::{meta::region::RegionProviderChain, BehaviorVersion, SdkConfig};
+use aws_sdk_dynamodb::{config::Region, meta::PKG_VERSION};
+
+// The same boxed error lambda_http and lambda_runtime use
+pub type Error = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Debug)]
+pub struct Opt {
+    /// The AWS Region.
+    pub region: Option<String>,
+    /// Whether to display additional information.
+    pub verbose: bool,
+}
+
+pub fn make_region_provider(region: Option<String>) -> RegionProviderChain {
+    RegionProviderChain::first_try(region.map(Region::new))
+        .or_default_provider()
+        .or_else(Region::new("us-east-1"))
+}
+
+pub async fn make_config(opt: Opt) -> Result<SdkConfig, Error> {
+    let region_provider = make_region_provider(opt.region);
+
+    if opt.verbose {
+        println!("DynamoDB client version: {}", PKG_VERSION);
+        if let Some(region) = region_provider.region().await {
+            println!("Region:                  {}", region.as_ref());
+        }
+    }
+
+    Ok(aws_config::defaults(BehaviorVersion::latest()).region(region_provider).load().await)
+}
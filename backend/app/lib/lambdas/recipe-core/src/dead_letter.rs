@@ -0,0 +1,92 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::job::now;
+use crate::store::StoreError;
+
+/**
+ * Why an import failed, which decides what to do about it. Retryable
+ * failures (rate limits, timeouts, 5xx) are worth trying again as they are,
+ * permanent ones (no API key, a response we can't use) need us to fix
+ * something first, and bad input (a 404 page, an unreadable image) needs a
+ * different request from the user.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    Retryable,
+    Permanent,
+    BadInput,
+}
+
+impl ErrorKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::Retryable => "retryable",
+            ErrorKind::Permanent => "permanent",
+            ErrorKind::BadInput => "bad_input",
+        }
+    }
+
+    pub fn parse(kind: &str) -> Option<ErrorKind> {
+        [ErrorKind::Retryable, ErrorKind::Permanent, ErrorKind::BadInput]
+            .into_iter()
+            .find(|k| k.as_str() == kind)
+    }
+}
+
+/**
+ * An import the worker gave up on, keyed by its job id. `request` is the
+ * message the worker was sent, a URLRequest as JSON, kept exactly as it was
+ * so the import can be replayed.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DeadLetter {
+    pub id: String,
+    pub request: String,
+    pub kind: ErrorKind,
+    pub error: String,
+    // Seconds since the epoch
+    pub failed_at: u64,
+}
+
+impl DeadLetter {
+    pub fn new(id: &str, request: &str, kind: ErrorKind, error: &str) -> DeadLetter {
+        DeadLetter {
+            id: id.to_string(),
+            request: request.to_string(),
+            kind,
+            error: error.to_string(),
+            failed_at: now(),
+        }
+    }
+}
+
+/**
+ * Where failed imports wait to be looked at or replayed. They're kept until
+ * someone replays or deletes them.
+ */
+#[async_trait]
+pub trait DeadLetterStore: Send + Sync {
+    // Replaces any earlier failure of the same job
+    async fn put_dead_letter(&self, letter: &DeadLetter) -> Result<(), StoreError>;
+
+    // Oldest first
+    async fn list_dead_letters(&self) -> Result<Vec<DeadLetter>, StoreError>;
+
+    async fn delete_dead_letter(&self, id: &str) -> Result<(), StoreError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kinds_round_trip() {
+        for kind in ["retryable", "permanent", "bad_input"] {
+            assert_eq!(ErrorKind::parse(kind).unwrap().as_str(), kind);
+        }
+        assert_eq!(ErrorKind::parse("flaky"), None);
+        assert_eq!(serde_json::to_value(ErrorKind::BadInput).unwrap(), "bad_input");
+    }
+}
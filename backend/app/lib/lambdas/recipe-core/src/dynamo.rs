@@ -0,0 +1,891 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use aws_config::SdkConfig;
+use aws_sdk_dynamodb::error::ProvideErrorMetadata;
+use aws_sdk_dynamodb::types::{AttributeValue, KeysAndAttributes};
+use aws_sdk_dynamodb::Client as DbClient;
+use recipe_ingredients::{parse_all, Ingredient};
+use serde_json::Value;
+use tokio::time::sleep;
+
+use crate::dead_letter::{DeadLetter, DeadLetterStore, ErrorKind};
+use crate::job::{Job, JobStatus, JobStore};
+use crate::recipe::{Recipe, RecipeUpdate, SCHEMA_VERSION};
+use crate::store::{RecipeStore, StoreError};
+
+// BatchGetItem takes at most this many keys a call
+const BATCH_SIZE: usize = 100;
+// Calls for one batch before the keys DynamoDB keeps handing back are given up on
+const BATCH_ATTEMPTS: u32 = 5;
+// Reads of a recipe before an edit racing other edits to it is given up on
+const UPDATE_ATTEMPTS: u32 = 3;
+
+// How long to wait before asking again for keys DynamoDB didn't get to: 50ms, 100ms, 200ms...
+fn batch_backoff(attempt: u32) -> Duration {
+    Duration::from_millis(50 * 2u64.pow(attempt.saturating_sub(1)))
+}
+
+/**
+ * Recipes live in one table keyed by `uuid`, user collections in another
+ * keyed by `username` and `uuid`, jobs in a third keyed by `id`, URL
+ * aliases in a fourth keyed by `alias` and failed imports in a fifth keyed
+ * by job `id`. Only the lambdas that use collections, jobs, aliases or
+ * dead letters need those tables.
+ */
+#[derive(Debug, Clone)]
+pub struct DynamoStore {
+    client: DbClient,
+    table_name: String,
+    user_table_name: Option<String>,
+    job_table_name: Option<String>,
+    alias_table_name: Option<String>,
+    dead_letter_table_name: Option<String>,
+}
+
+impl DynamoStore {
+    pub fn new(config: &SdkConfig, table_name: &str) -> DynamoStore {
+        DynamoStore {
+            client: DbClient::new(config),
+            table_name: table_name.to_string(),
+            user_table_name: None,
+            job_table_name: None,
+            alias_table_name: None,
+            dead_letter_table_name: None,
+        }
+    }
+
+    pub fn with_user_table(mut self, user_table_name: &str) -> DynamoStore {
+        self.user_table_name = Some(user_table_name.to_string());
+        self
+    }
+
+    pub fn with_job_table(mut self, job_table_name: &str) -> DynamoStore {
+        self.job_table_name = Some(job_table_name.to_string());
+        self
+    }
+
+    pub fn with_alias_table(mut self, alias_table_name: &str) -> DynamoStore {
+        self.alias_table_name = Some(alias_table_name.to_string());
+        self
+    }
+
+    pub fn with_dead_letter_table(mut self, dead_letter_table_name: &str) -> DynamoStore {
+        self.dead_letter_table_name = Some(dead_letter_table_name.to_string());
+        self
+    }
+
+    async fn get_item(&self, uuid: &str) -> Result<Option<HashMap<String, AttributeValue>>, StoreError> {
+        let response = self.client
+            .get_item()
+            .table_name(&self.table_name)
+            .key("uuid", AttributeValue::S(uuid.to_string()))
+            .send()
+            .await
+            .map_err(backend)?;
+        Ok(response.item)
+    }
+
+    // Whether the expression's condition held and the update applied
+    async fn update_item(&self, uuid: &str, expression: Expression) -> Result<bool, StoreError> {
+        println!("Expression: {:?}", expression.expression);
+        let result = self.client
+            .update_item()
+            .table_name(&self.table_name)
+            .key("uuid", AttributeValue::S(uuid.to_string()))
+            .update_expression(expression.expression)
+            .set_expression_attribute_names(Some(expression.names))
+            .set_expression_attribute_values(Some(expression.values))
+            .condition_expression(expression.condition)
+            .send()
+            .await;
+        match result {
+            Ok(_) => Ok(true),
+            Err(e) if e.as_service_error().is_some_and(|s| s.is_conditional_check_failed_exception()) => Ok(false),
+            Err(e) => Err(backend(e)),
+        }
+    }
+
+    fn user_table(&self) -> Result<&str, StoreError> {
+        self.user_table_name
+            .as_deref()
+            .ok_or_else(|| StoreError::Config(String::from("No user table")))
+    }
+
+    fn job_table(&self) -> Result<&str, StoreError> {
+        self.job_table_name
+            .as_deref()
+            .ok_or_else(|| StoreError::Config(String::from("No job table")))
+    }
+
+    fn alias_table(&self) -> Result<&str, StoreError> {
+        self.alias_table_name
+            .as_deref()
+            .ok_or_else(|| StoreError::Config(String::from("No alias table")))
+    }
+
+    fn dead_letter_table(&self) -> Result<&str, StoreError> {
+        self.dead_letter_table_name
+            .as_deref()
+            .ok_or_else(|| StoreError::Config(String::from("No dead letter table")))
+    }
+}
+
+fn backend<E: ProvideErrorMetadata + std::fmt::Debug>(e: E) -> StoreError {
+    match e.code() {
+        Some("ProvisionedThroughputExceededException" | "ThrottlingException" | "RequestLimitExceeded") => {
+            StoreError::Throttled(format!("{:?}", e))
+        }
+        _ => StoreError::Backend(format!("{:?}", e)),
+    }
+}
+
+#[async_trait]
+impl RecipeStore for DynamoStore {
+    async fn get(&self, uuid: &str) -> Result<Option<Recipe>, StoreError> {
+        Ok(self.get_item(uuid).await?.as_ref().map(from_item))
+    }
+
+    async fn list(&self) -> Result<Vec<Recipe>, StoreError> {
+        let mut recipes = Vec::new();
+        let mut start_key = None;
+        loop {
+            let page = self.client
+                .scan()
+                .table_name(&self.table_name)
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+                .map_err(backend)?;
+            recipes.extend(page.items().iter().map(from_item));
+            match page.last_evaluated_key {
+                Some(key) if !key.is_empty() => start_key = Some(key),
+                _ => return Ok(recipes),
+            }
+        }
+    }
+
+    async fn put(&self, recipe: &Recipe) -> Result<(), StoreError> {
+        self.client
+            .put_item()
+            .table_name(&self.table_name)
+            .set_item(Some(to_item(recipe)))
+            .send()
+            .await
+            .map_err(backend)?;
+        Ok(())
+    }
+
+    async fn update(&self, owner: &str, update: &RecipeUpdate) -> Result<(), StoreError> {
+        let not_allowed = || StoreError::NotAllowed(format!("{} can't update {}", owner, update.uuid));
+        if update.ingredients.is_none() && update.instructions.is_none() {
+            let expression = Expression::from(owner, update, None);
+            return match self.update_item(&update.uuid, expression).await? {
+                true => Ok(()),
+                false => Err(not_allowed()),
+            };
+        }
+
+        // Nutrition and the fingerprint depend on fields the edit may not have, so work them out on the whole recipe,
+        // and only store them if nobody else edited it in between
+        for _ in 0..UPDATE_ATTEMPTS {
+            let item = self.get_item(&update.uuid).await?.ok_or_else(not_allowed)?;
+            let mut recipe = from_item(&item);
+            if recipe.owner.as_deref() != Some(owner) {
+                return Err(not_allowed());
+            }
+            update.apply(&mut recipe);
+            let mut expression = Expression::from(owner, update, Some(&recipe));
+            expression.unchanged_since(item.get("fingerprint"));
+            // When it didn't apply, the owner changed or someone else's edit landed first, so read it again
+            if self.update_item(&update.uuid, expression).await? {
+                return Ok(());
+            }
+        }
+        Err(StoreError::Throttled(format!("{} kept changing while {} edited it", update.uuid, owner)))
+    }
+
+    async fn collect(&self, username: &str, uuid: &str) -> Result<(), StoreError> {
+        self.client
+            .put_item()
+            .table_name(self.user_table()?)
+            .item("username", AttributeValue::S(username.to_string()))
+            .item("uuid", AttributeValue::S(uuid.to_string()))
+            .send()
+            .await
+            .map_err(backend)?;
+        Ok(())
+    }
+
+    async fn collection(&self, username: &str) -> Result<Vec<String>, StoreError> {
+        let mut uuids = Vec::new();
+        let mut start_key = None;
+        loop {
+            let page = self.client
+                .query()
+                .table_name(self.user_table()?)
+                .key_condition_expression("#un = :username")
+                .expression_attribute_names("#un", "username")
+                .expression_attribute_values(":username", AttributeValue::S(username.to_string()))
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+                .map_err(backend)?;
+            uuids.extend(page.items().iter().map(|item| as_string(item.get("uuid"))));
+            match page.last_evaluated_key {
+                Some(key) if !key.is_empty() => start_key = Some(key),
+                _ => return Ok(uuids),
+            }
+        }
+    }
+
+    async fn get_many(&self, uuids: &[String]) -> Result<Vec<Recipe>, StoreError> {
+        let mut found: HashMap<String, Recipe> = HashMap::new();
+        // BatchGetItem rejects the same key twice in one call
+        let mut unique: Vec<String> = Vec::new();
+        for uuid in uuids {
+            if !unique.contains(uuid) {
+                unique.push(uuid.clone());
+            }
+        }
+        for chunk in unique.chunks(BATCH_SIZE) {
+            let keys: Vec<HashMap<String, AttributeValue>> = chunk
+                .iter()
+                .map(|uuid| HashMap::from([(String::from("uuid"), AttributeValue::S(uuid.clone()))]))
+                .collect();
+            let mut request = HashMap::from([(
+                self.table_name.clone(),
+                KeysAndAttributes::builder().set_keys(Some(keys)).build().map_err(|e| StoreError::Backend(format!("{:?}", e)))?,
+            )]);
+            // DynamoDB hands back whatever it didn't get to, usually because it's throttling us, so ask again more slowly
+            let mut attempt = 0;
+            while !request.is_empty() {
+                if attempt == BATCH_ATTEMPTS {
+                    let left: usize = request.values().map(|k| k.keys().len()).sum();
+                    return Err(StoreError::Throttled(format!("{} recipes still unread after {} attempts", left, BATCH_ATTEMPTS)));
+                }
+                if attempt > 0 {
+                    sleep(batch_backoff(attempt)).await;
+                }
+                attempt += 1;
+                let response = self.client
+                    .batch_get_item()
+                    .set_request_items(Some(request))
+                    .send()
+                    .await
+                    .map_err(backend)?;
+                let items = response.responses.unwrap_or_default().remove(&self.table_name).unwrap_or_default();
+                for recipe in items.iter().map(from_item) {
+                    found.insert(recipe.uuid.clone(), recipe);
+                }
+                request = response.unprocessed_keys.unwrap_or_default();
+            }
+        }
+        Ok(uuids.iter().filter_map(|uuid| found.get(uuid).cloned()).collect())
+    }
+
+    async fn alias(&self, alias: &str) -> Result<Option<String>, StoreError> {
+        let response = self.client
+            .get_item()
+            .table_name(self.alias_table()?)
+            .key("alias", AttributeValue::S(alias.to_string()))
+            .send()
+            .await
+            .map_err(backend)?;
+        Ok(response.item.map(|item| as_string(item.get("uuid"))))
+    }
+
+    async fn add_alias(&self, alias: &str, uuid: &str) -> Result<(), StoreError> {
+        self.client
+            .put_item()
+            .table_name(self.alias_table()?)
+            .item("alias", AttributeValue::S(alias.to_string()))
+            .item("uuid", AttributeValue::S(uuid.to_string()))
+            .send()
+            .await
+            .map_err(backend)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl JobStore for DynamoStore {
+    async fn get_job(&self, id: &str) -> Result<Option<Job>, StoreError> {
+        let response = self.client
+            .get_item()
+            .table_name(self.job_table()?)
+            .key("id", AttributeValue::S(id.to_string()))
+            .send()
+            .await
+            .map_err(backend)?;
+        Ok(response.item.as_ref().and_then(job_from_item))
+    }
+
+    async fn put_job(&self, job: &Job) -> Result<(), StoreError> {
+        self.client
+            .put_item()
+            .table_name(self.job_table()?)
+            .set_item(Some(job_to_item(job)))
+            .send()
+            .await
+            .map_err(backend)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DeadLetterStore for DynamoStore {
+    async fn put_dead_letter(&self, letter: &DeadLetter) -> Result<(), StoreError> {
+        self.client
+            .put_item()
+            .table_name(self.dead_letter_table()?)
+            .set_item(Some(dead_letter_to_item(letter)))
+            .send()
+            .await
+            .map_err(backend)?;
+        Ok(())
+    }
+
+    async fn list_dead_letters(&self) -> Result<Vec<DeadLetter>, StoreError> {
+        let mut letters = Vec::new();
+        let mut start_key = None;
+        loop {
+            let page = self.client
+                .scan()
+                .table_name(self.dead_letter_table()?)
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+                .map_err(backend)?;
+            letters.extend(page.items().iter().filter_map(dead_letter_from_item));
+            match page.last_evaluated_key {
+                Some(key) if !key.is_empty() => start_key = Some(key),
+                _ => break,
+            }
+        }
+        letters.sort_by(|a, b| a.failed_at.cmp(&b.failed_at).then_with(|| a.id.cmp(&b.id)));
+        Ok(letters)
+    }
+
+    async fn delete_dead_letter(&self, id: &str) -> Result<(), StoreError> {
+        self.client
+            .delete_item()
+            .table_name(self.dead_letter_table()?)
+            .key("id", AttributeValue::S(id.to_string()))
+            .send()
+            .await
+            .map_err(backend)?;
+        Ok(())
+    }
+}
+
+struct Expression {
+    expression: String,
+    condition: String,
+    names: HashMap<String, String>,
+    values: HashMap<String, AttributeValue>,
+}
+
+impl Expression {
+    // `edited` is the recipe with the update applied, when ingredients or instructions changed
+    fn from(owner: &str, update: &RecipeUpdate, edited: Option<&Recipe>) -> Expression {
+        let mut expressions: Vec<String> = Vec::new();
+        let mut removals: Vec<String> = Vec::new();
+        let mut names: HashMap<String, String> = HashMap::new();
+        let mut values: HashMap<String, AttributeValue> = HashMap::new();
+
+        // Create Condition
+        let condition = "attribute_exists(#owner) AND #owner = :currentOwner".to_string();
+        values.insert(":currentOwner".to_string(), AttributeValue::S(owner.to_string()));
+        names.insert("#owner".to_string(), "owner".to_string());
+
+        let mut set = |field: &str, value: AttributeValue| {
+            expressions.push(format!("#{0} = :{0}Value", field));
+            names.insert(format!("#{}", field), field.to_string());
+            values.insert(format!(":{}Value", field), value);
+        };
+
+        if let Some(name) = &update.name {
+            set("name", AttributeValue::S(name.clone()));
+        }
+        if let Some(ingredients) = &update.ingredients {
+            set("ingredients", ingredients_attribute(&parse_all(ingredients)));
+            // Lists are native now
+            set("schema_version", AttributeValue::N(SCHEMA_VERSION.to_string()));
+            removals.push(String::from("#parsedIngredients"));
+        }
+        if let Some(instructions) = &update.instructions {
+            set("instructions", instructions_attribute(instructions));
+        }
+        // Either changes what the recipe is, so store what it works out to now
+        if let Some(recipe) = edited {
+            if let Some(nutrition) = recipe.nutrition.as_ref().filter(|_| update.ingredients.is_some()) {
+                set("nutrition", AttributeValue::S(serde_json::to_string(nutrition).unwrap()));
+            }
+            if let Some(fingerprint) = &recipe.fingerprint {
+                set("fingerprint", AttributeValue::S(serde_json::to_string(fingerprint).unwrap()));
+            }
+        }
+        if let Some(notes) = &update.notes {
+            set("notes", AttributeValue::S(notes.clone()));
+        }
+        if let Some(summary) = &update.summary {
+            set("summary", AttributeValue::S(summary.clone()));
+        }
+        if let Some(new_owner) = &update.owner {
+            set("owner", AttributeValue::S(new_owner.clone()));
+        }
+        for removal in &removals {
+            let field = match removal.as_str() {
+                "#parsedIngredients" => "parsed_ingredients",
+                other => other.trim_start_matches('#'),
+            };
+            names.insert(removal.clone(), field.to_string());
+        }
+
+        let mut expression = format!("SET {}", expressions.join(","));
+        if !removals.is_empty() {
+            expression = format!("{} REMOVE {}", expression, removals.join(","));
+        }
+
+        Expression {
+            expression,
+            condition,
+            names,
+            values,
+        }
+    }
+
+    // Only applies if the stored fingerprint is still the one read before the edit
+    fn unchanged_since(&mut self, fingerprint: Option<&AttributeValue>) {
+        self.names.insert("#fingerprint".to_string(), "fingerprint".to_string());
+        match fingerprint {
+            Some(read) => {
+                self.condition.push_str(" AND #fingerprint = :readFingerprint");
+                self.values.insert(":readFingerprint".to_string(), read.clone());
+            }
+            None => self.condition.push_str(" AND attribute_not_exists(#fingerprint)"),
+        }
+    }
+}
+
+/**
+ * Data format:
+ * primary_key: uuid
+ * schema_version: number
+ * name, notes, summary, image: string
+ * credit, source, owner, recipe_yield, total_time, author: string, if known
+ * ingredients: [{raw, quantity, unit, item, preparation, notes}]
+ * instructions: [{text}]
+ * nutrition: string of JSON
+ * fingerprint: string of JSON, {ingredients, instructions} MinHash signatures
+ */
+pub fn to_item(recipe: &Recipe) -> HashMap<String, AttributeValue> {
+    let mut item = HashMap::from([
+        (String::from("uuid"), AttributeValue::S(recipe.uuid.clone())),
+        (String::from("schema_version"), AttributeValue::N(SCHEMA_VERSION.to_string())),
+        (String::from("name"), AttributeValue::S(recipe.name.clone())),
+        (String::from("ingredients"), ingredients_attribute(&recipe.ingredients)),
+        (String::from("instructions"), instructions_attribute(&recipe.instructions)),
+        (String::from("notes"), AttributeValue::S(recipe.notes.clone())),
+        (String::from("summary"), AttributeValue::S(recipe.summary.clone())),
+        (String::from("image"), AttributeValue::S(recipe.image.clone())),
+    ]);
+    let optional = [
+        ("credit", &recipe.credit),
+        ("source", &recipe.source),
+        ("owner", &recipe.owner),
+        ("recipe_yield", &recipe.recipe_yield),
+        ("total_time", &recipe.total_time),
+        ("author", &recipe.author),
+    ];
+    for (field, value) in optional {
+        if let Some(v) = value {
+            item.insert(field.to_string(), AttributeValue::S(v.clone()));
+        }
+    }
+    if let Some(nutrition) = &recipe.nutrition {
+        item.insert(String::from("nutrition"), AttributeValue::S(serde_json::to_string(nutrition).unwrap()));
+    }
+    if let Some(fingerprint) = &recipe.fingerprint {
+        item.insert(String::from("fingerprint"), AttributeValue::S(serde_json::to_string(fingerprint).unwrap()));
+    }
+    item
+}
+
+/**
+ * Reads any version of a stored recipe. Version 1 items have no
+ * `schema_version` and keep their lists as `;` joined strings, sometimes
+ * with a parsed copy of the ingredients in `parsed_ingredients`.
+ */
+pub fn from_item(item: &HashMap<String, AttributeValue>) -> Recipe {
+    let optional = |field: &str| item.get(field).and_then(|v| v.as_s().ok()).cloned();
+    Recipe {
+        schema_version: item
+            .get("schema_version")
+            .and_then(|v| v.as_n().ok())
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(1),
+        uuid: as_string(item.get("uuid")),
+        name: as_string(item.get("name")),
+        ingredients: as_ingredients(item),
+        instructions: as_list(item.get("instructions"), "text"),
+        notes: as_string(item.get("notes")),
+        summary: as_string(item.get("summary")),
+        image: as_string(item.get("image")),
+        credit: optional("credit"),
+        source: optional("source"),
+        owner: optional("owner"),
+        recipe_yield: optional("recipe_yield"),
+        total_time: optional("total_time"),
+        author: optional("author"),
+        nutrition: optional("nutrition").and_then(|n| serde_json::from_str(&n).ok()),
+        fingerprint: optional("fingerprint").and_then(|f| serde_json::from_str(&f).ok()),
+    }
+}
+
+/**
+ * Job format:
+ * primary_key: id
+ * status: string
+ * created_at, updated_at: number, seconds since the epoch
+ * expires_at: number, the table's TTL attribute
+ * name, image, error, recipe_uuid: string, if set
+ * recipe_uuids: [string], every recipe the job saved, if it's done
+ * results: string of JSON, [{part, name, recipe_uuid, error}] for bulk imports
+ */
+fn job_to_item(job: &Job) -> HashMap<String, AttributeValue> {
+    let mut item = HashMap::from([
+        (String::from("id"), AttributeValue::S(job.id.clone())),
+        (String::from("status"), AttributeValue::S(job.status.as_str().to_string())),
+        (String::from("created_at"), AttributeValue::N(job.created_at.to_string())),
+        (String::from("updated_at"), AttributeValue::N(job.updated_at.to_string())),
+        (String::from("expires_at"), AttributeValue::N(job.expires_at().to_string())),
+    ]);
+    let optional = [
+        ("name", &job.name),
+        ("image", &job.image),
+        ("error", &job.error),
+        ("recipe_uuid", &job.recipe_uuid),
+    ];
+    for (field, value) in optional {
+        if let Some(v) = value {
+            item.insert(field.to_string(), AttributeValue::S(v.clone()));
+        }
+    }
+    if !job.recipe_uuids.is_empty() {
+        let uuids = job.recipe_uuids.iter().map(|u| AttributeValue::S(u.clone())).collect();
+        item.insert(String::from("recipe_uuids"), AttributeValue::L(uuids));
+    }
+    if !job.results.is_empty() {
+        item.insert(String::from("results"), AttributeValue::S(serde_json::to_string(&job.results).unwrap()));
+    }
+    item
+}
+
+// Items we can't make sense of are treated as missing
+fn job_from_item(item: &HashMap<String, AttributeValue>) -> Option<Job> {
+    let number = |field: &str| item.get(field).and_then(|v| v.as_n().ok()).and_then(|n| n.parse().ok());
+    let optional = |field: &str| item.get(field).and_then(|v| v.as_s().ok()).cloned();
+    Some(Job {
+        id: optional("id")?,
+        status: JobStatus::parse(&optional("status")?)?,
+        created_at: number("created_at")?,
+        updated_at: number("updated_at")?,
+        name: optional("name"),
+        image: optional("image"),
+        error: optional("error"),
+        recipe_uuid: optional("recipe_uuid"),
+        // Jobs finished before there could be several only have recipe_uuid
+        recipe_uuids: match item.get("recipe_uuids").and_then(|v| v.as_l().ok()) {
+            Some(uuids) => uuids.iter().filter_map(|u| u.as_s().ok()).cloned().collect(),
+            None => optional("recipe_uuid").into_iter().collect(),
+        },
+        results: optional("results").and_then(|r| serde_json::from_str(&r).ok()).unwrap_or_default(),
+    })
+}
+
+/**
+ * Dead letter format:
+ * primary_key: id, the job's
+ * request: string, the worker's message as it was sent
+ * kind: string, retryable, permanent or bad_input
+ * error: string
+ * failed_at: number, seconds since the epoch
+ */
+fn dead_letter_to_item(letter: &DeadLetter) -> HashMap<String, AttributeValue> {
+    HashMap::from([
+        (String::from("id"), AttributeValue::S(letter.id.clone())),
+        (String::from("request"), AttributeValue::S(letter.request.clone())),
+        (String::from("kind"), AttributeValue::S(letter.kind.as_str().to_string())),
+        (String::from("error"), AttributeValue::S(letter.error.clone())),
+        (String::from("failed_at"), AttributeValue::N(letter.failed_at.to_string())),
+    ])
+}
+
+fn dead_letter_from_item(item: &HashMap<String, AttributeValue>) -> Option<DeadLetter> {
+    let string = |field: &str| item.get(field).and_then(|v| v.as_s().ok()).cloned();
+    Some(DeadLetter {
+        id: string("id")?,
+        request: string("request")?,
+        kind: ErrorKind::parse(&string("kind")?)?,
+        error: string("error").unwrap_or_default(),
+        failed_at: item.get("failed_at").and_then(|v| v.as_n().ok()).and_then(|n| n.parse().ok())?,
+    })
+}
+
+fn as_string(val: Option<&AttributeValue>) -> String {
+    val.and_then(|v| v.as_s().ok()).cloned().unwrap_or_default()
+}
+
+// Turns any serde value into the matching DynamoDB attribute, dropping empty fields from maps
+fn to_attribute(value: Value) -> AttributeValue {
+    match value {
+        Value::Null => AttributeValue::Null(true),
+        Value::Bool(b) => AttributeValue::Bool(b),
+        Value::Number(n) => AttributeValue::N(n.to_string()),
+        Value::String(s) => AttributeValue::S(s),
+        Value::Array(items) => AttributeValue::L(items.into_iter().map(to_attribute).collect()),
+        Value::Object(fields) => AttributeValue::M(
+            fields
+                .into_iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k, to_attribute(v)))
+                .collect(),
+        ),
+    }
+}
+
+fn from_attribute(value: &AttributeValue) -> Value {
+    match value {
+        AttributeValue::S(s) => Value::String(s.to_owned()),
+        AttributeValue::N(n) => n.parse::<f64>().ok().and_then(serde_json::Number::from_f64).map_or(Value::Null, Value::Number),
+        AttributeValue::Bool(b) => Value::Bool(*b),
+        AttributeValue::L(items) => Value::Array(items.iter().map(from_attribute).collect()),
+        AttributeValue::M(fields) => Value::Object(fields.iter().map(|(k, v)| (k.to_owned(), from_attribute(v))).collect()),
+        _ => Value::Null,
+    }
+}
+
+pub fn ingredients_attribute(ingredients: &[Ingredient]) -> AttributeValue {
+    AttributeValue::L(
+        ingredients
+            .iter()
+            .map(|i| to_attribute(serde_json::to_value(i).unwrap()))
+            .collect(),
+    )
+}
+
+pub fn instructions_attribute(instructions: &[String]) -> AttributeValue {
+    AttributeValue::L(
+        instructions
+            .iter()
+            .map(|text| AttributeValue::M(HashMap::from([(String::from("text"), AttributeValue::S(text.clone()))])))
+            .collect(),
+    )
+}
+
+fn as_ingredients(item: &HashMap<String, AttributeValue>) -> Vec<Ingredient> {
+    match item.get("ingredients") {
+        Some(AttributeValue::L(items)) => items
+            .iter()
+            .filter_map(|i| serde_json::from_value(from_attribute(i)).ok())
+            .collect(),
+        Some(AttributeValue::S(joined)) => {
+            let lines = split_string(joined);
+            let parsed: Option<Vec<Ingredient>> = item
+                .get("parsed_ingredients")
+                .and_then(|v| v.as_s().ok())
+                .and_then(|s| serde_json::from_str(s).ok())
+                .filter(|p: &Vec<Ingredient>| p.len() == lines.len());
+            parsed.unwrap_or_else(|| parse_all(&lines))
+        }
+        _ => Vec::new(),
+    }
+}
+
+// Lists of maps, e.g. instructions as [{text}], or a version 1 joined string
+fn as_list(val: Option<&AttributeValue>, field: &str) -> Vec<String> {
+    match val {
+        Some(AttributeValue::L(items)) => items
+            .iter()
+            .filter_map(|item| item.as_m().ok())
+            .filter_map(|fields| fields.get(field).and_then(|f| f.as_s().ok()).cloned())
+            .collect(),
+        Some(AttributeValue::S(joined)) => split_string(joined),
+        _ => Vec::new(),
+    }
+}
+
+/**
+ * Decodes a version 1 list. They were joined with `;`, escaping `\`, `,`
+ * and `;` with a backslash, and an empty string was an empty list.
+ */
+pub fn split_string(string: &str) -> Vec<String> {
+    if string.is_empty() {
+        return Vec::new();
+    }
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut chars = string.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => current.extend(chars.next()),
+            ';' => items.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    items.push(current);
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recipe() -> Recipe {
+        let mut recipe = Recipe::new("https://tasty.co/recipe/taco-soup", "Taco Soup");
+        recipe.ingredients = parse_all(&[String::from("1 1/2 lb ground beef"), String::from("Salt to taste")]);
+        recipe.instructions = vec![String::from("Brown the beef; drain it"), String::from("Simmer")];
+        recipe.credit = Some(String::from("Tasty"));
+        recipe.source = Some(String::from("https://tasty.co/best-soups"));
+        recipe.recipe_yield = Some(String::from("6 servings"));
+        recipe.estimate_nutrition();
+        recipe.compute_fingerprint();
+        recipe
+    }
+
+    #[test]
+    fn round_trips_a_recipe() {
+        let recipe = recipe();
+        let item = to_item(&recipe);
+        assert!(matches!(item.get("ingredients"), Some(AttributeValue::L(_))));
+        assert_eq!(item.get("owner"), None);
+        assert_eq!(from_item(&item), recipe);
+    }
+
+    #[test]
+    fn round_trips_a_job() {
+        let mut job = Job::new("job-1");
+        job.name = Some(String::from("Taco Soup"));
+        job.fail("Error parsing recipe");
+        let item = job_to_item(&job);
+        assert_eq!(item.get("status"), Some(&AttributeValue::S(String::from("failed"))));
+        assert_eq!(item.get("expires_at"), Some(&AttributeValue::N(job.expires_at().to_string())));
+        assert_eq!(job_from_item(&item), Some(job));
+
+        let mut done = Job::new("job-2");
+        done.record_parsed(1, "Taco Soup");
+        done.record_failed(2, "Error parsing recipe contents");
+        done.finish_all(&[String::from("https://tasty.co/best-soups"), String::from("https://tasty.co/best-soups#recipe-2")]);
+        assert_eq!(job_from_item(&job_to_item(&done)), Some(done));
+
+        let mut broken = item.clone();
+        broken.insert(String::from("status"), AttributeValue::S(String::from("cooking")));
+        assert_eq!(job_from_item(&broken), None);
+    }
+
+    #[test]
+    fn round_trips_a_dead_letter() {
+        let letter = DeadLetter::new("job-1", r#"{"url":"https://tasty.co/recipe/taco-soup"}"#, ErrorKind::Retryable, "429 Too Many Requests");
+        let item = dead_letter_to_item(&letter);
+        assert_eq!(item.get("kind"), Some(&AttributeValue::S(String::from("retryable"))));
+        assert_eq!(dead_letter_from_item(&item), Some(letter));
+    }
+
+    #[test]
+    fn reads_version_one_items() {
+        let item = HashMap::from([
+            (String::from("uuid"), AttributeValue::S(String::from("abc"))),
+            (String::from("name"), AttributeValue::S(String::from("Gyoza"))),
+            (String::from("ingredients"), AttributeValue::S(String::from("1 lb pork\\, ground;2 tbsp soy sauce"))),
+            (String::from("instructions"), AttributeValue::S(String::from("Mix\\; fold;Fry"))),
+            (String::from("owner"), AttributeValue::S(String::from("dmbluesmith"))),
+        ]);
+        let recipe = from_item(&item);
+        assert_eq!(recipe.raw_ingredients(), vec!["1 lb pork, ground", "2 tbsp soy sauce"]);
+        assert_eq!(recipe.ingredients[0].item, "pork");
+        assert_eq!(recipe.instructions, vec!["Mix; fold", "Fry"]);
+        assert_eq!(recipe.owner.as_deref(), Some("dmbluesmith"));
+        assert_eq!(recipe.nutrition, None);
+        assert_eq!(recipe.schema_version, 1);
+    }
+
+    #[test]
+    fn splits_joined_strings() {
+        assert_eq!(split_string("a\\,b;c\\\\;d"), vec!["a,b", "c\\", "d"]);
+        assert!(split_string("").is_empty());
+    }
+
+    #[test]
+    fn backs_off_batches() {
+        let waits: Vec<u128> = (1..BATCH_ATTEMPTS).map(|a| batch_backoff(a).as_millis()).collect();
+        assert_eq!(waits, [50, 100, 200, 400]);
+    }
+
+    #[test]
+    fn builds_update_expressions() {
+        let update = RecipeUpdate {
+            uuid: String::from("abc"),
+            name: Some(String::from("Soup")),
+            ingredients: Some(vec![String::from("2 cups stock")]),
+            ..RecipeUpdate::default()
+        };
+        let mut recipe = Recipe::new("abc", "Stock");
+        recipe.instructions = vec![String::from("Boil")];
+        update.apply(&mut recipe);
+        let expression = Expression::from("dmbluesmith", &update, Some(&recipe));
+        assert_eq!(
+            expression.expression,
+            "SET #name = :nameValue,#ingredients = :ingredientsValue,#schema_version = :schema_versionValue,#nutrition = :nutritionValue,#fingerprint = :fingerprintValue REMOVE #parsedIngredients"
+        );
+        assert_eq!(expression.values.get(":currentOwner"), Some(&AttributeValue::S(String::from("dmbluesmith"))));
+        assert_eq!(expression.names.get("#parsedIngredients").map(String::as_str), Some("parsed_ingredients"));
+        // What's stored is what the edited recipe works out to, not cleared for readers to redo
+        let stored = |name: &str| match expression.values.get(name) {
+            Some(AttributeValue::S(s)) => serde_json::from_str::<Value>(s).unwrap(),
+            other => panic!("{} is {:?}", name, other),
+        };
+        assert_eq!(stored(":nutritionValue"), serde_json::to_value(recipe.nutrition.as_ref().unwrap()).unwrap());
+        assert_eq!(stored(":fingerprintValue"), serde_json::to_value(recipe.fingerprint.as_ref().unwrap()).unwrap());
+
+        let steps = RecipeUpdate {
+            uuid: String::from("abc"),
+            instructions: Some(vec![String::from("Simmer")]),
+            ..RecipeUpdate::default()
+        };
+        steps.apply(&mut recipe);
+        let expression = Expression::from("dmbluesmith", &steps, Some(&recipe));
+        assert_eq!(expression.expression, "SET #instructions = :instructionsValue,#fingerprint = :fingerprintValue");
+        assert_eq!(expression.names.get("#fingerprint").map(String::as_str), Some("fingerprint"));
+    }
+
+    #[test]
+    fn conditions_edits_on_what_was_read() {
+        let update = RecipeUpdate {
+            uuid: String::from("abc"),
+            instructions: Some(vec![String::from("Simmer")]),
+            ..RecipeUpdate::default()
+        };
+        let mut recipe = Recipe::new("abc", "Stock");
+        update.apply(&mut recipe);
+
+        let read = AttributeValue::S(String::from("[1,2,3]"));
+        let mut expression = Expression::from("dmbluesmith", &update, Some(&recipe));
+        expression.unchanged_since(Some(&read));
+        assert_eq!(
+            expression.condition,
+            "attribute_exists(#owner) AND #owner = :currentOwner AND #fingerprint = :readFingerprint"
+        );
+        assert_eq!(expression.values.get(":readFingerprint"), Some(&read));
+
+        // Saved before fingerprints, so nobody else's edit may have added one
+        let mut expression = Expression::from("dmbluesmith", &update, Some(&recipe));
+        expression.unchanged_since(None);
+        assert_eq!(
+            expression.condition,
+            "attribute_exists(#owner) AND #owner = :currentOwner AND attribute_not_exists(#fingerprint)"
+        );
+    }
+}
@@ -0,0 +1,178 @@
+use serde_json::json;
+
+use crate::config::Error;
+use crate::store::StoreError;
+
+/**
+ * What the API lambdas send back when a request fails. Each variant is one
+ * HTTP status, and the body is JSON, `{"code": "not_found", "message":
+ * "No job found for 1234"}`, so clients can switch on `code` and show
+ * `message`.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiError {
+    // The request isn't valid JSON or is missing a parameter
+    BadRequest(String),
+    // The recipe belongs to someone else, or isn't there to check
+    Forbidden(String),
+    NotFound(String),
+    // The request clashes with something that already exists
+    Conflict(String),
+    // Well formed, but we can't do anything with it, e.g. an image with no uuid
+    Unprocessable(String),
+    // DynamoDB or another service is throttling us, try again shortly
+    TooManyRequests(String),
+    // Our fault, e.g. a missing table name
+    Internal(String),
+    // Something we depend on failed, e.g. DynamoDB or SNS
+    Unavailable(String),
+}
+
+impl ApiError {
+    pub fn status(&self) -> u16 {
+        match self {
+            ApiError::BadRequest(_) => 400,
+            ApiError::Forbidden(_) => 403,
+            ApiError::NotFound(_) => 404,
+            ApiError::Conflict(_) => 409,
+            ApiError::Unprocessable(_) => 422,
+            ApiError::TooManyRequests(_) => 429,
+            ApiError::Internal(_) => 500,
+            ApiError::Unavailable(_) => 503,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::BadRequest(_) => "bad_request",
+            ApiError::Forbidden(_) => "forbidden",
+            ApiError::NotFound(_) => "not_found",
+            ApiError::Conflict(_) => "conflict",
+            ApiError::Unprocessable(_) => "unprocessable",
+            ApiError::TooManyRequests(_) => "too_many_requests",
+            ApiError::Internal(_) => "internal",
+            ApiError::Unavailable(_) => "unavailable",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            ApiError::BadRequest(m)
+            | ApiError::Forbidden(m)
+            | ApiError::NotFound(m)
+            | ApiError::Conflict(m)
+            | ApiError::Unprocessable(m)
+            | ApiError::TooManyRequests(m)
+            | ApiError::Internal(m)
+            | ApiError::Unavailable(m) => m,
+        }
+    }
+
+    // The response body
+    pub fn body(&self) -> String {
+        json!({ "code": self.code(), "message": self.message() }).to_string()
+    }
+}
+
+/**
+ * The response for a failed request, `{"code", "message"}` JSON with the
+ * error's status, as a `Result<Response<B>, Error>` for any body made from
+ * a String. It's a macro so it builds the response with the calling
+ * lambda's own `lambda_http`, whichever version that is.
+ *
+ * `return error_response!(ApiError::BadRequest(e));`
+ */
+#[macro_export]
+macro_rules! error_response {
+    ($e:expr) => {{
+        let e: $crate::ApiError = $e;
+        ::lambda_http::Response::builder()
+            .status(e.status())
+            .header("Access-Control-Allow-Origin", "*")
+            .header("Content-Type", "application/json")
+            .body(e.body().into())
+            .map_err(::lambda_http::Error::from)
+    }};
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}): {}", self.status(), self.code(), self.message())
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl From<StoreError> for ApiError {
+    fn from(e: StoreError) -> ApiError {
+        match e {
+            StoreError::NotAllowed(m) => ApiError::Forbidden(m),
+            StoreError::Throttled(m) => ApiError::TooManyRequests(m),
+            StoreError::Config(m) => ApiError::Internal(format!("Store not configured: {}", m)),
+            StoreError::Backend(m) => ApiError::Unavailable(format!("Store error: {}", m)),
+        }
+    }
+}
+
+// For request bodies: broken JSON is a 400, JSON of the wrong shape a 422
+impl From<serde_json::Error> for ApiError {
+    fn from(e: serde_json::Error) -> ApiError {
+        match e.classify() {
+            serde_json::error::Category::Data => ApiError::Unprocessable(format!("Invalid request: {}", e)),
+            _ => ApiError::BadRequest(format!("Invalid JSON: {}", e)),
+        }
+    }
+}
+
+/**
+ * Whatever a handler's `?` passed up. Store and JSON errors keep their
+ * mapping, anything else we didn't expect is a 500.
+ */
+impl From<Error> for ApiError {
+    fn from(e: Error) -> ApiError {
+        let e = match e.downcast::<ApiError>() {
+            Ok(e) => return *e,
+            Err(e) => e,
+        };
+        let e = match e.downcast::<StoreError>() {
+            Ok(e) => return ApiError::from(*e),
+            Err(e) => e,
+        };
+        match e.downcast::<serde_json::Error>() {
+            Ok(e) => ApiError::from(*e),
+            Err(e) => ApiError::Internal(e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn maps_errors_to_statuses() {
+        let body: Value = serde_json::from_str(&ApiError::NotFound(String::from("No job found for 1234")).body()).unwrap();
+        assert_eq!(body["code"], "not_found");
+        assert_eq!(body["message"], "No job found for 1234");
+
+        assert_eq!(ApiError::from(StoreError::NotAllowed(String::from("hacker can't update soup"))).status(), 403);
+        assert_eq!(ApiError::from(StoreError::Throttled(String::from("slow down"))).status(), 429);
+        assert_eq!(ApiError::from(StoreError::Backend(String::from("timed out"))).status(), 503);
+
+        let broken = serde_json::from_str::<Value>("{\"url\":").unwrap_err();
+        assert_eq!(ApiError::from(broken).status(), 400);
+        let wrong_shape = serde_json::from_str::<Vec<String>>("{\"url\": 3}").unwrap_err();
+        assert_eq!(ApiError::from(wrong_shape).status(), 422);
+    }
+
+    #[test]
+    fn keeps_the_mapping_through_a_boxed_error() {
+        let boxed: Error = Box::new(StoreError::NotAllowed(String::from("hacker can't update soup")));
+        assert_eq!(ApiError::from(boxed).code(), "forbidden");
+        let boxed: Error = Box::new(ApiError::Conflict(String::from("Already exists")));
+        assert_eq!(ApiError::from(boxed).status(), 409);
+        let boxed: Error = "SNS Publish Failure".into();
+        assert_eq!(ApiError::from(boxed), ApiError::Internal(String::from("SNS Publish Failure")));
+    }
+}
@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures_util::stream::{self, Stream, StreamExt};
+use tokio::sync::broadcast;
+use tokio::time::{sleep, Instant};
+
+use crate::job::{Job, JobStore};
+use crate::store::StoreError;
+
+// How often `StoreEventBus` reads the job, and how long it waits for one to finish
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+const POLL_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+// Events a slow subscriber can fall behind by before it starts missing them
+const CHANNEL_SIZE: usize = 64;
+
+/**
+ * Each version of a job as it changes, ending after it's done or failed.
+ */
+pub type JobStream = Pin<Box<dyn Stream<Item = Job> + Send>>;
+
+/**
+ * How the worker tells anyone watching that a job has moved on. Events are
+ * whole jobs, so a subscriber that misses one still ends up with the latest.
+ */
+#[async_trait]
+pub trait EventBus: Send + Sync {
+    async fn publish(&self, job: &Job) -> Result<(), StoreError>;
+
+    // Starts with the job as it is now, if there is one yet
+    async fn subscribe(&self, id: &str) -> Result<JobStream, StoreError>;
+}
+
+// Skips repeats and stops once the job can't change any more
+fn until_finished(jobs: impl Stream<Item = Job> + Send + 'static) -> JobStream {
+    Box::pin(stream::unfold((Box::pin(jobs), None::<Job>), |(mut jobs, last)| async move {
+        if last.as_ref().is_some_and(|job| job.status.is_finished()) {
+            return None;
+        }
+        loop {
+            let job = jobs.next().await?;
+            if last.as_ref() != Some(&job) {
+                return Some((job.clone(), (jobs, Some(job))));
+            }
+        }
+    }))
+}
+
+/**
+ * Keeps events in memory, for tests and running the worker and the event
+ * stream in one process.
+ */
+pub struct MemoryEventBus {
+    sender: broadcast::Sender<Job>,
+    latest: Mutex<HashMap<String, Job>>,
+}
+
+impl MemoryEventBus {
+    pub fn new() -> MemoryEventBus {
+        MemoryEventBus {
+            sender: broadcast::channel(CHANNEL_SIZE).0,
+            latest: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl Default for MemoryEventBus {
+    fn default() -> MemoryEventBus {
+        MemoryEventBus::new()
+    }
+}
+
+#[async_trait]
+impl EventBus for MemoryEventBus {
+    async fn publish(&self, job: &Job) -> Result<(), StoreError> {
+        self.latest.lock().unwrap().insert(job.id.clone(), job.clone());
+        // Nobody listening isn't an error
+        let _ = self.sender.send(job.clone());
+        Ok(())
+    }
+
+    async fn subscribe(&self, id: &str) -> Result<JobStream, StoreError> {
+        // Subscribe before reading the latest so nothing is lost in between,
+        // `until_finished` drops the repeat if it arrives twice
+        let receiver = self.sender.subscribe();
+        let current = self.latest.lock().unwrap().get(id).cloned();
+        let id = id.to_string();
+        let live = stream::unfold(receiver, |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(job) => return Some((job, receiver)),
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+        .filter(move |job| std::future::ready(job.id == id));
+        Ok(until_finished(stream::iter(current).chain(live)))
+    }
+}
+
+/**
+ * Uses the job store as the bus: publishing saves the job and subscribers
+ * poll it. The worker and the event stream run in different lambdas, so
+ * this is how they talk in AWS. Stages quicker than the poll interval can
+ * be skipped, but the last one never is.
+ */
+pub struct StoreEventBus<S: JobStore + Clone + 'static> {
+    store: S,
+    interval: Duration,
+    timeout: Duration,
+}
+
+impl<S: JobStore + Clone + 'static> StoreEventBus<S> {
+    pub fn new(store: S) -> StoreEventBus<S> {
+        StoreEventBus {
+            store,
+            interval: POLL_INTERVAL,
+            timeout: POLL_TIMEOUT,
+        }
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> StoreEventBus<S> {
+        self.interval = interval;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> StoreEventBus<S> {
+        self.timeout = timeout;
+        self
+    }
+}
+
+#[async_trait]
+impl<S: JobStore + Clone + 'static> EventBus for StoreEventBus<S> {
+    async fn publish(&self, job: &Job) -> Result<(), StoreError> {
+        self.store.put_job(job).await
+    }
+
+    async fn subscribe(&self, id: &str) -> Result<JobStream, StoreError> {
+        let store = self.store.clone();
+        let id = id.to_string();
+        let interval = self.interval;
+        let deadline = Instant::now() + self.timeout;
+        let mut first = true;
+        let polls = stream::unfold((), move |_| {
+            let store = store.clone();
+            let id = id.clone();
+            let wait = !std::mem::replace(&mut first, false);
+            async move {
+                if wait {
+                    sleep(interval).await;
+                }
+                if Instant::now() > deadline {
+                    return None;
+                }
+                match store.get_job(&id).await {
+                    Ok(job) => Some((job, ())),
+                    Err(e) => {
+                        println!("Error polling job {}: {:?}", id, e);
+                        None
+                    }
+                }
+            }
+        });
+        Ok(until_finished(polls.filter_map(std::future::ready)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::job::JobStatus;
+    use crate::memory::MemoryStore;
+    use std::sync::Arc;
+
+    fn job(status: JobStatus) -> Job {
+        let mut job = Job::new("job-1");
+        job.advance(status);
+        job
+    }
+
+    #[tokio::test]
+    async fn memory_bus_streams_until_done() {
+        let bus = MemoryEventBus::new();
+        bus.publish(&job(JobStatus::Fetching)).await.unwrap();
+        let events = bus.subscribe("job-1").await.unwrap();
+
+        let mut parsed = job(JobStatus::Imaging);
+        parsed.name = Some(String::from("Taco Soup"));
+        bus.publish(&Job::new("someone-else")).await.unwrap();
+        bus.publish(&parsed).await.unwrap();
+        bus.publish(&parsed).await.unwrap();
+        bus.publish(&job(JobStatus::Done)).await.unwrap();
+
+        let seen: Vec<Job> = events.collect().await;
+        let statuses: Vec<JobStatus> = seen.iter().map(|j| j.status).collect();
+        assert_eq!(statuses, vec![JobStatus::Fetching, JobStatus::Imaging, JobStatus::Done]);
+        assert_eq!(seen[1].name.as_deref(), Some("Taco Soup"));
+    }
+
+    #[tokio::test]
+    async fn store_bus_polls_the_job() {
+        let store = Arc::new(MemoryStore::new());
+        let bus = StoreEventBus::new(store.clone()).with_interval(Duration::from_millis(5));
+        let mut events = bus.subscribe("job-1").await.unwrap();
+
+        bus.publish(&job(JobStatus::Parsing)).await.unwrap();
+        assert_eq!(events.next().await.unwrap().status, JobStatus::Parsing);
+        bus.publish(&job(JobStatus::Failed)).await.unwrap();
+        assert_eq!(events.next().await.unwrap().status, JobStatus::Failed);
+        assert!(events.next().await.is_none());
+
+        let quiet = StoreEventBus::new(store).with_interval(Duration::from_millis(5)).with_timeout(Duration::from_millis(20));
+        assert!(quiet.subscribe("missing").await.unwrap().next().await.is_none());
+    }
+}
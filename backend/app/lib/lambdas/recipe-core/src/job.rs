@@ -0,0 +1,249 @@
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::store::StoreError;
+
+// Finished jobs are only polled for a little while, DynamoDB expires them after this
+pub const JOB_TTL_SECONDS: u64 = 7 * 24 * 60 * 60;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Fetching,
+    Parsing,
+    Imaging,
+    Saving,
+    Done,
+    Failed,
+}
+
+impl JobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Fetching => "fetching",
+            JobStatus::Parsing => "parsing",
+            JobStatus::Imaging => "imaging",
+            JobStatus::Saving => "saving",
+            JobStatus::Done => "done",
+            JobStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(status: &str) -> Option<JobStatus> {
+        [
+            JobStatus::Queued,
+            JobStatus::Fetching,
+            JobStatus::Parsing,
+            JobStatus::Imaging,
+            JobStatus::Saving,
+            JobStatus::Done,
+            JobStatus::Failed,
+        ]
+        .into_iter()
+        .find(|s| s.as_str() == status)
+    }
+
+    // Nothing moves a job on once it's done or failed
+    pub fn is_finished(&self) -> bool {
+        matches!(self, JobStatus::Done | JobStatus::Failed)
+    }
+}
+
+/**
+ * How one recipe in a bulk import went. `part` is the piece of the pasted
+ * text it came from, counting from 1; a part can hold several recipes, or
+ * fail before we know what any of them are called.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecipeResult {
+    pub part: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recipe_uuid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/**
+ * One request to add a recipe, from addRecipe queueing it to the worker
+ * saving it. Timestamps are seconds since the epoch. The recipe's name and
+ * image are filled in as soon as the worker has them, before it's saved.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Job {
+    pub id: String,
+    pub status: JobStatus,
+    pub created_at: u64,
+    pub updated_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recipe_uuid: Option<String>,
+    // Every recipe it saved, some sources have several; recipe_uuid is the first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recipe_uuids: Vec<String>,
+    // Bulk imports only, so people can see which of their recipes made it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub results: Vec<RecipeResult>,
+}
+
+impl Job {
+    pub fn new(id: &str) -> Job {
+        let now = now();
+        Job {
+            id: id.to_string(),
+            status: JobStatus::Queued,
+            created_at: now,
+            updated_at: now,
+            name: None,
+            image: None,
+            error: None,
+            recipe_uuid: None,
+            recipe_uuids: Vec::new(),
+            results: Vec::new(),
+        }
+    }
+
+    // Forgets everything a previous run worked out, for running the same job again
+    pub fn reset(&mut self) {
+        self.name = None;
+        self.image = None;
+        self.error = None;
+        self.recipe_uuid = None;
+        self.recipe_uuids.clear();
+        self.results.clear();
+    }
+
+    pub fn advance(&mut self, status: JobStatus) {
+        self.status = status;
+        self.updated_at = now().max(self.created_at);
+    }
+
+    pub fn finish(&mut self, recipe_uuid: &str) {
+        self.finish_all(&[recipe_uuid.to_string()]);
+    }
+
+    pub fn finish_all(&mut self, recipe_uuids: &[String]) {
+        self.recipe_uuid = recipe_uuids.first().cloned();
+        self.recipe_uuids = recipe_uuids.to_vec();
+        self.advance(JobStatus::Done);
+    }
+
+    pub fn record_parsed(&mut self, part: usize, name: &str) {
+        self.results.push(RecipeResult { part, name: Some(name.to_string()), recipe_uuid: None, error: None });
+    }
+
+    pub fn record_failed(&mut self, part: usize, error: &str) {
+        self.results.push(RecipeResult { part, name: None, recipe_uuid: None, error: Some(error.to_string()) });
+    }
+
+    // Recipes are saved in the order they were parsed, so the first unsaved one by that name is it
+    pub fn record_saved(&mut self, name: &str, recipe_uuid: &str) {
+        let parsed = self
+            .results
+            .iter_mut()
+            .find(|r| r.recipe_uuid.is_none() && r.error.is_none() && r.name.as_deref() == Some(name));
+        if let Some(result) = parsed {
+            result.recipe_uuid = Some(recipe_uuid.to_string());
+        }
+    }
+
+    pub fn fail(&mut self, error: &str) {
+        self.error = Some(error.to_string());
+        self.advance(JobStatus::Failed);
+    }
+
+    pub fn expires_at(&self) -> u64 {
+        self.updated_at + JOB_TTL_SECONDS
+    }
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/**
+ * Where jobs are kept. Only the worker writes a job once it's queued, so
+ * saving the whole job each time is safe.
+ */
+#[async_trait]
+pub trait JobStore: Send + Sync {
+    async fn get_job(&self, id: &str) -> Result<Option<Job>, StoreError>;
+
+    // Saves the whole job, replacing any with the same id
+    async fn put_job(&self, job: &Job) -> Result<(), StoreError>;
+}
+
+// Lets one store be shared, e.g. by the worker and an event bus
+#[async_trait]
+impl<T: JobStore + ?Sized> JobStore for Arc<T> {
+    async fn get_job(&self, id: &str) -> Result<Option<Job>, StoreError> {
+        (**self).get_job(id).await
+    }
+
+    async fn put_job(&self, job: &Job) -> Result<(), StoreError> {
+        (**self).put_job(job).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn job_lifecycle() {
+        let mut job = Job::new("job-1");
+        assert_eq!(job.status, JobStatus::Queued);
+        assert!(!job.status.is_finished());
+
+        job.advance(JobStatus::Parsing);
+        job.finish("https://tasty.co/recipe/taco-soup");
+        assert_eq!(job.status, JobStatus::Done);
+        assert!(job.status.is_finished());
+        assert_eq!(job.recipe_uuid.as_deref(), Some("https://tasty.co/recipe/taco-soup"));
+        assert_eq!(job.recipe_uuids, vec!["https://tasty.co/recipe/taco-soup"]);
+        assert!(job.updated_at >= job.created_at);
+        assert_eq!(job.expires_at(), job.updated_at + JOB_TTL_SECONDS);
+
+        let json = serde_json::to_value(&job).unwrap();
+        assert_eq!(json["status"], "done");
+        assert!(json.get("error").is_none());
+    }
+
+    #[test]
+    fn records_bulk_results() {
+        let mut job = Job::new("job-1");
+        job.record_parsed(1, "Taco Soup");
+        job.record_failed(2, "Error parsing recipe contents");
+        job.record_parsed(3, "Taco Soup");
+        job.record_saved("Taco Soup", "recipe-1");
+        job.record_saved("Taco Soup", "recipe-1#recipe-2");
+        assert_eq!(job.results[0].recipe_uuid.as_deref(), Some("recipe-1"));
+        assert_eq!(job.results[1].recipe_uuid, None);
+        assert_eq!(job.results[2].recipe_uuid.as_deref(), Some("recipe-1#recipe-2"));
+
+        let json = serde_json::to_value(&job).unwrap();
+        assert_eq!(json["results"][1], serde_json::json!({ "part": 2, "error": "Error parsing recipe contents" }));
+    }
+
+    #[test]
+    fn statuses_round_trip() {
+        for status in ["queued", "fetching", "parsing", "imaging", "saving", "done", "failed"] {
+            assert_eq!(JobStatus::parse(status).unwrap().as_str(), status);
+        }
+        assert_eq!(JobStatus::parse("cooking"), None);
+    }
+}
@@ -0,0 +1,27 @@
+mod canonical;
+mod config;
+mod dead_letter;
+mod dynamo;
+mod error;
+mod events;
+mod job;
+mod memory;
+mod recipe;
+mod response;
+mod similarity;
+mod store;
+#[cfg(feature = "test-support")]
+pub mod test_support;
+
+pub use canonical::{aliases, canonicalize_url, derived_key, find_by_url, recipe_key, site_host};
+pub use config::{make_config, make_region_provider, Error, Opt};
+pub use dead_letter::{DeadLetter, DeadLetterStore, ErrorKind};
+pub use dynamo::{from_item, ingredients_attribute, instructions_attribute, split_string, to_item, DynamoStore};
+pub use error::ApiError;
+pub use events::{EventBus, JobStream, MemoryEventBus, StoreEventBus};
+pub use job::{Job, JobStatus, JobStore, RecipeResult, JOB_TTL_SECONDS};
+pub use memory::MemoryStore;
+pub use recipe::{Recipe, RecipeUpdate, SCHEMA_VERSION};
+pub use response::{get_units, RecipeResponse};
+pub use similarity::{duplicate_groups, duplicates_of, find_duplicates, Duplicate, DuplicateGroup, Fingerprint, DUPLICATE_THRESHOLD};
+pub use store::{RecipeStore, StoreError};
@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+
+use crate::dead_letter::{DeadLetter, DeadLetterStore};
+use crate::job::{Job, JobStore};
+use crate::recipe::{Recipe, RecipeUpdate};
+use crate::store::{RecipeStore, StoreError};
+
+/**
+ * Keeps everything in memory, for tests and running handlers locally. It
+ * follows the same rules as `DynamoStore`, e.g. only the owner can update.
+ */
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    recipes: Mutex<HashMap<String, Recipe>>,
+    collections: Mutex<HashMap<String, Vec<String>>>,
+    jobs: Mutex<HashMap<String, Job>>,
+    aliases: Mutex<HashMap<String, String>>,
+    dead_letters: Mutex<HashMap<String, DeadLetter>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+
+    pub fn with_recipes(recipes: Vec<Recipe>) -> MemoryStore {
+        let store = MemoryStore::new();
+        store
+            .recipes
+            .lock()
+            .unwrap()
+            .extend(recipes.into_iter().map(|r| (r.uuid.clone(), r)));
+        store
+    }
+}
+
+#[async_trait]
+impl RecipeStore for MemoryStore {
+    async fn get(&self, uuid: &str) -> Result<Option<Recipe>, StoreError> {
+        Ok(self.recipes.lock().unwrap().get(uuid).cloned())
+    }
+
+    async fn list(&self) -> Result<Vec<Recipe>, StoreError> {
+        let mut recipes: Vec<Recipe> = self.recipes.lock().unwrap().values().cloned().collect();
+        recipes.sort_by(|a, b| a.uuid.cmp(&b.uuid));
+        Ok(recipes)
+    }
+
+    async fn put(&self, recipe: &Recipe) -> Result<(), StoreError> {
+        self.recipes.lock().unwrap().insert(recipe.uuid.clone(), recipe.clone());
+        Ok(())
+    }
+
+    async fn update(&self, owner: &str, update: &RecipeUpdate) -> Result<(), StoreError> {
+        let mut recipes = self.recipes.lock().unwrap();
+        match recipes.get_mut(&update.uuid) {
+            Some(recipe) if recipe.owner.as_deref() == Some(owner) => {
+                update.apply(recipe);
+                Ok(())
+            }
+            _ => Err(StoreError::NotAllowed(format!("{} can't update {}", owner, update.uuid))),
+        }
+    }
+
+    async fn collect(&self, username: &str, uuid: &str) -> Result<(), StoreError> {
+        let mut collections = self.collections.lock().unwrap();
+        let collection = collections.entry(username.to_string()).or_default();
+        if !collection.iter().any(|u| u == uuid) {
+            collection.push(uuid.to_string());
+        }
+        Ok(())
+    }
+
+    async fn collection(&self, username: &str) -> Result<Vec<String>, StoreError> {
+        Ok(self.collections.lock().unwrap().get(username).cloned().unwrap_or_default())
+    }
+
+    async fn alias(&self, alias: &str) -> Result<Option<String>, StoreError> {
+        Ok(self.aliases.lock().unwrap().get(alias).cloned())
+    }
+
+    async fn add_alias(&self, alias: &str, uuid: &str) -> Result<(), StoreError> {
+        self.aliases.lock().unwrap().insert(alias.to_string(), uuid.to_string());
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl JobStore for MemoryStore {
+    async fn get_job(&self, id: &str) -> Result<Option<Job>, StoreError> {
+        Ok(self.jobs.lock().unwrap().get(id).cloned())
+    }
+
+    async fn put_job(&self, job: &Job) -> Result<(), StoreError> {
+        self.jobs.lock().unwrap().insert(job.id.clone(), job.clone());
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DeadLetterStore for MemoryStore {
+    async fn put_dead_letter(&self, letter: &DeadLetter) -> Result<(), StoreError> {
+        self.dead_letters.lock().unwrap().insert(letter.id.clone(), letter.clone());
+        Ok(())
+    }
+
+    async fn list_dead_letters(&self) -> Result<Vec<DeadLetter>, StoreError> {
+        let mut letters: Vec<DeadLetter> = self.dead_letters.lock().unwrap().values().cloned().collect();
+        letters.sort_by(|a, b| a.failed_at.cmp(&b.failed_at).then_with(|| a.id.cmp(&b.id)));
+        Ok(letters)
+    }
+
+    async fn delete_dead_letter(&self, id: &str) -> Result<(), StoreError> {
+        self.dead_letters.lock().unwrap().remove(id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owned(uuid: &str, owner: &str) -> Recipe {
+        let mut recipe = Recipe::new(uuid, "Taco Soup");
+        recipe.owner = Some(owner.to_string());
+        recipe
+    }
+
+    #[tokio::test]
+    async fn only_owners_update() {
+        let store = MemoryStore::with_recipes(vec![owned("taco-soup", "dmbluesmith")]);
+        let update = RecipeUpdate {
+            uuid: String::from("taco-soup"),
+            name: Some(String::from("Hack That Soup")),
+            ingredients: Some(vec![String::from("2 cups stock")]),
+            ..RecipeUpdate::default()
+        };
+        assert!(matches!(store.update("hacker", &update).await, Err(StoreError::NotAllowed(_))));
+        store.update("dmbluesmith", &update).await.unwrap();
+
+        let recipe = store.get("taco-soup").await.unwrap().unwrap();
+        assert_eq!(recipe.name, "Hack That Soup");
+        assert_eq!(recipe.ingredients[0].item, "stock");
+        let missing = RecipeUpdate { uuid: String::from("nope"), ..RecipeUpdate::default() };
+        assert!(store.update("dmbluesmith", &missing).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn collects_recipes() {
+        let store = MemoryStore::with_recipes(vec![owned("b", "x"), owned("a", "x")]);
+        store.collect("dmbluesmith", "b").await.unwrap();
+        store.collect("dmbluesmith", "gone").await.unwrap();
+        store.collect("dmbluesmith", "b").await.unwrap();
+
+        let uuids = store.collection("dmbluesmith").await.unwrap();
+        assert_eq!(uuids, vec!["b", "gone"]);
+        let recipes = store.get_many(&uuids).await.unwrap();
+        assert_eq!(recipes.len(), 1);
+        assert_eq!(store.list().await.unwrap()[0].uuid, "a");
+    }
+}
@@ -0,0 +1,126 @@
+use recipe_ingredients::{parse_all, parse_servings, Ingredient};
+use recipe_nutrition::{estimate, Nutrition};
+use serde::{Deserialize, Serialize};
+
+use crate::similarity::Fingerprint;
+
+/**
+ * Version 1 items stored ingredients and instructions as `;` joined strings,
+ * version 2 stores them as native lists of maps. Bump this whenever the
+ * stored shape changes and teach `from_item` to read the old one.
+ */
+pub const SCHEMA_VERSION: u32 = 2;
+
+/**
+ * The one recipe every lambda reads and writes. Ingredients are kept parsed,
+ * each with its original line in `raw`.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Recipe {
+    pub schema_version: u32,
+    pub uuid: String,
+    pub name: String,
+    pub ingredients: Vec<Ingredient>,
+    pub instructions: Vec<String>,
+    pub notes: String,
+    pub summary: String,
+    pub image: String,
+    // Who to credit for the recipe, the source URL when nobody was named
+    pub credit: Option<String>,
+    // Where it came from when that isn't its key, e.g. a roundup post or the page an imported recipe was saved from
+    pub source: Option<String>,
+    // The user allowed to edit it
+    pub owner: Option<String>,
+    pub recipe_yield: Option<String>,
+    pub total_time: Option<String>,
+    pub author: Option<String>,
+    // Estimated when the recipe is saved, recomputed when its ingredients are edited
+    pub nutrition: Option<Nutrition>,
+    // For spotting duplicates, recomputed when its ingredients or instructions are edited
+    pub fingerprint: Option<Fingerprint>,
+}
+
+impl Recipe {
+    pub fn new(uuid: &str, name: &str) -> Recipe {
+        Recipe {
+            schema_version: SCHEMA_VERSION,
+            uuid: uuid.to_string(),
+            name: name.to_string(),
+            ..Recipe::default()
+        }
+    }
+
+    pub fn raw_ingredients(&self) -> Vec<String> {
+        self.ingredients.iter().map(|i| i.raw.clone()).collect()
+    }
+
+    pub fn servings(&self) -> Option<f64> {
+        self.recipe_yield.as_deref().and_then(parse_servings)
+    }
+
+    // The stored estimate (edits recompute it), or a fresh one for recipes saved without
+    pub fn nutrition(&self) -> Nutrition {
+        match &self.nutrition {
+            Some(n) => n.clone(),
+            None => estimate(&self.ingredients, self.servings()),
+        }
+    }
+
+    pub fn estimate_nutrition(&mut self) {
+        self.nutrition = Some(estimate(&self.ingredients, self.servings()));
+    }
+
+    // The stored fingerprint, or a fresh one for recipes saved without
+    pub fn fingerprint(&self) -> Fingerprint {
+        match &self.fingerprint {
+            Some(f) => f.clone(),
+            None => Fingerprint::of(self),
+        }
+    }
+
+    pub fn compute_fingerprint(&mut self) {
+        self.fingerprint = Some(Fingerprint::of(self));
+    }
+}
+
+/**
+ * An edit from updateRecipe, anything left as None stays as it is.
+ * Ingredients come in as plain lines and are parsed when applied.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct RecipeUpdate {
+    pub uuid: String,
+    pub name: Option<String>,
+    pub ingredients: Option<Vec<String>>,
+    pub instructions: Option<Vec<String>>,
+    pub notes: Option<String>,
+    pub summary: Option<String>,
+    pub owner: Option<String>,
+}
+
+impl RecipeUpdate {
+    pub fn apply(&self, recipe: &mut Recipe) {
+        if let Some(name) = &self.name {
+            recipe.name = name.clone();
+        }
+        if let Some(ingredients) = &self.ingredients {
+            recipe.ingredients = parse_all(ingredients);
+            recipe.estimate_nutrition();
+        }
+        if let Some(instructions) = &self.instructions {
+            recipe.instructions = instructions.clone();
+        }
+        if self.ingredients.is_some() || self.instructions.is_some() {
+            recipe.compute_fingerprint();
+        }
+        if let Some(notes) = &self.notes {
+            recipe.notes = notes.clone();
+        }
+        if let Some(summary) = &self.summary {
+            recipe.summary = summary.clone();
+        }
+        if let Some(owner) = &self.owner {
+            recipe.owner = Some(owner.clone());
+        }
+    }
+}
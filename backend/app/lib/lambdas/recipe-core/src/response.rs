@@ -0,0 +1,87 @@
+use recipe_ingredients::{convert, convert_measure, convert_text, ConvertedIngredient, Ingredient, Measure, System};
+use recipe_nutrition::Nutrition;
+use serde::{Deserialize, Serialize};
+
+use crate::recipe::Recipe;
+
+/**
+ * A recipe as the API returns it. `ingredients` stays a list of plain lines
+ * for older clients, with the parsed form alongside.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecipeResponse {
+    pub uuid: String,
+    pub name: String,
+    pub ingredients: Vec<String>,
+    pub parsed_ingredients: Vec<Ingredient>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub converted_ingredients: Option<Vec<ConvertedIngredient>>,
+    pub instructions: Vec<String>,
+    pub nutrition: Nutrition,
+    pub notes: String,
+    pub summary: String,
+    pub image: String,
+    pub credit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recipe_yield: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_time: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+}
+
+impl From<Recipe> for RecipeResponse {
+    fn from(recipe: Recipe) -> Self {
+        RecipeResponse {
+            ingredients: recipe.raw_ingredients(),
+            nutrition: recipe.nutrition(),
+            uuid: recipe.uuid,
+            name: recipe.name,
+            parsed_ingredients: recipe.ingredients,
+            converted_ingredients: None,
+            instructions: recipe.instructions,
+            notes: recipe.notes,
+            summary: recipe.summary,
+            image: recipe.image,
+            credit: recipe.credit,
+            source: recipe.source,
+            owner: recipe.owner,
+            recipe_yield: recipe.recipe_yield,
+            total_time: recipe.total_time,
+            author: recipe.author,
+        }
+    }
+}
+
+impl RecipeResponse {
+    // ?units=metric or ?units=us, instructions get their temperatures and pan sizes converted too.
+    // ?measure=weight or ?measure=volume also switches ingredients using the density table.
+    pub fn convert_units(&mut self, system: System, measure: Option<Measure>) {
+        self.converted_ingredients = Some(match measure {
+            Some(m) => convert_measure(&self.parsed_ingredients, system, m),
+            None => convert(&self.parsed_ingredients, system),
+        });
+        self.instructions = self.instructions.iter().map(|i| convert_text(i, system)).collect();
+    }
+}
+
+// Weights default to metric and volumes to US when only a measure is asked for
+pub fn get_units(units: Option<&str>, measure: Option<&str>) -> Result<Option<(System, Option<Measure>)>, String> {
+    let measure = match measure {
+        Some(m) => Some(Measure::parse(m).ok_or_else(|| format!("Unknown measure {}, use weight or volume", m))?),
+        None => None,
+    };
+    let system = match units {
+        Some(u) => System::parse(u).ok_or_else(|| format!("Unknown units {}, use metric or us", u))?,
+        None => match measure {
+            Some(Measure::Weight) => System::Metric,
+            Some(Measure::Volume) => System::Us,
+            None => return Ok(None),
+        },
+    };
+    Ok(Some((system, measure)))
+}
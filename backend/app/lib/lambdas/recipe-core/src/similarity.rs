@@ -0,0 +1,254 @@
+use serde::{Deserialize, Serialize};
+
+use crate::recipe::Recipe;
+use crate::store::{RecipeStore, StoreError};
+
+// Hashes in each signature, the score is accurate to about 1 / sqrt of this
+const SIGNATURE_SIZE: u64 = 64;
+
+// Recipes scoring at least this are reported as likely duplicates
+pub const DUPLICATE_THRESHOLD: f64 = 0.6;
+
+// What goes in says more about a recipe than how its steps are worded,
+// which changes a lot between a blog and a photo of a recipe card
+const INGREDIENT_WEIGHT: f64 = 0.6;
+
+const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "for", "from", "if", "in", "into", "is", "it", "of", "on", "or",
+    "the", "then", "to", "until", "with", "your", "you",
+];
+
+/**
+ * MinHash signatures of a recipe's ingredient items and of the word pairs in
+ * its instructions. Two recipes agree on about as many hashes as their sets
+ * overlap, so reposts and retyped copies score high even when quantities,
+ * wording or order differ a little. Empty when the recipe has none.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Fingerprint {
+    pub ingredients: Vec<u32>,
+    pub instructions: Vec<u32>,
+}
+
+impl Fingerprint {
+    pub fn of(recipe: &Recipe) -> Fingerprint {
+        let items: Vec<String> = recipe
+            .ingredients
+            .iter()
+            .map(|i| words(&i.item).join(" "))
+            .filter(|item| !item.is_empty())
+            .collect();
+        let tokens: Vec<String> = recipe
+            .instructions
+            .iter()
+            .flat_map(|step| words(step))
+            .filter(|w| !STOPWORDS.contains(&w.as_str()))
+            .collect();
+        let pairs: Vec<String> = if tokens.len() < 2 {
+            tokens
+        } else {
+            tokens.windows(2).map(|pair| pair.join(" ")).collect()
+        };
+        Fingerprint {
+            ingredients: signature(&items),
+            instructions: signature(&pairs),
+        }
+    }
+
+    // From 0, nothing in common, to 1, as far as we can tell the same recipe
+    pub fn similarity(&self, other: &Fingerprint) -> f64 {
+        match (overlap(&self.ingredients, &other.ingredients), overlap(&self.instructions, &other.instructions)) {
+            (Some(i), Some(s)) => INGREDIENT_WEIGHT * i + (1.0 - INGREDIENT_WEIGHT) * s,
+            (Some(i), None) => i,
+            (None, Some(s)) => s,
+            (None, None) => 0.0,
+        }
+    }
+}
+
+// Lowercased, singular words, with punctuation and numbers dropped
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphabetic()).filter(|w| !w.is_empty()).map(singular).collect()
+}
+
+// Close enough that "tomatoes" and "tomato" match
+fn singular(word: &str) -> String {
+    let word = word.to_lowercase();
+    if let Some(stem) = word.strip_suffix("oes") {
+        return format!("{}o", stem);
+    }
+    match word.strip_suffix('s') {
+        Some(stem) if stem.len() > 2 && !stem.ends_with('s') => stem.to_string(),
+        _ => word,
+    }
+}
+
+fn signature(features: &[String]) -> Vec<u32> {
+    if features.is_empty() {
+        return Vec::new();
+    }
+    let hashes: Vec<u64> = features.iter().map(|f| fnv1a(f)).collect();
+    (0..SIGNATURE_SIZE)
+        .map(|seed| {
+            let salt = (seed + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+            let min = hashes.iter().map(|h| mix(h ^ salt)).min().unwrap_or(u64::MAX);
+            (min >> 32) as u32
+        })
+        .collect()
+}
+
+fn overlap(a: &[u32], b: &[u32]) -> Option<f64> {
+    if a.is_empty() || b.is_empty() || a.len() != b.len() {
+        return None;
+    }
+    let same = a.iter().zip(b).filter(|(x, y)| x == y).count();
+    Some(same as f64 / a.len() as f64)
+}
+
+// Hashes have to be the same on every lambda and every build, so no std hasher
+fn fnv1a(text: &str) -> u64 {
+    text.bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+// splitmix64's finalizer, turns one hash into a differently ordered one per seed
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Duplicate {
+    pub uuid: String,
+    pub name: String,
+    // Rounded to two places
+    pub score: f64,
+}
+
+/**
+ * A recipe with everything that looks like a copy of it, for merging.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DuplicateGroup {
+    pub uuid: String,
+    pub name: String,
+    pub duplicates: Vec<Duplicate>,
+}
+
+/**
+ * The recipes in `candidates` scoring at least `threshold` against `recipe`,
+ * best first. The recipe itself is left out.
+ */
+pub fn duplicates_of(recipe: &Recipe, candidates: &[Recipe], threshold: f64) -> Vec<Duplicate> {
+    let fingerprint = recipe.fingerprint();
+    let mut duplicates: Vec<Duplicate> = candidates
+        .iter()
+        .filter(|c| c.uuid != recipe.uuid)
+        .map(|c| Duplicate {
+            uuid: c.uuid.clone(),
+            name: c.name.clone(),
+            score: (fingerprint.similarity(&c.fingerprint()) * 100.0).round() / 100.0,
+        })
+        .filter(|d| d.score >= threshold)
+        .collect();
+    duplicates.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.uuid.cmp(&b.uuid)));
+    duplicates
+}
+
+// Every recipe that has at least one likely duplicate
+pub fn duplicate_groups(recipes: &[Recipe], threshold: f64) -> Vec<DuplicateGroup> {
+    // Fingerprint each recipe once rather than once per pair
+    let fingerprinted: Vec<Recipe> = recipes
+        .iter()
+        .cloned()
+        .map(|mut r| {
+            r.fingerprint = Some(r.fingerprint());
+            r
+        })
+        .collect();
+    fingerprinted
+        .iter()
+        .map(|recipe| DuplicateGroup {
+            uuid: recipe.uuid.clone(),
+            name: recipe.name.clone(),
+            duplicates: duplicates_of(recipe, &fingerprinted, threshold),
+        })
+        .filter(|group| !group.duplicates.is_empty())
+        .collect()
+}
+
+/**
+ * Likely duplicates of `recipe` already in the store. This reads every
+ * recipe, which is fine at our size; a band index would be the next step.
+ */
+pub async fn find_duplicates(store: &dyn RecipeStore, recipe: &Recipe, threshold: f64) -> Result<Vec<Duplicate>, StoreError> {
+    Ok(duplicates_of(recipe, &store.list().await?, threshold))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::MemoryStore;
+    use recipe_ingredients::parse_all;
+
+    fn recipe(uuid: &str, ingredients: &[&str], instructions: &[&str]) -> Recipe {
+        let mut recipe = Recipe::new(uuid, uuid);
+        recipe.ingredients = parse_all(&ingredients.iter().map(|i| i.to_string()).collect::<Vec<_>>());
+        recipe.instructions = instructions.iter().map(|i| i.to_string()).collect();
+        recipe
+    }
+
+    fn soup(uuid: &str) -> Recipe {
+        recipe(
+            uuid,
+            &["1 lb ground beef", "2 cups beef broth", "1 can black beans", "1 can diced tomatoes", "1 packet taco seasoning", "1 cup corn"],
+            &["Brown the beef in a large pot and drain the fat.", "Stir in the broth, beans, tomatoes, seasoning and corn.", "Simmer for 20 minutes and serve with cheese."],
+        )
+    }
+
+    #[test]
+    fn scores_reposts_above_other_recipes() {
+        let original = soup("blog");
+        let repost = recipe(
+            "photo",
+            &["1.5 pounds lean ground beef", "3 cups beef broth", "1 (15 oz) can black beans, drained", "1 can tomatoes", "1 packet taco seasoning", "1 cup frozen corn"],
+            &["Brown the beef in a large pot and drain the fat", "Stir in the broth, beans, tomato, seasoning and corn", "Simmer 20 minutes, serve with cheese"],
+        );
+        let pie = recipe(
+            "pie",
+            &["2 cups flour", "1 cup butter", "1/2 cup sugar", "4 apples", "1 tsp cinnamon"],
+            &["Cut the butter into the flour.", "Slice the apples and toss with sugar and cinnamon.", "Bake at 375F for 45 minutes."],
+        );
+
+        let same = original.fingerprint().similarity(&repost.fingerprint());
+        let different = original.fingerprint().similarity(&pie.fingerprint());
+        assert!(same >= DUPLICATE_THRESHOLD, "{}", same);
+        assert!(different < 0.2, "{}", different);
+        assert_eq!(original.fingerprint().similarity(&soup("copy").fingerprint()), 1.0);
+        assert_eq!(Recipe::new("empty", "Empty").fingerprint().similarity(&original.fingerprint()), 0.0);
+
+        let groups = duplicate_groups(&[original, repost, pie], DUPLICATE_THRESHOLD);
+        let uuids: Vec<&str> = groups.iter().map(|g| g.uuid.as_str()).collect();
+        assert_eq!(uuids, vec!["blog", "photo"]);
+        assert_eq!(groups[0].duplicates[0].uuid, "photo");
+    }
+
+    #[test]
+    fn fingerprints_are_stable() {
+        // Stored fingerprints are compared with fresh ones, so this must never change
+        let fingerprint = Fingerprint::of(&soup("blog"));
+        assert_eq!(fingerprint.ingredients.len(), SIGNATURE_SIZE as usize);
+        assert_eq!(fnv1a("beef"), 0x75f4_609b_951a_c55b);
+        assert_eq!(singular("tomatoes"), "tomato");
+        assert_eq!(singular("Beans"), "bean");
+        assert_eq!(singular("swiss"), "swiss");
+    }
+
+    #[tokio::test]
+    async fn finds_duplicates_in_the_store() {
+        let store = MemoryStore::with_recipes(vec![soup("blog"), soup("repost")]);
+        let duplicates = find_duplicates(&store, &soup("blog"), DUPLICATE_THRESHOLD).await.unwrap();
+        assert_eq!(duplicates, vec![Duplicate { uuid: String::from("repost"), name: String::from("repost"), score: 1.0 }]);
+    }
+}
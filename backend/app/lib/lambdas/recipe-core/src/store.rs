@@ -0,0 +1,67 @@
+use async_trait::async_trait;
+
+use crate::recipe::{Recipe, RecipeUpdate};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StoreError {
+    // The recipe isn't there, or belongs to someone else
+    NotAllowed(String),
+    // The store isn't set up for this, e.g. no users table
+    Config(String),
+    // Over the table's capacity, worth trying again shortly
+    Throttled(String),
+    // Anything the backend itself failed at
+    Backend(String),
+}
+
+impl std::fmt::Display for StoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StoreError::NotAllowed(e) => write!(f, "Not allowed: {}", e),
+            StoreError::Config(e) => write!(f, "Store not configured: {}", e),
+            StoreError::Throttled(e) => write!(f, "Store throttled: {}", e),
+            StoreError::Backend(e) => write!(f, "Store error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+/**
+ * Everything the lambdas need from storage. `DynamoStore` is what runs in
+ * AWS, `MemoryStore` lets handlers be tested without it.
+ */
+#[async_trait]
+pub trait RecipeStore: Send + Sync {
+    async fn get(&self, uuid: &str) -> Result<Option<Recipe>, StoreError>;
+
+    async fn list(&self) -> Result<Vec<Recipe>, StoreError>;
+
+    // Saves the whole recipe, replacing any with the same uuid
+    async fn put(&self, recipe: &Recipe) -> Result<(), StoreError>;
+
+    // Only applies if the recipe exists and `owner` owns it
+    async fn update(&self, owner: &str, update: &RecipeUpdate) -> Result<(), StoreError>;
+
+    // Adds a recipe to a user's collection
+    async fn collect(&self, username: &str, uuid: &str) -> Result<(), StoreError>;
+
+    // The uuids in a user's collection
+    async fn collection(&self, username: &str) -> Result<Vec<String>, StoreError>;
+
+    // The uuid of the recipe a non-canonical URL was saved under
+    async fn alias(&self, alias: &str) -> Result<Option<String>, StoreError>;
+
+    async fn add_alias(&self, alias: &str, uuid: &str) -> Result<(), StoreError>;
+
+    // Recipes that have gone missing since being collected are left out
+    async fn get_many(&self, uuids: &[String]) -> Result<Vec<Recipe>, StoreError> {
+        let mut recipes = Vec::new();
+        for uuid in uuids {
+            if let Some(recipe) = self.get(uuid).await? {
+                recipes.push(recipe);
+            }
+        }
+        Ok(recipes)
+    }
+}
@@ -0,0 +1,30 @@
+use crate::recipe::Recipe;
+
+/**
+ * The recipe the exporters, prints and books are all tested with: from a
+ * web page, with ingredients that have units and a preparation, a few
+ * steps, notes, nutrition and every optional detail filled in.
+ */
+pub fn taco_soup() -> Recipe {
+    let mut recipe = Recipe::new("https://www.seriouseats.com/taco-soup", "Taco Soup");
+    recipe.ingredients = recipe_ingredients::parse_all(&[
+        String::from("1 lb ground beef"),
+        String::from("1 onion, diced"),
+        String::from("2 cups beef broth"),
+        String::from("1 can black beans"),
+        String::from("Salt"),
+    ]);
+    recipe.instructions = vec![
+        String::from("Brown the ground beef with the onion in a large pot."),
+        String::from("Add the beef broth and black beans and simmer for 20 minutes."),
+        String::from("Season with salt."),
+    ];
+    recipe.notes = String::from("Even better the next day.");
+    recipe.summary = String::from("A quick weeknight soup.");
+    recipe.credit = Some(String::from("Serious Eats"));
+    recipe.recipe_yield = Some(String::from("4 servings"));
+    recipe.total_time = Some(String::from("PT35M"));
+    recipe.author = Some(String::from("Grandma"));
+    recipe.estimate_nutrition();
+    recipe
+}
@@ -0,0 +1,241 @@
+use std::collections::HashSet;
+use std::io::{Cursor, Write};
+
+use recipe_core::Recipe;
+use recipe_ingredients::{format_quantity, Ingredient};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::duration::to_words;
+use crate::format::page_url;
+
+// A step as plain text and the `@ingredient{}` tokens already put into it
+enum Part {
+    Text(String),
+    Token(String),
+}
+
+// Cooklang has no escapes, so a name can't have anything that would end it
+fn clean(item: &str) -> String {
+    let kept: String = item.chars().filter(|c| !"@#~{}%().,;".contains(*c)).collect();
+    kept.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+// Metadata is a line each
+fn line(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+// "1 1/2%cups", or nothing for "salt"
+fn amount(ingredient: &Ingredient) -> String {
+    let mut parts = Vec::new();
+    if let Some(quantity) = &ingredient.quantity {
+        parts.push(format_quantity(quantity, ingredient.unit));
+    }
+    if let Some(unit) = ingredient.unit {
+        let plural = ingredient.quantity.as_ref().is_some_and(|q| q.max.is_some() || q.value > 1.0);
+        parts.push(unit.name(plural).to_string());
+    }
+    parts.join("%")
+}
+
+fn token(name: &str, ingredient: &Ingredient) -> String {
+    let mut token = format!("@{}{{{}}}", name, amount(ingredient));
+    let preparation: Vec<String> = ingredient.preparation.iter().chain(&ingredient.notes).map(|p| clean(p)).filter(|p| !p.is_empty()).collect();
+    if !preparation.is_empty() {
+        token += &format!("({})", preparation.join(" "));
+    }
+    token
+}
+
+// Where `name`, or its plural, is first a whole word in `text`, and how long it is there
+fn find_word(text: &str, name: &str) -> Option<(usize, usize)> {
+    let lower = text.to_ascii_lowercase();
+    let name = name.to_ascii_lowercase();
+    let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '-';
+    let mut from = 0;
+    while let Some(i) = lower[from..].find(&name) {
+        let start = from + i;
+        let mut end = start + name.len();
+        for plural in ["es", "s"] {
+            if lower[end..].starts_with(plural) && !lower[end + plural.len()..].starts_with(is_word) {
+                end += plural.len();
+                break;
+            }
+        }
+        if !lower[..start].ends_with(is_word) && !lower[end..].starts_with(is_word) {
+            return Some((start, end));
+        }
+        from = start + lower[start..].chars().next().map_or(1, char::len_utf8);
+    }
+    None
+}
+
+// Marks up the first mention of the ingredient in the steps, false if none mention it
+fn mark(steps: &mut [Vec<Part>], ingredient: &Ingredient) -> bool {
+    let name = clean(&ingredient.item);
+    if name.is_empty() {
+        return false;
+    }
+    for parts in steps.iter_mut() {
+        for i in 0..parts.len() {
+            let text = match &parts[i] {
+                Part::Text(text) => text,
+                Part::Token(_) => continue,
+            };
+            if let Some((start, end)) = find_word(text, &name) {
+                let replacement = [
+                    Part::Text(text[..start].to_string()),
+                    Part::Token(token(&text[start..end], ingredient)),
+                    Part::Text(text[end..].to_string()),
+                ];
+                parts.splice(i..=i, replacement);
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/**
+ * A recipe as a Cooklang file. Cooklang has no ingredient list, a recipe
+ * uses what its steps mark up, so each ingredient is marked up where a
+ * step first mentions it. Any the steps never mention are gathered up in
+ * a step of their own at the start.
+ */
+pub(crate) fn recipe(recipe: &Recipe) -> String {
+    // 1. Metadata
+    let credit = recipe.credit.as_deref().filter(|c| Some(*c) != page_url(recipe));
+    let metadata = [
+        ("title", Some(recipe.name.as_str())),
+        ("description", Some(recipe.summary.as_str()).filter(|s| !s.is_empty())),
+        ("servings", recipe.recipe_yield.as_deref()),
+        ("time", recipe.total_time.as_deref().map(to_words).as_deref()),
+        ("author", recipe.author.as_deref()),
+        ("source.name", credit),
+        ("source.url", page_url(recipe)),
+        ("image", Some(recipe.image.as_str()).filter(|i| !i.is_empty())),
+    ]
+    .into_iter()
+    .filter_map(|(key, value)| value.map(|v| format!(">> {}: {}\n", key, line(v))))
+    .collect::<String>();
+
+    // 2. Steps, with the ingredients marked up where they're used
+    let mut steps: Vec<Vec<Part>> = recipe.instructions.iter().map(|s| vec![Part::Text(line(s))]).collect();
+    let unused: Vec<&Ingredient> = recipe.ingredients.iter().filter(|i| !mark(&mut steps, i)).collect();
+    let mut paragraphs: Vec<String> = Vec::new();
+    if !unused.is_empty() {
+        let tokens: Vec<String> = unused.iter().map(|i| token(&clean(if i.item.is_empty() { &i.raw } else { &i.item }), i)).collect();
+        paragraphs.push(format!("Gather {}.", tokens.join(", ")));
+    }
+    for parts in steps {
+        paragraphs.push(
+            parts
+                .into_iter()
+                .map(|p| match p {
+                    Part::Text(t) | Part::Token(t) => t,
+                })
+                .collect(),
+        );
+    }
+
+    // 3. Notes
+    let notes: Vec<String> = recipe.notes.lines().map(str::trim).filter(|l| !l.is_empty()).map(|l| format!("> {}", l)).collect();
+    if !notes.is_empty() {
+        paragraphs.push(notes.join("\n"));
+    }
+
+    format!("{}\n{}\n", metadata, paragraphs.join("\n\n"))
+}
+
+// Cooklang apps title a recipe by its file, so "Taco Soup" is "Taco Soup.cook", without what a file name can't have
+fn file_stem(name: &str) -> String {
+    let kept: String = name.chars().filter(|c| !c.is_control() && !"/\\:*?\"<>|".contains(*c)).collect();
+    let stem = line(kept.trim_start_matches('.'));
+    if stem.is_empty() {
+        String::from("Recipe")
+    } else {
+        stem
+    }
+}
+
+/**
+ * Several recipes as a zip of `.cook` files, one each, numbering the
+ * second of two with the same name.
+ */
+pub(crate) fn collection(recipes: &[Recipe]) -> Result<Vec<u8>, String> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let mut taken = HashSet::new();
+    for r in recipes {
+        let base = file_stem(&r.name);
+        let mut name = format!("{}.cook", base);
+        let mut n = 1;
+        while !taken.insert(name.to_lowercase()) {
+            n += 1;
+            name = format!("{} {}.cook", base, n);
+        }
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        zip.start_file(name.as_str(), options).map_err(|e| format!("Error writing {}: {}", name, e))?;
+        zip.write_all(recipe(r).as_bytes()).map_err(|e| format!("Error writing {}: {}", name, e))?;
+    }
+    Ok(zip.finish().map_err(|e| format!("Error finishing zip: {}", e))?.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use recipe_core::test_support::taco_soup;
+    use recipe_import::{read, Format};
+
+    #[test]
+    fn marks_up_steps() {
+        let cook = recipe(&taco_soup());
+        assert!(cook.starts_with(">> title: Taco Soup\n>> description: A quick weeknight soup.\n>> servings: 4 servings\n>> time: 35 mins\n"));
+        assert!(cook.contains(">> source.name: Serious Eats\n>> source.url: https://www.seriouseats.com/taco-soup\n"));
+        assert!(cook.contains("\nBrown the @ground beef{1%lb} with the @onion{1}(diced) in a large pot.\n"));
+        assert!(cook.contains("Add the @beef broth{2%cups} and @black beans{1%can} and simmer"));
+        assert!(cook.contains("Season with @salt{}."));
+        assert!(cook.ends_with("\n\n> Even better the next day.\n"));
+    }
+
+    #[test]
+    fn gathers_what_steps_dont_mention() {
+        let mut soup = taco_soup();
+        soup.instructions = vec![String::from("Simmer everything for 20 minutes.")];
+        let cook = recipe(&soup);
+        assert!(cook.contains("\nGather @ground beef{1%lb}, @onion{1}(diced), @beef broth{2%cups}, @black beans{1%can}, @Salt{}.\n\nSimmer everything"));
+    }
+
+    #[test]
+    fn finds_whole_words() {
+        assert_eq!(find_word("Add the onions.", "onion"), Some((8, 14)));
+        assert_eq!(find_word("Add the scallions, then the onion.", "onion"), Some((28, 33)));
+        assert_eq!(find_word("Add the oniony bits.", "onion"), None);
+    }
+
+    #[test]
+    fn names_files() {
+        assert_eq!(file_stem("Taco Soup"), "Taco Soup");
+        assert_eq!(file_stem("Mac & Cheese: 1/2 Batch"), "Mac & Cheese 12 Batch");
+        assert_eq!(file_stem("../.."), "Recipe");
+    }
+
+    #[test]
+    fn round_trips() {
+        let soup = taco_soup();
+        let parsed = read(Format::Cooklang, recipe(&soup).as_bytes(), None).unwrap();
+        assert_eq!(parsed.len(), 1);
+        let imported = parsed[0].as_ref().unwrap().to_recipe("import:dmbluesmith:1", "dmbluesmith");
+        assert_eq!(imported.name, soup.name);
+        assert_eq!(imported.summary, soup.summary);
+        assert_eq!(imported.instructions, soup.instructions);
+        assert_eq!(imported.notes, soup.notes);
+        assert_eq!(imported.recipe_yield, soup.recipe_yield);
+        assert_eq!(imported.total_time.as_deref(), Some("35 mins"));
+        assert_eq!(imported.author, soup.author);
+        assert_eq!(imported.credit, soup.credit);
+        assert_eq!(imported.source.as_deref(), Some("https://www.seriouseats.com/taco-soup"));
+        let items = |r: &Recipe| r.ingredients.iter().map(|i| (i.quantity.clone(), i.unit, i.item.to_lowercase(), i.preparation.clone())).collect::<Vec<_>>();
+        assert_eq!(items(&imported), items(&soup));
+    }
+}
@@ -0,0 +1,131 @@
+// Splits "1hr 20 mins" into ["1", "hr", "20", "mins"]
+fn tokens(text: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = Vec::new();
+    let mut last_numeric = None;
+    for c in text.chars() {
+        if c.is_whitespace() || c == ',' {
+            last_numeric = None;
+            continue;
+        }
+        let numeric = c.is_ascii_digit() || c == '.';
+        match tokens.last_mut() {
+            Some(token) if last_numeric == Some(numeric) => token.push(c),
+            _ => tokens.push(c.to_string()),
+        }
+        last_numeric = Some(numeric);
+    }
+    tokens
+}
+
+// Total minutes in an ISO 8601 duration like "PT1H20M" or "P0DT35M"
+fn iso_minutes(time: &str) -> Option<f64> {
+    let rest = time.trim().to_uppercase();
+    let rest = rest.strip_prefix('P')?;
+    let mut minutes = 0.0;
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in rest.chars() {
+        match c {
+            'T' => in_time = true,
+            '0'..='9' | '.' => number.push(c),
+            _ => {
+                let value: f64 = number.parse().ok()?;
+                number.clear();
+                minutes += value
+                    * match (c, in_time) {
+                        ('D', false) => 24.0 * 60.0,
+                        ('H', true) => 60.0,
+                        ('M', true) => 1.0,
+                        ('S', true) => 1.0 / 60.0,
+                        _ => return None,
+                    };
+            }
+        }
+    }
+    if number.is_empty() {
+        Some(minutes)
+    } else {
+        None
+    }
+}
+
+// Total minutes in how people write times, "1 hr 20 mins" or "35 minutes"
+fn word_minutes(time: &str) -> Option<f64> {
+    let tokens = tokens(&time.to_lowercase());
+    let mut minutes = 0.0;
+    let mut found = false;
+    let mut i = 0;
+    while i < tokens.len() {
+        if tokens[i] == "and" {
+            i += 1;
+            continue;
+        }
+        let value: f64 = tokens[i].parse().ok()?;
+        let factor = match tokens.get(i + 1).map(String::as_str) {
+            Some("h" | "hr" | "hrs" | "hour" | "hours") => 60.0,
+            Some("m" | "min" | "mins" | "minute" | "minutes") => 1.0,
+            _ => return None,
+        };
+        minutes += value * factor;
+        found = true;
+        i += 2;
+    }
+    if found {
+        Some(minutes)
+    } else {
+        None
+    }
+}
+
+/**
+ * The ISO 8601 duration schema.org wants, from however the recipe has its
+ * time: "PT35M" stays as it is and "1 hr 20 mins" becomes "PT1H20M". None
+ * if it doesn't read as a time, like "overnight".
+ */
+pub fn to_iso(time: &str) -> Option<String> {
+    let minutes = iso_minutes(time).or_else(|| word_minutes(time))?.round() as u64;
+    let (hours, minutes) = (minutes / 60, minutes % 60);
+    Some(match (hours, minutes) {
+        (0, m) => format!("PT{}M", m),
+        (h, 0) => format!("PT{}H", h),
+        (h, m) => format!("PT{}H{}M", h, m),
+    })
+}
+
+// How a time reads to people: "PT1H20M" is "1 hr 20 mins", anything else stays as it is
+pub fn to_words(time: &str) -> String {
+    let minutes = match iso_minutes(time) {
+        Some(m) => m.round() as u64,
+        None => return time.trim().to_string(),
+    };
+    let plural = |n: u64, one: &str| if n == 1 { format!("{} {}", n, one) } else { format!("{} {}s", n, one) };
+    match (minutes / 60, minutes % 60) {
+        (0, m) => plural(m, "min"),
+        (h, 0) => plural(h, "hr"),
+        (h, m) => format!("{} {}", plural(h, "hr"), plural(m, "min")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_to_iso() {
+        assert_eq!(to_iso("PT35M").as_deref(), Some("PT35M"));
+        assert_eq!(to_iso("P0DT1H20M").as_deref(), Some("PT1H20M"));
+        assert_eq!(to_iso("1 hr 20 mins").as_deref(), Some("PT1H20M"));
+        assert_eq!(to_iso("1.5 hours").as_deref(), Some("PT1H30M"));
+        assert_eq!(to_iso("2hrs").as_deref(), Some("PT2H"));
+        assert_eq!(to_iso("overnight"), None);
+        assert_eq!(to_iso("about 20 minutes"), None);
+    }
+
+    #[test]
+    fn converts_to_words() {
+        assert_eq!(to_words("PT35M"), "35 mins");
+        assert_eq!(to_words("PT1H1M"), "1 hr 1 min");
+        assert_eq!(to_words("PT2H"), "2 hrs");
+        assert_eq!(to_words(" overnight "), "overnight");
+    }
+}
@@ -0,0 +1,152 @@
+use recipe_core::Recipe;
+
+use crate::{cooklang, jsonld, markdown, recipeml};
+
+/**
+ * The formats recipes can be exported in, other than our own JSON.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Cooklang,
+    Markdown,
+    // schema.org Recipe, the JSON-LD recipe sites publish
+    JsonLd,
+    RecipeMl,
+}
+
+impl ExportFormat {
+    pub fn parse(name: &str) -> Option<ExportFormat> {
+        let format = match name.trim().to_lowercase().as_str() {
+            "cooklang" | "cook" => ExportFormat::Cooklang,
+            "markdown" | "md" => ExportFormat::Markdown,
+            "jsonld" | "json-ld" | "schema.org" => ExportFormat::JsonLd,
+            "recipeml" | "xml" => ExportFormat::RecipeMl,
+            _ => return None,
+        };
+        Some(format)
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Cooklang => "text/plain; charset=utf-8",
+            ExportFormat::Markdown => "text/markdown; charset=utf-8",
+            ExportFormat::JsonLd => "application/ld+json",
+            ExportFormat::RecipeMl => "application/xml",
+        }
+    }
+}
+
+/**
+ * Reads `?format=`. No format, or `json`, is our own JSON and comes back
+ * as None, the same way `get_units` treats no units.
+ */
+pub fn get_format(format: Option<&str>) -> Result<Option<ExportFormat>, String> {
+    match format {
+        None => Ok(None),
+        Some(f) if f.trim().eq_ignore_ascii_case("json") => Ok(None),
+        Some(f) => ExportFormat::parse(f)
+            .map(Some)
+            .ok_or_else(|| format!("Invalid format: {}, use json, cooklang, markdown, jsonld or recipeml", f)),
+    }
+}
+
+// What an export comes out as
+#[derive(Debug, Clone, PartialEq)]
+pub enum Document {
+    Text(String),
+    // A file for each recipe, for formats that only have one to a file
+    Zip(Vec<u8>),
+}
+
+impl Document {
+    pub fn content_type(&self, format: ExportFormat) -> &'static str {
+        match self {
+            Document::Text(_) => format.content_type(),
+            Document::Zip(_) => "application/zip",
+        }
+    }
+}
+
+/**
+ * Renders recipes as one document. RecipeML holds any number of recipes
+ * and JSON-LD puts several in a `@graph`; Markdown separates them with a
+ * rule. Cooklang only has one recipe to a file, and several run together
+ * would be read back as one, so more than one comes as a zip of `.cook`
+ * files.
+ */
+pub fn export(format: ExportFormat, recipes: &[Recipe]) -> Result<Document, String> {
+    let document = match format {
+        ExportFormat::Cooklang if recipes.len() > 1 => return Ok(Document::Zip(cooklang::collection(recipes)?)),
+        ExportFormat::Cooklang => recipes.iter().map(cooklang::recipe).collect(),
+        ExportFormat::Markdown => recipes.iter().map(markdown::recipe).collect::<Vec<String>>().join("\n---\n\n"),
+        ExportFormat::JsonLd => jsonld::document(recipes),
+        ExportFormat::RecipeMl => recipeml::document(recipes),
+    };
+    Ok(Document::Text(document))
+}
+
+// The page a recipe came from, if it came from one
+pub fn page_url(recipe: &Recipe) -> Option<&str> {
+    let is_web = |u: &&str| u.starts_with("https://") || u.starts_with("http://");
+    recipe.source.as_deref().filter(is_web).or(Some(recipe.uuid.as_str()).filter(is_web))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use recipe_core::test_support::taco_soup;
+    use recipe_import::{read, Format};
+
+    #[test]
+    fn reads_formats() {
+        assert_eq!(get_format(None), Ok(None));
+        assert_eq!(get_format(Some("JSON")), Ok(None));
+        assert_eq!(get_format(Some("md")), Ok(Some(ExportFormat::Markdown)));
+        assert_eq!(get_format(Some("json-ld")), Ok(Some(ExportFormat::JsonLd)));
+        assert!(get_format(Some("pdf")).is_err());
+    }
+
+    #[test]
+    fn finds_page() {
+        assert_eq!(page_url(&taco_soup()), Some("https://www.seriouseats.com/taco-soup"));
+        let mut imported = Recipe::new("import:dmbluesmith:1", "Taco Soup");
+        assert_eq!(page_url(&imported), None);
+        imported.source = Some(String::from("https://example.com/soup"));
+        assert_eq!(page_url(&imported), Some("https://example.com/soup"));
+    }
+
+    fn text(document: Document) -> String {
+        match document {
+            Document::Text(t) => t,
+            Document::Zip(_) => panic!("expected text"),
+        }
+    }
+
+    #[test]
+    fn exports_collections() {
+        let mut chili = taco_soup();
+        chili.name = String::from("Chili");
+        let mut copy = taco_soup();
+        copy.uuid = String::from("import:dmbluesmith:1");
+        let recipes = [taco_soup(), chili, copy];
+        assert_eq!(text(export(ExportFormat::Markdown, &recipes).unwrap()).matches("\n---\n").count(), 2);
+
+        // Every recipe in a collection reads back as itself, not run into the one before
+        let names = |format: Format, bytes: &[u8]| -> Vec<String> { read(format, bytes, None).unwrap().into_iter().map(|p| p.unwrap().name).collect() };
+        let xml = text(export(ExportFormat::RecipeMl, &recipes).unwrap());
+        assert_eq!(names(Format::RecipeMl, xml.as_bytes()), ["Taco Soup", "Chili", "Taco Soup"]);
+
+        let cook = export(ExportFormat::Cooklang, &recipes).unwrap();
+        assert_eq!(cook.content_type(ExportFormat::Cooklang), "application/zip");
+        let zip = match cook {
+            Document::Zip(z) => z,
+            Document::Text(_) => panic!("several Cooklang recipes should be a zip"),
+        };
+        assert_eq!(names(Format::Cooklang, &zip), ["Taco Soup", "Chili", "Taco Soup"]);
+
+        // One is just the file
+        let cook = export(ExportFormat::Cooklang, &recipes[1..2]).unwrap();
+        assert_eq!(cook.content_type(ExportFormat::Cooklang), "text/plain; charset=utf-8");
+        assert_eq!(names(Format::Cooklang, text(cook).as_bytes()), ["Chili"]);
+    }
+}
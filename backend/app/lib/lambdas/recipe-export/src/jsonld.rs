@@ -0,0 +1,95 @@
+use recipe_core::Recipe;
+use serde_json::{json, Map, Value};
+
+use crate::duration::to_iso;
+use crate::format::page_url;
+
+// Per serving, since that's what schema.org's NutritionInformation describes
+fn nutrition(recipe: &Recipe) -> Option<Value> {
+    let n = recipe.nutrition().per_serving?;
+    let grams = |g: f64| format!("{} g", g.round());
+    Some(json!({
+        "@type": "NutritionInformation",
+        "calories": format!("{} kcal", n.calories.round()),
+        "proteinContent": grams(n.protein),
+        "fatContent": grams(n.fat),
+        "carbohydrateContent": grams(n.carbohydrates),
+        "fiberContent": grams(n.fiber),
+        "sugarContent": grams(n.sugar),
+        "sodiumContent": format!("{} mg", n.sodium.round()),
+    }))
+}
+
+/**
+ * A schema.org Recipe, the same JSON-LD recipe sites publish and the
+ * worker reads, so it can be handed to anything that understands them.
+ */
+pub(crate) fn recipe(recipe: &Recipe) -> Value {
+    let mut ld = Map::new();
+    ld.insert(String::from("@type"), json!("Recipe"));
+    ld.insert(String::from("identifier"), json!(recipe.uuid));
+    ld.insert(String::from("name"), json!(recipe.name));
+    let mut optional = |key: &str, value: Option<Value>| {
+        if let Some(value) = value {
+            ld.insert(key.to_string(), value);
+        }
+    };
+    optional("description", Some(json!(recipe.summary)).filter(|_| !recipe.summary.is_empty()));
+    optional("image", Some(json!(recipe.image)).filter(|_| !recipe.image.is_empty()));
+    optional("url", page_url(recipe).map(|u| json!(u)));
+    optional("author", recipe.author.as_ref().map(|a| json!({"@type": "Person", "name": a})));
+    optional("creditText", recipe.credit.as_ref().map(|c| json!(c)));
+    optional("recipeYield", recipe.recipe_yield.as_ref().map(|y| json!(y)));
+    optional("totalTime", recipe.total_time.as_deref().and_then(to_iso).map(|t| json!(t)));
+    optional("nutrition", nutrition(recipe));
+    ld.insert(String::from("recipeIngredient"), json!(recipe.raw_ingredients()));
+    let steps: Vec<Value> = recipe.instructions.iter().map(|s| json!({"@type": "HowToStep", "text": s})).collect();
+    ld.insert(String::from("recipeInstructions"), json!(steps));
+    Value::Object(ld)
+}
+
+// One recipe on its own, several in a @graph
+pub(crate) fn document(recipes: &[Recipe]) -> String {
+    let doc = match recipes {
+        [only] => {
+            let mut ld = recipe(only);
+            ld["@context"] = json!("https://schema.org");
+            ld
+        }
+        _ => json!({
+            "@context": "https://schema.org",
+            "@graph": recipes.iter().map(recipe).collect::<Vec<Value>>(),
+        }),
+    };
+    serde_json::to_string_pretty(&doc).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use recipe_core::test_support::taco_soup;
+
+    #[test]
+    fn writes_schema_org() {
+        let ld: Value = serde_json::from_str(&document(&[taco_soup()])).unwrap();
+        assert_eq!(ld["@context"], "https://schema.org");
+        assert_eq!(ld["@type"], "Recipe");
+        assert_eq!(ld["name"], "Taco Soup");
+        assert_eq!(ld["url"], "https://www.seriouseats.com/taco-soup");
+        assert_eq!(ld["author"]["name"], "Grandma");
+        assert_eq!(ld["totalTime"], "PT35M");
+        assert_eq!(ld["recipeIngredient"][1], "1 onion, diced");
+        assert_eq!(ld["recipeInstructions"][2]["text"], "Season with salt.");
+        assert_eq!(ld["nutrition"]["@type"], "NutritionInformation");
+        assert!(ld["nutrition"]["calories"].as_str().unwrap().ends_with(" kcal"));
+        assert!(ld.get("image").is_none());
+    }
+
+    #[test]
+    fn writes_graph() {
+        let ld: Value = serde_json::from_str(&document(&[taco_soup(), Recipe::new("abc", "Chili")])).unwrap();
+        assert_eq!(ld["@graph"].as_array().unwrap().len(), 2);
+        assert_eq!(ld["@graph"][1]["name"], "Chili");
+        assert!(ld["@graph"][1].get("nutrition").is_none());
+    }
+}
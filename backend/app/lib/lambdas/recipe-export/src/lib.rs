@@ -0,0 +1,9 @@
+mod cooklang;
+mod duration;
+mod format;
+mod jsonld;
+mod markdown;
+mod recipeml;
+
+pub use duration::{to_iso, to_words};
+pub use format::{export, get_format, page_url, Document, ExportFormat};
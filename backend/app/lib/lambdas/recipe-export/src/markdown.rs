@@ -0,0 +1,82 @@
+use recipe_core::Recipe;
+
+use crate::duration::to_words;
+use crate::format::page_url;
+
+// Who it's from, linked to the page it came from when there is one
+fn source(recipe: &Recipe) -> Option<String> {
+    match (recipe.credit.as_deref(), page_url(recipe)) {
+        (Some(credit), Some(url)) if credit != url => Some(format!("[{}]({})", credit, url)),
+        (_, Some(url)) => Some(format!("<{}>", url)),
+        (Some(credit), None) => Some(credit.to_string()),
+        (None, None) => None,
+    }
+}
+
+/**
+ * A recipe as a Markdown page: its name as the heading, a list of the
+ * details, then ingredients, numbered steps and notes under their own
+ * headings, ready to paste into a wiki.
+ */
+pub(crate) fn recipe(recipe: &Recipe) -> String {
+    let mut out = format!("# {}\n", recipe.name);
+    if !recipe.summary.is_empty() {
+        out += &format!("\n{}\n", recipe.summary);
+    }
+    if !recipe.image.is_empty() {
+        out += &format!("\n![{}]({})\n", recipe.name, recipe.image);
+    }
+
+    let details: Vec<String> = [
+        ("Serves", recipe.recipe_yield.clone()),
+        ("Time", recipe.total_time.as_deref().map(to_words)),
+        ("By", recipe.author.clone()),
+        ("From", source(recipe)),
+    ]
+    .into_iter()
+    .filter_map(|(label, value)| value.map(|v| format!("- **{}:** {}\n", label, v)))
+    .collect();
+    if !details.is_empty() {
+        out += &format!("\n{}", details.concat());
+    }
+
+    if !recipe.ingredients.is_empty() {
+        out += "\n## Ingredients\n\n";
+        for ingredient in &recipe.ingredients {
+            out += &format!("- {}\n", ingredient.raw);
+        }
+    }
+    if !recipe.instructions.is_empty() {
+        out += "\n## Instructions\n\n";
+        for (i, step) in recipe.instructions.iter().enumerate() {
+            out += &format!("{}. {}\n", i + 1, step);
+        }
+    }
+    if !recipe.notes.is_empty() {
+        out += &format!("\n## Notes\n\n{}\n", recipe.notes);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use recipe_core::test_support::taco_soup;
+
+    #[test]
+    fn writes_markdown() {
+        let markdown = recipe(&taco_soup());
+        assert!(markdown.starts_with("# Taco Soup\n\nA quick weeknight soup.\n"));
+        assert!(markdown.contains("- **Serves:** 4 servings\n- **Time:** 35 mins\n- **By:** Grandma\n- **From:** [Serious Eats](https://www.seriouseats.com/taco-soup)\n"));
+        assert!(markdown.contains("## Ingredients\n\n- 1 lb ground beef\n- 1 onion, diced\n"));
+        assert!(markdown.contains("## Instructions\n\n1. Brown the ground beef"));
+        assert!(markdown.contains("\n3. Season with salt.\n"));
+        assert!(markdown.ends_with("## Notes\n\nEven better the next day.\n"));
+    }
+
+    #[test]
+    fn leaves_out_what_is_missing() {
+        let markdown = recipe(&Recipe::new("abc", "Chili"));
+        assert_eq!(markdown, "# Chili\n");
+    }
+}
@@ -0,0 +1,117 @@
+use recipe_core::Recipe;
+use recipe_ingredients::{format_quantity, Ingredient};
+
+use crate::duration::to_words;
+use crate::format::page_url;
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn element(name: &str, text: &str) -> String {
+    format!("<{}>{}</{}>", name, escape(text), name)
+}
+
+fn ingredient(ingredient: &Ingredient) -> String {
+    let mut out = String::from("<ing>");
+    if ingredient.quantity.is_some() || ingredient.unit.is_some() {
+        out += "<amt>";
+        if let Some(quantity) = &ingredient.quantity {
+            out += &element("qty", &format_quantity(quantity, ingredient.unit));
+        }
+        if let Some(unit) = ingredient.unit {
+            let plural = ingredient.quantity.as_ref().is_some_and(|q| q.max.is_some() || q.value > 1.0);
+            out += &element("unit", unit.name(plural));
+        }
+        out += "</amt>";
+    }
+    // A line we couldn't make out is all item
+    let item = if ingredient.item.is_empty() { &ingredient.raw } else { &ingredient.item };
+    out += &element("item", item);
+    let preparation: Vec<&str> = ingredient.preparation.iter().chain(&ingredient.notes).map(String::as_str).collect();
+    if !preparation.is_empty() {
+        out += &element("prep", &preparation.join(", "));
+    }
+    out + "</ing>"
+}
+
+// RecipeML's <source> is text, so a recipe from a page gives the page and anything else its credit
+fn recipe(recipe: &Recipe) -> String {
+    let mut head = vec![element("title", &recipe.name)];
+    head.extend(recipe.recipe_yield.as_deref().map(|y| element("yield", y)));
+    head.extend(page_url(recipe).or(recipe.credit.as_deref()).map(|s| element("source", s)));
+    head.extend(recipe.total_time.as_deref().map(|t| format!("<preptime type=\"total\">{}</preptime>", element("time", &to_words(t)))));
+
+    let mut out = String::from("  <recipe>\n    <head>\n");
+    for line in head {
+        out += &format!("      {}\n", line);
+    }
+    out += "    </head>\n";
+    if !recipe.summary.is_empty() {
+        out += &format!("    {}\n", element("description", &recipe.summary));
+    }
+    if !recipe.ingredients.is_empty() {
+        out += "    <ingredients>\n";
+        for i in &recipe.ingredients {
+            out += &format!("      {}\n", ingredient(i));
+        }
+        out += "    </ingredients>\n";
+    }
+    if !recipe.instructions.is_empty() {
+        out += "    <directions>\n";
+        for step in &recipe.instructions {
+            out += &format!("      {}\n", element("step", step));
+        }
+        out += "    </directions>\n";
+    }
+    if !recipe.notes.is_empty() {
+        out += &format!("    {}\n", element("note", &recipe.notes));
+    }
+    out + "  </recipe>\n"
+}
+
+pub(crate) fn document(recipes: &[Recipe]) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<recipeml version=\"0.5\">\n");
+    for r in recipes {
+        out += &recipe(r);
+    }
+    out + "</recipeml>\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use recipe_core::test_support::taco_soup;
+    use recipe_import::{read, Format};
+
+    #[test]
+    fn writes_recipeml() {
+        let mut soup = taco_soup();
+        soup.name = String::from("Mac & Cheese <Deluxe>");
+        let xml = document(&[soup]);
+        assert!(xml.contains("<title>Mac &amp; Cheese &lt;Deluxe&gt;</title>"));
+        assert!(xml.contains("<source>https://www.seriouseats.com/taco-soup</source>"));
+        assert!(xml.contains("<preptime type=\"total\"><time>35 mins</time></preptime>"));
+        assert!(xml.contains("<ing><amt><qty>1</qty></amt><item>onion</item><prep>diced</prep></ing>"));
+        assert!(xml.contains("<ing><item>Salt</item></ing>"));
+    }
+
+    #[test]
+    fn round_trips() {
+        let soup = taco_soup();
+        let chili = Recipe::new("abc", "Chili");
+        let parsed = read(Format::RecipeMl, document(&[soup.clone(), chili]).as_bytes(), None).unwrap();
+        assert_eq!(parsed.len(), 2);
+        let imported = parsed[0].as_ref().unwrap().to_recipe("import:dmbluesmith:1", "dmbluesmith");
+        assert_eq!(imported.name, soup.name);
+        assert_eq!(imported.summary, soup.summary);
+        assert_eq!(imported.ingredients, soup.ingredients);
+        assert_eq!(imported.instructions, soup.instructions);
+        assert_eq!(imported.notes, soup.notes);
+        assert_eq!(imported.recipe_yield, soup.recipe_yield);
+        assert_eq!(imported.total_time.as_deref(), Some("35 mins"));
+        assert_eq!(imported.source.as_deref(), Some("https://www.seriouseats.com/taco-soup"));
+        // Nothing in it to import
+        assert!(parsed[1].is_err());
+    }
+}
@@ -0,0 +1,138 @@
+use std::io::{Cursor, Read};
+
+use flate2::read::GzDecoder;
+
+use crate::format::ImportError;
+
+// No recipe is this big, so a file that unpacks past it is broken or hostile
+const MAX_FILE_BYTES: u64 = 20 * 1024 * 1024;
+// The same for a whole archive, so lots of small files can't add up to it instead
+const MAX_ARCHIVE_BYTES: u64 = 100 * 1024 * 1024;
+// Far more recipes than anyone has
+const MAX_ENTRIES: usize = 10_000;
+
+pub(crate) fn is_zip(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"PK\x03\x04")
+}
+
+pub(crate) fn is_gzip(bytes: &[u8]) -> bool {
+    bytes.starts_with(&[0x1f, 0x8b])
+}
+
+// A file from a zip, or why it couldn't be unpacked
+pub(crate) type Entry = (String, Result<Vec<u8>, String>);
+
+// Hidden files and the folders macOS adds to zips it makes aren't recipes
+fn is_recipe_file(name: &str) -> bool {
+    let file_name = name.rsplit('/').next().unwrap_or(name);
+    !name.starts_with("__MACOSX/") && !file_name.is_empty() && !file_name.starts_with('.')
+}
+
+/**
+ * The name and contents of every file in a zip archive, in order. A file
+ * that can't be unpacked is an error in its place, but an archive with too
+ * many files, or that unpacks to too much altogether, isn't read at all.
+ */
+pub(crate) fn entries(bytes: &[u8]) -> Result<Vec<Entry>, ImportError> {
+    entries_within(bytes, MAX_ARCHIVE_BYTES, MAX_ENTRIES)
+}
+
+fn entries_within(bytes: &[u8], max_bytes: u64, max_entries: usize) -> Result<Vec<Entry>, ImportError> {
+    let mut zip = zip::ZipArchive::new(Cursor::new(bytes)).map_err(|e| ImportError::Unreadable(format!("not a zip archive: {}", e)))?;
+    if zip.len() > max_entries {
+        return Err(ImportError::Unreadable(format!("has more than {} files", max_entries)));
+    }
+    let mut total: u64 = 0;
+    let mut entries = Vec::new();
+    for i in 0..zip.len() {
+        let file = match zip.by_index(i) {
+            Ok(f) => f,
+            Err(e) => {
+                entries.push((format!("file {}", i + 1), Err(e.to_string())));
+                continue;
+            }
+        };
+        let name = file.name().to_string();
+        if file.is_dir() || !is_recipe_file(&name) {
+            continue;
+        }
+        let contents = read_limited(file);
+        if let Ok(c) = &contents {
+            total += c.len() as u64;
+            if total > max_bytes {
+                return Err(ImportError::Unreadable(format!("unpacks to more than {} MB altogether", max_bytes / 1024 / 1024)));
+            }
+        }
+        entries.push((name, contents));
+    }
+    Ok(entries)
+}
+
+pub(crate) fn gunzip(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    read_limited(GzDecoder::new(bytes))
+}
+
+fn read_limited(reader: impl Read) -> Result<Vec<u8>, String> {
+    let mut contents = Vec::new();
+    reader.take(MAX_FILE_BYTES + 1).read_to_end(&mut contents).map_err(|e| e.to_string())?;
+    if contents.len() as u64 > MAX_FILE_BYTES {
+        return Err(format!("unpacks to more than {} MB", MAX_FILE_BYTES / 1024 / 1024));
+    }
+    Ok(contents)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    // Builds an export the way the apps do, for the format tests
+    pub(crate) fn zip_of(files: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, contents) in files {
+            zip.start_file(*name, zip::write::FileOptions::default()).unwrap();
+            zip.write_all(contents).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    pub(crate) fn gzip(contents: &str) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(contents.as_bytes()).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn lists_recipe_files() {
+        let zip = zip_of(&[
+            ("Taco Soup.paprikarecipe", gzip("{}")),
+            ("__MACOSX/._Taco Soup.paprikarecipe", Vec::new()),
+            (".DS_Store", Vec::new()),
+        ]);
+        assert!(is_zip(&zip));
+        let files = entries(&zip).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].0, "Taco Soup.paprikarecipe");
+        assert_eq!(gunzip(files[0].1.as_ref().unwrap()).unwrap(), b"{}");
+        assert!(entries(b"not a zip").is_err());
+    }
+
+    #[test]
+    fn limits_whole_archives() {
+        // Each file is under the limit for one, together they're over the limit for all
+        let megabyte = vec![b' '; 1024 * 1024];
+        let zip = zip_of(&[("1.paprikarecipe", megabyte.clone()), ("2.paprikarecipe", megabyte.clone()), ("3.paprikarecipe", megabyte)]);
+        assert!(zip.len() < 100 * 1024);
+        assert_eq!(entries_within(&zip, 3 * 1024 * 1024, 10).unwrap().len(), 3);
+        match entries_within(&zip, 2 * 1024 * 1024, 10) {
+            Err(ImportError::Unreadable(e)) => assert_eq!(e, "unpacks to more than 2 MB altogether"),
+            other => panic!("expected it to be too big, got {:?}", other.map(|e| e.len())),
+        }
+        match entries_within(&zip, 3 * 1024 * 1024, 2) {
+            Err(ImportError::Unreadable(e)) => assert_eq!(e, "has more than 2 files"),
+            other => panic!("expected too many files, got {:?}", other.map(|e| e.len())),
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use crate::format::Parsed;
+use crate::imported::{is_web_url, source, text, ImportedRecipe};
+
+// What a step's `@ingredient`, `#cookware` or `~timer` comes to
+struct Token {
+    name: String,
+    // "1%lb" is "1 lb"
+    amount: Option<String>,
+    // `@onion{1}(diced)` is diced
+    preparation: Option<String>,
+    // Bytes of the step the token took up
+    length: usize,
+}
+
+fn amount(value: &str) -> Option<String> {
+    let words: Vec<&str> = value.split('%').map(str::trim).filter(|w| !w.is_empty()).collect();
+    if words.is_empty() {
+        None
+    } else {
+        Some(words.join(" "))
+    }
+}
+
+/**
+ * The token at the start of `rest`, just after its sigil. A name of one
+ * word needs nothing after it; a longer one ends at its `{amount}`, which
+ * must come before any other token.
+ */
+fn token(rest: &str) -> Token {
+    let word_end = rest.find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-')).unwrap_or(rest.len());
+    let braced = rest.find('{').filter(|&open| {
+        open == word_end || !rest[..open].contains(['@', '#', '~', '{', '}', '\n', '.', ',', ';'])
+    });
+    let (name, mut length, amount) = match braced.and_then(|open| rest[open..].find('}').map(|close| (open, open + close))) {
+        Some((open, close)) => (rest[..open].trim().to_string(), close + 1, amount(&rest[open + 1..close])),
+        None => (rest[..word_end].to_string(), word_end, None),
+    };
+    let mut preparation = None;
+    if rest[length..].starts_with('(') {
+        if let Some(close) = rest[length..].find(')') {
+            preparation = text(Some(&rest[length + 1..length + close]));
+            length += close + 1;
+        }
+    }
+    Token { name, amount, preparation, length }
+}
+
+/**
+ * A step as it reads, with its tokens turned back into words, and the
+ * ingredients it uses as lines like "1 lb ground beef, browned".
+ */
+fn step(line: &str, ingredients: &mut Vec<String>) -> String {
+    let mut out = String::new();
+    let mut rest = line;
+    while let Some(at) = rest.find(['@', '#', '~']) {
+        out.push_str(&rest[..at]);
+        let sigil = rest.as_bytes()[at];
+        let token = token(&rest[at + 1..]);
+        if token.length == 0 {
+            out.push(sigil as char);
+            rest = &rest[at + 1..];
+            continue;
+        }
+        match sigil {
+            b'@' => {
+                let mut ingredient = token.amount.iter().chain([&token.name]).cloned().collect::<Vec<String>>().join(" ");
+                if let Some(preparation) = &token.preparation {
+                    ingredient = format!("{}, {}", ingredient, preparation);
+                }
+                ingredients.push(ingredient);
+                out.push_str(&token.name);
+            }
+            b'#' => out.push_str(&token.name),
+            // Timers read as their duration, "~eggs{3%minutes}" is "3 minutes"
+            _ => out.push_str(token.amount.as_deref().unwrap_or(&token.name)),
+        }
+        rest = &rest[at + 1 + token.length..];
+    }
+    out.push_str(rest);
+    out
+}
+
+// Strips `-- line` and `[- block -]` comments, leaving front matter's `---` be
+fn strip_comments(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(open) = rest.find("[-") {
+        out.push_str(&rest[..open]);
+        rest = match rest[open..].find("-]") {
+            Some(close) => &rest[open + close + 2..],
+            None => "",
+        };
+    }
+    out.push_str(rest);
+    out.lines()
+        .map(|line| match line.find("--") {
+            Some(_) if line.trim() == "---" => line,
+            Some(comment) => line[..comment].trim_end(),
+            None => line,
+        })
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
+
+fn set_metadata(recipe: &mut ImportedRecipe, key: &str, value: &str) {
+    let value = text(Some(value.trim().trim_matches('"')));
+    match key.trim().to_lowercase().as_str() {
+        "title" => recipe.name = value.unwrap_or_default(),
+        "description" | "introduction" => recipe.summary = value.unwrap_or_default(),
+        "servings" | "serves" | "yield" => recipe.recipe_yield = value,
+        "time" | "duration" | "total time" | "time required" => recipe.total_time = value,
+        "author" | "source.author" => recipe.author = value,
+        "source" => {
+            let (url, name) = source(value.as_deref());
+            recipe.source_url = recipe.source_url.take().or(url);
+            recipe.source_name = recipe.source_name.take().or(name);
+        }
+        "source.url" => recipe.source_url = value.filter(|v| is_web_url(v)),
+        "source.name" => recipe.source_name = value,
+        "image" | "images" => recipe.image = value.filter(|v| is_web_url(v)),
+        _ => {}
+    }
+}
+
+/**
+ * A Cooklang recipe: metadata in `>> key: value` lines or a front matter
+ * block, steps as paragraphs that mark up what they use, `= Section` lines
+ * and `> notes`. The ingredient list is what the steps use, in order.
+ */
+pub(crate) fn read(text: &str, title: Option<&str>) -> Parsed {
+    let text = strip_comments(text);
+    let mut recipe = ImportedRecipe { name: title.unwrap_or_default().to_string(), ..ImportedRecipe::default() };
+    let mut notes: Vec<&str> = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut lines = text.lines().peekable();
+
+    // YAML front matter, one level deep is all recipes use
+    if lines.peek().map(|l| l.trim()) == Some("---") {
+        lines.next();
+        for line in lines.by_ref() {
+            if line.trim() == "---" {
+                break;
+            }
+            if let Some((key, value)) = line.split_once(':') {
+                set_metadata(&mut recipe, key, value);
+            }
+        }
+    }
+
+    for line in lines {
+        let trimmed = line.trim();
+        let ends_step = trimmed.is_empty() || trimmed.starts_with('>') || trimmed.starts_with('=');
+        if ends_step && !paragraph.is_empty() {
+            let joined = paragraph.join(" ");
+            recipe.instructions.push(step(&joined, &mut recipe.ingredients));
+            paragraph.clear();
+        }
+        if let Some(metadata) = trimmed.strip_prefix(">>") {
+            if let Some((key, value)) = metadata.split_once(':') {
+                set_metadata(&mut recipe, key, value);
+            }
+        } else if let Some(note) = trimmed.strip_prefix('>') {
+            notes.push(note.trim());
+        } else if trimmed.starts_with('=') {
+            let section = trimmed.trim_matches('=').trim();
+            if !section.is_empty() {
+                recipe.instructions.push(section.to_string());
+            }
+        } else if !trimmed.is_empty() {
+            paragraph.push(trimmed);
+        }
+    }
+    if !paragraph.is_empty() {
+        let joined = paragraph.join(" ");
+        recipe.instructions.push(step(&joined, &mut recipe.ingredients));
+    }
+    recipe.notes = notes.join("\n");
+    Ok(recipe)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TACO_SOUP: &str = include_str!("../fixtures/taco_soup.cook");
+
+    #[test]
+    fn reads_recipe() {
+        let soup = read(TACO_SOUP, Some("taco_soup")).unwrap();
+        assert_eq!(soup.name, "Taco Soup");
+        assert_eq!(soup.recipe_yield.as_deref(), Some("4"));
+        assert_eq!(soup.total_time.as_deref(), Some("35 minutes"));
+        assert_eq!(soup.source_url.as_deref(), Some("https://www.seriouseats.com/taco-soup"));
+        assert_eq!(soup.author.as_deref(), Some("Grandma"));
+        assert_eq!(soup.ingredients, vec!["1 lb ground beef", "1 onion, diced", "2 cups beef broth", "1 can black beans", "salt"]);
+        assert_eq!(
+            soup.instructions,
+            vec![
+                "Brown the ground beef with the onion in a large pot.",
+                "Add the beef broth and black beans and simmer for 20 minutes.",
+                "To serve",
+                "Season with salt.",
+            ]
+        );
+        assert_eq!(soup.notes, "Even better the next day.");
+    }
+
+    #[test]
+    fn reads_front_matter() {
+        let soup = read("---\ntitle: Salted Water\nsource: The Joy of Cooking\n---\nBoil @water{1%l} with @salt{}.", None).unwrap();
+        assert_eq!(soup.name, "Salted Water");
+        assert_eq!(soup.source_name.as_deref(), Some("The Joy of Cooking"));
+        assert_eq!(soup.ingredients, vec!["1 l water", "salt"]);
+        assert_eq!(soup.instructions, vec!["Boil water with salt."]);
+    }
+
+    #[test]
+    fn keeps_stray_sigils() {
+        let mut ingredients = Vec::new();
+        assert_eq!(step("Email me @ home, use # 2 pan", &mut ingredients), "Email me @ home, use # 2 pan");
+        assert!(ingredients.is_empty());
+    }
+}
@@ -0,0 +1,156 @@
+use crate::archive::{entries, is_zip};
+use crate::imported::ImportedRecipe;
+use crate::{cooklang, mealmaster, mela, paprika, recipeml};
+
+/**
+ * The apps we import from, by the format of the files they export.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    // A `.paprikarecipes` zip of gzipped JSON, or one `.paprikarecipe`
+    Paprika,
+    // `.mmf` text, any number of recipes to a file
+    MealMaster,
+    // One `.cook` recipe, or a zip of them
+    Cooklang,
+    // RecipeML XML, any number of recipes to a file
+    RecipeMl,
+    // A `.melarecipes` zip of JSON, or one `.melarecipe`
+    Mela,
+}
+
+impl Format {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Format::Paprika => "paprika",
+            Format::MealMaster => "mealmaster",
+            Format::Cooklang => "cooklang",
+            Format::RecipeMl => "recipeml",
+            Format::Mela => "mela",
+        }
+    }
+
+    // The format a client names, by app or by file extension
+    pub fn parse(name: &str) -> Option<Format> {
+        let format = match name.trim().trim_start_matches('.').to_lowercase().as_str() {
+            "paprika" | "paprikarecipes" | "paprikarecipe" => Format::Paprika,
+            "mealmaster" | "meal-master" | "mmf" | "mm" => Format::MealMaster,
+            "cooklang" | "cook" => Format::Cooklang,
+            "recipeml" | "xml" | "rml" => Format::RecipeMl,
+            "mela" | "melarecipes" | "melarecipe" => Format::Mela,
+            _ => return None,
+        };
+        Some(format)
+    }
+
+    // Guessed from an uploaded file's name, e.g. "Dinners.paprikarecipes"
+    pub fn from_filename(filename: &str) -> Option<Format> {
+        let (_, extension) = filename.rsplit_once('.')?;
+        Format::parse(extension)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportError {
+    // The file as a whole isn't the format, e.g. a broken zip
+    Unreadable(String),
+    // One recipe in it is missing what we need to save it
+    Incomplete(String),
+}
+
+impl std::fmt::Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportError::Unreadable(e) => write!(f, "Can't read file: {}", e),
+            ImportError::Incomplete(e) => write!(f, "Can't import recipe: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+// One recipe from an export, or why it couldn't be used
+pub type Parsed = Result<ImportedRecipe, ImportError>;
+
+/**
+ * Every recipe in an exported file, in order. A file we can't read at all
+ * is an error; a recipe we can't use is an error in its place, so one bad
+ * recipe doesn't cost the rest. Cooklang keeps a recipe's title in its
+ * file name, so that's used when the file doesn't say, for each file in
+ * a zip of them too.
+ */
+pub fn read(format: Format, bytes: &[u8], filename: Option<&str>) -> Result<Vec<Parsed>, ImportError> {
+    let parsed = match format {
+        Format::Paprika => paprika::read(bytes)?,
+        Format::Mela => mela::read(bytes)?,
+        Format::MealMaster => mealmaster::read(&decode(bytes)),
+        Format::Cooklang if is_zip(bytes) => entries(bytes)?
+            .into_iter()
+            .filter(|(name, _)| name.to_lowercase().ends_with(".cook"))
+            .map(|(name, contents)| match contents {
+                Ok(c) => cooklang::read(&decode(&c), Some(stem(&name))),
+                Err(e) => Err(ImportError::Unreadable(format!("{}: {}", name, e))),
+            })
+            .collect(),
+        Format::Cooklang => vec![cooklang::read(&decode(bytes), filename.map(stem))],
+        Format::RecipeMl => recipeml::read(&decode(bytes))?,
+    };
+    if parsed.is_empty() {
+        return Err(ImportError::Unreadable(format!("no {} recipes found", format.as_str())));
+    }
+    Ok(parsed
+        .into_iter()
+        .map(|p| p.and_then(|recipe| match recipe.problem() {
+            Some(problem) => Err(ImportError::Incomplete(problem)),
+            None => Ok(recipe),
+        }))
+        .collect())
+}
+
+// Older exports aren't always UTF-8, and a stray character shouldn't cost the recipe
+fn decode(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).trim_start_matches('\u{feff}').to_string()
+}
+
+// "Taco Soup.cook" is "Taco Soup"
+fn stem(filename: &str) -> &str {
+    let name = filename.rsplit(['/', '\\']).next().unwrap_or(filename);
+    name.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archive::tests::zip_of;
+
+    #[test]
+    fn names_formats() {
+        assert_eq!(Format::parse("Paprika"), Some(Format::Paprika));
+        assert_eq!(Format::parse(".mmf"), Some(Format::MealMaster));
+        assert_eq!(Format::parse("word"), None);
+        assert_eq!(Format::from_filename("Dinners.melarecipes"), Some(Format::Mela));
+        assert_eq!(Format::from_filename("Taco Soup.cook"), Some(Format::Cooklang));
+        assert_eq!(Format::from_filename("recipes"), None);
+        assert_eq!(stem("recipes/Taco Soup.cook"), "Taco Soup");
+    }
+
+    #[test]
+    fn reports_unusable_recipes_in_place() {
+        let parsed = read(Format::Cooklang, b">> title: Taco Soup\n", None).unwrap();
+        assert_eq!(parsed, vec![Err(ImportError::Incomplete(String::from("Taco Soup has no ingredients or instructions")))]);
+        assert_eq!(read(Format::Cooklang, b"Add @salt.", None).unwrap()[0], Err(ImportError::Incomplete(String::from("recipe has no title"))));
+        assert_eq!(read(Format::Cooklang, b"Add @salt.", Some("Salted Water.cook")).unwrap()[0].as_ref().unwrap().name, "Salted Water");
+        assert!(read(Format::MealMaster, b"Not a recipe", None).is_err());
+    }
+
+    #[test]
+    fn reads_zipped_cooklang() {
+        let zip = zip_of(&[
+            ("Taco Soup.cook", b"Add @ground beef{1%lb} and simmer.".to_vec()),
+            ("photos/taco-soup.jpg", b"not a recipe".to_vec()),
+            ("Chili.cook", b">> title: Texas Chili\nAdd @chuck{2%lb} and simmer.".to_vec()),
+        ]);
+        let names: Vec<String> = read(Format::Cooklang, &zip, Some("recipes.zip")).unwrap().into_iter().map(|p| p.unwrap().name).collect();
+        assert_eq!(names, ["Taco Soup", "Texas Chili"]);
+    }
+}
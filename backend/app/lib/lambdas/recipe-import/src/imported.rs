@@ -0,0 +1,159 @@
+use recipe_core::Recipe;
+use recipe_ingredients::parse_all;
+use url::Url;
+
+/**
+ * A recipe as another app exported it, before it's keyed and saved.
+ * Ingredients and instructions are one line each, with section names as
+ * their own line, the way the rest of the pipeline keeps them.
+ */
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportedRecipe {
+    pub name: String,
+    pub ingredients: Vec<String>,
+    pub instructions: Vec<String>,
+    pub notes: String,
+    pub summary: String,
+    // A web address for the photo, photos embedded in the export aren't kept
+    pub image: Option<String>,
+    // The page the app saved it from
+    pub source_url: Option<String>,
+    // Who or what the app credits it to, e.g. a site or a cookbook
+    pub source_name: Option<String>,
+    pub author: Option<String>,
+    pub recipe_yield: Option<String>,
+    pub total_time: Option<String>,
+}
+
+impl ImportedRecipe {
+    /**
+     * The recipe to save under `uuid`, owned by the user importing it so
+     * they can edit it. Credit goes to whoever the app names, or else the
+     * page it came from, and that page is kept as its source.
+     */
+    pub fn to_recipe(&self, uuid: &str, owner: &str) -> Recipe {
+        let mut recipe = Recipe::new(uuid, &self.name);
+        recipe.ingredients = parse_all(&self.ingredients);
+        recipe.instructions = self.instructions.clone();
+        recipe.notes = self.notes.clone();
+        recipe.summary = self.summary.clone();
+        recipe.image = self.image.clone().unwrap_or_default();
+        recipe.credit = self.source_name.clone().or_else(|| self.author.clone()).or_else(|| self.source_url.clone());
+        recipe.source = self.source_url.clone();
+        recipe.owner = Some(owner.to_string());
+        recipe.recipe_yield = self.recipe_yield.clone();
+        recipe.total_time = self.total_time.clone();
+        recipe.author = self.author.clone();
+        recipe.estimate_nutrition();
+        recipe.compute_fingerprint();
+        recipe
+    }
+
+    // What's missing for it to be worth saving, None if it'll do
+    pub(crate) fn problem(&self) -> Option<String> {
+        if self.name.trim().is_empty() {
+            Some(String::from("recipe has no title"))
+        } else if self.ingredients.is_empty() && self.instructions.is_empty() {
+            Some(format!("{} has no ingredients or instructions", self.name))
+        } else {
+            None
+        }
+    }
+}
+
+/**
+ * The key an imported recipe is saved under. It's made from the user and
+ * the recipe's name and ingredients rather than its source page, so an
+ * import never replaces a recipe someone else saved from that page, and
+ * importing the same export twice updates the recipes instead of copying
+ * them.
+ */
+pub fn import_key(username: &str, recipe: &ImportedRecipe) -> String {
+    let mut hash = FNV_OFFSET;
+    for line in std::iter::once(&recipe.name).chain(&recipe.ingredients) {
+        for byte in line.trim().to_lowercase().bytes().chain([b'\n']) {
+            hash = (hash ^ byte as u64).wrapping_mul(FNV_PRIME);
+        }
+    }
+    format!("import:{}:{:016x}", username, hash)
+}
+
+// FNV-1a, which unlike std's hasher is guaranteed to stay the same between releases
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+// The trimmed text, None when there's nothing there
+pub(crate) fn text(value: Option<&str>) -> Option<String> {
+    value.map(str::trim).filter(|v| !v.is_empty()).map(str::to_string)
+}
+
+// Each non-blank line of a field apps keep as one newline separated string
+pub(crate) fn lines(value: Option<&str>) -> Vec<String> {
+    value
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+pub(crate) fn is_web_url(value: &str) -> bool {
+    Url::parse(value).map(|u| u.scheme() == "http" || u.scheme() == "https").unwrap_or(false)
+}
+
+/**
+ * Sorts a "source" field apps use for either a link or a name, like a
+ * cookbook title, into (source_url, source_name).
+ */
+pub(crate) fn source(value: Option<&str>) -> (Option<String>, Option<String>) {
+    match text(value) {
+        Some(v) if is_web_url(&v) => (Some(v), None),
+        other => (None, other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn taco_soup() -> ImportedRecipe {
+        ImportedRecipe {
+            name: String::from("Taco Soup"),
+            ingredients: vec![String::from("1 lb ground beef"), String::from("2 cups beef broth")],
+            instructions: vec![String::from("Simmer.")],
+            source_url: Some(String::from("https://example.com/taco-soup")),
+            recipe_yield: Some(String::from("4 servings")),
+            ..ImportedRecipe::default()
+        }
+    }
+
+    #[test]
+    fn converts_to_recipe() {
+        let recipe = taco_soup().to_recipe("import:dmbluesmith:1", "dmbluesmith");
+        assert_eq!(recipe.ingredients[0].item, "ground beef");
+        assert_eq!(recipe.owner.as_deref(), Some("dmbluesmith"));
+        assert_eq!(recipe.credit.as_deref(), Some("https://example.com/taco-soup"));
+        assert_eq!(recipe.source.as_deref(), Some("https://example.com/taco-soup"));
+        assert!(recipe.nutrition.is_some() && recipe.fingerprint.is_some());
+
+        let named = ImportedRecipe { source_name: Some(String::from("Serious Eats")), ..taco_soup() };
+        assert_eq!(named.to_recipe("1", "dmbluesmith").credit.as_deref(), Some("Serious Eats"));
+    }
+
+    #[test]
+    fn keys_by_user_and_contents() {
+        let key = import_key("dmbluesmith", &taco_soup());
+        assert!(key.starts_with("import:dmbluesmith:"));
+        assert_eq!(key, import_key("dmbluesmith", &ImportedRecipe { notes: String::from("Edited"), ..taco_soup() }));
+        assert_ne!(key, import_key("someone", &taco_soup()));
+        assert_ne!(key, import_key("dmbluesmith", &ImportedRecipe { name: String::from("Chili"), ..taco_soup() }));
+    }
+
+    #[test]
+    fn sorts_sources() {
+        assert_eq!(source(Some(" https://example.com/soup ")), (Some(String::from("https://example.com/soup")), None));
+        assert_eq!(source(Some("The Joy of Cooking")), (None, Some(String::from("The Joy of Cooking"))));
+        assert_eq!(source(Some("  ")), (None, None));
+    }
+}
@@ -0,0 +1,11 @@
+mod archive;
+mod cooklang;
+mod format;
+mod imported;
+mod mealmaster;
+mod mela;
+mod paprika;
+mod recipeml;
+
+pub use format::{read, Format, ImportError, Parsed};
+pub use imported::{import_key, ImportedRecipe};
@@ -0,0 +1,242 @@
+use crate::format::Parsed;
+use crate::imported::{text, ImportedRecipe};
+
+// Meal-Master's two letter units, spelled out so the ingredient parser knows them
+const UNITS: [(&str, &str); 36] = [
+    ("x", ""), ("ea", ""), ("bn", "bunch"), ("c", "cup"), ("cg", "cg"), ("cl", "cl"), ("cn", "can"),
+    ("ct", "carton"), ("dg", "dg"), ("dl", "dl"), ("dr", "drop"), ("ds", "dash"), ("fl", "fl oz"),
+    ("g", "g"), ("ga", "gallon"), ("kg", "kg"), ("l", "l"), ("lb", "lb"), ("lg", "large"),
+    ("md", "medium"), ("mg", "mg"), ("ml", "ml"), ("oz", "oz"), ("pk", "package"), ("pn", "pinch"),
+    ("pt", "pint"), ("qt", "quart"), ("sl", "slice"), ("sm", "small"), ("t", "tsp"), ("T", "tbsp"),
+    ("tb", "tbsp"), ("tg", "tsp"), ("ts", "tsp"), ("cb", "cubic cm"), ("pc", "piece"),
+];
+
+// Columns of an ingredient: amount, unit and item, and where a second column starts
+const UNIT_COLUMN: usize = 8;
+const ITEM_COLUMN: usize = 11;
+const SECOND_COLUMN: usize = 41;
+
+fn unit(abbreviation: &str) -> Option<&'static str> {
+    UNITS.iter().find(|(a, _)| *a == abbreviation).map(|(_, unit)| *unit)
+}
+
+fn is_marker(line: &str) -> bool {
+    let line = line.trim();
+    line.starts_with("MMMMM") || line.starts_with("-----")
+}
+
+// "MMMMM----- Recipe via Meal-Master (tm) v8.05", or with dashes for the Ms
+fn is_start(line: &str) -> bool {
+    is_marker(line) && line.to_lowercase().contains("meal-master")
+}
+
+fn is_end(line: &str) -> bool {
+    matches!(line.trim(), "MMMMM" | "-----")
+}
+
+// "MMMMM---------------DRESSING----------------" names a section
+fn section(line: &str) -> Option<String> {
+    if !is_marker(line) || is_start(line) {
+        return None;
+    }
+    let name = line.trim().trim_start_matches('M').trim_matches('-').trim();
+    if name.is_empty() {
+        return None;
+    }
+    let words: Vec<String> = name
+        .split_whitespace()
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect(),
+                None => String::new(),
+            }
+        })
+        .collect();
+    Some(words.join(" "))
+}
+
+// "      Title: Taco Soup" and the like, above the ingredients
+fn header(line: &str) -> Option<(String, String)> {
+    let (key, value) = line.split_once(':')?;
+    let key = key.trim().to_lowercase();
+    if ["title", "categories", "yield", "servings"].contains(&key.as_str()) {
+        Some((key, value.trim().to_string()))
+    } else {
+        None
+    }
+}
+
+enum Column {
+    Ingredient(String),
+    // An item too long for its column carries on under it, starting with "-"
+    Continuation(String),
+}
+
+fn column(chars: &[char]) -> Option<Column> {
+    if chars.len() <= ITEM_COLUMN {
+        return None;
+    }
+    let amount: String = chars[..UNIT_COLUMN - 1].iter().collect();
+    if !amount.chars().all(|c| c.is_ascii_digit() || " /.-".contains(c)) || chars[UNIT_COLUMN - 1] != ' ' || chars[ITEM_COLUMN - 1] != ' ' {
+        return None;
+    }
+    let abbreviation: String = chars[UNIT_COLUMN..ITEM_COLUMN - 1].iter().collect();
+    let abbreviation = abbreviation.trim();
+    let unit = if abbreviation.is_empty() { "" } else { unit(abbreviation)? };
+    let item: String = chars[ITEM_COLUMN..].iter().collect();
+    let item = item.trim();
+    if item.is_empty() {
+        return None;
+    }
+    let amount = amount.trim();
+    if amount.is_empty() && unit.is_empty() {
+        if let Some(rest) = item.strip_prefix('-') {
+            return Some(Column::Continuation(rest.trim().to_string()));
+        }
+    }
+    let words: Vec<&str> = [amount, unit, item].into_iter().filter(|w| !w.is_empty()).collect();
+    Some(Column::Ingredient(words.join(" ")))
+}
+
+// One or two ingredients side by side, None if the line isn't in the columns
+fn ingredient_line(line: &str) -> Option<Vec<Column>> {
+    let chars: Vec<char> = line.trim_end().chars().collect();
+    if chars.len() > SECOND_COLUMN {
+        if let (Some(left), Some(right)) = (column(&chars[..SECOND_COLUMN]), column(&chars[SECOND_COLUMN..])) {
+            return Some(vec![left, right]);
+        }
+    }
+    column(&chars).map(|c| vec![c])
+}
+
+fn add_ingredient(ingredients: &mut Vec<String>, column: Column) {
+    match (column, ingredients.last_mut()) {
+        (Column::Continuation(rest), Some(last)) => {
+            last.push(' ');
+            last.push_str(&rest);
+        }
+        (Column::Continuation(rest), None) | (Column::Ingredient(rest), _) => ingredients.push(rest),
+    }
+}
+
+/**
+ * The lines between a recipe's start and end markers: the title and yield,
+ * the ingredients in their columns, then directions wrapped to the page,
+ * which are put back together a paragraph per step.
+ */
+fn recipe(lines: &[&str]) -> Parsed {
+    let mut recipe = ImportedRecipe::default();
+    let mut steps: Vec<String> = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut in_directions = false;
+    for line in lines {
+        if !in_directions {
+            if let Some((key, value)) = header(line) {
+                match key.as_str() {
+                    "title" => recipe.name = value,
+                    "yield" => recipe.recipe_yield = text(Some(&value)),
+                    "servings" => recipe.recipe_yield = text(Some(&value)).map(|v| format!("{} servings", v)),
+                    _ => {}
+                }
+                continue;
+            }
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(name) = section(line) {
+                recipe.ingredients.push(name);
+                continue;
+            }
+            if let Some(columns) = ingredient_line(line) {
+                columns.into_iter().for_each(|c| add_ingredient(&mut recipe.ingredients, c));
+                continue;
+            }
+            in_directions = true;
+        }
+        let section = section(line);
+        if line.trim().is_empty() || section.is_some() {
+            if !paragraph.is_empty() {
+                steps.push(paragraph.join(" "));
+                paragraph.clear();
+            }
+            steps.extend(section);
+        } else {
+            paragraph.push(line.trim());
+        }
+    }
+    if !paragraph.is_empty() {
+        steps.push(paragraph.join(" "));
+    }
+    recipe.instructions = steps;
+    Ok(recipe)
+}
+
+/**
+ * Every recipe in a Meal-Master file. Files are often several exports
+ * pasted together with mail headers and chatter between them, so only
+ * what's between start and end markers is read.
+ */
+pub(crate) fn read(text: &str) -> Vec<Parsed> {
+    let mut recipes = Vec::new();
+    let mut current: Option<Vec<&str>> = None;
+    for line in text.lines() {
+        if is_start(line) {
+            // A start without an end before it still ends the last one
+            if let Some(lines) = current.replace(Vec::new()) {
+                recipes.push(recipe(&lines));
+            }
+        } else if is_end(line) {
+            if let Some(lines) = current.take() {
+                recipes.push(recipe(&lines));
+            }
+        } else if let Some(lines) = current.as_mut() {
+            lines.push(line);
+        }
+    }
+    if let Some(lines) = current {
+        recipes.push(recipe(&lines));
+    }
+    recipes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECIPES: &str = include_str!("../fixtures/recipes.mmf");
+
+    #[test]
+    fn reads_recipes() {
+        let parsed = read(RECIPES);
+        assert_eq!(parsed.len(), 2);
+
+        let soup = parsed[0].as_ref().unwrap();
+        assert_eq!(soup.name, "Taco Soup");
+        assert_eq!(soup.recipe_yield.as_deref(), Some("6 servings"));
+        assert_eq!(
+            soup.ingredients,
+            vec!["1 lb Ground beef", "1 medium Onion; chopped", "2 cup Beef broth", "1 can Black beans; drained and rinsed", "Topping", "1 cup Sour cream", "1/2 cup Cheddar; shredded", "Salt"]
+        );
+        assert_eq!(
+            soup.instructions,
+            vec![
+                "Brown the beef and onion in a large pot over medium heat, then drain off the fat.",
+                "Add the broth and beans and simmer for 20 minutes.",
+                "Serving",
+                "Top each bowl with sour cream and cheese.",
+            ]
+        );
+
+        let bread = parsed[1].as_ref().unwrap();
+        assert_eq!(bread.name, "Skillet Cornbread");
+        assert_eq!(bread.recipe_yield.as_deref(), Some("8 servings"));
+        assert_eq!(bread.ingredients, vec!["1 cup Cornmeal", "1 cup Flour", "1 cup Buttermilk", "1 Egg"]);
+        assert_eq!(bread.instructions, vec!["Mix and bake at 425F for 20 minutes."]);
+    }
+
+    #[test]
+    fn ignores_text_outside_recipes() {
+        assert!(read("Subject: Soup recipes\n\nHere are some soups!").is_empty());
+    }
+}
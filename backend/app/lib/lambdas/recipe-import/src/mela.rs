@@ -0,0 +1,95 @@
+use serde::Deserialize;
+
+use crate::archive::{entries, is_zip};
+use crate::format::{ImportError, Parsed};
+use crate::imported::{lines, source, text, ImportedRecipe};
+
+// The fields we keep from Mela's export, photos come base64 encoded and are left out
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct MelaRecipe {
+    title: Option<String>,
+    text: Option<String>,
+    ingredients: Option<String>,
+    instructions: Option<String>,
+    notes: Option<String>,
+    #[serde(rename = "yield")]
+    recipe_yield: Option<String>,
+    total_time: Option<String>,
+    link: Option<String>,
+}
+
+/**
+ * A `.melarecipes` export is a zip with a JSON `.melarecipe` file for each
+ * recipe, and a single `.melarecipe` is one of those on its own.
+ */
+pub(crate) fn read(bytes: &[u8]) -> Result<Vec<Parsed>, ImportError> {
+    if !is_zip(bytes) {
+        return Ok(vec![Ok(recipe(bytes).map_err(ImportError::Unreadable)?)]);
+    }
+    Ok(entries(bytes)?
+        .into_iter()
+        .map(|(name, contents)| contents.and_then(|c| recipe(&c)).map_err(|e| ImportError::Unreadable(format!("{}: {}", name, e))))
+        .collect())
+}
+
+// Mela marks section names with "#", like a markdown heading
+fn section_lines(value: Option<&str>) -> Vec<String> {
+    lines(value)
+        .into_iter()
+        .map(|line| match line.strip_prefix('#') {
+            Some(section) => section.trim_start_matches('#').trim().to_string(),
+            None => line,
+        })
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+fn recipe(bytes: &[u8]) -> Result<ImportedRecipe, String> {
+    let mela: MelaRecipe = serde_json::from_slice(bytes).map_err(|e| format!("not a Mela recipe: {}", e))?;
+    // The link is a web page for saved recipes, or whatever the user typed, like a cookbook
+    let (source_url, source_name) = source(mela.link.as_deref());
+    Ok(ImportedRecipe {
+        name: text(mela.title.as_deref()).unwrap_or_default(),
+        ingredients: section_lines(mela.ingredients.as_deref()),
+        instructions: section_lines(mela.instructions.as_deref()),
+        notes: text(mela.notes.as_deref()).unwrap_or_default(),
+        summary: text(mela.text.as_deref()).unwrap_or_default(),
+        image: None,
+        source_url,
+        source_name,
+        author: None,
+        recipe_yield: text(mela.recipe_yield.as_deref()),
+        total_time: text(mela.total_time.as_deref()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archive::tests::zip_of;
+
+    const CORNBREAD: &str = include_str!("../fixtures/cornbread.melarecipe");
+
+    #[test]
+    fn reads_export() {
+        let export = zip_of(&[("cornbread.melarecipe", CORNBREAD.as_bytes().to_vec()), ("broken.melarecipe", b"{\"title\": ".to_vec())]);
+        let parsed = read(&export).unwrap();
+
+        let cornbread = parsed[0].as_ref().unwrap();
+        assert_eq!(cornbread.name, "Skillet Cornbread");
+        assert_eq!(cornbread.ingredients, vec!["Dry", "1 cup cornmeal", "1 cup flour", "Wet", "1 cup buttermilk", "1 egg"]);
+        assert_eq!(cornbread.instructions, vec!["Heat the skillet in a 425F oven.", "Mix, pour into the hot skillet and bake for 20 minutes."]);
+        assert_eq!(cornbread.source_url, None);
+        assert_eq!(cornbread.source_name.as_deref(), Some("Grandma's recipe box"));
+        assert_eq!(cornbread.recipe_yield.as_deref(), Some("8 wedges"));
+        assert!(parsed[1].is_err());
+    }
+
+    #[test]
+    fn reads_single_recipe() {
+        let linked = CORNBREAD.replace("Grandma's recipe box", "https://example.com/cornbread");
+        let parsed = read(linked.as_bytes()).unwrap();
+        assert_eq!(parsed[0].as_ref().unwrap().source_url.as_deref(), Some("https://example.com/cornbread"));
+    }
+}
@@ -0,0 +1,92 @@
+use serde::Deserialize;
+
+use crate::archive::{entries, gunzip, is_gzip, is_zip};
+use crate::format::{ImportError, Parsed};
+use crate::imported::{is_web_url, lines, source, text, ImportedRecipe};
+
+// The fields we keep from Paprika's export, which has many more
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct PaprikaRecipe {
+    name: Option<String>,
+    ingredients: Option<String>,
+    directions: Option<String>,
+    notes: Option<String>,
+    description: Option<String>,
+    servings: Option<String>,
+    total_time: Option<String>,
+    source: Option<String>,
+    source_url: Option<String>,
+    image_url: Option<String>,
+}
+
+/**
+ * A `.paprikarecipes` export is a zip with a gzipped JSON file for each
+ * recipe. A single `.paprikarecipe` is one of those files on its own.
+ */
+pub(crate) fn read(bytes: &[u8]) -> Result<Vec<Parsed>, ImportError> {
+    if !is_zip(bytes) {
+        return Ok(vec![Ok(recipe(bytes).map_err(ImportError::Unreadable)?)]);
+    }
+    Ok(entries(bytes)?
+        .into_iter()
+        .map(|(name, contents)| contents.and_then(|c| recipe(&c)).map_err(|e| ImportError::Unreadable(format!("{}: {}", name, e))))
+        .collect())
+}
+
+fn recipe(bytes: &[u8]) -> Result<ImportedRecipe, String> {
+    let json = if is_gzip(bytes) { gunzip(bytes)? } else { bytes.to_vec() };
+    let paprika: PaprikaRecipe = serde_json::from_slice(&json).map_err(|e| format!("not a Paprika recipe: {}", e))?;
+    // Paprika's "source" is usually the site's name, but people paste links in it too
+    let (source_link, source_name) = source(paprika.source.as_deref());
+    Ok(ImportedRecipe {
+        name: text(paprika.name.as_deref()).unwrap_or_default(),
+        ingredients: lines(paprika.ingredients.as_deref()),
+        instructions: lines(paprika.directions.as_deref()),
+        notes: text(paprika.notes.as_deref()).unwrap_or_default(),
+        summary: text(paprika.description.as_deref()).unwrap_or_default(),
+        image: text(paprika.image_url.as_deref()).filter(|u| is_web_url(u)),
+        source_url: text(paprika.source_url.as_deref()).filter(|u| is_web_url(u)).or(source_link),
+        source_name,
+        author: None,
+        recipe_yield: text(paprika.servings.as_deref()),
+        total_time: text(paprika.total_time.as_deref()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archive::tests::{gzip, zip_of};
+
+    const TACO_SOUP: &str = include_str!("../fixtures/taco_soup.paprikarecipe.json");
+
+    #[test]
+    fn reads_export() {
+        let export = zip_of(&[
+            ("Taco Soup.paprikarecipe", gzip(TACO_SOUP)),
+            ("Broken.paprikarecipe", gzip("{\"name\": ")),
+        ]);
+        let parsed = read(&export).unwrap();
+        assert_eq!(parsed.len(), 2);
+
+        let soup = parsed[0].as_ref().unwrap();
+        assert_eq!(soup.name, "Taco Soup");
+        assert_eq!(soup.ingredients, vec!["1 lb ground beef", "1 onion, diced", "2 cups beef broth", "1 can black beans, drained"]);
+        assert_eq!(soup.instructions.len(), 2);
+        assert_eq!(soup.summary, "A quick weeknight soup with everything you love about tacos.");
+        assert_eq!(soup.source_name.as_deref(), Some("Serious Eats"));
+        assert_eq!(soup.source_url.as_deref(), Some("https://www.seriouseats.com/taco-soup"));
+        assert_eq!(soup.image, None);
+        assert_eq!(soup.recipe_yield.as_deref(), Some("4 servings"));
+
+        assert!(parsed[1].as_ref().unwrap_err().to_string().contains("Broken.paprikarecipe"));
+    }
+
+    #[test]
+    fn reads_single_recipe() {
+        let parsed = read(&gzip(TACO_SOUP)).unwrap();
+        assert_eq!(parsed[0].as_ref().unwrap().name, "Taco Soup");
+        assert!(read(b"not paprika").is_err());
+    }
+}
@@ -0,0 +1,139 @@
+use roxmltree::{Document, Node, ParsingOptions};
+
+use crate::format::{ImportError, Parsed};
+use crate::imported::{lines, source, text, ImportedRecipe};
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name(name))
+}
+
+// All the text under a node, as it would read, with the XML's indenting taken out
+fn node_text(node: Node) -> String {
+    let words: Vec<&str> = node.descendants().filter(|n| n.is_text()).flat_map(|n| n.text().unwrap_or_default().split_whitespace()).collect();
+    words.join(" ")
+}
+
+fn child_text(node: Node, name: &str) -> Option<String> {
+    child(node, name).map(node_text).filter(|t| !t.is_empty())
+}
+
+// "1 lb ground beef, browned" from its <amt>, <item> and <prep>
+fn ingredient(ing: Node) -> Option<String> {
+    let item = child_text(ing, "item")?;
+    let mut line: Vec<String> = child_text(ing, "amt").into_iter().collect();
+    line.push(item);
+    let mut line = line.join(" ");
+    if let Some(prep) = child_text(ing, "prep") {
+        line = format!("{}, {}", line, prep);
+    }
+    Some(line)
+}
+
+// <ing> elements, and <ing-div> sections with a <title> and their own
+fn ingredients(node: Node, out: &mut Vec<String>) {
+    for child in node.children().filter(Node::is_element) {
+        match child.tag_name().name() {
+            "ing" => out.extend(ingredient(child)),
+            "ing-div" => {
+                out.extend(child_text(child, "title"));
+                ingredients(child, out);
+            }
+            _ => {}
+        }
+    }
+}
+
+// <step> elements, <dir-div> sections, or plain text a line per step
+fn directions(node: Node, out: &mut Vec<String>) {
+    let mut has_steps = false;
+    for child in node.children().filter(Node::is_element) {
+        match child.tag_name().name() {
+            "step" => {
+                has_steps = true;
+                out.extend(Some(node_text(child)).filter(|t| !t.is_empty()));
+            }
+            "dir-div" => {
+                has_steps = true;
+                out.extend(child_text(child, "title"));
+                directions(child, out);
+            }
+            _ => {}
+        }
+    }
+    if !has_steps {
+        let text: String = node.children().filter(|n| n.is_text()).map(|n| n.text().unwrap_or_default()).collect();
+        out.extend(lines(Some(&text)));
+    }
+}
+
+fn recipe(node: Node) -> Parsed {
+    let mut recipe = ImportedRecipe::default();
+    if let Some(head) = child(node, "head") {
+        recipe.name = child_text(head, "title").unwrap_or_default();
+        recipe.recipe_yield = child_text(head, "yield");
+        let (source_url, source_name) = source(child_text(head, "source").as_deref());
+        recipe.source_url = source_url;
+        recipe.source_name = source_name;
+        // Only the total says how long the whole recipe takes
+        recipe.total_time = head
+            .children()
+            .filter(|n| n.has_tag_name("preptime"))
+            .find(|n| n.attribute("type").is_some_and(|t| t.to_lowercase().contains("total")))
+            .map(node_text)
+            .filter(|t| !t.is_empty());
+    }
+    recipe.summary = child_text(node, "description").unwrap_or_default();
+    if let Some(node) = child(node, "ingredients") {
+        ingredients(node, &mut recipe.ingredients);
+    }
+    if let Some(node) = child(node, "directions") {
+        directions(node, &mut recipe.instructions);
+    }
+    let notes: Vec<String> = node.children().filter(|n| n.has_tag_name("note")).map(node_text).filter(|t| !t.is_empty()).collect();
+    recipe.notes = text(Some(&notes.join("\n"))).unwrap_or_default();
+    Ok(recipe)
+}
+
+/**
+ * Every <recipe> in a RecipeML document. Exports usually declare the
+ * RecipeML DTD, which we allow but don't fetch or check against.
+ */
+pub(crate) fn read(text: &str) -> Result<Vec<Parsed>, ImportError> {
+    let options = ParsingOptions { allow_dtd: true, ..ParsingOptions::default() };
+    let doc = Document::parse_with_options(text, options).map_err(|e| ImportError::Unreadable(format!("not RecipeML: {}", e)))?;
+    Ok(doc.descendants().filter(|n| n.has_tag_name("recipe")).map(recipe).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECIPES: &str = include_str!("../fixtures/recipes.recipeml");
+
+    #[test]
+    fn reads_recipes() {
+        let parsed = read(RECIPES).unwrap();
+        assert_eq!(parsed.len(), 2);
+
+        let soup = parsed[0].as_ref().unwrap();
+        assert_eq!(soup.name, "Taco Soup");
+        assert_eq!(soup.recipe_yield.as_deref(), Some("6 servings"));
+        assert_eq!(soup.total_time.as_deref(), Some("35 minutes"));
+        assert_eq!(soup.source_name.as_deref(), Some("Grandma's Kitchen"));
+        assert_eq!(soup.summary, "A quick weeknight soup.");
+        assert_eq!(soup.ingredients, vec!["1 lb ground beef, browned", "2 cups beef broth", "Topping", "1 cup sour cream"]);
+        assert_eq!(soup.instructions, vec!["Brown the beef.", "Add the broth and simmer for 20 minutes."]);
+        assert_eq!(soup.notes, "Freezes well.");
+
+        let bread = parsed[1].as_ref().unwrap();
+        assert_eq!(bread.name, "Skillet Cornbread");
+        assert_eq!(bread.ingredients, vec!["1 cup cornmeal", "1 egg"]);
+        assert_eq!(bread.instructions, vec!["Mix everything.", "Bake at 425F for 20 minutes."]);
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(read("Taco Soup\n1 lb ground beef").is_err());
+        assert!(read("<html><body>Taco Soup</body></html>").unwrap().is_empty());
+    }
+}
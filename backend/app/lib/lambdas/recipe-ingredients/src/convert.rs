@@ -0,0 +1,321 @@
+use std::sync::OnceLock;
+
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+
+use crate::density::lookup;
+use crate::format::{describe, format_amount, format_decimal};
+use crate::parser::{Ingredient, Quantity};
+use crate::scale::{promote, round_amount};
+use crate::units::{Dimension, System, Unit};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ConvertedIngredient {
+    #[serde(flatten)]
+    pub ingredient: Ingredient,
+    // The line in the requested system, or the original line when skipped
+    pub text: String,
+    // True when we couldn't convert the line reliably and left it as it was
+    pub skipped: bool,
+    // The density table entry used to switch between weight and volume
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub density: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Measure {
+    Weight,
+    Volume,
+}
+
+impl Measure {
+    pub fn parse(value: &str) -> Option<Measure> {
+        match value.to_lowercase().as_str() {
+            "weight" | "mass" => Some(Measure::Weight),
+            "volume" => Some(Measure::Volume),
+            _ => None,
+        }
+    }
+
+    fn dimension(&self) -> Dimension {
+        match self {
+            Measure::Weight => Dimension::Mass,
+            Measure::Volume => Dimension::Volume,
+        }
+    }
+}
+
+/**
+ * Converts every ingredient's volume, mass and length to `system`. Lines
+ * already in that system, and counts like "2 eggs" or "1 can", don't need
+ * converting and are left as written. Lines we couldn't parse a quantity
+ * out of are left as written and flagged as skipped.
+ */
+pub fn convert(ingredients: &[Ingredient], system: System) -> Vec<ConvertedIngredient> {
+    ingredients
+        .iter()
+        .map(|ingredient| convert_one(ingredient, system))
+        .collect()
+}
+
+/**
+ * Like `convert`, but also switches volumes to weights (or the other way
+ * round) using the density table, so "2 cups flour" becomes "250 g flour".
+ * Ingredients missing from the table fall back to a plain unit conversion.
+ */
+pub fn convert_measure(ingredients: &[Ingredient], system: System, measure: Measure) -> Vec<ConvertedIngredient> {
+    ingredients
+        .iter()
+        .map(|ingredient| switch_measure(ingredient, system, measure))
+        .collect()
+}
+
+fn unchanged(ingredient: &Ingredient, skipped: bool) -> ConvertedIngredient {
+    ConvertedIngredient {
+        ingredient: ingredient.clone(),
+        text: ingredient.raw.clone(),
+        skipped,
+        density: None,
+    }
+}
+
+fn switch_measure(ingredient: &Ingredient, system: System, measure: Measure) -> ConvertedIngredient {
+    let (quantity, (dimension, _, size)) = match (&ingredient.quantity, ingredient.unit.and_then(|u| u.measure())) {
+        (Some(q), Some(m)) => (q, m),
+        _ => return convert_one(ingredient, system),
+    };
+    let target_dimension = measure.dimension();
+    if dimension == target_dimension || dimension == Dimension::Length {
+        return convert_one(ingredient, system);
+    }
+    let density = match lookup(&ingredient.item) {
+        Some(d) => d,
+        None => return convert_one(ingredient, system),
+    };
+
+    // Milliliters to grams, or grams to milliliters
+    let factor = match target_dimension {
+        Dimension::Mass => size * density.grams_per_ml(),
+        _ => size / density.grams_per_ml(),
+    };
+    let base = quantity.value * factor;
+    let (target, target_size) = target_unit(target_dimension, system, base);
+    let mut converted = ingredient.clone();
+    converted.unit = Some(target);
+    converted.quantity = Some(Quantity {
+        value: round_amount(base / target_size, Some(target)),
+        max: quantity.max.map(|m| round_amount(m * factor / target_size, Some(target))),
+    });
+    ConvertedIngredient {
+        text: describe(&converted),
+        ingredient: converted,
+        skipped: false,
+        density: Some(density.name.clone()),
+    }
+}
+
+fn convert_one(ingredient: &Ingredient, system: System) -> ConvertedIngredient {
+    let quantity = match &ingredient.quantity {
+        Some(q) => q,
+        None => return unchanged(ingredient, true),
+    };
+    let unit = match ingredient.unit {
+        Some(u) => u,
+        None => return unchanged(ingredient, false),
+    };
+    let (dimension, from, size) = match unit.measure() {
+        Some(m) => m,
+        None => return unchanged(ingredient, false),
+    };
+    // Metric recipes measure with the same 5 ml and 15 ml spoons
+    let spoon = matches!(unit, Unit::Teaspoon | Unit::Tablespoon);
+    if from == system || (system == System::Metric && spoon) {
+        return unchanged(ingredient, false);
+    }
+
+    let base = quantity.value * size;
+    let (target, target_size) = target_unit(dimension, system, base);
+    let mut converted = ingredient.clone();
+    converted.unit = Some(target);
+    converted.quantity = Some(Quantity {
+        value: round_amount(base / target_size, Some(target)),
+        max: quantity.max.map(|m| round_amount(m * size / target_size, Some(target))),
+    });
+    ConvertedIngredient {
+        text: describe(&converted),
+        ingredient: converted,
+        skipped: false,
+        density: None,
+    }
+}
+
+// The unit to write `base` milliliters, grams or centimeters in, and its size
+pub(crate) fn target_unit(dimension: Dimension, system: System, base: f64) -> (Unit, f64) {
+    let (unit, candidate) = match (dimension, system) {
+        (Dimension::Volume, System::Metric) if base >= 1000.0 => return (Unit::Liter, 1000.0),
+        (Dimension::Volume, System::Metric) => return (Unit::Milliliter, 1.0),
+        (Dimension::Mass, System::Metric) if base >= 1000.0 => return (Unit::Kilogram, 1000.0),
+        (Dimension::Mass, System::Metric) => return (Unit::Gram, 1.0),
+        (Dimension::Length, System::Metric) => return (Unit::Centimeter, 1.0),
+        (Dimension::Length, System::Us) => return (Unit::Inch, 2.54),
+        (Dimension::Volume, System::Us) => (Unit::Teaspoon, base / 4.92892),
+        (Dimension::Mass, System::Us) => (Unit::Ounce, base / 28.3495),
+    };
+    let (_, _, unit_size) = unit.measure().unwrap();
+    let (best, factor) = promote(unit, candidate);
+    (best.unwrap_or(unit), unit_size * factor)
+}
+
+fn temperature_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"(?i)(\d+(?:\.\d+)?)(?:\s*(?:-|–|to)\s*(\d+(?:\.\d+)?))?\s*(?:°|º|degrees?\s+)\s*(fahrenheit|celsius|f|c)\b").unwrap()
+    })
+}
+
+fn length_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r#"(?i)(\d+(?:\.\d+)?)(?:\s*(?:x|×|by)\s*(\d+(?:\.\d+)?))?(?:\s*(?:x|×|by)\s*(\d+(?:\.\d+)?))?(?:\s*|-)(inches|inch|in\.|"|centimeters|centimetres|centimeter|centimetre|cm)(?:\W|$)"#).unwrap()
+    })
+}
+
+/**
+ * Rewrites oven temperatures ("350°F", "180 degrees C") and lengths such as
+ * pan sizes ("9x13-inch", "23 cm") in free text, e.g. instructions, into
+ * `system`. Everything else in the text is left alone.
+ */
+pub fn convert_text(text: &str, system: System) -> String {
+    let text = temperature_pattern().replace_all(text, |caps: &Captures| {
+        let fahrenheit = caps[3].to_lowercase().starts_with('f');
+        let (from, to, symbol) = if fahrenheit {
+            (System::Us, System::Metric, "°C")
+        } else {
+            (System::Metric, System::Us, "°F")
+        };
+        if from == system || to != system {
+            return caps[0].to_string();
+        }
+        let convert = |value: &str| {
+            let value: f64 = value.parse().unwrap_or_default();
+            let degrees = if fahrenheit {
+                (value - 32.0) * 5.0 / 9.0
+            } else {
+                value * 9.0 / 5.0 + 32.0
+            };
+            // Ovens go in steps of 5, sugar thermometers don't
+            if degrees >= 100.0 && value % 5.0 == 0.0 {
+                format_decimal((degrees / 5.0).round() * 5.0)
+            } else {
+                format_decimal(degrees.round())
+            }
+        };
+        match caps.get(2) {
+            Some(max) => format!("{}-{}{}", convert(&caps[1]), convert(max.as_str()), symbol),
+            None => format!("{}{}", convert(&caps[1]), symbol),
+        }
+    });
+
+    length_pattern()
+        .replace_all(&text, |caps: &Captures| {
+            let whole = &caps[0];
+            let unit = caps[4].to_lowercase();
+            let inches = !unit.starts_with('c');
+            if (inches && system == System::Us) || (!inches && system == System::Metric) {
+                return whole.to_string();
+            }
+            // Keep whatever terminated the match, e.g. the space before "pan"
+            let trailing = &whole[caps.get(4).unwrap().end() - caps.get(0).unwrap().start()..];
+            let convert = |value: &str| {
+                let value: f64 = value.parse().unwrap_or_default();
+                if inches {
+                    let cm = value * 2.54;
+                    format_decimal(if cm >= 10.0 { cm.round() } else { (cm * 2.0).round() / 2.0 })
+                } else {
+                    format_amount(((value / 2.54) * 2.0).round() / 2.0)
+                }
+            };
+            let sizes: Vec<String> = (1..=3)
+                .filter_map(|i| caps.get(i))
+                .map(|m| convert(m.as_str()))
+                .collect();
+            let unit = if inches { "cm" } else { "inch" };
+            format!("{} {}{}", sizes.join(" x "), unit, trailing)
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn converted(line: &str, system: System) -> ConvertedIngredient {
+        convert(&[parse(line)], system).remove(0)
+    }
+
+    #[test]
+    fn converts_volume_and_mass() {
+        assert_eq!(converted("2 cups milk", System::Metric).text, "475 ml milk");
+        assert_eq!(converted("1 lb ground beef", System::Metric).text, "455 g ground beef");
+        assert_eq!(converted("3 lb pork shoulder", System::Metric).text, "1.4 kg pork shoulder");
+        assert_eq!(converted("250 ml stock", System::Us).text, "1 cup stock");
+        assert_eq!(converted("15 ml soy sauce", System::Us).text, "1 tbsp soy sauce");
+        assert_eq!(converted("100 g dark chocolate, chopped", System::Us).text, "3 1/2 oz dark chocolate, chopped");
+        assert_eq!(converted("1 inch piece ginger", System::Metric).text, "2.5 cm piece ginger");
+    }
+
+    #[test]
+    fn leaves_lines_that_need_no_conversion() {
+        for line in ["2 large eggs", "1 (14 oz) can tomatoes", "1 tbsp olive oil", "200 g flour"] {
+            let result = converted(line, System::Metric);
+            assert_eq!(result.text, line);
+            assert!(!result.skipped);
+        }
+    }
+
+    #[test]
+    fn flags_lines_it_cannot_convert() {
+        let result = converted("Salt and pepper to taste", System::Metric);
+        assert!(result.skipped);
+        assert_eq!(result.text, "Salt and pepper to taste");
+    }
+
+    #[test]
+    fn switches_between_weight_and_volume() {
+        let measured = |line: &str, system, measure| convert_measure(&[parse(line)], system, measure).remove(0);
+        let flour = measured("2 cups all-purpose flour, sifted", System::Metric, Measure::Weight);
+        assert_eq!(flour.text, "250 g all-purpose flour, sifted");
+        assert_eq!(flour.density.as_deref(), Some("all-purpose flour"));
+        assert_eq!(measured("1 stick butter", System::Metric, Measure::Weight).text, "1 stick butter");
+        assert_eq!(measured("1 cup sugar", System::Us, Measure::Weight).text, "7 oz sugar");
+        assert_eq!(measured("200 g granulated sugar", System::Us, Measure::Volume).text, "1 cup granulated sugar");
+        assert_eq!(measured("113 g butter", System::Us, Measure::Volume).text, "1/2 cup butter");
+    }
+
+    #[test]
+    fn falls_back_without_a_density() {
+        let result = convert_measure(&[parse("2 cups dragon fruit")], System::Metric, Measure::Weight).remove(0);
+        assert_eq!(result.text, "475 ml dragon fruit");
+        assert_eq!(result.density, None);
+        assert!(!result.skipped);
+    }
+
+    #[test]
+    fn converts_temperatures_in_text() {
+        assert_eq!(convert_text("Bake at 350°F for 20 minutes.", System::Metric), "Bake at 175°C for 20 minutes.");
+        assert_eq!(convert_text("Heat oven to 200 degrees C.", System::Us), "Heat oven to 390°F.");
+        assert_eq!(convert_text("Cook to 238°F (soft ball).", System::Metric), "Cook to 114°C (soft ball).");
+        assert_eq!(convert_text("Bake at 350°F.", System::Us), "Bake at 350°F.");
+        assert_eq!(convert_text("Add 2 C of flour.", System::Metric), "Add 2 C of flour.");
+    }
+
+    #[test]
+    fn converts_pan_sizes_in_text() {
+        assert_eq!(convert_text("Grease a 9x13-inch pan.", System::Metric), "Grease a 23 x 33 cm pan.");
+        assert_eq!(convert_text("Use a 9\" springform", System::Metric), "Use a 23 cm springform");
+        assert_eq!(convert_text("Line a 23 cm tin", System::Us), "Line a 9 inch tin");
+        assert_eq!(convert_text("Cut into 1 inch cubes", System::Metric), "Cut into 2.5 cm cubes");
+    }
+}
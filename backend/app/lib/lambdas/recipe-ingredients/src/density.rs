@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+// Grams per US cup for a few hundred common ingredients, mostly from baking
+// weight charts. Each line is `name,grams per cup,synonym|synonym`.
+const DENSITIES: &str = include_str!("../data/densities.csv");
+
+const ML_PER_CUP: f64 = 236.588;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Density {
+    pub name: String,
+    pub grams_per_cup: f64,
+}
+
+impl Density {
+    pub fn grams_per_ml(&self) -> f64 {
+        self.grams_per_cup / ML_PER_CUP
+    }
+}
+
+struct Table {
+    densities: Vec<Density>,
+    // Every name and synonym, pointing into `densities`
+    names: HashMap<String, usize>,
+}
+
+fn table() -> &'static Table {
+    static TABLE: OnceLock<Table> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = Table { densities: Vec::new(), names: HashMap::new() };
+        for line in DENSITIES.lines().filter(|l| !l.is_empty() && !l.starts_with('#')) {
+            let mut fields = line.splitn(3, ',');
+            let name = fields.next().unwrap_or_default().trim();
+            let grams_per_cup = match fields.next().and_then(|g| g.trim().parse().ok()) {
+                Some(g) => g,
+                None => continue,
+            };
+            let index = table.densities.len();
+            table.densities.push(Density { name: name.to_string(), grams_per_cup });
+            let synonyms = fields.next().unwrap_or_default().split('|');
+            for alias in std::iter::once(name).chain(synonyms) {
+                let alias = normalize(alias);
+                if !alias.is_empty() {
+                    table.names.entry(alias).or_insert(index);
+                }
+            }
+        }
+        table
+    })
+}
+
+fn normalize(name: &str) -> String {
+    name.to_lowercase()
+        .replace(['’', '\''], "")
+        .split(|c: char| !(c.is_alphanumeric() || c == '-' || c == '%'))
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/**
+ * Finds the density for a parsed ingredient name. Tries the whole name, then
+ * the name with or without a plural "s", then the longest known name it ends
+ * with, so "sifted cake flour" finds cake flour and "homemade chicken stock"
+ * finds chicken broth. Returns None rather than guessing from a word in the
+ * middle.
+ */
+pub fn lookup(item: &str) -> Option<&'static Density> {
+    let table = table();
+    let name = normalize(item);
+    let candidates = [
+        Some(name.clone()),
+        name.strip_suffix("es").map(String::from),
+        name.strip_suffix('s').map(String::from),
+        Some(format!("{}s", name)),
+    ];
+    for candidate in candidates.into_iter().flatten() {
+        if let Some(index) = table.names.get(&candidate) {
+            return Some(&table.densities[*index]);
+        }
+    }
+
+    let words: Vec<&str> = name.split(' ').collect();
+    (1..words.len())
+        .filter_map(|start| table.names.get(&words[start..].join(" ")))
+        .next()
+        .map(|index| &table.densities[*index])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_the_whole_table() {
+        assert!(table().densities.len() > 250);
+        assert!(table().densities.iter().all(|d| d.grams_per_cup > 0.0));
+    }
+
+    #[test]
+    fn matches_names_and_synonyms() {
+        assert_eq!(lookup("all-purpose flour").unwrap().grams_per_cup, 125.0);
+        assert_eq!(lookup("Plain Flour").unwrap().name, "all-purpose flour");
+        assert_eq!(lookup("confectioners' sugar").unwrap().name, "powdered sugar");
+        assert_eq!(lookup("walnut").unwrap().name, "walnuts");
+        assert_eq!(lookup("sifted cake flour").unwrap().name, "cake flour");
+        assert_eq!(lookup("homemade chicken stock").unwrap().name, "chicken broth");
+    }
+
+    #[test]
+    fn does_not_guess() {
+        assert_eq!(lookup("dragon fruit"), None);
+        assert_eq!(lookup("flour tortillas"), None);
+    }
+}
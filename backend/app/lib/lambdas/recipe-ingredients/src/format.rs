@@ -0,0 +1,104 @@
+use crate::parser::{Ingredient, Quantity};
+use crate::units::Unit;
+
+const FRACTIONS: &[(f64, &str)] = &[
+    (1.0 / 8.0, "1/8"),
+    (1.0 / 4.0, "1/4"),
+    (1.0 / 3.0, "1/3"),
+    (3.0 / 8.0, "3/8"),
+    (1.0 / 2.0, "1/2"),
+    (5.0 / 8.0, "5/8"),
+    (2.0 / 3.0, "2/3"),
+    (3.0 / 4.0, "3/4"),
+    (7.0 / 8.0, "7/8"),
+];
+
+/**
+ * Writes an amount the way a recipe would: 1.5 becomes "1 1/2", 0.333 becomes
+ * "1/3" and anything that isn't close to a kitchen fraction keeps up to two
+ * decimals, e.g. "2.4".
+ */
+pub fn format_amount(value: f64) -> String {
+    let whole = value.trunc();
+    let fraction = value - whole;
+    if !(0.01..=0.99).contains(&fraction) {
+        return format!("{}", value.round());
+    }
+    if let Some((_, text)) = FRACTIONS.iter().find(|(f, _)| (fraction - f).abs() < 0.01) {
+        return if whole == 0.0 {
+            text.to_string()
+        } else {
+            format!("{} {}", whole, text)
+        };
+    }
+    format_decimal(value)
+}
+
+pub fn format_decimal(value: f64) -> String {
+    let decimal = format!("{:.2}", value);
+    decimal.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+pub fn format_quantity(quantity: &Quantity, unit: Option<Unit>) -> String {
+    let format = if unit.is_some_and(|u| u.is_metric()) {
+        format_decimal
+    } else {
+        format_amount
+    };
+    match quantity.max {
+        Some(max) => format!("{}-{}", format(quantity.value), format(max)),
+        None => format(quantity.value),
+    }
+}
+
+// Renders a parsed ingredient back into a single line, e.g.
+// "1 1/2 cups onion, finely chopped (divided)"
+pub fn describe(ingredient: &Ingredient) -> String {
+    let mut parts = Vec::new();
+    if let Some(quantity) = &ingredient.quantity {
+        parts.push(format_quantity(quantity, ingredient.unit));
+    }
+    if let Some(unit) = ingredient.unit {
+        let plural = ingredient
+            .quantity
+            .as_ref()
+            .is_some_and(|q| q.max.is_some() || q.value > 1.0);
+        parts.push(unit.name(plural).to_string());
+    }
+    if !ingredient.item.is_empty() {
+        parts.push(ingredient.item.clone());
+    }
+    let mut line = parts.join(" ");
+    if let Some(preparation) = &ingredient.preparation {
+        line = format!("{}, {}", line, preparation);
+    }
+    if let Some(notes) = &ingredient.notes {
+        line = format!("{} ({})", line, notes);
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn formats_kitchen_fractions() {
+        assert_eq!(format_amount(1.5), "1 1/2");
+        assert_eq!(format_amount(1.0 / 3.0), "1/3");
+        assert_eq!(format_amount(2.0), "2");
+        assert_eq!(format_amount(2.4), "2.4");
+        assert_eq!(format_amount(0.999), "1");
+    }
+
+    #[test]
+    fn describes_parsed_lines() {
+        assert_eq!(
+            describe(&parse("1 1/2 cups finely chopped onion, divided")),
+            "1 1/2 cups onion, finely chopped (divided)"
+        );
+        assert_eq!(describe(&parse("2-3 cloves garlic")), "2-3 cloves garlic");
+        assert_eq!(describe(&parse("Salt to taste")), "Salt (to taste)");
+    }
+}
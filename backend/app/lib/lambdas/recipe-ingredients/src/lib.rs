@@ -0,0 +1,13 @@
+mod convert;
+mod density;
+mod format;
+mod parser;
+mod scale;
+mod units;
+
+pub use convert::{convert, convert_measure, convert_text, ConvertedIngredient, Measure};
+pub use density::{lookup as lookup_density, Density};
+pub use format::{describe, format_amount, format_decimal, format_quantity};
+pub use parser::{parse, parse_all, Ingredient, Quantity};
+pub use scale::{parse_servings, scale, ScaledIngredient};
+pub use units::{Dimension, System, Unit};
@@ -0,0 +1,421 @@
+use serde::{Deserialize, Serialize};
+
+use crate::units::Unit;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Quantity {
+    pub value: f64,
+    // Upper end of a range such as "2-3 cloves"
+    pub max: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Ingredient {
+    pub raw: String,
+    pub quantity: Option<Quantity>,
+    pub unit: Option<Unit>,
+    pub item: String,
+    pub preparation: Option<String>,
+    pub notes: Option<String>,
+}
+
+const UNICODE_FRACTIONS: &[(char, &str)] = &[
+    ('½', "1/2"), ('⅓', "1/3"), ('⅔', "2/3"), ('¼', "1/4"), ('¾', "3/4"), ('⅕', "1/5"),
+    ('⅖', "2/5"), ('⅗', "3/5"), ('⅘', "4/5"), ('⅙', "1/6"), ('⅚', "5/6"), ('⅛', "1/8"),
+    ('⅜', "3/8"), ('⅝', "5/8"), ('⅞', "7/8"),
+];
+
+const NUMBER_WORDS: &[(&str, f64)] = &[
+    ("one", 1.0), ("two", 2.0), ("three", 3.0), ("four", 4.0), ("five", 5.0), ("six", 6.0),
+    ("seven", 7.0), ("eight", 8.0), ("nine", 9.0), ("ten", 10.0), ("eleven", 11.0),
+    ("twelve", 12.0), ("dozen", 12.0),
+];
+
+// Words that describe how an ingredient is prepared rather than what it is
+const PREPARATION_WORDS: &[&str] = &[
+    "chopped", "diced", "minced", "sliced", "grated", "shredded", "crushed", "melted", "softened",
+    "cubed", "peeled", "beaten", "sifted", "packed", "toasted", "cooked", "drained", "rinsed",
+    "halved", "quartered", "julienned", "trimmed", "seeded", "cored", "pitted", "zested",
+    "juiced", "mashed", "whisked", "thawed", "deveined", "shelled", "torn", "crumbled", "cut",
+    "separated", "room-temperature", "chilled", "warmed", "smashed", "roasted",
+];
+
+const ADVERBS: &[&str] = &[
+    "finely", "coarsely", "roughly", "thinly", "thickly", "freshly", "lightly", "firmly",
+    "loosely", "well", "very", "evenly",
+];
+
+// Phrases that start a note; checked before the preparation rules because
+// "divided" would otherwise look like a past participle.
+const NOTE_STARTS: &[&str] = &[
+    "divided", "optional", "to taste", "for serving", "for garnish", "for the", "for dusting",
+    "plus", "or ", "such as", "about", "at room temperature", "if ", "preferably", "as needed",
+    "more ", "and more", "store-bought", "homemade",
+];
+
+/**
+ * Parses one ingredient line such as "1 1/2 cups finely chopped onion, divided"
+ * into quantity (1.5), unit (cup), item (onion), preparation (finely chopped)
+ * and notes (divided). Anything we can't make sense of ends up in `item`, and
+ * the original line is always kept in `raw`.
+ */
+pub fn parse(raw: &str) -> Ingredient {
+    let normalized = normalize(raw);
+    let (text, mut notes) = take_parentheticals(&normalized);
+    let mut rest = text.trim();
+
+    let quantity = take_quantity(&mut rest);
+    let unit = take_unit(&mut rest, quantity.is_some());
+    if unit.is_some() {
+        if let Some(after) = strip_word(rest, "of") {
+            rest = after;
+        }
+    }
+
+    let mut segments = rest.split(',').map(str::trim).filter(|s| !s.is_empty());
+    let head = segments.next().unwrap_or_default();
+    let (item, mut preparation) = split_head(head);
+
+    for segment in segments {
+        let lower = segment.to_lowercase();
+        if NOTE_STARTS.iter().any(|n| lower.starts_with(n)) {
+            notes.push(segment.to_string());
+        } else if is_preparation(&lower) {
+            preparation.push(segment.to_string());
+        } else {
+            notes.push(segment.to_string());
+        }
+    }
+
+    let (item, trailing_note) = split_trailing_note(&item);
+    if let Some(note) = trailing_note {
+        notes.insert(0, note);
+    }
+
+    let join = |parts: Vec<String>| Some(parts.join(", ")).filter(|s| !s.is_empty());
+    Ingredient {
+        raw: raw.trim().to_string(),
+        quantity,
+        unit,
+        item,
+        preparation: join(preparation),
+        notes: join(notes),
+    }
+}
+
+pub fn parse_all(lines: &[String]) -> Vec<Ingredient> {
+    lines.iter().map(|line| parse(line)).collect()
+}
+
+// Expands unicode fractions, unifies dashes and splits "1-2" and "200g"
+// into separate tokens.
+fn normalize(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len() + 8);
+    let mut previous: Option<char> = None;
+    for c in raw.chars() {
+        if let Some((_, fraction)) = UNICODE_FRACTIONS.iter().find(|(u, _)| *u == c) {
+            if previous.is_some_and(|p| p.is_ascii_digit()) {
+                out.push(' ');
+            }
+            out.push_str(fraction);
+            previous = Some('0');
+            continue;
+        }
+        let c = match c {
+            '⁄' => '/',
+            '–' | '—' | '‒' => '-',
+            '\u{a0}' => ' ',
+            c => c,
+        };
+        let after_number = previous.is_some_and(|p| p.is_ascii_digit());
+        if c == '-' && after_number {
+            out.push_str(" - ");
+        } else if c.is_alphabetic() && after_number {
+            out.push(' ');
+            out.push(c);
+        } else {
+            out.push(c);
+        }
+        previous = Some(c);
+    }
+    out.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+fn take_parentheticals(text: &str) -> (String, Vec<String>) {
+    let mut kept = String::new();
+    let mut notes = Vec::new();
+    let mut depth = 0;
+    let mut current = String::new();
+    for c in text.chars() {
+        match c {
+            '(' => {
+                if depth > 0 {
+                    current.push(c);
+                }
+                depth += 1;
+            }
+            ')' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    let note = current.trim().to_string();
+                    if !note.is_empty() {
+                        notes.push(note);
+                    }
+                    current.clear();
+                } else {
+                    current.push(c);
+                }
+            }
+            c if depth > 0 => current.push(c),
+            c => kept.push(c),
+        }
+    }
+    let kept = kept.split_whitespace().collect::<Vec<&str>>().join(" ");
+    (kept.replace(" ,", ","), notes)
+}
+
+fn next_token(text: &str) -> Option<(&str, &str)> {
+    let text = text.trim_start();
+    if text.is_empty() {
+        return None;
+    }
+    let end = text.find(char::is_whitespace).unwrap_or(text.len());
+    Some((&text[..end], &text[end..]))
+}
+
+fn strip_word<'a>(text: &'a str, word: &str) -> Option<&'a str> {
+    let (token, rest) = next_token(text)?;
+    if token.eq_ignore_ascii_case(word) {
+        Some(rest.trim_start())
+    } else {
+        None
+    }
+}
+
+pub(crate) fn parse_number(token: &str) -> Option<f64> {
+    let token = token.trim_end_matches(',');
+    if let Some((n, d)) = token.split_once('/') {
+        let n: f64 = n.parse().ok()?;
+        let d: f64 = d.parse().ok()?;
+        return if d == 0.0 { None } else { Some(n / d) };
+    }
+    if token.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+        return token.parse().ok();
+    }
+    let lower = token.to_lowercase();
+    NUMBER_WORDS.iter().find(|(w, _)| *w == lower).map(|(_, n)| *n)
+}
+
+// A number, a mixed number ("1 1/2") or a fraction
+fn take_amount(text: &mut &str) -> Option<f64> {
+    let (token, rest) = next_token(text)?;
+    let whole = parse_number(token)?;
+    *text = rest;
+    if whole.fract() == 0.0 && !token.contains('/') {
+        if let Some((next, after)) = next_token(text) {
+            if next.contains('/') {
+                if let Some(fraction) = parse_number(next) {
+                    if fraction < 1.0 {
+                        *text = after;
+                        return Some(whole + fraction);
+                    }
+                }
+            }
+        }
+    }
+    Some(whole)
+}
+
+fn take_quantity(text: &mut &str) -> Option<Quantity> {
+    let mut rest = *text;
+    // "a pinch", "an 8 oz block" only count when a unit or number follows
+    if let Some((token, after)) = next_token(rest) {
+        if token.eq_ignore_ascii_case("a") || token.eq_ignore_ascii_case("an") {
+            let (next, _) = next_token(after)?;
+            if Unit::parse(next).is_some() {
+                *text = after;
+                return Some(Quantity { value: 1.0, max: None });
+            }
+            rest = after;
+        }
+    }
+
+    let value = take_amount(&mut rest)?;
+    let mut max = None;
+    let mut lookahead = rest;
+    if let Some((token, after)) = next_token(lookahead) {
+        if matches!(token.to_lowercase().as_str(), "-" | "to" | "or") {
+            lookahead = after;
+            if let Some(upper) = take_amount(&mut lookahead) {
+                if upper > value {
+                    max = Some(upper);
+                    rest = lookahead;
+                }
+            }
+        }
+    }
+    *text = rest;
+    Some(Quantity { value, max })
+}
+
+fn take_unit(text: &mut &str, has_quantity: bool) -> Option<Unit> {
+    let (first, after_first) = next_token(text)?;
+    if let Some((second, after_second)) = next_token(after_first) {
+        if let Some(unit) = Unit::parse(&format!("{} {}", first, second).to_lowercase()) {
+            *text = after_second;
+            return Some(unit);
+        }
+    }
+    let unit = Unit::parse(first)?;
+    // Without a number only take obvious measures: "pinch of salt", "Dash hot sauce"
+    if !has_quantity && !matches!(unit, Unit::Pinch | Unit::Dash | Unit::Handful) && strip_word(after_first, "of").is_none() {
+        return None;
+    }
+    *text = after_first;
+    Some(unit)
+}
+
+fn is_preparation(segment: &str) -> bool {
+    segment
+        .split_whitespace()
+        .find(|w| !ADVERBS.contains(w))
+        .is_some_and(|w| PREPARATION_WORDS.contains(&w) || w.ends_with("ed") || w.ends_with("en"))
+}
+
+// Leading descriptors like "finely chopped" or "peeled and diced" are
+// preparation; what follows them is the item.
+fn split_head(head: &str) -> (String, Vec<String>) {
+    let words: Vec<&str> = head.split_whitespace().collect();
+    let mut taken = 0;
+    let mut last_prep = 0;
+    while taken < words.len() {
+        let lower = words[taken].to_lowercase();
+        if ADVERBS.contains(&lower.as_str()) || (taken > 0 && lower == "and") {
+            taken += 1;
+        } else if PREPARATION_WORDS.contains(&lower.as_str()) {
+            taken += 1;
+            last_prep = taken;
+        } else {
+            break;
+        }
+    }
+    // Never eat the whole line, "1 cup chopped" is better left alone
+    if last_prep == 0 || last_prep == words.len() {
+        return (head.to_string(), Vec::new());
+    }
+    (
+        words[last_prep..].join(" "),
+        vec![words[..last_prep].join(" ")],
+    )
+}
+
+// "salt to taste" and "parsley for garnish" without the comma
+fn split_trailing_note(item: &str) -> (String, Option<String>) {
+    let lower = item.to_lowercase();
+    for phrase in [" to taste", " for serving", " for garnish", " for dusting", " as needed", " optional"] {
+        if let Some(index) = lower.find(phrase) {
+            if index > 0 {
+                return (item[..index].trim().to_string(), Some(item[index..].trim().to_string()));
+            }
+        }
+    }
+    (item.to_string(), None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quantity(value: f64, max: Option<f64>) -> Option<Quantity> {
+        Some(Quantity { value, max })
+    }
+
+    #[test]
+    fn parses_the_full_example() {
+        let ingredient = parse("1 1/2 cups finely chopped onion, divided");
+        assert_eq!(ingredient.raw, "1 1/2 cups finely chopped onion, divided");
+        assert_eq!(ingredient.quantity, quantity(1.5, None));
+        assert_eq!(ingredient.unit, Some(Unit::Cup));
+        assert_eq!(ingredient.item, "onion");
+        assert_eq!(ingredient.preparation.as_deref(), Some("finely chopped"));
+        assert_eq!(ingredient.notes.as_deref(), Some("divided"));
+    }
+
+    #[test]
+    fn parses_unicode_fractions() {
+        let ingredient = parse("½ tsp kosher salt");
+        assert_eq!(ingredient.quantity, quantity(0.5, None));
+        assert_eq!(ingredient.unit, Some(Unit::Teaspoon));
+        assert_eq!(ingredient.item, "kosher salt");
+
+        let ingredient = parse("1¾ cups milk");
+        assert_eq!(ingredient.quantity, quantity(1.75, None));
+    }
+
+    #[test]
+    fn parses_ranges() {
+        assert_eq!(parse("1–2 Tbsp olive oil").quantity, quantity(1.0, Some(2.0)));
+        assert_eq!(parse("1-2 T olive oil").unit, Some(Unit::Tablespoon));
+        let ingredient = parse("3 to 4 pounds chicken thighs");
+        assert_eq!(ingredient.quantity, quantity(3.0, Some(4.0)));
+        assert_eq!(ingredient.unit, Some(Unit::Pound));
+        assert_eq!(ingredient.item, "chicken thighs");
+    }
+
+    #[test]
+    fn parses_glued_units_and_decimals() {
+        let ingredient = parse("200g plain flour");
+        assert_eq!(ingredient.quantity, quantity(200.0, None));
+        assert_eq!(ingredient.unit, Some(Unit::Gram));
+        assert_eq!(ingredient.item, "plain flour");
+        assert_eq!(parse("1.5 fl oz bourbon").unit, Some(Unit::FluidOunce));
+        assert_eq!(parse("1.5 fl oz bourbon").item, "bourbon");
+    }
+
+    #[test]
+    fn keeps_parentheticals_as_notes() {
+        let ingredient = parse("1 (14 oz) can diced tomatoes, drained");
+        assert_eq!(ingredient.quantity, quantity(1.0, None));
+        assert_eq!(ingredient.unit, Some(Unit::Can));
+        assert_eq!(ingredient.item, "tomatoes");
+        assert_eq!(ingredient.preparation.as_deref(), Some("diced, drained"));
+        assert_eq!(ingredient.notes.as_deref(), Some("14 oz"));
+    }
+
+    #[test]
+    fn handles_items_without_units() {
+        let ingredient = parse("2 large eggs, beaten");
+        assert_eq!(ingredient.quantity, quantity(2.0, None));
+        assert_eq!(ingredient.unit, None);
+        assert_eq!(ingredient.item, "large eggs");
+        assert_eq!(ingredient.preparation.as_deref(), Some("beaten"));
+
+        let ingredient = parse("Salt and pepper to taste");
+        assert_eq!(ingredient.quantity, None);
+        assert_eq!(ingredient.item, "Salt and pepper");
+        assert_eq!(ingredient.notes.as_deref(), Some("to taste"));
+
+        let ingredient = parse("a pinch of cayenne");
+        assert_eq!(ingredient.quantity, quantity(1.0, None));
+        assert_eq!(ingredient.unit, Some(Unit::Pinch));
+        assert_eq!(ingredient.item, "cayenne");
+    }
+
+    #[test]
+    fn leaves_unparseable_lines_alone() {
+        let ingredient = parse("For the sauce:");
+        assert_eq!(ingredient.quantity, None);
+        assert_eq!(ingredient.unit, None);
+        assert_eq!(ingredient.item, "For the sauce:");
+        assert_eq!(parse("Juice of 1 lemon").item, "Juice of 1 lemon");
+        assert_eq!(parse("1 cup chopped").item, "chopped");
+    }
+
+    #[test]
+    fn serializes_units_in_snake_case() {
+        let json = serde_json::to_string(&parse("2 fl oz cream")).unwrap();
+        assert!(json.contains("\"unit\":\"fluid_ounce\""));
+        let back: Ingredient = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.item, "cream");
+    }
+}
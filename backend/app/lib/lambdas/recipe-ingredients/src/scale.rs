@@ -0,0 +1,192 @@
+use serde::{Deserialize, Serialize};
+
+use crate::format::describe;
+use crate::parser::{parse_number, Ingredient, Quantity};
+use crate::units::Unit;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ScaledIngredient {
+    #[serde(flatten)]
+    pub ingredient: Ingredient,
+    // The rescaled line, ready to show
+    pub text: String,
+    // False when there was no quantity to scale and the line was passed through
+    pub scaled: bool,
+}
+
+// Units that can be swapped for each other, smallest first, with their size
+// in the smallest unit and the least of it we'd write before stepping down
+const LADDERS: &[&[(Unit, f64, f64)]] = &[
+    &[(Unit::Teaspoon, 1.0, 0.0), (Unit::Tablespoon, 3.0, 1.0), (Unit::Cup, 48.0, 0.25)],
+    &[(Unit::Ounce, 1.0, 0.0), (Unit::Pound, 16.0, 1.0)],
+    &[(Unit::Gram, 1.0, 0.0), (Unit::Kilogram, 1000.0, 1.0)],
+    &[(Unit::Milliliter, 1.0, 0.0), (Unit::Liter, 1000.0, 1.0)],
+];
+
+const FRIENDLY_FRACTIONS: &[f64] = &[
+    0.0, 1.0 / 8.0, 1.0 / 4.0, 1.0 / 3.0, 3.0 / 8.0, 1.0 / 2.0, 5.0 / 8.0, 2.0 / 3.0, 3.0 / 4.0,
+    7.0 / 8.0, 1.0,
+];
+
+/**
+ * Multiplies every quantity by `factor`, moves it to the unit a cook would
+ * use (48 tsp is 1 cup, 2000 g is 2 kg) and rounds to something measurable.
+ * Lines without a quantity come back untouched with `scaled` set to false.
+ */
+pub fn scale(ingredients: &[Ingredient], factor: f64) -> Vec<ScaledIngredient> {
+    ingredients
+        .iter()
+        .map(|ingredient| scale_one(ingredient, factor))
+        .collect()
+}
+
+fn scale_one(ingredient: &Ingredient, factor: f64) -> ScaledIngredient {
+    let quantity = match &ingredient.quantity {
+        Some(q) => q,
+        None => {
+            return ScaledIngredient {
+                ingredient: ingredient.clone(),
+                text: ingredient.raw.clone(),
+                scaled: false,
+            }
+        }
+    };
+
+    let value = quantity.value * factor;
+    let max = quantity.max.map(|m| m * factor);
+    let (unit, size) = match ingredient.unit {
+        Some(unit) => promote(unit, value),
+        None => (None, 1.0),
+    };
+    let unit = unit.or(ingredient.unit);
+
+    let mut scaled = ingredient.clone();
+    scaled.quantity = Some(Quantity {
+        value: round_amount(value / size, unit),
+        max: max.map(|m| round_amount(m / size, unit)),
+    });
+    scaled.unit = unit;
+    ScaledIngredient {
+        text: describe(&scaled),
+        ingredient: scaled,
+        scaled: true,
+    }
+}
+
+// Picks the biggest unit on the same ladder that still reads naturally and
+// returns it with its size relative to the original unit.
+pub(crate) fn promote(unit: Unit, value: f64) -> (Option<Unit>, f64) {
+    let ladder = match LADDERS.iter().find(|l| l.iter().any(|(u, _, _)| *u == unit)) {
+        Some(l) => l,
+        None => return (Some(unit), 1.0),
+    };
+    let (_, base, _) = ladder.iter().find(|(u, _, _)| *u == unit).unwrap();
+    let amount = value * base;
+    let (best, size, _) = ladder
+        .iter()
+        .rev()
+        .find(|(_, size, minimum)| amount / size >= *minimum)
+        .unwrap_or(&ladder[0]);
+    (Some(*best), size / base)
+}
+
+pub(crate) fn round_amount(value: f64, unit: Option<Unit>) -> f64 {
+    let metric = unit.is_some_and(|u| u.is_metric());
+    let rounded = if metric {
+        match value {
+            v if v >= 100.0 => (v / 5.0).round() * 5.0,
+            v if v >= 10.0 => v.round(),
+            v => (v * 10.0).round() / 10.0,
+        }
+    } else if value >= 10.0 {
+        (value * 2.0).round() / 2.0
+    } else {
+        let whole = value.trunc();
+        let fraction = value - whole;
+        let nearest = FRIENDLY_FRACTIONS
+            .iter()
+            .min_by(|a, b| (fraction - *a).abs().total_cmp(&(fraction - *b).abs()))
+            .unwrap();
+        whole + nearest
+    };
+    // Never scale something away entirely
+    if rounded == 0.0 && value > 0.0 {
+        if metric {
+            0.1
+        } else {
+            1.0 / 8.0
+        }
+    } else {
+        rounded
+    }
+}
+
+/**
+ * Reads a serving count out of a yield such as "Serves 4-6", "12 cookies"
+ * or "Makes one dozen". A range counts as its lower end.
+ */
+pub fn parse_servings(recipe_yield: &str) -> Option<f64> {
+    let words: Vec<&str> = recipe_yield
+        .split(|c: char| c.is_whitespace() || c == '-' || c == '–')
+        .filter(|w| !w.is_empty())
+        .collect();
+    for (i, word) in words.iter().enumerate() {
+        let servings = match parse_number(word) {
+            Some(n) if n > 0.0 => n,
+            _ => continue,
+        };
+        let dozen = words.get(i + 1).is_some_and(|w| w.eq_ignore_ascii_case("dozen"));
+        return Some(if dozen && servings != 12.0 { servings * 12.0 } else { servings });
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn scaled(line: &str, factor: f64) -> ScaledIngredient {
+        scale(&[parse(line)], factor).remove(0)
+    }
+
+    #[test]
+    fn promotes_units() {
+        assert_eq!(scaled("12 tsp sugar", 4.0).text, "1 cup sugar");
+        assert_eq!(scaled("1 1/2 tsp baking soda", 2.0).text, "1 tbsp baking soda");
+        assert_eq!(scaled("10 oz cheddar", 2.0).text, "1 1/4 lb cheddar");
+        assert_eq!(scaled("500g flour", 3.0).text, "1.5 kg flour");
+    }
+
+    #[test]
+    fn demotes_units_when_halving() {
+        assert_eq!(scaled("1/4 cup butter", 0.5).text, "2 tbsp butter");
+        assert_eq!(scaled("1 tbsp vanilla", 0.5).text, "1 1/2 tsp vanilla");
+    }
+
+    #[test]
+    fn rounds_to_measurable_amounts() {
+        assert_eq!(scaled("1 cup milk", 1.0 / 3.0).text, "1/3 cup milk");
+        assert_eq!(scaled("3 large eggs", 0.5).text, "1 1/2 large eggs");
+        assert_eq!(scaled("2-3 cloves garlic, minced", 2.0).text, "4-6 cloves garlic, minced");
+        assert_eq!(scaled("1 pinch salt", 0.1).text, "1/8 pinch salt");
+        assert_eq!(scaled("333 g sugar", 1.0).text, "335 g sugar");
+    }
+
+    #[test]
+    fn passes_through_unparsed_lines() {
+        let line = scaled("Salt and pepper to taste", 2.0);
+        assert!(!line.scaled);
+        assert_eq!(line.text, "Salt and pepper to taste");
+        assert!(scaled("2 eggs", 2.0).scaled);
+    }
+
+    #[test]
+    fn reads_servings_from_yields() {
+        assert_eq!(parse_servings("Serves 4-6"), Some(4.0));
+        assert_eq!(parse_servings("12 cookies"), Some(12.0));
+        assert_eq!(parse_servings("Makes one dozen"), Some(12.0));
+        assert_eq!(parse_servings("2 dozen rolls"), Some(24.0));
+        assert_eq!(parse_servings("a lot"), None);
+    }
+}
@@ -0,0 +1,187 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Dimension {
+    Volume,
+    Mass,
+    Length,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum System {
+    Metric,
+    Us,
+}
+
+impl System {
+    pub fn parse(value: &str) -> Option<System> {
+        match value.to_lowercase().as_str() {
+            "metric" | "si" => Some(System::Metric),
+            "us" | "imperial" | "customary" => Some(System::Us),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Unit {
+    Teaspoon,
+    Tablespoon,
+    FluidOunce,
+    Cup,
+    Pint,
+    Quart,
+    Gallon,
+    Milliliter,
+    Liter,
+    Milligram,
+    Gram,
+    Kilogram,
+    Ounce,
+    Pound,
+    Inch,
+    Centimeter,
+    Pinch,
+    Dash,
+    Clove,
+    Can,
+    Jar,
+    Package,
+    Stick,
+    Slice,
+    Sprig,
+    Bunch,
+    Head,
+    Piece,
+    Handful,
+}
+
+impl Unit {
+    /**
+     * Recognises the spellings recipes actually use: "Tbsp", "tbs.", "cups",
+     * "c", "fl oz", "lbs"... A lone capital T is a tablespoon and a lone t a
+     * teaspoon, so that one check is case sensitive.
+     */
+    pub fn parse(word: &str) -> Option<Unit> {
+        let word = word.trim_end_matches(['.', ',']);
+        match word {
+            "T" | "Tb" => return Some(Unit::Tablespoon),
+            "t" => return Some(Unit::Teaspoon),
+            _ => {}
+        }
+        let unit = match word.to_lowercase().as_str() {
+            "tsp" | "tsps" | "teaspoon" | "teaspoons" => Unit::Teaspoon,
+            "tbsp" | "tbsps" | "tbs" | "tbl" | "tablespoon" | "tablespoons" => Unit::Tablespoon,
+            "fl oz" | "fl. oz" | "floz" | "fluid ounce" | "fluid ounces" => Unit::FluidOunce,
+            "c" | "cup" | "cups" => Unit::Cup,
+            "pt" | "pint" | "pints" => Unit::Pint,
+            "qt" | "quart" | "quarts" => Unit::Quart,
+            "gal" | "gallon" | "gallons" => Unit::Gallon,
+            "ml" | "milliliter" | "milliliters" | "millilitre" | "millilitres" => Unit::Milliliter,
+            "l" | "liter" | "liters" | "litre" | "litres" => Unit::Liter,
+            "mg" | "milligram" | "milligrams" => Unit::Milligram,
+            "g" | "gr" | "gram" | "grams" => Unit::Gram,
+            "kg" | "kilogram" | "kilograms" => Unit::Kilogram,
+            "oz" | "ounce" | "ounces" => Unit::Ounce,
+            "lb" | "lbs" | "pound" | "pounds" => Unit::Pound,
+            "in" | "inch" | "inches" => Unit::Inch,
+            "cm" | "centimeter" | "centimeters" | "centimetre" | "centimetres" => Unit::Centimeter,
+            "pinch" | "pinches" => Unit::Pinch,
+            "dash" | "dashes" => Unit::Dash,
+            "clove" | "cloves" => Unit::Clove,
+            "can" | "cans" | "tin" | "tins" => Unit::Can,
+            "jar" | "jars" => Unit::Jar,
+            "package" | "packages" | "pkg" | "pkgs" | "packet" | "packets" => Unit::Package,
+            "stick" | "sticks" => Unit::Stick,
+            "slice" | "slices" => Unit::Slice,
+            "sprig" | "sprigs" => Unit::Sprig,
+            "bunch" | "bunches" => Unit::Bunch,
+            "head" | "heads" => Unit::Head,
+            "piece" | "pieces" => Unit::Piece,
+            "handful" | "handfuls" => Unit::Handful,
+            _ => return None,
+        };
+        Some(unit)
+    }
+
+    /**
+     * What the unit measures, which system it belongs to and how many
+     * milliliters, grams or centimeters one of it is. Counts like cans and
+     * cloves, and vague amounts like a pinch, have no measure.
+     */
+    pub fn measure(&self) -> Option<(Dimension, System, f64)> {
+        use Dimension::*;
+        use System::*;
+        let measure = match self {
+            Unit::Teaspoon => (Volume, Us, 4.92892),
+            Unit::Tablespoon => (Volume, Us, 14.7868),
+            Unit::FluidOunce => (Volume, Us, 29.5735),
+            Unit::Cup => (Volume, Us, 236.588),
+            Unit::Pint => (Volume, Us, 473.176),
+            Unit::Quart => (Volume, Us, 946.353),
+            Unit::Gallon => (Volume, Us, 3785.41),
+            Unit::Milliliter => (Volume, Metric, 1.0),
+            Unit::Liter => (Volume, Metric, 1000.0),
+            Unit::Milligram => (Mass, Metric, 0.001),
+            Unit::Gram => (Mass, Metric, 1.0),
+            Unit::Kilogram => (Mass, Metric, 1000.0),
+            Unit::Ounce => (Mass, Us, 28.3495),
+            Unit::Pound => (Mass, Us, 453.592),
+            Unit::Inch => (Length, Us, 2.54),
+            Unit::Centimeter => (Length, Metric, 1.0),
+            _ => return None,
+        };
+        Some(measure)
+    }
+
+    // Metric amounts read as decimals ("1.5 kg") rather than fractions
+    pub fn is_metric(&self) -> bool {
+        matches!(
+            self,
+            Unit::Milliliter | Unit::Liter | Unit::Milligram | Unit::Gram | Unit::Kilogram | Unit::Centimeter
+        )
+    }
+
+    // Display name, e.g. "cup" / "cups"
+    pub fn name(&self, plural: bool) -> &'static str {
+        let (one, many) = match self {
+            Unit::Teaspoon => ("tsp", "tsp"),
+            Unit::Tablespoon => ("tbsp", "tbsp"),
+            Unit::FluidOunce => ("fl oz", "fl oz"),
+            Unit::Cup => ("cup", "cups"),
+            Unit::Pint => ("pint", "pints"),
+            Unit::Quart => ("quart", "quarts"),
+            Unit::Gallon => ("gallon", "gallons"),
+            Unit::Milliliter => ("ml", "ml"),
+            Unit::Liter => ("l", "l"),
+            Unit::Milligram => ("mg", "mg"),
+            Unit::Gram => ("g", "g"),
+            Unit::Kilogram => ("kg", "kg"),
+            Unit::Ounce => ("oz", "oz"),
+            Unit::Pound => ("lb", "lb"),
+            Unit::Inch => ("inch", "inches"),
+            Unit::Centimeter => ("cm", "cm"),
+            Unit::Pinch => ("pinch", "pinches"),
+            Unit::Dash => ("dash", "dashes"),
+            Unit::Clove => ("clove", "cloves"),
+            Unit::Can => ("can", "cans"),
+            Unit::Jar => ("jar", "jars"),
+            Unit::Package => ("package", "packages"),
+            Unit::Stick => ("stick", "sticks"),
+            Unit::Slice => ("slice", "slices"),
+            Unit::Sprig => ("sprig", "sprigs"),
+            Unit::Bunch => ("bunch", "bunches"),
+            Unit::Head => ("head", "heads"),
+            Unit::Piece => ("piece", "pieces"),
+            Unit::Handful => ("handful", "handfuls"),
+        };
+        if plural {
+            many
+        } else {
+            one
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::env;
+
+use crate::mock::{Fixtures, MockProvider};
+use crate::openai::OpenAiProvider;
+use crate::provider::{LlmError, LlmProvider};
+
+/**
+ * Which model does what. The defaults are what we've always used with
+ * OpenAI; a local server wants its own names, e.g. LLM_CHAT_MODEL=llama3.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Models {
+    pub chat: String,
+    pub vision: String,
+    pub image: String,
+}
+
+impl Default for Models {
+    fn default() -> Models {
+        Models {
+            chat: String::from("gpt-4"),
+            vision: String::from("gpt-4-vision-preview"),
+            image: String::from("dall-e-2"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Backend {
+    OpenAi,
+    // Anything speaking the OpenAI API, at this base URL
+    Compatible(String),
+    // Fixtures from this JSON file, or none at all
+    Mock(Option<String>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LlmConfig {
+    pub backend: Backend,
+    pub api_key: Option<String>,
+    pub models: Models,
+}
+
+impl LlmConfig {
+    /**
+     * Reads LLM_PROVIDER (openai, the default, compatible or mock),
+     * LLM_BASE_URL for compatible servers, LLM_FIXTURES for the mock,
+     * OPEN_AI_API_KEY and LLM_CHAT_MODEL, LLM_VISION_MODEL and
+     * LLM_IMAGE_MODEL to override the defaults.
+     */
+    pub fn from_env() -> Result<LlmConfig, String> {
+        LlmConfig::from_vars(|name| env::var(name).ok())
+    }
+
+    pub fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<LlmConfig, String> {
+        let backend = match var("LLM_PROVIDER").as_deref() {
+            None | Some("openai") => Backend::OpenAi,
+            Some("compatible") => match var("LLM_BASE_URL") {
+                Some(base_url) => Backend::Compatible(base_url),
+                None => return Err(String::from("LLM_BASE_URL not set")),
+            },
+            Some("mock") => Backend::Mock(var("LLM_FIXTURES")),
+            Some(other) => return Err(format!("Unknown LLM_PROVIDER: {}", other)),
+        };
+        let defaults = Models::default();
+        Ok(LlmConfig {
+            backend,
+            api_key: var("OPEN_AI_API_KEY"),
+            models: Models {
+                chat: var("LLM_CHAT_MODEL").unwrap_or(defaults.chat),
+                vision: var("LLM_VISION_MODEL").unwrap_or(defaults.vision),
+                image: var("LLM_IMAGE_MODEL").unwrap_or(defaults.image),
+            },
+        })
+    }
+
+    pub fn provider(&self) -> Result<Box<dyn LlmProvider>, LlmError> {
+        match &self.backend {
+            Backend::OpenAi => match &self.api_key {
+                Some(api_key) => Ok(Box::new(OpenAiProvider::new(api_key, self.models.clone()))),
+                None => Err(LlmError::new(None, "API Key Not Set")),
+            },
+            Backend::Compatible(base_url) => {
+                Ok(Box::new(OpenAiProvider::compatible(base_url, self.api_key.as_deref(), self.models.clone())))
+            }
+            Backend::Mock(Some(path)) => Ok(Box::new(MockProvider::from_file(path)?)),
+            Backend::Mock(None) => Ok(Box::new(MockProvider::new(Fixtures::default()))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn config(vars: &[(&str, &str)]) -> Result<LlmConfig, String> {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        LlmConfig::from_vars(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn reads_config() {
+        let openai = config(&[("OPEN_AI_API_KEY", "sk-test")]).unwrap();
+        assert_eq!(openai.backend, Backend::OpenAi);
+        assert_eq!(openai.models, Models::default());
+        assert!(openai.provider().is_ok());
+        assert!(config(&[]).unwrap().provider().is_err());
+
+        let local = config(&[("LLM_PROVIDER", "compatible"), ("LLM_BASE_URL", "http://localhost:8080/v1"), ("LLM_CHAT_MODEL", "llama3")]).unwrap();
+        assert_eq!(local.backend, Backend::Compatible(String::from("http://localhost:8080/v1")));
+        assert_eq!(local.models.chat, "llama3");
+        assert_eq!(local.models.image, "dall-e-2");
+        assert!(local.provider().is_ok());
+
+        assert!(config(&[("LLM_PROVIDER", "compatible")]).is_err());
+        assert!(config(&[("LLM_PROVIDER", "claude")]).is_err());
+        assert_eq!(config(&[("LLM_PROVIDER", "mock")]).unwrap().backend, Backend::Mock(None));
+    }
+}
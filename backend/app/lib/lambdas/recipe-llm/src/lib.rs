@@ -0,0 +1,9 @@
+mod config;
+mod mock;
+mod openai;
+mod provider;
+
+pub use config::{Backend, LlmConfig, Models};
+pub use mock::{Fixture, Fixtures, MockProvider};
+pub use openai::OpenAiProvider;
+pub use provider::{LlmError, LlmProvider, TRANSCRIBE_PROMPT};
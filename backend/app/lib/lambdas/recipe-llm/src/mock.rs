@@ -0,0 +1,145 @@
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::provider::{LlmError, LlmProvider};
+
+// A 1x1 PNG, for when the fixtures don't bring an image of their own
+const BLANK_IMAGE: &str = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNk+M9QDwADhgGAWjR9awAAAABJRU5ErkJggg==";
+
+/**
+ * A canned reply, used for the first prompt containing `contains` (or any
+ * prompt, without it). With a `status` the call fails instead, with `reply`
+ * as the message, e.g. a 429 to exercise retries.
+ */
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Fixture {
+    #[serde(default)]
+    pub contains: Option<String>,
+    pub reply: String,
+    #[serde(default)]
+    pub status: Option<u16>,
+}
+
+impl Fixture {
+    pub fn reply(contains: &str, reply: &str) -> Fixture {
+        Fixture { contains: Some(contains.to_string()), reply: reply.to_string(), status: None }
+    }
+
+    fn answer(&self) -> Result<String, LlmError> {
+        match self.status {
+            Some(status) => Err(LlmError::new(Some(status), self.reply.clone())),
+            None => Ok(self.reply.clone()),
+        }
+    }
+}
+
+/**
+ * What a `MockProvider` answers with, e.g. from a JSON file:
+ * { "chat": [{ "contains": "Taco Soup", "reply": "{\"name\": ...}" }],
+ *   "vision": [{ "reply": "Taco Soup\n1 lb ground beef..." }],
+ *   "image": "<base64>" }
+ */
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Fixtures {
+    #[serde(default)]
+    pub chat: Vec<Fixture>,
+    #[serde(default)]
+    pub vision: Vec<Fixture>,
+    #[serde(default)]
+    pub image: Option<String>,
+}
+
+/**
+ * Answers from fixtures instead of a model, the same way every time, and
+ * remembers what it was asked so tests can check the prompts.
+ */
+#[derive(Debug, Default)]
+pub struct MockProvider {
+    fixtures: Fixtures,
+    calls: Mutex<Vec<String>>,
+}
+
+impl MockProvider {
+    pub fn new(fixtures: Fixtures) -> MockProvider {
+        MockProvider { fixtures, calls: Mutex::new(Vec::new()) }
+    }
+
+    pub fn from_file(path: &str) -> Result<MockProvider, LlmError> {
+        let json = std::fs::read_to_string(path).map_err(|e| LlmError::new(None, format!("Error reading {}: {}", path, e)))?;
+        let fixtures = serde_json::from_str(&json).map_err(|e| LlmError::new(None, format!("Error reading {}: {}", path, e)))?;
+        Ok(MockProvider::new(fixtures))
+    }
+
+    // Every prompt it was sent, oldest first
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    fn answer(&self, fixtures: &[Fixture], prompt: &str) -> Result<String, LlmError> {
+        self.calls.lock().unwrap().push(prompt.to_string());
+        fixtures
+            .iter()
+            .find(|f| f.contains.as_deref().is_none_or(|c| prompt.contains(c)))
+            // Not worth retrying, the fixtures won't change
+            .ok_or_else(|| LlmError::new(Some(404), format!("No fixture for {:.80}", prompt)))?
+            .answer()
+    }
+}
+
+#[async_trait]
+impl LlmProvider for MockProvider {
+    async fn chat(&self, prompt: &str) -> Result<String, LlmError> {
+        self.answer(&self.fixtures.chat, prompt)
+    }
+
+    // Like a real model's structured output, without the schema in the prompt
+    async fn chat_json(&self, prompt: &str, _name: &str, _schema: &Value) -> Result<String, LlmError> {
+        self.answer(&self.fixtures.chat, prompt)
+    }
+
+    async fn vision(&self, prompt: &str, image_url: &str) -> Result<String, LlmError> {
+        self.answer(&self.fixtures.vision, &format!("{} {}", prompt, image_url))
+    }
+
+    async fn generate_image(&self, prompt: &str) -> Result<String, LlmError> {
+        self.calls.lock().unwrap().push(prompt.to_string());
+        Ok(self.fixtures.image.clone().unwrap_or_else(|| BLANK_IMAGE.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn answers_from_fixtures() {
+        let mock = MockProvider::new(Fixtures {
+            chat: vec![
+                Fixture { contains: Some(String::from("busy")), reply: String::from("Rate limit reached"), status: Some(429) },
+                Fixture::reply("Taco Soup", "{\"name\": \"Taco Soup\"}"),
+            ],
+            vision: vec![Fixture { contains: None, reply: String::from("Grandma's Pie"), status: None }],
+            image: None,
+        });
+        assert_eq!(mock.chat("Parse this: Taco Soup, 1 lb beef").await.unwrap(), "{\"name\": \"Taco Soup\"}");
+        assert_eq!(mock.chat("busy").await.unwrap_err().status, Some(429));
+        assert_eq!(mock.chat("Chili").await.unwrap_err().status, Some(404));
+        assert_eq!(mock.vision("What is the text in this image?", "https://example.com/card.png").await.unwrap(), "Grandma's Pie");
+        assert_eq!(mock.generate_image("A realistic photo of Taco Soup").await.unwrap(), BLANK_IMAGE);
+        assert_eq!(mock.calls().len(), 5);
+        assert_eq!(mock.calls()[4], "A realistic photo of Taco Soup");
+    }
+
+    #[tokio::test]
+    async fn reads_fixture_files() {
+        let mock = MockProvider::from_file(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/taco_soup.json")).unwrap();
+        let reply = mock.chat("Parse the recipe... Taco Soup\n1 lb ground beef").await.unwrap();
+        // Wrapped in prose, like GPT-4 tends to
+        assert!(reply.starts_with("Here is the recipe:"));
+        assert!(reply.contains("\"name\": \"Taco Soup\""));
+        assert!(MockProvider::from_file("missing.json").is_err());
+    }
+}
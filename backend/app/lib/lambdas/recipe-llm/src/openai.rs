@@ -0,0 +1,172 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use crate::config::Models;
+use crate::provider::{LlmError, LlmProvider};
+
+pub const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+
+// Enough for the text of a recipe card
+const VISION_MAX_TOKENS: u32 = 300;
+
+/**
+ * The OpenAI API over HTTP. Servers that copy it, like llama.cpp's server
+ * or vLLM, work the same way from their own base URL, usually without a key.
+ */
+#[derive(Debug, Clone)]
+pub struct OpenAiProvider {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    models: Models,
+}
+
+impl OpenAiProvider {
+    pub fn new(api_key: &str, models: Models) -> OpenAiProvider {
+        OpenAiProvider::compatible(OPENAI_BASE_URL, Some(api_key), models)
+    }
+
+    // e.g. http://localhost:8080/v1
+    pub fn compatible(base_url: &str, api_key: Option<&str>, models: Models) -> OpenAiProvider {
+        OpenAiProvider {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: api_key.map(String::from),
+            models,
+        }
+    }
+
+    async fn post(&self, path: &str, body: &Value) -> Result<Value, LlmError> {
+        let mut request = self.client.post(format!("{}{}", self.base_url, path)).json(body);
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| LlmError::new(e.status().map(|s| s.as_u16()), format!("Error calling {}: {}", self.base_url, e)))?;
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| LlmError::new(None, format!("Error reading response: {}", e)))?;
+        if !status.is_success() {
+            return Err(LlmError::new(Some(status.as_u16()), text));
+        }
+        serde_json::from_str(&text).map_err(|e| LlmError::new(None, format!("Error reading response: {}", e)))
+    }
+}
+
+pub fn chat_body(model: &str, prompt: &str) -> Value {
+    json!({
+        "model": model,
+        "messages": [{ "role": "user", "content": prompt }],
+    })
+}
+
+// Forcing a call to a function taking `schema` is the most widely supported
+// way to get JSON in a given shape; gpt-4, llama.cpp and vLLM all honour it
+pub fn function_body(model: &str, prompt: &str, name: &str, schema: &Value) -> Value {
+    json!({
+        "model": model,
+        "messages": [{ "role": "user", "content": prompt }],
+        "tools": [{ "type": "function", "function": { "name": name, "parameters": schema } }],
+        "tool_choice": { "type": "function", "function": { "name": name } },
+    })
+}
+
+pub fn vision_body(model: &str, prompt: &str, image_url: &str) -> Value {
+    json!({
+        "model": model,
+        "messages": [{
+            "role": "user",
+            "content": [
+                { "type": "text", "text": prompt },
+                { "type": "image_url", "image_url": { "url": image_url } },
+            ],
+        }],
+        "max_tokens": VISION_MAX_TOKENS,
+    })
+}
+
+// b64_json saves downloading the image again from OpenAI's short lived URL
+pub fn image_body(model: &str, prompt: &str) -> Value {
+    json!({
+        "model": model,
+        "prompt": prompt,
+        "n": 1,
+        "response_format": "b64_json",
+    })
+}
+
+pub fn chat_reply(response: &Value) -> Result<String, LlmError> {
+    response["choices"][0]["message"]["content"]
+        .as_str()
+        .map(String::from)
+        .ok_or_else(|| LlmError::new(None, "Could not get message content"))
+}
+
+// Servers that ignore tool_choice answer in the message instead
+pub fn function_reply(response: &Value) -> Result<String, LlmError> {
+    match response["choices"][0]["message"]["tool_calls"][0]["function"]["arguments"].as_str() {
+        Some(arguments) => Ok(arguments.to_string()),
+        None => chat_reply(response),
+    }
+}
+
+pub fn image_reply(response: &Value) -> Result<String, LlmError> {
+    response["data"][0]["b64_json"]
+        .as_str()
+        .map(String::from)
+        .ok_or_else(|| LlmError::new(None, "No image in response"))
+}
+
+#[async_trait]
+impl LlmProvider for OpenAiProvider {
+    async fn chat(&self, prompt: &str) -> Result<String, LlmError> {
+        chat_reply(&self.post("/chat/completions", &chat_body(&self.models.chat, prompt)).await?)
+    }
+
+    async fn chat_json(&self, prompt: &str, name: &str, schema: &Value) -> Result<String, LlmError> {
+        function_reply(&self.post("/chat/completions", &function_body(&self.models.chat, prompt, name, schema)).await?)
+    }
+
+    async fn vision(&self, prompt: &str, image_url: &str) -> Result<String, LlmError> {
+        chat_reply(&self.post("/chat/completions", &vision_body(&self.models.vision, prompt, image_url)).await?)
+    }
+
+    async fn generate_image(&self, prompt: &str) -> Result<String, LlmError> {
+        image_reply(&self.post("/images/generations", &image_body(&self.models.image, prompt)).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_requests() {
+        let body = vision_body("gpt-4-vision-preview", "What is the text in this image?", "https://example.com/card.png");
+        assert_eq!(body["model"], "gpt-4-vision-preview");
+        assert_eq!(body["messages"][0]["content"][1]["image_url"]["url"], "https://example.com/card.png");
+        assert_eq!(chat_body("llama3", "Parse this")["messages"][0]["content"], "Parse this");
+        assert_eq!(image_body("dall-e-2", "Taco soup")["response_format"], "b64_json");
+        let function = function_body("gpt-4", "Parse this", "recipe", &json!({ "type": "object" }));
+        assert_eq!(function["tools"][0]["function"]["parameters"]["type"], "object");
+        assert_eq!(function["tool_choice"]["function"]["name"], "recipe");
+
+        let provider = OpenAiProvider::compatible("http://localhost:8080/v1/", None, Models::default());
+        assert_eq!(provider.base_url, "http://localhost:8080/v1");
+    }
+
+    #[test]
+    fn reads_replies() {
+        let chat = json!({ "choices": [{ "message": { "role": "assistant", "content": "{\"name\": \"Taco Soup\"}" } }] });
+        assert_eq!(chat_reply(&chat).unwrap(), "{\"name\": \"Taco Soup\"}");
+        assert!(chat_reply(&json!({ "choices": [] })).is_err());
+        let call = json!({ "choices": [{ "message": { "tool_calls": [{ "function": { "name": "recipe", "arguments": "{\"name\": \"Taco Soup\"}" } }] } }] });
+        assert_eq!(function_reply(&call).unwrap(), "{\"name\": \"Taco Soup\"}");
+        assert_eq!(function_reply(&chat).unwrap(), "{\"name\": \"Taco Soup\"}");
+        assert_eq!(image_reply(&json!({ "data": [{ "b64_json": "aGk=" }] })).unwrap(), "aGk=");
+    }
+}
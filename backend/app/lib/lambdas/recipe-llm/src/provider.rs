@@ -0,0 +1,64 @@
+use async_trait::async_trait;
+use serde_json::Value;
+
+pub const TRANSCRIBE_PROMPT: &str = "What is the text in this image?";
+
+/**
+ * Why a model call failed. `status` is the HTTP status the provider
+ * answered with, if it answered at all, so callers can tell a rate limit
+ * from a bad key from a dropped connection.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct LlmError {
+    pub status: Option<u16>,
+    pub message: String,
+}
+
+impl LlmError {
+    pub fn new(status: Option<u16>, message: impl Into<String>) -> LlmError {
+        LlmError { status, message: message.into() }
+    }
+}
+
+impl std::fmt::Display for LlmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.status {
+            Some(status) => write!(f, "{} {}", status, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for LlmError {}
+
+/**
+ * Everything we ask a model for. `OpenAiProvider` talks to OpenAI or any
+ * server with the same API, `MockProvider` answers from fixtures so the
+ * import pipeline can be tested without a network.
+ */
+#[async_trait]
+pub trait LlmProvider: Send + Sync {
+    // The model's reply to a single user message
+    async fn chat(&self, prompt: &str) -> Result<String, LlmError>;
+
+    /**
+     * The model's reply to `prompt` as a JSON object matching `schema`, a
+     * JSON Schema called `name`. Providers that can constrain their output
+     * should; this fallback only describes the schema in the prompt, so the
+     * reply may still have prose around it or not match.
+     */
+    async fn chat_json(&self, prompt: &str, name: &str, schema: &Value) -> Result<String, LlmError> {
+        self.chat(&format!("{}\n\nAnswer with a single {} JSON object matching this schema: {}", prompt, name, schema)).await
+    }
+
+    // The model's reply to a message about the image at `image_url`
+    async fn vision(&self, prompt: &str, image_url: &str) -> Result<String, LlmError>;
+
+    // A base64 encoded image of `prompt`
+    async fn generate_image(&self, prompt: &str) -> Result<String, LlmError>;
+
+    // The text in the image at `image_url`, e.g. a photo of a recipe card
+    async fn transcribe(&self, image_url: &str) -> Result<String, LlmError> {
+        self.vision(TRANSCRIBE_PROMPT, image_url).await
+    }
+}
@@ -0,0 +1,163 @@
+use recipe_ingredients::{lookup_density, Dimension, Ingredient, Unit};
+use serde::{Deserialize, Serialize};
+
+use crate::foods::{find, Food};
+use crate::nutrients::Nutrients;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct IngredientNutrition {
+    pub ingredient: String,
+    // The SR Legacy description we matched, if any
+    pub food: Option<String>,
+    pub grams: Option<f64>,
+    // 0 to 1, how sure we are of both the food match and the weight
+    pub confidence: f64,
+    pub nutrients: Nutrients,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Nutrition {
+    pub servings: Option<f64>,
+    pub total: Nutrients,
+    pub per_serving: Option<Nutrients>,
+    // Share of ingredients we could match and weigh, weighted by confidence
+    pub confidence: f64,
+    pub ingredients: Vec<IngredientNutrition>,
+}
+
+/**
+ * Estimates calories and macros for a recipe from its parsed ingredients,
+ * entirely from the bundled SR Legacy extract. Lines we can't match or
+ * weigh, like "salt to taste", count as nothing and have confidence 0.
+ */
+pub fn estimate(ingredients: &[Ingredient], servings: Option<f64>) -> Nutrition {
+    let ingredients: Vec<IngredientNutrition> = ingredients.iter().map(estimate_one).collect();
+    let total = ingredients
+        .iter()
+        .fold(Nutrients::default(), |sum, i| sum + i.nutrients);
+    let confidence = if ingredients.is_empty() {
+        0.0
+    } else {
+        ingredients.iter().map(|i| i.confidence).sum::<f64>() / ingredients.len() as f64
+    };
+    let servings = servings.filter(|s| *s > 0.0);
+
+    Nutrition {
+        servings,
+        total: total.rounded(),
+        per_serving: servings.map(|s| total.scale(1.0 / s).rounded()),
+        confidence: round(confidence, 100.0),
+        ingredients,
+    }
+}
+
+fn estimate_one(ingredient: &Ingredient) -> IngredientNutrition {
+    let mut nutrition = IngredientNutrition {
+        ingredient: ingredient.raw.clone(),
+        food: None,
+        grams: None,
+        confidence: 0.0,
+        nutrients: Nutrients::default(),
+    };
+    let (food, match_confidence) = match find(&ingredient.item) {
+        Some(f) => f,
+        None => return nutrition,
+    };
+    nutrition.food = Some(food.description.clone());
+    let (grams, weight_confidence) = match weigh(ingredient, food) {
+        Some(w) => w,
+        None => return nutrition,
+    };
+    nutrition.grams = Some(round(grams, 10.0));
+    nutrition.confidence = round(match_confidence * weight_confidence, 100.0);
+    nutrition.nutrients = food.per_100g.scale(grams / 100.0).rounded();
+    nutrition
+}
+
+fn round(value: f64, precision: f64) -> f64 {
+    (value * precision).round() / precision
+}
+
+// How many grams the line is, and how sure we are of that
+fn weigh(ingredient: &Ingredient, food: &Food) -> Option<(f64, f64)> {
+    let quantity = ingredient.quantity.as_ref()?;
+    // Ranges count as their midpoint
+    let amount = match quantity.max {
+        Some(max) => (quantity.value + max) / 2.0,
+        None => quantity.value,
+    };
+
+    let unit = match ingredient.unit {
+        Some(u) => u,
+        None => return food.piece_grams.map(|g| (amount * g, 0.8)),
+    };
+    if let Some((dimension, _, size)) = unit.measure() {
+        return match dimension {
+            Dimension::Mass => Some((amount * size, 1.0)),
+            Dimension::Volume => match lookup_density(&ingredient.item) {
+                Some(density) => Some((amount * size * density.grams_per_ml(), 0.9)),
+                // Close enough for watery things, a guess for anything else
+                None => Some((amount * size, 0.5)),
+            },
+            Dimension::Length => None,
+        };
+    }
+
+    let (grams, confidence) = match unit {
+        Unit::Pinch => (0.3, 0.9),
+        Unit::Dash => (0.6, 0.9),
+        Unit::Stick => (food.piece_grams.unwrap_or(113.0), 0.9),
+        Unit::Clove | Unit::Slice | Unit::Piece | Unit::Head => (food.piece_grams?, 0.7),
+        Unit::Can => (400.0, 0.6),
+        Unit::Jar | Unit::Package => (450.0, 0.4),
+        Unit::Handful => (30.0, 0.5),
+        Unit::Sprig => (1.0, 0.7),
+        Unit::Bunch => (60.0, 0.5),
+        _ => return None,
+    };
+    Some((amount * grams, confidence))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use recipe_ingredients::parse;
+
+    fn lines(lines: &[&str]) -> Vec<Ingredient> {
+        lines.iter().map(|l| parse(l)).collect()
+    }
+
+    #[test]
+    fn estimates_a_recipe() {
+        let ingredients = lines(&["1 cup all-purpose flour", "1/2 cup sugar", "2 large eggs", "1 stick butter, melted"]);
+        let nutrition = estimate(&ingredients, Some(4.0));
+
+        // 125 g flour, 100 g sugar, 100 g egg and 113 g butter
+        let grams: Vec<f64> = nutrition.ingredients.iter().map(|i| i.grams.unwrap()).collect();
+        assert_eq!(grams, vec![125.0, 100.0, 100.0, 113.0]);
+        assert_eq!(nutrition.total.calories, 1795.2);
+        assert_eq!(nutrition.per_serving.unwrap().calories, 448.8);
+        assert!(nutrition.total.protein > 25.0);
+        assert!(nutrition.confidence > 0.8);
+    }
+
+    #[test]
+    fn handles_unknown_ingredients() {
+        let nutrition = estimate(&lines(&["Salt and pepper to taste", "2 cups dragon fruit"]), None);
+        assert_eq!(nutrition.per_serving, None);
+        assert_eq!(nutrition.total, Nutrients::default());
+        assert_eq!(nutrition.confidence, 0.0);
+        assert_eq!(nutrition.ingredients[1].food, None);
+    }
+
+    #[test]
+    fn weighs_mass_and_pieces() {
+        let nutrition = estimate(&lines(&["200 g chicken breast", "3 cloves garlic", "1-2 bananas"]), None);
+        let first = &nutrition.ingredients[0];
+        assert_eq!(first.grams, Some(200.0));
+        assert_eq!(first.confidence, 1.0);
+        assert_eq!(first.nutrients.protein, 45.0);
+        assert_eq!(nutrition.ingredients[1].grams, Some(9.0));
+        assert_eq!(nutrition.ingredients[2].grams, Some(177.0));
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+use crate::nutrients::Nutrients;
+
+// Our extract of USDA FoodData Central SR Legacy. Each line is
+// `description;kcal;protein;fat;carbohydrate;fiber;sugars;sodium;grams per piece;names`
+// with nutrients per 100 g.
+const SR_LEGACY: &str = include_str!("../data/sr_legacy.csv");
+
+// Fuzzy matches below this share of words in common aren't worth using
+const MIN_OVERLAP: f64 = 0.5;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Food {
+    // The SR Legacy description, e.g. "Butter, without salt"
+    pub description: String,
+    pub per_100g: Nutrients,
+    // What one egg, clove or slice weighs, for lines without a unit
+    pub piece_grams: Option<f64>,
+}
+
+struct Table {
+    foods: Vec<Food>,
+    names: HashMap<String, usize>,
+}
+
+fn table() -> &'static Table {
+    static TABLE: OnceLock<Table> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = Table { foods: Vec::new(), names: HashMap::new() };
+        for line in SR_LEGACY.lines().filter(|l| !l.is_empty() && !l.starts_with('#')) {
+            let fields: Vec<&str> = line.split(';').map(str::trim).collect();
+            if fields.len() != 10 {
+                continue;
+            }
+            let number = |i: usize| fields[i].parse::<f64>().unwrap_or_default();
+            let index = table.foods.len();
+            table.foods.push(Food {
+                description: fields[0].to_string(),
+                per_100g: Nutrients {
+                    calories: number(1),
+                    protein: number(2),
+                    fat: number(3),
+                    carbohydrates: number(4),
+                    fiber: number(5),
+                    sugar: number(6),
+                    sodium: number(7),
+                },
+                piece_grams: fields[8].parse().ok(),
+            });
+            for name in fields[9].split('|') {
+                let name = normalize(name);
+                if !name.is_empty() {
+                    table.names.entry(name).or_insert(index);
+                }
+            }
+        }
+        table
+    })
+}
+
+fn normalize(name: &str) -> String {
+    name.to_lowercase()
+        .replace(['’', '\''], "")
+        .split(|c: char| !(c.is_alphanumeric() || c == '-' || c == '%'))
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+fn singular(word: &str) -> &str {
+    word.strip_suffix("es")
+        .filter(|w| w.ends_with("toe") || w.ends_with("ch") || w.ends_with("sh"))
+        .or_else(|| word.strip_suffix('s').filter(|w| !w.ends_with('s')))
+        .unwrap_or(word)
+}
+
+fn words(name: &str) -> HashSet<&str> {
+    name.split(' ').map(singular).collect()
+}
+
+/**
+ * Matches a parsed ingredient name to a food and says how sure we are: 1.0
+ * for one of the food's names, 0.8 when the ingredient ends with one, like
+ * "sifted cake flour", and less for names that only share most of their
+ * words, like "boneless chicken breast halves".
+ */
+pub fn find(item: &str) -> Option<(&'static Food, f64)> {
+    let table = table();
+    let name = normalize(item);
+    if name.is_empty() {
+        return None;
+    }
+    let singular_name = name.split(' ').map(singular).collect::<Vec<&str>>().join(" ");
+    for candidate in [&name, &singular_name] {
+        if let Some(index) = table.names.get(candidate) {
+            return Some((&table.foods[*index], 1.0));
+        }
+    }
+
+    let parts: Vec<&str> = name.split(' ').collect();
+    for start in 1..parts.len() {
+        if let Some(index) = table.names.get(&parts[start..].join(" ")) {
+            return Some((&table.foods[*index], 0.8));
+        }
+    }
+
+    let item_words = words(&name);
+    table
+        .names
+        .iter()
+        .map(|(known, index)| {
+            let known_words = words(known);
+            let shared = item_words.intersection(&known_words).count() as f64;
+            (shared / item_words.union(&known_words).count() as f64, *index)
+        })
+        .filter(|(overlap, _)| *overlap >= MIN_OVERLAP)
+        .max_by(|a, b| a.0.total_cmp(&b.0).then(b.1.cmp(&a.1)))
+        .map(|(overlap, index)| (&table.foods[index], 0.7 * overlap))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_the_extract() {
+        assert!(table().foods.len() > 150);
+        let (butter, _) = find("butter").unwrap();
+        assert_eq!(butter.description, "Butter, without salt");
+        assert_eq!(butter.per_100g.calories, 717.0);
+        assert_eq!(butter.piece_grams, Some(113.0));
+    }
+
+    #[test]
+    fn scores_matches() {
+        assert_eq!(find("Large Eggs").unwrap().1, 1.0);
+        assert_eq!(find("tomatoes").unwrap().0.description, "Tomatoes, red, ripe, raw, year round average");
+        let (flour, confidence) = find("sifted cake flour").unwrap();
+        assert_eq!(flour.description, "Wheat flour, white, cake, enriched");
+        assert_eq!(confidence, 0.8);
+        let (chicken, confidence) = find("boneless chicken breast halves").unwrap();
+        assert!(chicken.description.starts_with("Chicken, broilers or fryers, breast"));
+        assert!(confidence > 0.3 && confidence < 0.8);
+    }
+
+    #[test]
+    fn leaves_unknown_foods_unmatched() {
+        assert_eq!(find("dragon fruit"), None);
+        assert_eq!(find(""), None);
+    }
+}
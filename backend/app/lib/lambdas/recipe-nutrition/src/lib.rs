@@ -0,0 +1,7 @@
+mod estimate;
+mod foods;
+mod nutrients;
+
+pub use estimate::{estimate, IngredientNutrition, Nutrition};
+pub use foods::{find, Food};
+pub use nutrients::Nutrients;
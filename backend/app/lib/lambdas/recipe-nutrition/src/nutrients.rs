@@ -0,0 +1,65 @@
+use std::ops::{Add, AddAssign};
+
+use serde::{Deserialize, Serialize};
+
+// Calories in kcal, sodium in mg, everything else in grams
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct Nutrients {
+    pub calories: f64,
+    pub protein: f64,
+    pub fat: f64,
+    pub carbohydrates: f64,
+    pub fiber: f64,
+    pub sugar: f64,
+    pub sodium: f64,
+}
+
+impl Nutrients {
+    pub fn scale(&self, factor: f64) -> Nutrients {
+        Nutrients {
+            calories: self.calories * factor,
+            protein: self.protein * factor,
+            fat: self.fat * factor,
+            carbohydrates: self.carbohydrates * factor,
+            fiber: self.fiber * factor,
+            sugar: self.sugar * factor,
+            sodium: self.sodium * factor,
+        }
+    }
+
+    // One decimal place is as precise as an estimate like this gets
+    pub fn rounded(&self) -> Nutrients {
+        let round = |v: f64| (v * 10.0).round() / 10.0;
+        Nutrients {
+            calories: round(self.calories),
+            protein: round(self.protein),
+            fat: round(self.fat),
+            carbohydrates: round(self.carbohydrates),
+            fiber: round(self.fiber),
+            sugar: round(self.sugar),
+            sodium: round(self.sodium),
+        }
+    }
+}
+
+impl Add for Nutrients {
+    type Output = Nutrients;
+
+    fn add(self, other: Nutrients) -> Nutrients {
+        Nutrients {
+            calories: self.calories + other.calories,
+            protein: self.protein + other.protein,
+            fat: self.fat + other.fat,
+            carbohydrates: self.carbohydrates + other.carbohydrates,
+            fiber: self.fiber + other.fiber,
+            sugar: self.sugar + other.sugar,
+            sodium: self.sodium + other.sodium,
+        }
+    }
+}
+
+impl AddAssign for Nutrients {
+    fn add_assign(&mut self, other: Nutrients) {
+        *self = *self + other;
+    }
+}
@@ -0,0 +1,133 @@
+use recipe_core::Recipe;
+
+use crate::card::write_recipe;
+use crate::fonts::{printable, text_width, Style, PT};
+use crate::page::{line_height, Writer, CONTENT_WIDTH, MARGIN, PAGE_HEIGHT, PAGE_WIDTH};
+
+const CONTENTS: f32 = 10.0;
+const CONTENTS_HEADING: f32 = 16.0;
+
+// The text cut down with an ellipsis until it fits `width` mm
+fn fit(text: &str, style: Style, size: f32, width: f32) -> String {
+    let mut text = printable(text);
+    if text_width(&text, style, size) <= width {
+        return text;
+    }
+    while !text.is_empty() && text_width(&format!("{}…", text), style, size) > width {
+        text.pop();
+    }
+    format!("{}…", text.trim_end())
+}
+
+// How many contents lines go on a page, under the heading the first one has
+fn contents_per_page() -> usize {
+    ((PAGE_HEIGHT - 2.0 * MARGIN - line_height(CONTENTS_HEADING) - 4.0) / line_height(CONTENTS)).floor() as usize
+}
+
+/**
+ * A whole recipe book as one PDF: a title page, the contents with the page
+ * each recipe starts on, then every recipe from the top of a new page.
+ * Each recipe is also in the PDF's outline so viewers can jump to it.
+ */
+pub fn render_book(title: &str, recipes: &[(&Recipe, Option<&[u8]>)]) -> Result<Vec<u8>, String> {
+    let mut writer = Writer::new(title)?;
+
+    // 1. Title page
+    writer.gap(PAGE_HEIGHT / 3.0);
+    writer.centred(title, Style::Bold, 24.0);
+    writer.gap(4.0);
+    let count = match recipes.len() {
+        1 => String::from("1 recipe"),
+        n => format!("{} recipes", n),
+    };
+    writer.centred(&count, Style::Italic, 11.0);
+
+    // 2. Pages for the contents, written once we know where each recipe lands
+    let per_page = contents_per_page();
+    let contents_pages = recipes.len().div_ceil(per_page).max(1);
+    let first_contents = writer.page_number() + 1;
+    for _ in 0..contents_pages {
+        writer.new_page();
+    }
+
+    // 3. The recipes
+    let mut starts = Vec::new();
+    for (recipe, image) in recipes {
+        writer.new_page();
+        starts.push(writer.page_number());
+        writer.bookmark(&recipe.name, writer.page_number());
+        write_recipe(&mut writer, recipe, *image);
+    }
+    for page in 2..=writer.page_number() {
+        let number = page.to_string();
+        let x = (PAGE_WIDTH - text_width(&number, Style::Regular, 8.0)) / 2.0;
+        writer.text_at(page, &number, Style::Regular, 8.0, x, MARGIN / 2.0);
+    }
+
+    // 4. The contents, with dots leading from each name to its page
+    let top = PAGE_HEIGHT - MARGIN;
+    writer.text_at(first_contents, "Contents", Style::Bold, CONTENTS_HEADING, MARGIN, top - CONTENTS_HEADING * PT);
+    let first_line = top - line_height(CONTENTS_HEADING) - 4.0;
+    let dot = text_width(".", Style::Regular, CONTENTS);
+    for (i, ((recipe, _), start)) in recipes.iter().zip(&starts).enumerate() {
+        let page = first_contents + i / per_page;
+        let baseline = first_line - (i % per_page) as f32 * line_height(CONTENTS) - CONTENTS * PT;
+        let number = start.to_string();
+        let number_width = text_width(&number, Style::Regular, CONTENTS);
+        let name = fit(&recipe.name, Style::Regular, CONTENTS, CONTENT_WIDTH - number_width - 6.0 * dot);
+        let name_width = text_width(&name, Style::Regular, CONTENTS);
+        let dots = (((CONTENT_WIDTH - name_width - number_width) / dot).floor() as usize).saturating_sub(2);
+        writer.text_at(page, &name, Style::Regular, CONTENTS, MARGIN, baseline);
+        writer.text_at(page, &".".repeat(dots), Style::Regular, CONTENTS, MARGIN + name_width + dot, baseline);
+        writer.text_at(page, &number, Style::Regular, CONTENTS, MARGIN + CONTENT_WIDTH - number_width, baseline);
+    }
+
+    writer.save()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::tests::{pages, png};
+    use recipe_core::test_support::taco_soup;
+
+    #[test]
+    fn renders_book() {
+        let soup = taco_soup();
+        let mut long = taco_soup();
+        long.name = String::from("Chili");
+        long.instructions = (1..=60).map(|i| format!("Step {} of a very long recipe that goes on and on about the chili.", i)).collect();
+        let gyoza = Recipe::new("abc", "Gyoza");
+        let image = png();
+        let pdf = render_book("Family Recipes", &[(&soup, Some(&image)), (&long, None), (&gyoza, None)]).unwrap();
+        let pages = pages(&pdf);
+
+        // Title, contents, soup, two or more of chili, then gyoza last
+        assert!(pages.len() >= 6);
+        assert!(pages[0].contains("Family Recipes"));
+        assert!(pages[0].contains("3 recipes"));
+        assert!(pages[1].contains("Contents"));
+        assert!(pages[2].contains("Taco Soup"));
+        assert!(pages[3].contains("Chili"));
+        assert!(pages.last().unwrap().contains("Gyoza"));
+        let gyoza_page = pages.len().to_string();
+        assert!(pages[1].contains(&gyoza_page));
+    }
+
+    #[test]
+    fn fits_names() {
+        assert_eq!(fit("Taco Soup", Style::Regular, 10.0, 50.0), "Taco Soup");
+        let name = fit("The Very Best Slow Cooker Taco Soup With All The Fixings", Style::Regular, 10.0, 50.0);
+        assert!(name.ends_with('…'));
+        assert!(text_width(&name, Style::Regular, 10.0) <= 50.0);
+    }
+
+    #[test]
+    fn spreads_contents_over_pages() {
+        let recipes: Vec<Recipe> = (0..contents_per_page() + 1).map(|i| Recipe::new(&i.to_string(), &format!("Soup {}", i))).collect();
+        let recipes: Vec<(&Recipe, Option<&[u8]>)> = recipes.iter().map(|r| (r, None)).collect();
+        let pages = pages(&render_book("Soups", &recipes).unwrap());
+        assert_eq!(pages.len(), 1 + 2 + recipes.len());
+        assert!(pages[2].contains(&format!("Soup {}", recipes.len() - 1)));
+    }
+}
@@ -0,0 +1,208 @@
+use recipe_core::Recipe;
+use recipe_export::to_words;
+
+use crate::fonts::{wrap, Style};
+use crate::page::{line_height, Writer, CONTENT_WIDTH, MARGIN};
+
+const BODY: f32 = 9.5;
+const HEADING: f32 = 11.0;
+const GUTTER: f32 = 6.0;
+// How far wrapped lines of an ingredient or step sit in from its first
+const INDENT: f32 = 4.0;
+// The picture takes at most about a third of a page
+const IMAGE_HEIGHT: f32 = 65.0;
+
+// "By Grandma · From Serious Eats"
+fn byline(recipe: &Recipe) -> Option<String> {
+    let parts: Vec<String> = [recipe.author.as_ref().map(|a| format!("By {}", a)), recipe.credit.as_ref().map(|c| format!("From {}", c))]
+        .into_iter()
+        .flatten()
+        .collect();
+    Some(parts.join(" · ")).filter(|b| !b.is_empty())
+}
+
+// "Makes 4 servings · 35 mins"
+fn details(recipe: &Recipe) -> Option<String> {
+    let parts: Vec<String> = [recipe.recipe_yield.as_ref().map(|y| format!("Makes {}", y)), recipe.total_time.as_deref().map(to_words)]
+        .into_iter()
+        .flatten()
+        .collect();
+    Some(parts.join(" · ")).filter(|d| !d.is_empty())
+}
+
+// A heading, kept on the same page as the first few lines under it
+fn heading(writer: &mut Writer, text: &str) {
+    writer.keep(line_height(HEADING) + 3.0 * line_height(BODY) + 4.0);
+    writer.gap(4.0);
+    writer.line(text, Style::Bold, HEADING, MARGIN);
+    writer.rule(MARGIN, CONTENT_WIDTH);
+    writer.gap(1.5);
+}
+
+/**
+ * Ingredients side by side in two columns, split so the columns come out
+ * even. A list too long for what's left of the page fills both columns
+ * and carries on over the page.
+ */
+fn ingredient_columns(writer: &mut Writer, recipe: &Recipe) {
+    let width = (CONTENT_WIDTH - GUTTER) / 2.0;
+    let height = line_height(BODY);
+    let mut blocks: Vec<Vec<String>> = recipe.ingredients.iter().map(|i| wrap(&i.raw, Style::Regular, BODY, width - INDENT)).filter(|b| !b.is_empty()).collect();
+    while !blocks.is_empty() {
+        let room = ((writer.y - MARGIN) / height).floor() as usize;
+        if room == 0 {
+            writer.new_page();
+            continue;
+        }
+        let total: usize = blocks.iter().map(Vec::len).sum();
+        let per_column = if total <= 2 * room { total.div_ceil(2) } else { room };
+
+        // Every column takes at least one ingredient, however long, so this always moves on
+        let mut columns: Vec<Vec<(f32, String)>> = Vec::new();
+        for (i, limit) in [per_column, room].into_iter().enumerate() {
+            let x = MARGIN + i as f32 * (width + GUTTER);
+            let mut column: Vec<(f32, String)> = Vec::new();
+            while let Some(block) = blocks.first() {
+                if !column.is_empty() && column.len() + block.len() > limit {
+                    break;
+                }
+                for (j, line) in blocks.remove(0).into_iter().enumerate() {
+                    column.push((if j == 0 { x } else { x + INDENT }, line));
+                }
+            }
+            columns.push(column);
+        }
+        writer.row(&columns, Style::Regular, BODY);
+        if !blocks.is_empty() {
+            writer.new_page();
+        }
+    }
+}
+
+/**
+ * Lays a recipe out from wherever the writer is up to: the title, who
+ * it's by and how much it makes, the picture, ingredients in columns,
+ * numbered steps and notes. A long recipe runs onto as many pages as it
+ * needs.
+ */
+pub(crate) fn write_recipe(writer: &mut Writer, recipe: &Recipe, image: Option<&[u8]>) {
+    // 1. Title and details
+    writer.paragraph(&recipe.name, Style::Bold, 18.0, MARGIN, CONTENT_WIDTH);
+    if let Some(byline) = byline(recipe) {
+        writer.paragraph(&byline, Style::Italic, 9.0, MARGIN, CONTENT_WIDTH);
+    }
+    if let Some(details) = details(recipe) {
+        writer.paragraph(&details, Style::Regular, 9.0, MARGIN, CONTENT_WIDTH);
+    }
+    if !recipe.summary.is_empty() {
+        writer.gap(2.0);
+        writer.paragraph(&recipe.summary, Style::Regular, BODY, MARGIN, CONTENT_WIDTH);
+    }
+    if let Some(image) = image {
+        writer.gap(4.0);
+        writer.image(image, IMAGE_HEIGHT);
+    }
+
+    // 2. Ingredients
+    if !recipe.ingredients.is_empty() {
+        heading(writer, "Ingredients");
+        ingredient_columns(writer, recipe);
+    }
+
+    // 3. Steps, numbered with the text hanging clear of the number
+    if !recipe.instructions.is_empty() {
+        heading(writer, "Instructions");
+        let x = MARGIN + 2.0 * INDENT;
+        for (i, step) in recipe.instructions.iter().enumerate() {
+            for (j, line) in wrap(step, Style::Regular, BODY, CONTENT_WIDTH - 2.0 * INDENT).iter().enumerate() {
+                if j == 0 {
+                    writer.keep(line_height(BODY));
+                    writer.line(&format!("{}.", i + 1), Style::Bold, BODY, MARGIN);
+                    writer.gap(-line_height(BODY));
+                }
+                writer.line(line, Style::Regular, BODY, x);
+            }
+            writer.gap(1.5);
+        }
+    }
+
+    // 4. Notes
+    if !recipe.notes.is_empty() {
+        heading(writer, "Notes");
+        for paragraph in recipe.notes.lines().filter(|l| !l.trim().is_empty()) {
+            writer.paragraph(paragraph, Style::Italic, BODY, MARGIN, CONTENT_WIDTH);
+        }
+    }
+}
+
+/**
+ * A printable card for one recipe, as a PDF. `image` is the recipe's
+ * picture if the caller has it; nothing here goes out to fetch one.
+ */
+pub fn render_card(recipe: &Recipe, image: Option<&[u8]>) -> Result<Vec<u8>, String> {
+    let mut writer = Writer::new(&recipe.name)?;
+    write_recipe(&mut writer, recipe, image);
+    writer.save()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use printpdf::image_crate::{DynamicImage, ImageOutputFormat, RgbImage};
+    use printpdf::lopdf::Document;
+    use recipe_core::test_support::taco_soup;
+    use std::io::Cursor;
+
+    pub(crate) fn png() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        DynamicImage::ImageRgb8(RgbImage::new(40, 30)).write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Png).unwrap();
+        bytes
+    }
+
+    // Every page's text, in page order
+    pub(crate) fn pages(pdf: &[u8]) -> Vec<String> {
+        let doc = Document::load_mem(pdf).unwrap();
+        doc.get_pages().keys().map(|&n| doc.extract_text(&[n]).unwrap()).collect()
+    }
+
+    #[test]
+    fn renders_card() {
+        let pdf = render_card(&taco_soup(), None).unwrap();
+        assert!(pdf.starts_with(b"%PDF"));
+        let pages = pages(&pdf);
+        assert_eq!(pages.len(), 1);
+        for text in ["Taco Soup", "From Serious Eats", "Makes 4 servings", "35 mins", "Ingredients", "1 lb ground beef", "Instructions", "Season with salt.", "Even better the next day."] {
+            assert!(pages[0].contains(text), "{} isn't on the card", text);
+        }
+    }
+
+    #[test]
+    fn renders_image() {
+        let pdf = render_card(&taco_soup(), Some(&png())).unwrap();
+        assert!(String::from_utf8_lossy(&pdf).contains("/Image"));
+        // Something that isn't an image is left out
+        let pdf = render_card(&taco_soup(), Some(b"not an image")).unwrap();
+        assert!(!String::from_utf8_lossy(&pdf).contains("/Image"));
+    }
+
+    #[test]
+    fn runs_onto_more_pages() {
+        let mut soup = taco_soup();
+        soup.ingredients = recipe_ingredients::parse_all(&(1..=90).map(|i| format!("{} cups beef broth", i)).collect::<Vec<String>>());
+        soup.instructions = (1..=30).map(|i| format!("Step {} of a very long recipe that goes on and on about the soup.", i)).collect();
+        let pages = pages(&render_card(&soup, None).unwrap());
+        assert!(pages.len() > 2);
+        let text = pages.concat();
+        assert!(text.contains("90 cups beef broth"));
+        assert!(text.contains("30."));
+    }
+
+    #[test]
+    fn balances_columns() {
+        let mut writer = Writer::new("Taco Soup").unwrap();
+        let top = writer.y;
+        ingredient_columns(&mut writer, &taco_soup());
+        // Five one-line ingredients make columns of three and two
+        assert!((top - writer.y - 3.0 * line_height(BODY)).abs() < 0.01);
+    }
+}
@@ -0,0 +1,136 @@
+// PDF's built-in Helvetica needs no font file, but we have to measure text
+// ourselves to wrap it. Widths are in thousandths of the font size for
+// ' ' through '~', from Adobe's font metrics.
+const HELVETICA: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, // ' ' to '/'
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, // '0' to '9'
+    278, 278, 584, 584, 584, 556, 1015, // ':' to '@'
+    667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, // 'A' to 'M'
+    722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, // 'N' to 'Z'
+    278, 278, 278, 469, 556, 333, // '[' to '`'
+    556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, // 'a' to 'm'
+    556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, // 'n' to 'z'
+    334, 260, 334, 584, // '{' to '~'
+];
+
+const HELVETICA_BOLD: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278, // ' ' to '/'
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, // '0' to '9'
+    333, 333, 584, 584, 584, 611, 975, // ':' to '@'
+    722, 722, 722, 722, 667, 611, 778, 722, 278, 556, 722, 611, 833, // 'A' to 'M'
+    722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, // 'N' to 'Z'
+    333, 278, 333, 584, 556, 333, // '[' to '`'
+    556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556, 278, 889, // 'a' to 'm'
+    611, 611, 611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, // 'n' to 'z'
+    389, 280, 389, 584, // '{' to '~'
+];
+
+// Millimetres in a point
+pub(crate) const PT: f32 = 0.3528;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Style {
+    Regular,
+    Bold,
+    // Oblique is Regular slanted, it measures the same
+    Italic,
+}
+
+/**
+ * The text as the built-in fonts can show it. They only have the Windows
+ * Latin characters, anything else would silently disappear from the page,
+ * so the fractions recipes use are spelled out and the rest become "?".
+ */
+pub(crate) fn printable(text: &str) -> String {
+    let mut out = String::new();
+    for c in text.chars() {
+        match c {
+            '⅓' => out.push_str("1/3"),
+            '⅔' => out.push_str("2/3"),
+            '⅛' => out.push_str("1/8"),
+            '⅜' => out.push_str("3/8"),
+            '⅝' => out.push_str("5/8"),
+            '⅞' => out.push_str("7/8"),
+            '\u{2044}' => out.push('/'),
+            '\t' | '\n' | '\r' => out.push(' '),
+            ' '..='~' | '\u{a0}'..='\u{ff}' => out.push(c),
+            '€' | '‚' | 'ƒ' | '„' | '…' | '†' | '‡' | 'ˆ' | '‰' | 'Š' | '‹' | 'Œ' | 'Ž' | '‘' | '’' | '“' | '”' | '•' | '–' | '—' | '˜' | '™' | 'š' | '›' | 'œ' | 'ž' | 'Ÿ' => out.push(c),
+            _ => out.push('?'),
+        }
+    }
+    out
+}
+
+// Width of printable text in mm
+pub(crate) fn text_width(text: &str, style: Style, size: f32) -> f32 {
+    let widths = if style == Style::Bold { &HELVETICA_BOLD } else { &HELVETICA };
+    let thousandths: u32 = text
+        .chars()
+        .map(|c| match c {
+            ' '..='~' => widths[c as usize - 32] as u32,
+            // Accented letters are as wide as a plain one, near enough
+            _ => 556,
+        })
+        .sum();
+    thousandths as f32 / 1000.0 * size * PT
+}
+
+/**
+ * Breaks text into lines no wider than `width` mm, between words where it
+ * can. A word too long for a line on its own, like a URL, is broken
+ * wherever it runs out of room.
+ */
+pub(crate) fn wrap(text: &str, style: Style, size: f32, width: f32) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    for word in printable(text).split_whitespace() {
+        let candidate = if line.is_empty() { word.to_string() } else { format!("{} {}", line, word) };
+        if text_width(&candidate, style, size) <= width {
+            line = candidate;
+            continue;
+        }
+        if !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+        }
+        for c in word.chars() {
+            line.push(c);
+            if text_width(&line, style, size) > width && line.chars().count() > 1 {
+                line.pop();
+                lines.push(std::mem::replace(&mut line, c.to_string()));
+            }
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measures_text() {
+        // "Hi" in 10pt Helvetica is 722 + 222 thousandths of 10pt
+        assert!((text_width("Hi", Style::Regular, 10.0) - 9.44 * PT).abs() < 0.001);
+        assert!(text_width("Hi", Style::Bold, 10.0) > text_width("Hi", Style::Regular, 10.0));
+    }
+
+    #[test]
+    fn wraps_words() {
+        let lines = wrap("Brown the ground beef with the onion in a large pot.", Style::Regular, 10.0, 40.0);
+        assert!(lines.len() > 1);
+        assert!(lines.iter().all(|l| text_width(l, Style::Regular, 10.0) <= 40.0));
+        assert_eq!(lines.join(" "), "Brown the ground beef with the onion in a large pot.");
+        let url = wrap("https://www.seriouseats.com/the-best-taco-soup-recipe", Style::Regular, 10.0, 30.0);
+        assert!(url.len() > 1);
+        assert_eq!(url.concat(), "https://www.seriouseats.com/the-best-taco-soup-recipe");
+    }
+
+    #[test]
+    fn keeps_what_helvetica_has() {
+        assert_eq!(printable("⅓ cup crème fraîche, 350°F"), "1/3 cup crème fraîche, 350°F");
+        assert_eq!(printable("Gyoza 餃子"), "Gyoza ??");
+    }
+}
@@ -0,0 +1,7 @@
+mod book;
+mod card;
+mod fonts;
+mod page;
+
+pub use book::render_book;
+pub use card::render_card;
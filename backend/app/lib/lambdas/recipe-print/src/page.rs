@@ -0,0 +1,171 @@
+use printpdf::image_crate::{self, DynamicImage, GenericImageView};
+use printpdf::{
+    BuiltinFont, Color, Image, ImageTransform, IndirectFontRef, Line, Mm, PdfDocument, PdfDocumentReference, PdfLayerIndex, PdfLayerReference,
+    PdfPageIndex, Point, Rgb,
+};
+
+use crate::fonts::{printable, text_width, wrap, Style, PT};
+
+// Half letter, 5.5 x 8.5 in, prints two to a sheet or on card stock
+pub(crate) const PAGE_WIDTH: f32 = 139.7;
+pub(crate) const PAGE_HEIGHT: f32 = 215.9;
+pub(crate) const MARGIN: f32 = 12.0;
+pub(crate) const CONTENT_WIDTH: f32 = PAGE_WIDTH - 2.0 * MARGIN;
+
+// Images are shrunk to this many pixels across before they go in, plenty for print at this size
+const IMAGE_PIXELS: u32 = 1000;
+
+pub(crate) fn line_height(size: f32) -> f32 {
+    size * 1.35 * PT
+}
+
+/**
+ * Writes flowing text down the page, starting a new page whenever the
+ * next line won't fit. `y` is where the top of the next line goes,
+ * measured up from the bottom of the page like PDF does.
+ */
+pub(crate) struct Writer {
+    doc: PdfDocumentReference,
+    fonts: Vec<IndirectFontRef>,
+    pages: Vec<(PdfPageIndex, PdfLayerIndex)>,
+    pub(crate) y: f32,
+}
+
+impl Writer {
+    pub(crate) fn new(title: &str) -> Result<Writer, String> {
+        let (doc, page, layer) = PdfDocument::new(printable(title), Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Page");
+        let mut fonts = Vec::new();
+        for font in [BuiltinFont::Helvetica, BuiltinFont::HelveticaBold, BuiltinFont::HelveticaOblique] {
+            fonts.push(doc.add_builtin_font(font).map_err(|e| format!("Error adding font: {}", e))?);
+        }
+        Ok(Writer { doc, fonts, pages: vec![(page, layer)], y: PAGE_HEIGHT - MARGIN })
+    }
+
+    fn font(&self, style: Style) -> &IndirectFontRef {
+        match style {
+            Style::Regular => &self.fonts[0],
+            Style::Bold => &self.fonts[1],
+            Style::Italic => &self.fonts[2],
+        }
+    }
+
+    fn layer(&self, page: usize) -> PdfLayerReference {
+        let (page, layer) = self.pages[page - 1];
+        self.doc.get_page(page).get_layer(layer)
+    }
+
+    // The page being written, counting from 1
+    pub(crate) fn page_number(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub(crate) fn new_page(&mut self) {
+        self.pages.push(self.doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Page"));
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    // Starts a new page unless `height` mm still fits on this one
+    pub(crate) fn keep(&mut self, height: f32) {
+        if self.y - height < MARGIN {
+            self.new_page();
+        }
+    }
+
+    pub(crate) fn gap(&mut self, height: f32) {
+        self.y -= height;
+    }
+
+    // Text on any page, with its baseline at `baseline`
+    pub(crate) fn text_at(&self, page: usize, text: &str, style: Style, size: f32, x: f32, baseline: f32) {
+        self.layer(page).use_text(printable(text), size, Mm(x), Mm(baseline), self.font(style));
+    }
+
+    // One line at `x`, which needs to already fit across the page
+    pub(crate) fn line(&mut self, text: &str, style: Style, size: f32, x: f32) {
+        let height = line_height(size);
+        self.keep(height);
+        self.text_at(self.page_number(), text, style, size, x, self.y - size * PT);
+        self.y -= height;
+    }
+
+    // Columns of lines side by side, each line at its own x, moving down by the longest
+    pub(crate) fn row(&mut self, columns: &[Vec<(f32, String)>], style: Style, size: f32) {
+        let height = line_height(size);
+        let rows = columns.iter().map(Vec::len).max().unwrap_or(0);
+        for column in columns {
+            for (i, (x, line)) in column.iter().enumerate() {
+                self.text_at(self.page_number(), line, style, size, *x, self.y - size * PT - i as f32 * height);
+            }
+        }
+        self.y -= rows as f32 * height;
+    }
+
+    pub(crate) fn paragraph(&mut self, text: &str, style: Style, size: f32, x: f32, width: f32) {
+        for line in wrap(text, style, size, width) {
+            self.line(&line, style, size, x);
+        }
+    }
+
+    // A line `width` wide across the page at the cursor
+    pub(crate) fn rule(&mut self, x: f32, width: f32) {
+        let layer = self.layer(self.page_number());
+        layer.set_outline_color(Color::Rgb(Rgb::new(0.6, 0.6, 0.6, None)));
+        layer.set_outline_thickness(0.5);
+        layer.add_line(Line {
+            points: vec![(Point::new(Mm(x), Mm(self.y)), false), (Point::new(Mm(x + width), Mm(self.y)), false)],
+            is_closed: false,
+        });
+    }
+
+    /**
+     * The picture, as wide as the text and no taller than `max_height`,
+     * centred. A file that isn't an image we can read is left out rather
+     * than failing the whole print.
+     */
+    pub(crate) fn image(&mut self, bytes: &[u8], max_height: f32) {
+        let image = match image_crate::load_from_memory(bytes) {
+            Ok(i) => i,
+            Err(e) => {
+                println!("Leaving out an image we can't read: {}", e);
+                return;
+            }
+        };
+        // Lets PDF handle transparency and gives it fewer bytes to hold
+        let image = DynamicImage::ImageRgb8(image.thumbnail(IMAGE_PIXELS, IMAGE_PIXELS).to_rgb8());
+        let (pixels_wide, pixels_high) = image.dimensions();
+        let scale = (CONTENT_WIDTH / pixels_wide as f32).min(max_height / pixels_high as f32);
+        let (width, height) = (pixels_wide as f32 * scale, pixels_high as f32 * scale);
+        self.keep(height);
+        // At 300 dpi a pixel is 25.4 / 300 mm, scale that to the size we want
+        let dpi = 300.0;
+        let natural = 25.4 / dpi;
+        Image::from_dynamic_image(&image).add_to_layer(
+            self.layer(self.page_number()),
+            ImageTransform {
+                translate_x: Some(Mm(MARGIN + (CONTENT_WIDTH - width) / 2.0)),
+                translate_y: Some(Mm(self.y - height)),
+                scale_x: Some(scale / natural),
+                scale_y: Some(scale / natural),
+                dpi: Some(dpi),
+                ..ImageTransform::default()
+            },
+        );
+        self.y -= height;
+    }
+
+    // Adds the page to the PDF's outline, the list viewers show down the side
+    pub(crate) fn bookmark(&self, name: &str, page: usize) {
+        self.doc.add_bookmark(printable(name), self.pages[page - 1].0);
+    }
+
+    pub(crate) fn centred(&mut self, text: &str, style: Style, size: f32) {
+        for line in wrap(text, style, size, CONTENT_WIDTH) {
+            let x = MARGIN + (CONTENT_WIDTH - text_width(&line, style, size)) / 2.0;
+            self.line(&line, style, size, x);
+        }
+    }
+
+    pub(crate) fn save(self) -> Result<Vec<u8>, String> {
+        self.doc.save_to_bytes().map_err(|e| format!("Error writing PDF: {}", e))
+    }
+}
@@ -0,0 +1,169 @@
+use serde::Serialize;
+use std::env;
+use lambda_http::{service_fn, Response, Error, Request, RequestExt};
+use recipe_core::{error_response, make_config, ApiError, DynamoStore, Opt, Recipe, RecipeStore};
+use recipe_ingredients::{scale, ScaledIngredient};
+
+#[derive(Debug, PartialEq)]
+pub enum Target {
+    Servings(f64),
+    Multiplier(f64),
+}
+
+#[derive(Serialize, Debug)]
+pub struct ScaledRecipe {
+    pub uuid: String,
+    pub name: String,
+    pub multiplier: f64,
+    pub recipe_yield: Option<String>,
+    pub servings: Option<f64>,
+    pub ingredients: Vec<ScaledIngredient>,
+}
+
+async fn get_table_name() -> Option<String> {
+    env::var("TABLE_NAME").ok()
+}
+
+// Either ?servings=8 or ?multiplier=0.5, servings wins if both are given
+fn get_target(servings: Option<&str>, multiplier: Option<&str>) -> Result<Target, String> {
+    let (value, target): (&str, fn(f64) -> Target) = match (servings, multiplier) {
+        (Some(s), _) => (s, Target::Servings),
+        (None, Some(m)) => (m, Target::Multiplier),
+        (None, None) => return Err(String::from("Supply either servings or multiplier")),
+    };
+    match value.parse::<f64>() {
+        Ok(v) if v > 0.0 && v.is_finite() => Ok(target(v)),
+        _ => Err(format!("Invalid scale: {}", value)),
+    }
+}
+
+pub fn scale_recipe(recipe: Recipe, target: Target) -> Result<ScaledRecipe, String> {
+    let (multiplier, servings) = match target {
+        Target::Multiplier(m) => (m, recipe.servings().map(|s| s * m)),
+        Target::Servings(s) => {
+            let original = recipe
+                .servings()
+                .ok_or_else(|| String::from("Recipe has no yield to scale from, use multiplier instead"))?;
+            (s / original, Some(s))
+        }
+    };
+
+    Ok(ScaledRecipe {
+        uuid: recipe.uuid,
+        name: recipe.name,
+        multiplier,
+        recipe_yield: recipe.recipe_yield,
+        servings,
+        ingredients: scale(&recipe.ingredients, multiplier),
+    })
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let func = service_fn(handler);
+    lambda_http::run(func).await?;
+
+    Ok(())
+}
+
+async fn handler(request: Request) -> Result<Response<String>, Error> {
+    // 1. Read the recipe and the target size from the query string
+    let query_params = request.query_string_parameters();
+    let uuid = match query_params.first("url") {
+        Some(u) => u.to_string(),
+        None => return error_response!(ApiError::BadRequest(String::from("No recipe url supplied"))),
+    };
+    let target = match get_target(query_params.first("servings"), query_params.first("multiplier")) {
+        Ok(t) => t,
+        Err(e) => return error_response!(ApiError::BadRequest(e)),
+    };
+
+    // 2. Create the store from the table name in env
+    let opt = Opt {
+        region: Some("us-east-1".to_string()),
+        verbose: true,
+    };
+    let config = match make_config(opt).await {
+        Ok(c) => c,
+        Err(e) => return error_response!(ApiError::Internal(format!("Error making config: {}", e))),
+    };
+    let table_name = match get_table_name().await {
+        Some(t) => t,
+        None => return error_response!(ApiError::Internal(String::from("TABLE_NAME not set"))),
+    };
+    let store = DynamoStore::new(&config, &table_name);
+
+    scale_stored_recipe(&store, &uuid, target).await.or_else(|e| error_response!(e.into()))
+}
+
+async fn scale_stored_recipe(store: &dyn RecipeStore, uuid: &str, target: Target) -> Result<Response<String>, Error> {
+    // 3. Get the recipe and scale it
+    let recipe = match store.get(uuid).await? {
+        Some(r) => r,
+        None => return error_response!(ApiError::NotFound(format!("No recipe found for {}", uuid))),
+    };
+    // A recipe without a yield can't be scaled to servings, however the request is written
+    let scaled = match scale_recipe(recipe, target) {
+        Ok(s) => s,
+        Err(e) => return error_response!(ApiError::Unprocessable(e)),
+    };
+
+    let json_string = match serde_json::to_string(&scaled) {
+        Ok(j) => j,
+        Err(e) => return error_response!(ApiError::Internal(format!("Error writing recipe: {}", e))),
+    };
+    Ok(Response::builder()
+        .status(200)
+        .header("Access-Control-Allow-Origin", "*")
+        .body(json_string)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recipe(recipe_yield: Option<&str>) -> Recipe {
+        let mut recipe = Recipe::new("https://tasty.co/recipe/taco-soup", "Taco Soup");
+        recipe.recipe_yield = recipe_yield.map(String::from);
+        recipe.ingredients = recipe_ingredients::parse_all(&[String::from("1 tbsp chili powder"), String::from("Salt to taste")]);
+        recipe
+    }
+
+    #[test]
+    fn test_get_target() {
+        assert_eq!(get_target(Some("8"), None), Ok(Target::Servings(8.0)));
+        assert_eq!(get_target(None, Some("0.5")), Ok(Target::Multiplier(0.5)));
+        assert!(get_target(None, None).is_err());
+        assert!(get_target(Some("-2"), None).is_err());
+    }
+
+    #[test]
+    fn test_scale_to_servings() {
+        let scaled = scale_recipe(recipe(Some("4 servings")), Target::Servings(8.0)).unwrap();
+        assert_eq!(scaled.multiplier, 2.0);
+        assert_eq!(scaled.ingredients[0].text, "2 tbsp chili powder");
+        assert!(!scaled.ingredients[1].scaled);
+    }
+
+    #[test]
+    fn test_scale_without_yield() {
+        assert!(scale_recipe(recipe(None), Target::Servings(8.0)).is_err());
+        let scaled = scale_recipe(recipe(None), Target::Multiplier(0.5)).unwrap();
+        assert_eq!(scaled.servings, None);
+        assert_eq!(scaled.ingredients[0].text, "1 1/2 tsp chili powder");
+    }
+
+    #[tokio::test]
+    async fn test_scale_stored_recipe() {
+        let store = recipe_core::MemoryStore::with_recipes(vec![recipe(Some("4 servings"))]);
+        let res = scale_stored_recipe(&store, "https://tasty.co/recipe/taco-soup", Target::Servings(2.0)).await.unwrap();
+        assert_eq!(res.status().as_u16(), 200);
+        assert!(res.body().contains("1 1/2 tsp chili powder"));
+        let res = scale_stored_recipe(&store, "missing", Target::Multiplier(2.0)).await.unwrap();
+        assert_eq!(res.status().as_u16(), 404);
+        let store = recipe_core::MemoryStore::with_recipes(vec![recipe(None)]);
+        let res = scale_stored_recipe(&store, "https://tasty.co/recipe/taco-soup", Target::Servings(2.0)).await.unwrap();
+        assert_eq!(res.status().as_u16(), 422);
+        assert!(res.body().contains("\"code\":\"unprocessable\""));
+    }
+}
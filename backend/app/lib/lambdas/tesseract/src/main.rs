@@ -1,14 +1,8 @@
-use lambda_http::{service_fn, Response, Body, Error, Request};
-use serde_json::json;
+use lambda_http::{service_fn, Response, Error, Request};
 use serde::Deserialize;
 use serde::Serialize;
-use reqwest;
-use dotenv::dotenv;
-use std::env;
-
-
-// Constants
-const PROMPT: &str = "What is the text in this image?";
+use recipe_core::{error_response, ApiError};
+use recipe_llm::{LlmConfig, LlmError, LlmProvider};
 
 // Tesseract Request
 #[derive(Deserialize, Debug)]
@@ -16,115 +10,24 @@ pub struct TesseractRequest {
     pub url: String
 }
 
-// OpenAIImageURL
-#[derive(Serialize, Debug)]
-pub struct OpenAIImageURL {
-    pub url: String
-}
-
-// OpenAITextContent
-#[derive(Serialize, Debug)]
-pub struct OpenAITextContent {
-    #[serde(rename = "type")]
-    pub content_type: String,
-    pub text: String
-}
-
-// OpenAIImageContent
-#[derive(Serialize, Debug)]
-pub struct OpenAIImageContent {
-    #[serde(rename = "type")]
-    pub content_type: String,
-    pub image_url: OpenAIImageURL
-}
-
-// OpenAIContent
-#[derive(Serialize, Debug)]
-#[serde(tag = "type")]
-pub enum OpenAIContent {
-    #[serde(rename = "text")]
-    TextContent(OpenAITextContent),
-    #[serde(rename = "image_url")]
-    ImageContent(OpenAIImageContent),
-}
-
-// OpenAIMessage
-#[derive(Serialize, Debug)]
-pub struct OpenAIMessage {
-    pub role: String,
-    pub content: Vec<OpenAIContent>
-}
-
-// OpenAI Request
-#[derive(Serialize, Debug)]
-pub struct OpenAIRequest {
-    pub model: String,
-    pub messages: Vec<OpenAIMessage>,
-    pub max_tokens: u16
-}
-
-impl OpenAIRequest {
-    pub fn from(url: String) -> OpenAIRequest {
-        let image_url = OpenAIImageURL {
-            url: url
-        };
-        let prompt_content = OpenAITextContent {
-            content_type: String::from("text"),
-            text: PROMPT.to_string()
-        };
-        let image_content = OpenAIImageContent {
-            content_type: String::from("image_url"),
-            image_url: image_url
-        };
-        let message = OpenAIMessage {
-            role: String::from("user"),
-            content: vec![OpenAIContent::TextContent(prompt_content), OpenAIContent::ImageContent(image_content)]
-        };
-        OpenAIRequest {
-            model: String::from("gpt-4-vision-preview"),
-            messages: vec![message],
-            max_tokens: 300
-        }
-    }
-}
-
-// OpenAI Response
-#[derive(Deserialize, Debug)]
-pub struct OpenAIResponse {
-    pub json: String
-}
-
 // Tesseract Response
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct TesseractResponse {
     pub contents: String
 }
 
-async fn get_api_key() -> Option<String> {
-    env::var("OPEN_AI_API_KEY").ok()
+// The model's trouble, not the caller's, though a rate limit is worth passing on
+fn llm_error(e: LlmError) -> ApiError {
+    match e.status {
+        Some(429) => ApiError::TooManyRequests(e.message),
+        _ => ApiError::Unavailable(format!("Error reading image: {}", e)),
+    }
 }
 
-async fn tesseract(url: TesseractRequest) -> Result<TesseractResponse, Error> {
-    let request = OpenAIRequest::from(url.url);
-    // Serialize the struct to JSON
-    let json_string = serde_json::to_string(&request).unwrap();
-
-    // Print the JSON string
-    println!("Request: {}", json_string);
-    let client = reqwest::Client::new();
-    let uri = "https://api.openai.com/v1/chat/completions";
-
-    let response = client
-        .post(uri)
-        .header("Authorization", format!("Bearer {}", get_api_key().await.unwrap()))
-        .json(&request)
-        .send()
-        .await.unwrap();
-
-    println!("Response: {:?}", response);
-    Ok(TesseractResponse {
-        contents: response.text().await?
-    })
+async fn tesseract(llm: &dyn LlmProvider, url: TesseractRequest) -> Result<TesseractResponse, ApiError> {
+    println!("Reading image: {}", url.url);
+    let contents = llm.transcribe(&url.url).await.map_err(llm_error)?;
+    Ok(TesseractResponse { contents })
 }
 
 #[tokio::main]
@@ -137,29 +40,34 @@ async fn main() -> Result<(), Error> {
 
 async fn handler(request: Request) -> Result<Response<String>, Error> {
     // 1. Get URL from request body
-    let body = request.body();
-    let url: TesseractRequest = serde_json::from_slice(&body)?;
-
-    // 2. Call Tesseract Function and return success/failure
-    match tesseract(url).await {
-        Ok(resp) => {
-            return Ok(Response::builder()
-                .status(200)
-                .header("Access-Control-Allow-Origin", "*")
-                .body(resp.contents)?);
-        },
-        Err(e) => {
-            return Ok(Response::builder()
-                .status(400)
-                .header("Access-Control-Allow-Origin", "*")
-                .body(format!("Tesseract Failure: {:?}", e))?);
-        }
-    }
+    let url: TesseractRequest = match serde_json::from_slice(request.body()) {
+        Ok(u) => u,
+        Err(e) => return error_response!(e.into()),
+    };
+
+    // 2. Pick the model from env, OpenAI unless LLM_PROVIDER says otherwise
+    let llm = match LlmConfig::from_env().map_err(|e| LlmError::new(None, e)).and_then(|c| c.provider()) {
+        Ok(l) => l,
+        Err(e) => return error_response!(ApiError::Internal(format!("Error configuring LLM: {}", e))),
+    };
+
+    // 3. Call Tesseract Function and return success/failure
+    let resp = match tesseract(llm.as_ref(), url).await {
+        Ok(r) => r,
+        Err(e) => return error_response!(e),
+    };
+    Ok(Response::builder()
+        .status(200)
+        .header("Access-Control-Allow-Origin", "*")
+        .header("Content-Type", "application/json")
+        .body(serde_json::to_string(&resp)?)?)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use lambda_http::Body;
+    use recipe_llm::{Fixture, Fixtures, MockProvider};
 
     macro_rules! aw {
         ($e:expr) => {
@@ -167,6 +75,20 @@ mod tests {
         };
     }
 
+    #[test]
+    fn test_tesseract_mock() {
+        let llm = MockProvider::new(Fixtures {
+            vision: vec![Fixture::reply("card.png", "Grandma's Pie\n2 cups flour")],
+            ..Fixtures::default()
+        });
+        let url = TesseractRequest { url: String::from("https://example.com/card.png") };
+        let resp = aw!(tesseract(&llm, url)).unwrap();
+        assert_eq!(resp.contents, "Grandma's Pie\n2 cups flour");
+
+        let missing = TesseractRequest { url: String::from("https://example.com/other.png") };
+        assert_eq!(aw!(tesseract(&llm, missing)).unwrap_err().status(), 503);
+    }
+
     #[test]
     fn test_tesseract() {
         dotenv::from_filename("../../.env").ok();
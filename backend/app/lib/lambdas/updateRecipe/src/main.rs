@@ -1,170 +1,17 @@
-use std::collections::HashMap;
 use serde::Deserialize;
-use serde::Serialize;
 use lambda_runtime::{LambdaEvent};
-use aws_sdk_dynamodb::types::{AttributeValue};
 use std::env;
-use aws_config::{meta::region::RegionProviderChain, SdkConfig};
-use aws_sdk_dynamodb::{config::Region, meta::PKG_VERSION};
-use aws_sdk_dynamodb::Client as DbClient;
 use lambda_http::{service_fn, Response, Body, Error, Request};
-use serde_json::Value;
-
-
-const NAME: &str = "name";
-const INGREDIENTS: &str = "ingredients";
-const INSTRUCTIONS: &str = "instructions";
-const NOTES: &str = "notes";
-const SUMMARY: &str = "summary";
-const OWNER: &str = "owner";
-
-#[derive(Debug)]
-pub struct Opt {
-    /// The AWS Region.
-    pub region: Option<String>,
-    /// Whether to display additional information.
-    pub verbose: bool,
-}
+use recipe_core::{error_response, make_config, ApiError, DynamoStore, Opt, RecipeStore, RecipeUpdate};
 
 #[derive(Deserialize, Debug)]
 pub struct UpdateRequest {
     owner: String,
-    updated_recipe: Recipe
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct Recipe {
-    pub uuid: String,
-    pub name: Option<String>,
-    pub ingredients: Option<Vec<String>>,
-    pub instructions: Option<Vec<String>>,
-    pub notes: Option<String>,
-    pub summary: Option<String>,
-    pub owner: Option<String>
-}
-
-#[derive(Debug)]
-pub struct Expression {
-    pub expression: String,
-    pub condition: String,
-    pub names: HashMap<String, String>,
-    pub values: HashMap<String, AttributeValue>
+    updated_recipe: RecipeUpdate
 }
 
-impl Expression {
-    fn from(req: UpdateRequest) -> Expression {
-        let recipe: Recipe = req.updated_recipe;
-        let mut expressions: Vec<String> = Vec::new();
-        let mut names: HashMap<String, String> = HashMap::new();
-        let mut values: HashMap<String, AttributeValue> = HashMap::new();
-
-        // Create Condition
-        let condition = "attribute_exists(#owner) AND #owner = :currentOwner".to_string();
-        values.insert(":currentOwner".to_string(), AttributeValue::S(req.owner.clone()));
-        names.insert("#owner".to_string(), OWNER.to_string());
-
-        // Name
-        if let Some(name) = &recipe.name {
-            expressions.push(String::from("#name = :nameValue"));
-            names.insert("#name".to_string(), NAME.to_string());
-            values.insert(":nameValue".to_string(), AttributeValue::S(name.clone()));
-        }
-
-        // Ingredients
-        if let Some(ingredients) = &recipe.ingredients {
-            let string_ingredients = join_strings(ingredients.to_vec());
-            expressions.push(String::from("#ingredients = :ingredientsValue"));
-            names.insert("#ingredients".to_string(), INGREDIENTS.to_string());
-            values.insert(":ingredientsValue".to_string(), AttributeValue::S(string_ingredients));
-        }
-
-        // Instructions
-        if let Some(instructions) = &recipe.instructions {
-            let string_instructions = join_strings(instructions.to_vec());
-            expressions.push(String::from("#instructions = :instructionsValue"));
-            names.insert("#instructions".to_string(), INSTRUCTIONS.to_string());
-            values.insert(":instructionsValue".to_string(), AttributeValue::S(string_instructions));
-        }
-
-        // Notes
-        if let Some(notes) = &recipe.notes {
-            expressions.push(String::from("#notes = :notesValue"));
-            names.insert("#notes".to_string(), NOTES.to_string());
-            values.insert(":notesValue".to_string(), AttributeValue::S(notes.clone()));
-        }
-
-        // Summary
-        if let Some(summary) = &recipe.summary {
-            expressions.push(String::from("#summary = :summaryValue"));
-            names.insert("#summary".to_string(), SUMMARY.to_string());
-            values.insert(":summaryValue".to_string(), AttributeValue::S(summary.clone()));
-        }
-
-        if let Some(owner) = &recipe.owner {
-            expressions.push(String::from("#owner = :ownerValue"));
-            names.insert("#owner".to_string(), OWNER.to_string());
-            values.insert(":ownerValue".to_string(), AttributeValue::S(owner.clone()));
-        }
-
-        Expression {
-            expression: format!("SET {}", expressions.join(",")),
-            condition,
-            names,
-            values
-        }
-    }
-}
-
-pub fn make_region_provider(region: Option<String>) -> RegionProviderChain {
-    RegionProviderChain::first_try(region.map(Region::new))
-        .or_default_provider()
-        .or_else(Region::new("us-east-1"))
-}
-
-pub async fn make_config(opt: Opt) -> Result<SdkConfig, Error> {
-    let region_provider = make_region_provider(opt.region);
-
-    Ok(aws_config::from_env().region(region_provider).load().await)
-}
-
-fn join_strings(strings: Vec<String>) -> String {
-    strings
-        .iter()
-        .map(|string| {
-            let escaped_string = string
-                .replace("\\", "\\\\") // Escape backslashes
-                .replace(",", "\\,") // Escape commas
-                .replace(";", "\\;"); // Escape semicolons
-            escaped_string
-        })
-        .collect::<Vec<String>>()
-        .join(";")
-}
-
-fn split_string(string: String) -> Vec<String> {
-    let escaped_strings: Vec<String> = string
-        .split(";")
-        .map(|substring| substring.replace("\\;", ";").replace("\\,", ",").replace("\\\\", "\\"))
-        .collect();
-
-    escaped_strings
-}
-
-pub async fn update_db(client: &DbClient, req: UpdateRequest, table: &String) -> Result<String, Error> {
-    let uuid = AttributeValue::S(req.updated_recipe.uuid.clone());
-    let expression = Expression::from(req);
-    println!("Expression: {:?}", expression.expression);
-
-    let request = client
-        .update_item()
-        .table_name(table)
-        .key("uuid".to_string(), uuid)
-        .update_expression(expression.expression)
-        .set_expression_attribute_names(Some(expression.names))
-        .set_expression_attribute_values(Some(expression.values))
-        .set_condition_expression(Some(expression.condition));
-
-    request.send().await?;
+pub async fn update_db(store: &dyn RecipeStore, req: UpdateRequest) -> Result<String, Error> {
+    store.update(&req.owner, &req.updated_recipe).await?;
 
     Ok(String::from("Recipe Updated!"))
 }
@@ -182,47 +29,38 @@ async fn main() -> Result<(), Error> {
 }
 
 async fn handler(request: Request) -> Result<Response<String>, Error> {
-    // 1. Create db client and get table name from env
+    // 1. Create the store from the table name in env
     let opt = Opt {
         region: Some("us-east-1".to_string()),
         verbose: true,
     };
     let config = match make_config(opt).await {
         Ok(c) => c,
-        Err(e) => {
-            return Ok(Response::builder()
-            .status(400)
-            .body(format!("Error making config: {}", e.to_string()))?);
-            
-        },
+        Err(e) => return error_response!(ApiError::Internal(format!("Error making config: {}", e))),
     };
-    let db_client = DbClient::new(&config);
     let table_name = match get_table_name().await {
         Some(t) => t,
-        None => {
-            return Ok(Response::builder()
-            .status(400)
-            .body(String::from("TABLE_NAME not set"))?);
-        }
+        None => return error_response!(ApiError::Internal(String::from("TABLE_NAME not set"))),
     };
     println!("Table Name: {}", table_name);
+    let store = DynamoStore::new(&config, &table_name);
 
     let body = request.body();
-    let req: UpdateRequest = serde_json::from_slice(&body)?;
+    let req: UpdateRequest = match serde_json::from_slice(body) {
+        Ok(r) => r,
+        Err(e) => return error_response!(ApiError::from(e)),
+    };
 
     println!("Recipe: {:?}", req);
 
-    match update_db(&db_client, req, &table_name).await {
+    // Someone else's recipe is a 403, DynamoDB having trouble a 429 or 503
+    match update_db(&store, req).await {
         Ok(_) => {
             return Ok(Response::builder()
                 .status(200)
                 .body(String::from("DB Updated!"))?);
         },
-        Err(e) => {
-            return Ok(Response::builder()
-                .status(400)
-                .body(format!("DB Failed to Update! {:?}", e))?);
-        }
+        Err(e) => error_response!(e.into()),
     }
 
 
@@ -238,6 +76,31 @@ mod tests {
         };
     }
 
+    #[test]
+    fn test_update_in_memory() {
+        let mut soup = recipe_core::Recipe::new("https://tasty.co/recipe/taco-soup", "Taco Soup");
+        soup.owner = Some(String::from("dmbluesmith"));
+        let store = recipe_core::MemoryStore::with_recipes(vec![soup]);
+        let body = r#"
+        {
+            "owner": "dmbluesmith",
+            "updated_recipe": {
+                "uuid": "https://tasty.co/recipe/taco-soup",
+                "ingredients": ["2 cans black beans, drained"]
+            }
+        }"#;
+        let req: UpdateRequest = serde_json::from_str(body).unwrap();
+        aw!(update_db(&store, req)).unwrap();
+
+        let recipe = aw!(store.get("https://tasty.co/recipe/taco-soup")).unwrap().unwrap();
+        assert_eq!(recipe.name, "Taco Soup");
+        assert_eq!(recipe.ingredients[0].preparation.as_deref(), Some("drained"));
+
+        let hacker: UpdateRequest = serde_json::from_str(&body.replace("\"owner\": \"dmbluesmith\"", "\"owner\": \"hacker\"")).unwrap();
+        let e = aw!(update_db(&store, hacker)).unwrap_err();
+        assert_eq!(ApiError::from(e).status(), 403);
+    }
+
     #[test]
     fn test_update() {
         dotenv::from_filename("../../.env").ok();
@@ -277,7 +140,7 @@ mod tests {
         let req = Request::new(Body::from(body));
         let res = aw!(handler(req));
         println!("Response: {:?}", res);
-        assert_eq!(res.unwrap().status().as_u16(), 400);
+        assert_eq!(res.unwrap().status().as_u16(), 403);
     }
 
     #[test]
@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+use std::env;
+use aws_config::{meta::region::RegionProviderChain, BehaviorVersion};
+use aws_sdk_dynamodb::config::Region;
+use aws_sdk_dynamodb::types::AttributeValue;
+use aws_sdk_dynamodb::Client as DbClient;
+use recipe_core::{ingredients_attribute, instructions_attribute, split_string, SCHEMA_VERSION};
+use recipe_ingredients::{parse_all, Ingredient};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+const INGREDIENTS: &str = "ingredients";
+const PARSED_INGREDIENTS: &str = "parsed_ingredients";
+const INSTRUCTIONS: &str = "instructions";
+
+#[derive(Debug)]
+pub struct Opt {
+    pub table_name: String,
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct Summary {
+    pub scanned: usize,
+    pub migrated: usize,
+    pub already_native: usize,
+    pub failed: Vec<(String, String)>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Migration {
+    pub ingredients: Option<Vec<Ingredient>>,
+    pub instructions: Option<Vec<String>>,
+}
+
+/**
+ * Usage: migrateRecipes [--dry-run] [--table <name>]
+ *
+ * The table defaults to TABLE_NAME from the environment. Run it with
+ * --dry-run first to see what would change.
+ */
+fn get_opt(args: Vec<String>) -> Result<Opt, String> {
+    let mut table_name = env::var("TABLE_NAME").ok();
+    let mut dry_run = false;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dry-run" => dry_run = true,
+            "--table" => table_name = args.next(),
+            other => return Err(format!("Unknown argument: {}", other)),
+        }
+    }
+    match table_name {
+        Some(table_name) => Ok(Opt { table_name, dry_run }),
+        None => Err(String::from("Pass --table or set TABLE_NAME")),
+    }
+}
+
+/**
+ * Works out what a stored recipe needs rewritten. Lists that are already
+ * native are left alone, so Ok(None) means there is nothing to do. Anything
+ * that is neither a string nor a list is an error for a person to look at.
+ */
+pub fn plan(item: &HashMap<String, AttributeValue>) -> Result<Option<Migration>, String> {
+    let ingredients = match item.get(INGREDIENTS) {
+        Some(AttributeValue::S(joined)) => {
+            let lines = split_string(joined);
+            // Reuse the parsed copy we used to keep alongside, if it still lines up
+            let parsed: Option<Vec<Ingredient>> = item
+                .get(PARSED_INGREDIENTS)
+                .and_then(|v| v.as_s().ok())
+                .and_then(|s| serde_json::from_str(s).ok())
+                .filter(|p: &Vec<Ingredient>| p.len() == lines.len());
+            Some(parsed.unwrap_or_else(|| parse_all(&lines)))
+        }
+        Some(AttributeValue::L(_)) | None => None,
+        Some(other) => return Err(format!("ingredients is not a string or list: {:?}", other)),
+    };
+    let instructions = match item.get(INSTRUCTIONS) {
+        Some(AttributeValue::S(joined)) => Some(split_string(joined)),
+        Some(AttributeValue::L(_)) | None => None,
+        Some(other) => return Err(format!("instructions is not a string or list: {:?}", other)),
+    };
+
+    if ingredients.is_none() && instructions.is_none() {
+        return Ok(None);
+    }
+    Ok(Some(Migration { ingredients, instructions }))
+}
+
+// The update_item parts that rewrite one recipe
+#[derive(Debug)]
+pub struct Update {
+    pub expression: String,
+    pub condition: String,
+    pub names: HashMap<String, String>,
+    pub values: HashMap<String, AttributeValue>,
+}
+
+/**
+ * The update for a migration. Whatever it rewrites, the recipe is then in
+ * the current layout, so it's stamped with SCHEMA_VERSION to match.
+ */
+pub fn update(migration: &Migration) -> Update {
+    let mut expressions: Vec<String> = Vec::new();
+    let mut conditions: Vec<String> = Vec::new();
+    let mut names: HashMap<String, String> = HashMap::new();
+    let mut values: HashMap<String, AttributeValue> = HashMap::new();
+    values.insert(":string".to_string(), AttributeValue::S("S".to_string()));
+
+    if let Some(ingredients) = &migration.ingredients {
+        expressions.push(String::from("#ingredients = :ingredientsValue"));
+        conditions.push(String::from("attribute_type(#ingredients, :string)"));
+        names.insert("#ingredients".to_string(), INGREDIENTS.to_string());
+        values.insert(":ingredientsValue".to_string(), ingredients_attribute(ingredients));
+    }
+    if let Some(instructions) = &migration.instructions {
+        expressions.push(String::from("#instructions = :instructionsValue"));
+        conditions.push(String::from("attribute_type(#instructions, :string)"));
+        names.insert("#instructions".to_string(), INSTRUCTIONS.to_string());
+        values.insert(":instructionsValue".to_string(), instructions_attribute(instructions));
+    }
+    expressions.push(String::from("#schema_version = :schemaVersion"));
+    names.insert("#schema_version".to_string(), String::from("schema_version"));
+    values.insert(":schemaVersion".to_string(), AttributeValue::N(SCHEMA_VERSION.to_string()));
+    names.insert("#parsedIngredients".to_string(), PARSED_INGREDIENTS.to_string());
+
+    Update {
+        expression: format!("SET {} REMOVE #parsedIngredients", expressions.join(",")),
+        // Makes sure nobody rewrote the item since we scanned it
+        condition: conditions.join(" AND "),
+        names,
+        values,
+    }
+}
+
+async fn migrate(client: &DbClient, table_name: &str, uuid: AttributeValue, migration: Migration) -> Result<(), String> {
+    let update = update(&migration);
+    client
+        .update_item()
+        .table_name(table_name)
+        .key("uuid".to_string(), uuid)
+        .update_expression(update.expression)
+        .condition_expression(update.condition)
+        .set_expression_attribute_names(Some(update.names))
+        .set_expression_attribute_values(Some(update.values))
+        .send()
+        .await
+        .map_err(|e| format!("Update failed: {:?}", e))?;
+    Ok(())
+}
+
+async fn run(client: &DbClient, opt: &Opt) -> Result<Summary, Error> {
+    let mut summary = Summary::default();
+    let mut start_key: Option<HashMap<String, AttributeValue>> = None;
+
+    loop {
+        let page = client
+            .scan()
+            .table_name(&opt.table_name)
+            .set_exclusive_start_key(start_key.take())
+            .send()
+            .await?;
+
+        for item in page.items() {
+            summary.scanned += 1;
+            let uuid = match item.get("uuid") {
+                Some(uuid) => uuid.clone(),
+                None => {
+                    summary.failed.push((String::from("?"), String::from("Item has no uuid")));
+                    continue;
+                }
+            };
+            let label = uuid.as_s().cloned().unwrap_or_else(|_| format!("{:?}", uuid));
+
+            let migration = match plan(item) {
+                Ok(Some(m)) => m,
+                Ok(None) => {
+                    summary.already_native += 1;
+                    continue;
+                }
+                Err(e) => {
+                    summary.failed.push((label, e));
+                    continue;
+                }
+            };
+
+            if opt.dry_run {
+                println!("Would migrate {}", label);
+                summary.migrated += 1;
+                continue;
+            }
+            match migrate(client, &opt.table_name, uuid, migration).await {
+                Ok(_) => {
+                    println!("Migrated {}", label);
+                    summary.migrated += 1;
+                }
+                Err(e) => summary.failed.push((label, e)),
+            }
+        }
+
+        match page.last_evaluated_key {
+            Some(key) if !key.is_empty() => start_key = Some(key),
+            _ => break,
+        }
+    }
+
+    Ok(summary)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let opt = match get_opt(env::args().skip(1).collect()) {
+        Ok(o) => o,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let region_provider = RegionProviderChain::default_provider().or_else(Region::new("us-east-1"));
+    let config = aws_config::defaults(BehaviorVersion::latest()).region(region_provider).load().await;
+    let client = DbClient::new(&config);
+
+    let summary = run(&client, &opt).await?;
+    for (uuid, reason) in &summary.failed {
+        eprintln!("Could not migrate {}: {}", uuid, reason);
+    }
+    println!(
+        "Scanned {}, {} {}, {} already native, {} failed",
+        summary.scanned,
+        if opt.dry_run { "would migrate" } else { "migrated" },
+        summary.migrated,
+        summary.already_native,
+        summary.failed.len()
+    );
+    if !summary.failed.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use recipe_core::from_item;
+
+    fn item(fields: &[(&str, AttributeValue)]) -> HashMap<String, AttributeValue> {
+        fields.iter().map(|(k, v)| (k.to_string(), v.clone())).collect()
+    }
+
+    #[test]
+    fn test_split_string() {
+        assert_eq!(split_string("1 cup flour;2 eggs"), vec!["1 cup flour", "2 eggs"]);
+        assert_eq!(split_string("salt\\, pepper;a\\;b;back\\\\slash"), vec!["salt, pepper", "a;b", "back\\slash"]);
+        assert_eq!(split_string("one;;three"), vec!["one", "", "three"]);
+        assert!(split_string("").is_empty());
+    }
+
+    #[test]
+    fn test_plan_legacy_item() {
+        let legacy = item(&[
+            ("ingredients", AttributeValue::S(String::from("1 cup flour;2 eggs\\, beaten"))),
+            ("instructions", AttributeValue::S(String::from("Mix;Bake"))),
+        ]);
+        let migration = plan(&legacy).unwrap().unwrap();
+        let ingredients = migration.ingredients.unwrap();
+        assert_eq!(ingredients[1].raw, "2 eggs, beaten");
+        assert_eq!(ingredients[1].preparation.as_deref(), Some("beaten"));
+        assert_eq!(migration.instructions, Some(vec![String::from("Mix"), String::from("Bake")]));
+    }
+
+    // What DynamoDB would store after the update, for the SET a = :b and REMOVE c it builds
+    fn apply(item: &HashMap<String, AttributeValue>, update: &Update) -> HashMap<String, AttributeValue> {
+        let mut item = item.clone();
+        let (set, remove) = update.expression.trim_start_matches("SET ").split_once(" REMOVE ").unwrap();
+        for assignment in set.split(',') {
+            let (name, value) = assignment.split_once(" = ").unwrap();
+            item.insert(update.names[name].clone(), update.values[value].clone());
+        }
+        item.remove(&update.names[remove]);
+        item
+    }
+
+    #[test]
+    fn test_migrated_item_reads_as_current_version() {
+        let legacy = item(&[
+            ("uuid", AttributeValue::S(String::from("https://tasty.co/recipe/pancakes"))),
+            ("ingredients", AttributeValue::S(String::from("1 cup flour;2 eggs"))),
+            ("instructions", AttributeValue::S(String::from("Mix;Bake"))),
+            ("parsed_ingredients", AttributeValue::S(String::from("[]"))),
+        ]);
+        assert_eq!(from_item(&legacy).schema_version, 1);
+
+        let full = update(&plan(&legacy).unwrap().unwrap());
+        assert_eq!(
+            full.expression,
+            "SET #ingredients = :ingredientsValue,#instructions = :instructionsValue,#schema_version = :schemaVersion REMOVE #parsedIngredients"
+        );
+        let migrated = from_item(&apply(&legacy, &full));
+        assert_eq!(migrated.schema_version, 2);
+        assert_eq!(migrated.ingredients[1].raw, "2 eggs");
+        assert_eq!(migrated.instructions, vec!["Mix", "Bake"]);
+        // Only the instructions were left to do
+        let half = item(&[("ingredients", AttributeValue::L(Vec::new())), ("instructions", AttributeValue::S(String::from("Mix")))]);
+        assert_eq!(from_item(&apply(&half, &update(&plan(&half).unwrap().unwrap()))).schema_version, 2);
+    }
+
+    #[test]
+    fn test_plan_native_and_broken_items() {
+        let native = item(&[
+            ("ingredients", AttributeValue::L(Vec::new())),
+            ("instructions", AttributeValue::L(Vec::new())),
+        ]);
+        assert_eq!(plan(&native), Ok(None));
+        let broken = item(&[("ingredients", AttributeValue::N(String::from("3")))]);
+        assert!(plan(&broken).is_err());
+    }
+}
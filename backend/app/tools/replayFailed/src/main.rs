@@ -0,0 +1,205 @@
+use std::env;
+use aws_config::{meta::region::RegionProviderChain, BehaviorVersion, Region};
+use aws_sdk_sns::Client as SnsClient;
+use recipe_core::{DeadLetter, DeadLetterStore, DynamoStore, ErrorKind, Job, JobStatus, JobStore};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Debug, PartialEq)]
+pub struct Opt {
+    pub table_name: String,
+    pub job_table_name: String,
+    pub topic_arn: String,
+    pub ids: Vec<String>,
+    pub all: bool,
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct Summary {
+    pub replayed: usize,
+    pub skipped: usize,
+    pub failed: Vec<(String, String)>,
+}
+
+/**
+ * Usage: replayFailed [--dry-run] [--all] [--id <job id>]... [--table <name>]
+ *                     [--job-table <name>] [--topic-arn <arn>]
+ *
+ * The tables and topic default to DEAD_LETTER_TABLE_NAME, JOB_TABLE_NAME
+ * and SNS_ARN from the environment. Without --id every retryable failure is
+ * replayed; --all takes permanent and bad input ones too, once whatever
+ * broke them has been fixed.
+ */
+fn get_opt(args: Vec<String>) -> Result<Opt, String> {
+    let mut table_name = env::var("DEAD_LETTER_TABLE_NAME").ok();
+    let mut job_table_name = env::var("JOB_TABLE_NAME").ok();
+    let mut topic_arn = env::var("SNS_ARN").ok();
+    let mut ids: Vec<String> = Vec::new();
+    let mut all = false;
+    let mut dry_run = false;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dry-run" => dry_run = true,
+            "--all" => all = true,
+            "--id" => match args.next() {
+                Some(id) => ids.push(id),
+                None => return Err(String::from("--id needs a job id")),
+            },
+            "--table" => table_name = args.next(),
+            "--job-table" => job_table_name = args.next(),
+            "--topic-arn" => topic_arn = args.next(),
+            other => return Err(format!("Unknown argument: {}", other)),
+        }
+    }
+    match (table_name, job_table_name, topic_arn) {
+        (Some(table_name), Some(job_table_name), Some(topic_arn)) => Ok(Opt { table_name, job_table_name, topic_arn, ids, all, dry_run }),
+        (None, _, _) => Err(String::from("Pass --table or set DEAD_LETTER_TABLE_NAME")),
+        (_, None, _) => Err(String::from("Pass --job-table or set JOB_TABLE_NAME")),
+        (_, _, None) => Err(String::from("Pass --topic-arn or set SNS_ARN")),
+    }
+}
+
+/**
+ * Whether a dead letter should be replayed. Asking for one by id replays it
+ * whatever went wrong, otherwise only retryable failures go unless --all.
+ */
+pub fn selected(letter: &DeadLetter, opt: &Opt) -> bool {
+    if !opt.ids.is_empty() {
+        return opt.ids.contains(&letter.id);
+    }
+    opt.all || letter.kind == ErrorKind::Retryable
+}
+
+// Back to how addRecipe left it, so GET /jobs/{id} shows it going again
+pub fn requeue(job: Option<Job>, id: &str) -> Job {
+    let mut job = job.unwrap_or_else(|| Job::new(id));
+    job.reset();
+    job.advance(JobStatus::Queued);
+    job
+}
+
+async fn replay<S: DeadLetterStore + JobStore>(store: &S, sns: &SnsClient, topic_arn: &str, letter: &DeadLetter) -> Result<(), Error> {
+    let job = requeue(store.get_job(&letter.id).await?, &letter.id);
+    store.put_job(&job).await?;
+    sns.publish()
+        .topic_arn(topic_arn)
+        .message(&letter.request)
+        .send()
+        .await?;
+    // Only once it's back on the topic, if the worker fails again it writes a new one
+    store.delete_dead_letter(&letter.id).await?;
+    Ok(())
+}
+
+async fn run<S: DeadLetterStore + JobStore>(store: &S, sns: &SnsClient, opt: &Opt) -> Result<Summary, Error> {
+    let mut summary = Summary::default();
+    for letter in store.list_dead_letters().await? {
+        if !selected(&letter, opt) {
+            summary.skipped += 1;
+            continue;
+        }
+        if opt.dry_run {
+            println!("Would replay {} ({}): {}", letter.id, letter.kind.as_str(), letter.error);
+            summary.replayed += 1;
+            continue;
+        }
+        match replay(store, sns, &opt.topic_arn, &letter).await {
+            Ok(_) => {
+                println!("Replayed {}", letter.id);
+                summary.replayed += 1;
+            }
+            Err(e) => summary.failed.push((letter.id.clone(), e.to_string())),
+        }
+    }
+    Ok(summary)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let opt = match get_opt(env::args().skip(1).collect()) {
+        Ok(o) => o,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let region_provider = RegionProviderChain::default_provider().or_else(Region::new("us-east-1"));
+    let config = aws_config::defaults(BehaviorVersion::latest()).region(region_provider).load().await;
+    // Replaying never reads recipes, so there's no recipe table to give it
+    let store = DynamoStore::new(&config, "")
+        .with_job_table(&opt.job_table_name)
+        .with_dead_letter_table(&opt.table_name);
+    let sns = SnsClient::new(&config);
+
+    let summary = run(&store, &sns, &opt).await?;
+    for (id, reason) in &summary.failed {
+        eprintln!("Could not replay {}: {}", id, reason);
+    }
+    println!(
+        "{} {}, skipped {}, {} failed",
+        if opt.dry_run { "Would replay" } else { "Replayed" },
+        summary.replayed,
+        summary.skipped,
+        summary.failed.len()
+    );
+    if !summary.failed.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        ["--table", "DeadLetters", "--job-table", "Jobs", "--topic-arn", "arn:aws:sns:us-east-1:1:recipes"]
+            .iter()
+            .chain(args)
+            .map(|a| a.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_get_opt() {
+        let opt = get_opt(args(&["--id", "job-1", "--id", "job-2", "--dry-run"])).unwrap();
+        assert_eq!(opt.table_name, "DeadLetters");
+        assert_eq!(opt.ids, vec!["job-1", "job-2"]);
+        assert!(opt.dry_run && !opt.all);
+        assert!(get_opt(args(&["--id"])).is_err());
+        assert!(get_opt(args(&["--force"])).is_err());
+    }
+
+    #[test]
+    fn test_selected() {
+        let limited = DeadLetter::new("job-1", "{}", ErrorKind::Retryable, "429 Too Many Requests");
+        let missing = DeadLetter::new("job-2", "{}", ErrorKind::BadInput, "Error reading URL: 404 Not Found");
+        let mut opt = get_opt(args(&[])).unwrap();
+        assert!(selected(&limited, &opt));
+        assert!(!selected(&missing, &opt));
+        opt.all = true;
+        assert!(selected(&missing, &opt));
+        opt.all = false;
+        opt.ids = vec![String::from("job-2")];
+        assert!(!selected(&limited, &opt));
+        assert!(selected(&missing, &opt));
+    }
+
+    #[test]
+    fn test_requeue() {
+        let mut failed = Job::new("job-1");
+        failed.name = Some(String::from("Taco Soup"));
+        failed.image = Some(String::from("https://example.com/soup.jpg"));
+        failed.record_parsed(1, "Taco Soup");
+        failed.finish_all(&[String::from("https://tasty.co/recipe/taco-soup")]);
+        failed.fail("Error getting response from OpenAI: 429 Too Many Requests");
+        let job = requeue(Some(failed), "job-1");
+        assert_eq!(job.status, JobStatus::Queued);
+        // Nothing from the last run shows while it's queued again
+        assert_eq!(job, Job { created_at: job.created_at, updated_at: job.updated_at, ..Job::new("job-1") });
+        assert_eq!(requeue(None, "job-2").id, "job-2");
+    }
+}
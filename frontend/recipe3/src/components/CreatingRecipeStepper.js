@@ -13,7 +13,6 @@ import { Stack } from '@mui/system';
 import CreatingRecipeStep1 from './CreatingRecipeStep1';
 import CreatingRecipeStep2 from './CreatingRecipeStep2';
 import CreatingRecipeStep3 from './CreatingRecipeStep3';
-import AWS from 'aws-sdk';
 import dotenv from 'dotenv';
 
 dotenv.config();
@@ -26,7 +25,7 @@ const CreatingRecipeStepper = (props) => {
   const [recipe, setRecipe] = useState(null);
   const [submitted, setSubmitted] = useState(false);
   const [processing, setProcessing] = useState(false);
-  const [sqsUrl, setSqsUrl] = useState(null);
+  const [jobId, setJobId] = useState(null);
   const [init, setInit] = useState(false);
 
     const submitRecipe = async () => {
@@ -55,7 +54,7 @@ const CreatingRecipeStepper = (props) => {
               if (responseData.uuid !== undefined) {
                 setRecipe(responseData);
               } else {
-                setSqsUrl(responseData.sqs_url);
+                setJobId(responseData.job_id);
               }
               handleSubmitNext();
             }
@@ -89,61 +88,44 @@ const CreatingRecipeStepper = (props) => {
         }
       }
     };
-    const pollForMessages = (sqs) => {
-      const params = {
-        QueueUrl: sqsUrl,
-        MaxNumberOfMessages: 1, // Adjust this as needed
-        WaitTimeSeconds: 20,   // Adjust this as needed
-      };
-    
-      sqs.receiveMessage(params, (err, data) => {
-        if (err) {
-          console.error('Error receiving message:', err);
-          // Handle the error (e.g., show an error message)
-        } else {
-          const message = data.Messages && data.Messages[0];
-          if (message) {
-            // Handle the received message (e.g., update state)
-            let data = JSON.parse(message.Body);
-            console.log("Message Received: " + data.status_code);
-            if (data.status_code === 500) {
-              props.handleClose();
-              props.handleFailed();
-            } else {
-              handleProcessingNext();
-              props.success();
+    const pollJob = async () => {
+      const apiUrl = `https://ucowpmolm0.execute-api.us-east-1.amazonaws.com/prod/jobs/${jobId}`;
+      try {
+        const response = await fetch(apiUrl, {
+          method: 'GET'
+        });
+        if (response.ok) {
+          const job = await response.json();
+          console.log("Job status: " + job.status);
+          if (job.status === "failed") {
+            console.error(job.error);
+            props.handleClose();
+            props.handleFailed();
+            return;
+          } else if (job.status === "done") {
+            if (job.recipe !== undefined) {
+              setRecipe(job.recipe);
             }
-            // Delete the message from the queue
-            sqs.deleteMessage({
-              QueueUrl: sqsUrl,
-              ReceiptHandle: message.ReceiptHandle,
-            }, (deleteErr) => {
-              if (deleteErr) {
-                console.error('Error deleting message:', deleteErr);
-                // Handle the delete error
-              }
-            });
+            handleProcessingNext();
+            props.success();
+            return;
           }
         }
-    
-        // Poll for new messages again after a delay
-        setTimeout(() => {
-          pollForMessages(sqs);
-        }, 5000); // Poll every 5 seconds
-      });
+      } catch (error) {
+        console.error('Error getting job:', error);
+      }
+
+      // Poll again after a delay until the job is done or failed
+      setTimeout(() => {
+        pollJob();
+      }, 5000); // Poll every 5 seconds
     }
-    const subscribeToSns = async () => {
+    const waitForJob = async () => {
       if (recipe !== null) {
         console.log("Recipe: " + recipe);
         handleProcessingNext();
       } else {
-        AWS.config.update({
-          accessKeyId: process.env.REACT_APP_AWS_ACCESS_KEY_ID,
-          secretAccessKey: process.env.REACT_APP_AWS_SECRET_ACCESS_KEY,
-          region: 'us-east-1'
-        });
-        const sqs = new AWS.SQS();
-        pollForMessages(sqs);
+        pollJob();
       }
     }
 
@@ -156,7 +138,7 @@ const CreatingRecipeStepper = (props) => {
         if (activeStep === 0 && !submitted) {
             submitRecipe();
         } else if (activeStep === 1 && processing) {
-            subscribeToSns();
+            waitForJob();
         } else if (activeStep === 2) {
             getRecipe();
         }